@@ -0,0 +1,15 @@
+//! Offline mock of the Bedrock gateway
+//!
+//! Implements `POST /generate` as described in `docs/BEDROCK_GATEWAY_CONTRACT.md`:
+//! password-hash auth against a local user file, the `auth_check` prompt, JSON error
+//! bodies with `status`, and SSE `data:` chunks ending in a `done` chunk that carries
+//! `remaining_tokens`. Responses come from a script (canned text, faults), from replayed
+//! interaction logs or cache entries, or from a built-in canned question set.
+
+pub mod script;
+pub mod server;
+pub mod users;
+
+pub use script::{Faults, Script};
+pub use server::{serve, MockGateway};
+pub use users::UserStore;
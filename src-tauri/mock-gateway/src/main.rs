@@ -0,0 +1,108 @@
+//! Run the offline mock gateway
+//!
+//! ```text
+//! cargo run -p mock-gateway -- [--port 8787] [--users users.json] [--script script.json]
+//!     [--replay <interactions.jsonl | llm-cache dir>]... [--chunk-delay-ms N]
+//! ```
+//!
+//! Point the app at it with `BEDROCK_GATEWAY_URL=http://127.0.0.1:8787/generate`.
+//! Without `--users`, a single `demo` / `demo` account is available.
+
+use mock_gateway::{serve, Faults, MockGateway, Script, UserStore};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const DEFAULT_PORT: u16 = 8787;
+
+struct Options {
+    port: u16,
+    users: Option<PathBuf>,
+    script: Option<PathBuf>,
+    replay: Vec<PathBuf>,
+    chunk_delay_ms: Option<u64>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        port: DEFAULT_PORT,
+        users: None,
+        script: None,
+        replay: Vec::new(),
+        chunk_delay_ms: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--port" => {
+                options.port = value()?
+                    .parse()
+                    .map_err(|e| format!("Invalid --port: {}", e))?
+            }
+            "--users" => options.users = Some(PathBuf::from(value()?)),
+            "--script" => options.script = Some(PathBuf::from(value()?)),
+            "--replay" => options.replay.push(PathBuf::from(value()?)),
+            "--chunk-delay-ms" => {
+                options.chunk_delay_ms = Some(
+                    value()?
+                        .parse()
+                        .map_err(|e| format!("Invalid --chunk-delay-ms: {}", e))?,
+                )
+            }
+            "-h" | "--help" => {
+                println!(
+                    "Usage: mock-gateway [--port N] [--users FILE] [--script FILE] \
+                     [--replay PATH]... [--chunk-delay-ms N]"
+                );
+                std::process::exit(0);
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        eprintln!("mock-gateway: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<(), String> {
+    let options = parse_args()?;
+
+    let users = match &options.users {
+        Some(path) => UserStore::load(path)?,
+        None => UserStore::demo(),
+    };
+    let mut script = match &options.script {
+        Some(path) => Script::load(path)?,
+        None => Script::new(),
+    };
+    for path in &options.replay {
+        let loaded = script.load_replay(path)?;
+        eprintln!(
+            "mock-gateway: loaded {} replayable response(s) from {}",
+            loaded,
+            path.display()
+        );
+    }
+    let script = script.with_defaults(Faults {
+        chunk_delay_ms: options.chunk_delay_ms,
+        ..Faults::default()
+    });
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", options.port))
+        .await
+        .map_err(|e| format!("Failed to bind port {}: {}", options.port, e))?;
+    eprintln!(
+        "mock-gateway: listening on http://127.0.0.1:{}/generate",
+        options.port
+    );
+
+    serve(listener, Arc::new(MockGateway { users, script }))
+        .await
+        .map_err(|e| format!("Server stopped: {}", e))
+}
@@ -0,0 +1,356 @@
+//! Scripted responses, replayed interactions and fault injection
+//!
+//! A script file looks like:
+//!
+//! ```json
+//! {
+//!   "chunk_chars": 40,
+//!   "defaults": { "chunk_delay_ms": 20 },
+//!   "rules": [
+//!     { "match": "Generate 3", "status": 429, "retry_after_secs": 1, "times": 1 },
+//!     { "match": "Generate 3", "file": "three-questions.json" },
+//!     { "text": "[]", "disconnect_after_chunks": 2 }
+//!   ]
+//! }
+//! ```
+//!
+//! The first rule whose `match` is a substring of the prompt (no `match` matches
+//! everything) and whose `times` is not used up answers the request. Without a matching
+//! rule the prompt is looked up in the replayed interactions, then a canned question set
+//! is returned. Fault fields on a rule override `defaults`.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const DEFAULT_CHUNK_CHARS: usize = 40;
+/// Upper bound on canned questions so a stray prompt cannot produce a huge reply.
+const MAX_CANNED_QUESTIONS: usize = 20;
+
+/// Ways to misbehave, applied to one response.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Faults {
+    /// Reply with a JSON error instead of a stream.
+    pub status: Option<u16>,
+    pub retry_after_secs: Option<u64>,
+    /// Send the error status only in the JSON body, with HTTP 200 (stream-mode Lambda).
+    pub status_in_body: Option<bool>,
+    /// Wait before the response headers.
+    pub first_byte_delay_ms: Option<u64>,
+    /// Wait between SSE chunks.
+    pub chunk_delay_ms: Option<u64>,
+    /// Drop the connection after this many chunks.
+    pub disconnect_after_chunks: Option<usize>,
+    /// Send `event: error` after this many chunks.
+    pub stream_error_after_chunks: Option<usize>,
+    /// End the stream without the final `done` chunk.
+    pub omit_done: Option<bool>,
+}
+
+impl Faults {
+    /// Fill unset fields from `fallback`.
+    pub fn or(self, fallback: &Faults) -> Faults {
+        Faults {
+            status: self.status.or(fallback.status),
+            retry_after_secs: self.retry_after_secs.or(fallback.retry_after_secs),
+            status_in_body: self.status_in_body.or(fallback.status_in_body),
+            first_byte_delay_ms: self.first_byte_delay_ms.or(fallback.first_byte_delay_ms),
+            chunk_delay_ms: self.chunk_delay_ms.or(fallback.chunk_delay_ms),
+            disconnect_after_chunks: self
+                .disconnect_after_chunks
+                .or(fallback.disconnect_after_chunks),
+            stream_error_after_chunks: self
+                .stream_error_after_chunks
+                .or(fallback.stream_error_after_chunks),
+            omit_done: self.omit_done.or(fallback.omit_done),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Rule {
+    #[serde(default, rename = "match")]
+    pattern: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    /// Response text read from a file, relative to the script.
+    #[serde(default)]
+    file: Option<PathBuf>,
+    #[serde(default)]
+    times: Option<u32>,
+    #[serde(flatten)]
+    faults: Faults,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScriptFile {
+    #[serde(default)]
+    chunk_chars: Option<usize>,
+    #[serde(default)]
+    defaults: Faults,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+/// What to send back for one request.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub text: String,
+    pub faults: Faults,
+    /// Where the text came from, for the request log.
+    pub source: &'static str,
+}
+
+#[derive(Default)]
+pub struct Script {
+    chunk_chars: usize,
+    defaults: Faults,
+    rules: Vec<Rule>,
+    /// Uses left per rule; `None` means unlimited.
+    uses_left: Mutex<Vec<Option<u32>>>,
+    replay: HashMap<String, String>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self {
+            chunk_chars: DEFAULT_CHUNK_CHARS,
+            ..Self::default()
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        Self::parse(&data, base)
+    }
+
+    fn parse(data: &str, base: &Path) -> Result<Self, String> {
+        let file: ScriptFile =
+            serde_json::from_str(data).map_err(|e| format!("Invalid script: {}", e))?;
+        let mut rules = file.rules;
+        for rule in &mut rules {
+            if let Some(file) = rule.file.take() {
+                let path = base.join(file);
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                rule.text = Some(text);
+            }
+        }
+        Ok(Self {
+            chunk_chars: file.chunk_chars.unwrap_or(DEFAULT_CHUNK_CHARS).max(1),
+            defaults: file.defaults,
+            uses_left: Mutex::new(rules.iter().map(|rule| rule.times).collect()),
+            rules,
+            replay: HashMap::new(),
+        })
+    }
+
+    pub fn chunk_chars(&self) -> usize {
+        self.chunk_chars
+    }
+
+    /// Override the default faults, e.g. from command-line flags.
+    pub fn with_defaults(mut self, faults: Faults) -> Self {
+        self.defaults = faults.or(&self.defaults);
+        self
+    }
+
+    /// Load prompt/response pairs to replay: the app's interaction log (`*.jsonl`),
+    /// response cache entries (`*.json`), or a directory of either.
+    pub fn load_replay(&mut self, path: &Path) -> Result<usize, String> {
+        let before = self.replay.len();
+        if path.is_dir() {
+            let entries = std::fs::read_dir(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect();
+            paths.sort();
+            for path in paths {
+                self.load_replay_file(&path)?;
+            }
+        } else {
+            self.load_replay_file(path)?;
+        }
+        Ok(self.replay.len() - before)
+    }
+
+    fn load_replay_file(&mut self, path: &Path) -> Result<(), String> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        if extension != "json" && extension != "jsonl" {
+            return Ok(());
+        }
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let values: Vec<Value> = if extension == "jsonl" {
+            data.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        } else {
+            serde_json::from_str(&data).into_iter().collect()
+        };
+        for value in values {
+            if let Some((prompt, response)) = replay_pair(&value) {
+                self.replay.insert(prompt, response);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pick the reply for `prompt`, using up one `times` of the matching rule.
+    pub fn reply(&self, prompt: &str) -> Reply {
+        let mut uses_left = self.uses_left.lock().unwrap();
+        for (rule, uses) in self.rules.iter().zip(uses_left.iter_mut()) {
+            if uses == &Some(0) {
+                continue;
+            }
+            if let Some(pattern) = &rule.pattern {
+                if !prompt.contains(pattern.as_str()) {
+                    continue;
+                }
+            }
+            if let Some(left) = uses {
+                *left -= 1;
+            }
+            let (text, source) = match &rule.text {
+                Some(text) => (text.clone(), "script"),
+                None => self.unscripted_text(prompt),
+            };
+            return Reply {
+                text,
+                faults: rule.faults.clone().or(&self.defaults),
+                source,
+            };
+        }
+        drop(uses_left);
+
+        let (text, source) = self.unscripted_text(prompt);
+        Reply {
+            text,
+            faults: self.defaults.clone(),
+            source,
+        }
+    }
+
+    fn unscripted_text(&self, prompt: &str) -> (String, &'static str) {
+        match self.replay.get(prompt) {
+            Some(response) => (response.clone(), "replay"),
+            None => (canned_questions(requested_count(prompt)), "canned"),
+        }
+    }
+}
+
+/// Completed, unredacted interactions from the log, or a cache entry.
+fn replay_pair(value: &Value) -> Option<(String, String)> {
+    if let Some(kind) = value.get("type").and_then(Value::as_str) {
+        if kind != "interaction" {
+            return None;
+        }
+    }
+    if value.get("redacted").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    if let Some(outcome) = value.get("outcome").and_then(Value::as_str) {
+        if outcome != "completed" {
+            return None;
+        }
+    }
+    let prompt = value.get("prompt")?.as_str()?.to_string();
+    let response = value.get("response")?.as_str()?.to_string();
+    Some((prompt, response))
+}
+
+/// Read N from the "Generate N multiple choice question(s)" line; 1 otherwise.
+fn requested_count(prompt: &str) -> usize {
+    prompt
+        .split("Generate ")
+        .skip(1)
+        .find_map(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<usize>().ok()
+        })
+        .unwrap_or(1)
+        .clamp(1, MAX_CANNED_QUESTIONS)
+}
+
+/// A valid question array in the shape the prompts ask for.
+pub fn canned_questions(count: usize) -> String {
+    let questions: Vec<Value> = (1..=count)
+        .map(|n| {
+            serde_json::json!({
+                "text": format!("Mock question {}: which value does `1 + {}` evaluate to?", n, n),
+                "explanation": format!("`1 + {}` is {}.", n, n + 1),
+                "distractors": "Off-by-one values and string concatenation are common slips.",
+                "answers": [
+                    {"text": format!("{}", n + 1), "is_correct": true, "explanation": "Integer addition."},
+                    {"text": format!("{}", n), "is_correct": false, "explanation": "Ignores the 1."},
+                    {"text": format!("1{}", n), "is_correct": false, "explanation": "Treats the values as strings."},
+                    {"text": format!("{}", n + 2), "is_correct": false, "explanation": "Off by one."}
+                ]
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&questions).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_match_in_order_and_run_out() {
+        let script = Script::parse(
+            r#"{
+                "defaults": {"chunk_delay_ms": 5},
+                "rules": [
+                    {"match": "Generate 2", "status": 429, "times": 1},
+                    {"match": "Generate 2", "text": "[]"}
+                ]
+            }"#,
+            Path::new("."),
+        )
+        .unwrap();
+
+        let first = script.reply("Generate 2 multiple choice question(s)");
+        assert_eq!(first.faults.status, Some(429));
+        assert_eq!(first.faults.chunk_delay_ms, Some(5));
+
+        let second = script.reply("Generate 2 multiple choice question(s)");
+        assert_eq!(second.faults.status, None);
+        assert_eq!(second.text, "[]");
+        assert_eq!(second.source, "script");
+
+        let other = script.reply("Generate 3 multiple choice question(s)");
+        assert_eq!(other.source, "canned");
+        let parsed: Vec<Value> = serde_json::from_str(&other.text).unwrap();
+        assert_eq!(parsed.len(), 3);
+    }
+
+    #[test]
+    fn test_replay_pairs_skip_failed_and_redacted_records() {
+        let completed = serde_json::json!({
+            "type": "interaction", "prompt": "p", "response": "r", "outcome": "completed"
+        });
+        let failed = serde_json::json!({
+            "type": "interaction", "prompt": "p", "response": "r", "outcome": "failed"
+        });
+        let redacted = serde_json::json!({
+            "type": "interaction", "prompt": "p", "response": "r",
+            "outcome": "completed", "redacted": true
+        });
+        let cache_entry = serde_json::json!({"key": "k", "prompt": "p", "response": "r"});
+
+        assert_eq!(
+            replay_pair(&completed),
+            Some(("p".to_string(), "r".to_string()))
+        );
+        assert_eq!(replay_pair(&failed), None);
+        assert_eq!(replay_pair(&redacted), None);
+        assert!(replay_pair(&cache_entry).is_some());
+        assert_eq!(replay_pair(&serde_json::json!({"type": "parse"})), None);
+    }
+}
@@ -0,0 +1,343 @@
+//! Minimal HTTP/1.1 server for `POST /generate`
+//!
+//! Each connection carries one request and is closed after the response, so streamed
+//! bodies need neither `Content-Length` nor chunked encoding.
+
+use crate::script::{Faults, Script};
+use crate::users::UserStore;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+/// Rough characters-per-token ratio used for quota accounting.
+const CHARS_PER_TOKEN: u64 = 4;
+
+pub struct MockGateway {
+    pub users: UserStore,
+    pub script: Script,
+}
+
+#[derive(Deserialize)]
+struct GenerateRequest {
+    user: String,
+    password_hash: String,
+    prompt: String,
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Accept connections until the listener fails.
+pub async fn serve(listener: TcpListener, gateway: Arc<MockGateway>) -> std::io::Result<()> {
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        let gateway = gateway.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(socket, &gateway).await {
+                eprintln!("mock-gateway: {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut socket: TcpStream, gateway: &MockGateway) -> std::io::Result<()> {
+    let request = match read_request(&mut socket).await? {
+        Ok(request) => request,
+        Err(message) => return write_json_error(&mut socket, 400, &message, None, false).await,
+    };
+
+    if request.method != "POST" || request.path.split('?').next() != Some("/generate") {
+        return write_json_error(&mut socket, 404, "Not found", None, false).await;
+    }
+
+    let body: GenerateRequest = match serde_json::from_slice(&request.body) {
+        Ok(body) => body,
+        Err(e) => {
+            let message = format!("Invalid request: {}", e);
+            return write_json_error(&mut socket, 400, &message, None, false).await;
+        }
+    };
+
+    let remaining = match gateway.users.authenticate(&body.user, &body.password_hash) {
+        Ok(remaining) => remaining,
+        Err(err) => {
+            eprintln!("mock-gateway: {} -> {}", body.user, err.status);
+            return write_json_error(&mut socket, err.status, &err.message, None, false).await;
+        }
+    };
+
+    // Credential check used by the login dialog; costs nothing.
+    if body.prompt == "auth_check" {
+        write_stream_headers(&mut socket).await?;
+        return write_chunk(&mut socket, "", true, Some(remaining)).await;
+    }
+
+    if remaining == 0 {
+        return write_json_error(&mut socket, 429, "Token quota exhausted", None, false).await;
+    }
+
+    let reply = gateway.script.reply(&body.prompt);
+    eprintln!(
+        "mock-gateway: {} prompt={} chars reply={} ({} chars)",
+        body.user,
+        body.prompt.chars().count(),
+        reply.source,
+        reply.text.chars().count()
+    );
+
+    let faults = reply.faults;
+    if let Some(ms) = faults.first_byte_delay_ms {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+    }
+    if let Some(status) = faults.status {
+        let message = format!("Injected error ({})", status);
+        let in_body = faults.status_in_body.unwrap_or(false);
+        return write_json_error(
+            &mut socket,
+            status,
+            &message,
+            faults.retry_after_secs,
+            in_body,
+        )
+        .await;
+    }
+
+    write_stream_headers(&mut socket).await?;
+    let chars: Vec<char> = reply.text.chars().collect();
+    for (i, chunk) in chars.chunks(gateway.script.chunk_chars()).enumerate() {
+        if should_stop(&faults, i, &mut socket).await? {
+            return Ok(());
+        }
+        let text: String = chunk.iter().collect();
+        write_chunk(&mut socket, &text, false, None).await?;
+        if let Some(ms) = faults.chunk_delay_ms {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+        }
+    }
+    let sent = chars.len().div_ceil(gateway.script.chunk_chars());
+    if should_stop(&faults, sent, &mut socket).await? {
+        return Ok(());
+    }
+
+    let used = (body.prompt.chars().count() + chars.len()) as u64 / CHARS_PER_TOKEN;
+    let remaining = gateway.users.charge(&body.user, used.max(1));
+    if faults.omit_done.unwrap_or(false) {
+        return Ok(());
+    }
+    write_chunk(&mut socket, "", true, Some(remaining)).await
+}
+
+/// Apply mid-stream faults before chunk `index`; true when the stream must end here.
+async fn should_stop(
+    faults: &Faults,
+    index: usize,
+    socket: &mut TcpStream,
+) -> std::io::Result<bool> {
+    if faults.disconnect_after_chunks == Some(index) {
+        socket.shutdown().await?;
+        return Ok(true);
+    }
+    if faults.stream_error_after_chunks == Some(index) {
+        let payload = serde_json::json!({"error": "Injected stream error", "status": 500});
+        socket
+            .write_all(format!("event: error\ndata: {}\n\n", payload).as_bytes())
+            .await?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Read one request. The outer error is I/O; the inner one is a malformed request.
+async fn read_request(socket: &mut TcpStream) -> std::io::Result<Result<HttpRequest, String>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        if let Some(pos) = find_subslice(&buf, b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEADER_BYTES {
+            return Ok(Err("Request headers too large".to_string()));
+        }
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(Err(
+                "Connection closed before request was complete".to_string()
+            ));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return Ok(Err("Request body too large".to_string()));
+    }
+
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = socket.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(Err("Connection closed before body was complete".to_string()));
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(Ok(HttpRequest { method, path, body }))
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+/// JSON error body as the gateway sends it; `in_body` keeps HTTP 200 like stream-mode Lambda.
+async fn write_json_error(
+    socket: &mut TcpStream,
+    status: u16,
+    message: &str,
+    retry_after_secs: Option<u64>,
+    in_body: bool,
+) -> std::io::Result<()> {
+    let body = serde_json::json!({"error": message, "status": status}).to_string();
+    let http_status = if in_body { 200 } else { status };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        http_status,
+        reason_phrase(http_status),
+        body.len()
+    );
+    if let Some(secs) = retry_after_secs {
+        head.push_str(&format!("Retry-After: {}\r\n", secs));
+    }
+    head.push_str("\r\n");
+    socket.write_all(head.as_bytes()).await?;
+    socket.write_all(body.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+async fn write_stream_headers(socket: &mut TcpStream) -> std::io::Result<()> {
+    socket
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        )
+        .await
+}
+
+async fn write_chunk(
+    socket: &mut TcpStream,
+    text: &str,
+    done: bool,
+    remaining_tokens: Option<u64>,
+) -> std::io::Result<()> {
+    let mut payload = serde_json::json!({"text": text, "done": done});
+    if let Some(tokens) = remaining_tokens {
+        payload["remaining_tokens"] = tokens.into();
+    }
+    socket
+        .write_all(format!("data: {}\n\n", payload).as_bytes())
+        .await?;
+    socket.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::users::{hash_password, DEFAULT_TOKENS};
+
+    async fn start(script: Script) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = Arc::new(MockGateway {
+            users: UserStore::demo(),
+            script,
+        });
+        tokio::spawn(serve(listener, gateway));
+        addr.to_string()
+    }
+
+    async fn post(addr: &str, body: serde_json::Value) -> String {
+        let body = body.to_string();
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST /generate HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            addr,
+            body.len(),
+            body
+        );
+        socket.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_streams_canned_questions_and_charges_tokens() {
+        let addr = start(Script::new()).await;
+        let response = post(
+            &addr,
+            serde_json::json!({
+                "user": "demo",
+                "password_hash": hash_password("demo"),
+                "prompt": "Generate 2 multiple choice question(s) about loops"
+            }),
+        )
+        .await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("text/event-stream"));
+        assert!(response.contains("\"done\":false"));
+        let last = response.trim_end().lines().last().unwrap();
+        let done: serde_json::Value =
+            serde_json::from_str(last.trim_start_matches("data: ")).unwrap();
+        assert_eq!(done["done"], true);
+        assert!(done["remaining_tokens"].as_u64().unwrap() < DEFAULT_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn test_auth_errors_are_json_with_status() {
+        let addr = start(Script::new()).await;
+        let response = post(
+            &addr,
+            serde_json::json!({"user": "demo", "password_hash": "bad", "prompt": "auth_check"}),
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+        assert!(response.contains(r#""status":401"#));
+
+        let missing = post(&addr, serde_json::json!({"user": "demo"})).await;
+        assert!(missing.starts_with("HTTP/1.1 400"));
+    }
+}
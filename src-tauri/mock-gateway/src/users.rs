@@ -0,0 +1,152 @@
+//! Local user file with password hashes and token quotas
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Quota given to users that do not set `remaining_tokens`.
+pub const DEFAULT_TOKENS: u64 = 1_000_000;
+
+#[derive(Deserialize)]
+struct UserFile {
+    users: Vec<UserFileEntry>,
+}
+
+/// Either `password` (hashed on load) or `password_hash` must be set.
+#[derive(Deserialize)]
+struct UserFileEntry {
+    user: String,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    password_hash: Option<String>,
+    #[serde(default)]
+    remaining_tokens: Option<u64>,
+}
+
+struct Account {
+    password_hash: String,
+    remaining_tokens: u64,
+}
+
+pub struct UserStore {
+    accounts: Mutex<HashMap<String, Account>>,
+}
+
+/// Rejected credentials, mapped to the gateway's HTTP status.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AuthError {
+    pub status: u16,
+    pub message: String,
+}
+
+/// SHA-256 hex digest, matching what the app sends as `password_hash`.
+pub fn hash_password(password: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(password.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+impl UserStore {
+    /// A single `demo` user with password `demo`.
+    pub fn demo() -> Self {
+        Self::from_entries(vec![UserFileEntry {
+            user: "demo".to_string(),
+            password: Some("demo".to_string()),
+            password_hash: None,
+            remaining_tokens: None,
+        }])
+        .expect("demo user is valid")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file: UserFile = serde_json::from_str(&data)
+            .map_err(|e| format!("Invalid user file {}: {}", path.display(), e))?;
+        Self::from_entries(file.users)
+    }
+
+    fn from_entries(entries: Vec<UserFileEntry>) -> Result<Self, String> {
+        let mut accounts = HashMap::new();
+        for entry in entries {
+            let password_hash = match (entry.password_hash, entry.password) {
+                (Some(hash), _) => hash.trim().to_ascii_lowercase(),
+                (None, Some(password)) => hash_password(&password),
+                (None, None) => {
+                    return Err(format!(
+                        "User '{}' needs a password or password_hash",
+                        entry.user
+                    ))
+                }
+            };
+            accounts.insert(
+                entry.user,
+                Account {
+                    password_hash,
+                    remaining_tokens: entry.remaining_tokens.unwrap_or(DEFAULT_TOKENS),
+                },
+            );
+        }
+        Ok(Self {
+            accounts: Mutex::new(accounts),
+        })
+    }
+
+    /// Check credentials and return the user's remaining tokens.
+    pub fn authenticate(&self, user: &str, password_hash: &str) -> Result<u64, AuthError> {
+        let accounts = self.accounts.lock().unwrap();
+        let account = accounts.get(user).ok_or_else(|| AuthError {
+            status: 404,
+            message: "User not found".to_string(),
+        })?;
+        if !account
+            .password_hash
+            .eq_ignore_ascii_case(password_hash.trim())
+        {
+            return Err(AuthError {
+                status: 401,
+                message: "Invalid password".to_string(),
+            });
+        }
+        Ok(account.remaining_tokens)
+    }
+
+    /// Deduct tokens from a user's quota and return what is left.
+    pub fn charge(&self, user: &str, tokens: u64) -> u64 {
+        let mut accounts = self.accounts.lock().unwrap();
+        match accounts.get_mut(user) {
+            Some(account) => {
+                account.remaining_tokens = account.remaining_tokens.saturating_sub(tokens);
+                account.remaining_tokens
+            }
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authenticate_checks_user_and_hash() {
+        let store = UserStore::demo();
+        assert_eq!(
+            store.authenticate("demo", &hash_password("demo")),
+            Ok(DEFAULT_TOKENS)
+        );
+        assert_eq!(
+            store
+                .authenticate("demo", &hash_password("nope"))
+                .unwrap_err()
+                .status,
+            401
+        );
+        assert_eq!(store.authenticate("ghost", "").unwrap_err().status, 404);
+
+        assert_eq!(store.charge("demo", 10), DEFAULT_TOKENS - 10);
+    }
+}
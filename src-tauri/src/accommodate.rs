@@ -0,0 +1,583 @@
+//! Accommodated versions of questions
+//!
+//! IEP/504 and ELL students take a modified version of the same test: simpler wording,
+//! three choices instead of four, glossed vocabulary and more space on the page. The
+//! model rewrites each question and names the original choice every new choice comes
+//! from, so the answer key is carried over rather than trusted. Reading level is scored
+//! locally with the Flesch-Kincaid grade formula so teachers can see that it dropped.
+//!
+//! The result is stored on the question as an [`Accommodation`]; exports asked for the
+//! accommodated document use [`accommodated_set`] in place of the standard questions.
+
+use crate::choices::{self, letter};
+use crate::jobs::Job;
+use crate::llm::{self, GatewayAuth, StreamTarget};
+use crate::{Answer, Question};
+use serde::{Deserialize, Serialize};
+
+/// Questions rewritten at once.
+pub const ACCOMMODATION_CONCURRENCY: usize = 4;
+
+/// Reading level the rewrite aims for.
+const TARGET_GRADE: u32 = 6;
+
+const MAX_GLOSSARY_TERMS: usize = 6;
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AccommodationOptions {
+    #[serde(default = "default_true")]
+    pub simplify_language: bool,
+    /// Drop wrong answers down to three choices.
+    #[serde(default = "default_true")]
+    pub three_choices: bool,
+    #[serde(default = "default_true")]
+    pub gloss_vocabulary: bool,
+    /// Export the accommodated document with extra space between questions and choices.
+    #[serde(default = "default_true")]
+    pub larger_spacing: bool,
+}
+
+impl Default for AccommodationOptions {
+    fn default() -> Self {
+        AccommodationOptions {
+            simplify_language: true,
+            three_choices: true,
+            gloss_vocabulary: true,
+            larger_spacing: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gloss {
+    pub term: String,
+    pub definition: String,
+}
+
+/// Flesch-Kincaid grade of the stem and choices; `None` when there is no prose to score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Readability {
+    pub standard: Option<f64>,
+    pub accommodated: Option<f64>,
+}
+
+/// The accommodated version of a question: same concept, same key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Accommodation {
+    /// Rewritten stem, code included.
+    pub text: String,
+    /// Kept choices in their original order; keys and rationales come from the original.
+    pub answers: Vec<Answer>,
+    #[serde(default)]
+    pub glossary: Vec<Gloss>,
+    pub options: AccommodationOptions,
+    pub readability: Readability,
+    /// Things a teacher should look at, such as a reading level that did not drop.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub job_id: String,
+    pub generated_at: String,
+}
+
+/// Emitted as `accommodation-progress` each time a question finishes.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccommodationProgressEvent {
+    pub job_id: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccommodationFailure {
+    pub index: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccommodationRun {
+    /// The full question list with the new accommodations attached.
+    pub questions: Vec<Question>,
+    pub failed: Vec<AccommodationFailure>,
+}
+
+/// Flesch-Kincaid grade level of markdown text, ignoring code, math and markup.
+/// Each line counts as at least one sentence so choices are scored as fragments.
+pub fn grade_level(text: &str) -> Option<f64> {
+    let (mut words, mut sentences, mut syllables) = (0usize, 0usize, 0usize);
+    for line in prose(text).lines() {
+        let line_words: Vec<&str> = line
+            .split_whitespace()
+            .filter(|w| w.chars().any(char::is_alphabetic))
+            .collect();
+        if line_words.is_empty() {
+            continue;
+        }
+        words += line_words.len();
+        syllables += line_words.iter().map(|w| syllable_count(w)).sum::<usize>();
+        let closes = |token: &str| {
+            token
+                .trim_end_matches(['"', '\'', ')'])
+                .ends_with(['.', '!', '?'])
+        };
+        let ends = line.split_whitespace().filter(|t| closes(t)).count();
+        let open_end = !line.split_whitespace().last().is_some_and(closes);
+        sentences += ends + usize::from(open_end);
+    }
+    if words == 0 {
+        return None;
+    }
+    let grade = 0.39 * words as f64 / sentences.max(1) as f64
+        + 11.8 * syllables as f64 / words as f64
+        - 15.59;
+    Some((grade * 10.0).round() / 10.0)
+}
+
+/// Grade level of a question as students read it: the stem, then one line per choice.
+pub fn question_grade(text: &str, answers: &[Answer]) -> Option<f64> {
+    let mut all = text.to_string();
+    for answer in answers {
+        all.push('\n');
+        all.push_str(&answer.text);
+    }
+    grade_level(&all)
+}
+
+/// Text with fenced code, inline code, `$` math and markdown symbols removed.
+fn prose(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            out.push('\n');
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let mut skipping: Option<char> = None;
+        for c in line.chars() {
+            match skipping {
+                Some(end) if c == end => skipping = None,
+                Some(_) => {}
+                None if c == '`' || c == '$' => skipping = Some(c),
+                None if matches!(c, '*' | '_' | '#' | '>' | '|') => out.push(' '),
+                None => out.push(c),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Vowel groups, less a silent final `e`; at least one.
+fn syllable_count(word: &str) -> usize {
+    let word: String = word
+        .chars()
+        .filter(char::is_ascii_alphabetic)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    let silent_e = word.ends_with('e')
+        && !word.ends_with("le")
+        && !word[..word.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u', 'y']);
+    if silent_e && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+/// Fenced code blocks with whitespace collapsed, to check the rewrite kept the code.
+fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<String> = None;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            match current.take() {
+                Some(block) => blocks.push(block),
+                None => current = Some(String::new()),
+            }
+        } else if let Some(block) = current.as_mut() {
+            block.extend(line.split_whitespace());
+        }
+    }
+    blocks
+}
+
+pub fn build_prompt(question: &Question, options: &AccommodationOptions) -> String {
+    let stem = question.stem_markdown();
+    let mut prompt = String::from(
+        "Rewrite the multiple-choice question below as an accommodated version for \
+         students with IEP/504 plans and English language learners. It must assess exactly \
+         the same concept, and the same answer must be correct.\n\nAccommodations:\n",
+    );
+    if options.simplify_language {
+        let current = question_grade(&stem, &question.answers)
+            .map(|grade| format!(" The current wording scores grade {:.1}.", grade))
+            .unwrap_or_default();
+        prompt.push_str(&format!(
+            "- Simplify the language: short sentences, common words, active voice, one idea \
+             per sentence. Aim for a Flesch-Kincaid grade of {} or lower.{} Keep the \
+             technical terms the question is testing.\n",
+            TARGET_GRADE, current
+        ));
+    } else {
+        prompt.push_str("- Keep the wording of the stem and choices as close as possible.\n");
+    }
+    if options.three_choices && question.answers.len() > 3 {
+        prompt.push_str(&format!(
+            "- Offer three choices instead of {}: keep the correct answer and the wrong \
+             answers that reflect the most common misconceptions.\n",
+            question.answers.len()
+        ));
+    } else {
+        prompt.push_str("- Keep every choice.\n");
+    }
+    if options.gloss_vocabulary {
+        prompt.push_str(&format!(
+            "- Gloss up to {} words a struggling reader may not know, each with a short, plain \
+             definition. Do not define a term if knowing it is what the question tests.\n",
+            MAX_GLOSSARY_TERMS
+        ));
+    }
+    prompt.push_str(
+        "- Copy any code block exactly, and keep every number and value unchanged.\n\n\
+         Question:\n",
+    );
+    prompt.push_str(stem.trim());
+    prompt.push_str("\n\nChoices:\n");
+    for (i, answer) in question.answers.iter().enumerate() {
+        let key = if answer.is_correct { " (correct)" } else { "" };
+        prompt.push_str(&format!("{}. {}{}\n", letter(i), answer.text.trim(), key));
+    }
+    prompt.push_str(
+        "\nReturn ONLY a JSON object of the form {\"text\": \"<rewritten question in \
+         markdown, code block included>\", \"choices\": [{\"original\": \"<letter of the \
+         choice it rewrites>\", \"text\": \"<rewritten choice>\"}], \"glossary\": \
+         [{\"term\": \"<word>\", \"definition\": \"<meaning>\"}]}, with the choices in their \
+         original order.",
+    );
+    prompt
+}
+
+#[derive(Deserialize)]
+struct RawAccommodation {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    choices: Vec<RawChoice>,
+    #[serde(default)]
+    glossary: Vec<Gloss>,
+}
+
+#[derive(Deserialize)]
+struct RawChoice {
+    #[serde(default)]
+    original: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Check the model's rewrite against the original and attach the original key.
+pub fn parse_accommodation(
+    question: &Question,
+    response: &str,
+    options: &AccommodationOptions,
+) -> Result<Accommodation, String> {
+    let raw: RawAccommodation = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&response[start..=end])
+            .map_err(|e| format!("The accommodated version is not valid JSON: {}", e))?,
+        _ => return Err("The model did not return an accommodated version".to_string()),
+    };
+    let text = raw.text.trim().to_string();
+    if text.is_empty() {
+        return Err("The accommodated version has no question text".to_string());
+    }
+
+    let mut kept: Vec<usize> = Vec::new();
+    let mut rewritten: Vec<String> = Vec::new();
+    for choice in &raw.choices {
+        let index = match choices::index_of(&choice.original, question.answers.len()) {
+            Some(index) => index,
+            None => {
+                return Err(format!(
+                    "The accommodated choice {:?} does not name an original choice",
+                    choice.text
+                ))
+            }
+        };
+        if kept.contains(&index) {
+            return Err(format!("Choice {} was rewritten twice", letter(index)));
+        }
+        kept.push(index);
+        rewritten.push(choice.text.trim().to_string());
+    }
+    let dropped_key = question
+        .answers
+        .iter()
+        .enumerate()
+        .any(|(i, answer)| answer.is_correct && !kept.contains(&i));
+    if dropped_key {
+        return Err("The accommodated version dropped the correct answer".to_string());
+    }
+    if kept.len() < 2 {
+        return Err("The accommodated version has fewer than two choices".to_string());
+    }
+    if code_blocks(&question.stem_markdown()) != code_blocks(&text) {
+        return Err("The accommodated version changed the code".to_string());
+    }
+
+    // Keep the original order so a student comparing versions sees the same sequence
+    let mut order: Vec<usize> = (0..kept.len()).collect();
+    order.sort_by_key(|&i| kept[i]);
+    let answers: Vec<Answer> = order
+        .iter()
+        .map(|&i| {
+            let original = &question.answers[kept[i]];
+            Answer {
+                text: if rewritten[i].is_empty() {
+                    original.text.clone()
+                } else {
+                    rewritten[i].clone()
+                },
+                is_correct: original.is_correct,
+                explanation: original.explanation.clone(),
+            }
+        })
+        .collect();
+
+    let mut warnings = Vec::new();
+    let wanted = if options.three_choices {
+        question.answers.len().min(3)
+    } else {
+        question.answers.len()
+    };
+    if answers.len() != wanted {
+        warnings.push(format!(
+            "Has {} choices instead of {}",
+            answers.len(),
+            wanted
+        ));
+    }
+    let readability = Readability {
+        standard: question_grade(&question.stem_markdown(), &question.answers),
+        accommodated: question_grade(&text, &answers),
+    };
+    if let (true, Some(standard), Some(accommodated)) = (
+        options.simplify_language,
+        readability.standard,
+        readability.accommodated,
+    ) {
+        if accommodated >= standard {
+            warnings.push(format!(
+                "Reading level did not drop (grade {:.1} to {:.1})",
+                standard, accommodated
+            ));
+        }
+    }
+    let glossary = if options.gloss_vocabulary {
+        raw.glossary
+            .into_iter()
+            .filter(|g| !g.term.trim().is_empty() && !g.definition.trim().is_empty())
+            .take(MAX_GLOSSARY_TERMS)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Accommodation {
+        text,
+        answers,
+        glossary,
+        options: *options,
+        readability,
+        warnings,
+        job_id: String::new(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Ask the model for an accommodated version of `question`.
+pub async fn accommodate_question(
+    question: &Question,
+    options: &AccommodationOptions,
+    gateway_auth: Option<GatewayAuth>,
+    bypass_cache: bool,
+    job: &Job,
+) -> Result<Accommodation, String> {
+    let prompt = build_prompt(question, options);
+    let stream = StreamTarget::new(None, job)
+        .with_log_context("accommodate", &question.subject, &question.topics)
+        .with_cache_bypass(bypass_cache);
+    let response = llm::generate(&prompt, stream, gateway_auth, job).await?;
+    if job.is_cancelled() {
+        return Err("Accommodation cancelled".to_string());
+    }
+    Ok(Accommodation {
+        job_id: job.id.clone(),
+        ..parse_accommodation(question, &response, options)?
+    })
+}
+
+impl Accommodation {
+    /// The accommodated version as a question for export, with the glossary after the stem.
+    pub fn to_question(&self, standard: &Question) -> Question {
+        let mut text = self.text.clone();
+        if !self.glossary.is_empty() {
+            text.push_str("\n\n**Key words**\n");
+            for gloss in &self.glossary {
+                text.push_str(&format!(
+                    "\n- **{}**: {}",
+                    gloss.term.trim(),
+                    gloss.definition.trim()
+                ));
+            }
+        }
+        Question {
+            text,
+            code: None,
+            answers: self.answers.clone(),
+            accommodation: None,
+            ..standard.clone()
+        }
+    }
+}
+
+/// The accommodated document: each question's accommodated version, or the question
+/// itself when it has none, in the original order.
+pub fn accommodated_set(questions: &[Question]) -> Vec<Question> {
+    questions
+        .iter()
+        .map(|question| match &question.accommodation {
+            Some(accommodation) => accommodation.to_question(question),
+            None => question.clone(),
+        })
+        .collect()
+}
+
+/// Questions for an export, and whether it wants larger spacing: the standard set, or
+/// the accommodated one when `accommodated` is set.
+pub fn export_set(questions: &[Question], accommodated: bool) -> (Vec<Question>, bool) {
+    if !accommodated {
+        return (questions.to_vec(), false);
+    }
+    let larger_spacing = questions
+        .iter()
+        .filter_map(|q| q.accommodation.as_ref())
+        .any(|a| a.options.larger_spacing);
+    (accommodated_set(questions), larger_spacing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::fixtures::question;
+
+    #[test]
+    fn test_grade_level_ignores_code_and_prefers_plain_words() {
+        let hard = "Subsequently, determine the cumulative consequences of the \
+                    aforementioned invocation, considering every intermediate computation.";
+        let easy = "What does the call return? Look at each step.";
+        assert!(grade_level(hard).unwrap() > grade_level(easy).unwrap() + 5.0);
+        assert_eq!(
+            grade_level("```java\nSystem.out.println(\"Subsequently\");\n```"),
+            None
+        );
+        assert_eq!(syllable_count("table"), 2);
+        assert_eq!(syllable_count("value"), 2);
+        assert_eq!(syllable_count("simple"), 2);
+        assert_eq!(syllable_count("code"), 1);
+    }
+
+    #[test]
+    fn test_parse_keeps_original_key_and_code() {
+        let q = question();
+        let code = "```java\nint mystery(int n) {\n    return n * 2;\n}\n```";
+        let response = serde_json::json!({
+            "text": format!("Look at this method.\n\n{}\n\nWhat does `mystery(4)` return?", code),
+            "choices": [
+                {"original": "A", "text": "4"},
+                {"original": "B", "text": "8"},
+                {"original": "C", "text": "16"}
+            ],
+            "glossary": [{"term": "method", "definition": "a named block of code"}]
+        })
+        .to_string();
+
+        let options = AccommodationOptions::default();
+        let accommodation = parse_accommodation(&q, &response, &options).unwrap();
+        let keys: Vec<bool> = accommodation.answers.iter().map(|a| a.is_correct).collect();
+        assert_eq!(keys, [false, true, false]);
+        assert_eq!(
+            accommodation.answers[2].explanation.as_deref(),
+            Some("Squared n")
+        );
+        assert!(
+            accommodation.warnings.is_empty(),
+            "{:?}",
+            accommodation.warnings
+        );
+        let readability = &accommodation.readability;
+        assert!(readability.accommodated.unwrap() < readability.standard.unwrap());
+
+        let exported = accommodated_set(std::slice::from_ref(&Question {
+            accommodation: Some(accommodation),
+            ..q.clone()
+        }));
+        assert!(exported[0]
+            .text
+            .contains("**Key words**\n\n- **method**: a named block"));
+        assert_eq!(exported[0].answers.len(), 3);
+
+        let no_key = response.replace("\"B\"", "\"D\"");
+        assert!(parse_accommodation(&q, &no_key, &options)
+            .unwrap_err()
+            .contains("correct answer"));
+        let changed_code = response.replace("n * 2", "n * 3");
+        assert!(parse_accommodation(&q, &changed_code, &options)
+            .unwrap_err()
+            .contains("code"));
+    }
+
+    #[test]
+    fn test_editing_the_key_drops_the_accommodation() {
+        let q = question();
+        let response = serde_json::json!({
+            "text": "What does `mystery(4)` return?\n\n```java\nint mystery(int n) {\n    return n * 2;\n}\n```",
+            "choices": [{"original": "A", "text": "4"}, {"original": "B", "text": "8"}]
+        })
+        .to_string();
+        let accommodation =
+            parse_accommodation(&q, &response, &AccommodationOptions::default()).unwrap();
+        let accommodated = Question {
+            accommodation: Some(accommodation),
+            ..q
+        };
+
+        let mut retagged = Question {
+            difficulty: "hard".to_string(),
+            ..accommodated.clone()
+        };
+        retagged.clear_stale_results(&accommodated);
+        assert!(retagged.accommodation.is_some());
+
+        let mut rekeyed = accommodated.clone();
+        rekeyed.answers[1].is_correct = false;
+        rekeyed.answers[2].is_correct = true;
+        rekeyed.clear_stale_results(&accommodated);
+        assert!(rekeyed.accommodation.is_none());
+    }
+}
@@ -0,0 +1,531 @@
+//! Assembling a test from the question bank
+//!
+//! `assemble_from_bank` builds a test out of existing bank entries instead of generating
+//! new questions. The request sets a question count, minimum questions per topic, a
+//! difficulty mix, a cap on code-heavy items and entries to leave out (last year's test,
+//! say). [`select`] is greedy: it covers the topic minimums first, then the difficulty
+//! targets, then tops up to the count, and explains every constraint it could not meet.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::{Answer, Provenance, Question, QuestionBankEntry};
+
+/// Lines of fenced code from which a stem counts as code-heavy.
+const CODE_HEAVY_LINES: usize = 4;
+
+const LEVELS: [&str; 3] = ["easy", "medium", "hard"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssemblyRequest {
+    pub subject: String,
+    pub count: u32,
+    /// Topics the test covers; entries outside them are not used. Empty for the whole bank.
+    #[serde(default)]
+    pub topics: Vec<TopicQuota>,
+    #[serde(default)]
+    pub difficulty: DifficultyMix,
+    /// Most questions with a code block of `CODE_HEAVY_LINES` or more lines.
+    #[serde(default)]
+    pub max_code_items: Option<u32>,
+    /// Bank entry ids to leave out.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Files listing more ids to leave out, separated by whitespace or commas.
+    #[serde(default)]
+    pub exclude_files: Vec<String>,
+    #[serde(default)]
+    pub append: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicQuota {
+    pub topic: String,
+    /// Fewest questions on this topic; 0 only keeps the topic in the pool.
+    #[serde(default)]
+    pub min: u32,
+}
+
+/// Questions wanted at each difficulty; a level left out takes whatever fits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DifficultyMix {
+    #[serde(default)]
+    pub easy: Option<u32>,
+    #[serde(default)]
+    pub medium: Option<u32>,
+    #[serde(default)]
+    pub hard: Option<u32>,
+}
+
+impl DifficultyMix {
+    fn targets(&self) -> [Option<u32>; 3] {
+        [self.easy, self.medium, self.hard]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssemblyResult {
+    /// The full question list after loading the selection.
+    pub questions: Vec<Question>,
+    /// Bank ids of the selected entries, in test order.
+    pub selected: Vec<String>,
+    /// One sentence per constraint that could not be met.
+    pub unmet: Vec<String>,
+}
+
+/// A bank entry as the solver sees it.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// `easy`, `medium` or `hard`.
+    pub difficulty: String,
+    /// Indexes into `AssemblyRequest::topics` of the topics the entry covers.
+    pub topics: Vec<usize>,
+    pub code_heavy: bool,
+}
+
+/// The chosen candidates, by index, and the constraints left unmet.
+#[derive(Debug, Clone)]
+pub struct Selection {
+    pub picked: Vec<usize>,
+    pub unmet: Vec<String>,
+}
+
+impl AssemblyRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.count == 0 {
+            return Err("Ask for at least one question".to_string());
+        }
+        let targets = self.difficulty.targets();
+        let targeted: u32 = targets.iter().flatten().sum();
+        // A full mix must add up exactly; a partial one leaves the rest to the other levels
+        if targeted > self.count || (targets.iter().all(Option::is_some) && targeted != self.count)
+        {
+            return Err(format!(
+                "The difficulty mix adds up to {} questions but the test has {}",
+                targeted, self.count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Every id to leave out, from the request and its exclusion files.
+    pub fn exclusions(&self) -> Result<HashSet<String>, String> {
+        let mut ids: HashSet<String> = self
+            .exclude
+            .iter()
+            .map(|id| id.trim().to_string())
+            .collect();
+        for path in &self.exclude_files {
+            let text = std::fs::read_to_string(Path::new(path))
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            ids.extend(parse_ids(&text));
+        }
+        ids.remove("");
+        Ok(ids)
+    }
+}
+
+fn parse_ids(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .map(|id| id.trim_matches(|c| c == '"' || c == '\''))
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+}
+
+/// `easy`, `medium` or `hard` for a bank difficulty code (`D1`..`D3`).
+pub fn difficulty_name(code: &str) -> &'static str {
+    match code.trim() {
+        "D1" => "easy",
+        "D3" => "hard",
+        _ => "medium",
+    }
+}
+
+/// Whether the text has a fenced code block of at least `CODE_HEAVY_LINES` lines.
+pub fn is_code_heavy(text: &str) -> bool {
+    let mut in_fence = false;
+    let mut lines = 0;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            if in_fence && lines >= CODE_HEAVY_LINES {
+                return true;
+            }
+            in_fence = !in_fence;
+            lines = 0;
+        } else if in_fence && !line.trim().is_empty() {
+            lines += 1;
+        }
+    }
+    in_fence && lines >= CODE_HEAVY_LINES
+}
+
+/// A bank entry as an editable question; each wrong option keeps its misconception.
+pub fn to_question(entry: &QuestionBankEntry, subject: &str) -> Question {
+    let misconception = |option_id: &str| {
+        entry
+            .distractors
+            .common_mistakes
+            .iter()
+            .find(|m| m.option_id == option_id)
+            .map(|m| m.misconception.clone())
+    };
+    let common_errors = entry.distractors.common_errors.join("\n");
+    Question {
+        text: entry.text.clone(),
+        answers: entry
+            .options
+            .iter()
+            .map(|option| Answer {
+                text: option.text.clone(),
+                is_correct: option.is_correct,
+                explanation: misconception(&option.id),
+            })
+            .collect(),
+        explanation: Some(entry.explanation.clone()).filter(|e| !e.trim().is_empty()),
+        distractors: Some(common_errors).filter(|e| !e.trim().is_empty()),
+        subject: subject.to_string(),
+        topics: entry.topics.clone(),
+        difficulty: difficulty_name(&entry.difficulty).to_string(),
+        provenance: Some(Provenance {
+            kind: "bank".to_string(),
+            template_version: None,
+            template_hash: None,
+            example_ids: vec![entry.id.clone()],
+            provider: "local".to_string(),
+            model: "question-bank".to_string(),
+            job_id: String::new(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            regeneration_mode: None,
+            seed: None,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Running totals of a selection in progress.
+struct Tally<'a> {
+    request: &'a AssemblyRequest,
+    candidates: &'a [Candidate],
+    picked: Vec<usize>,
+    /// Picked, or off the chosen topics.
+    taken: Vec<bool>,
+    topics: Vec<u32>,
+    levels: [u32; 3],
+    code: u32,
+}
+
+impl<'a> Tally<'a> {
+    fn new(request: &'a AssemblyRequest, candidates: &'a [Candidate]) -> Self {
+        Tally {
+            request,
+            candidates,
+            picked: Vec::new(),
+            taken: vec![false; candidates.len()],
+            topics: vec![0; request.topics.len()],
+            levels: [0; 3],
+            code: 0,
+        }
+    }
+
+    fn level(candidate: &Candidate) -> usize {
+        LEVELS
+            .iter()
+            .position(|l| *l == candidate.difficulty)
+            .unwrap_or(1)
+    }
+
+    /// Questions still owed to difficulty targets.
+    fn target_need(&self) -> u32 {
+        let targets = self.request.difficulty.targets();
+        (0..3)
+            .filter_map(|l| targets[l].map(|t| t.saturating_sub(self.levels[l])))
+            .sum()
+    }
+
+    /// Why the candidate cannot be added now, or `None` when it can.
+    fn blocked_by(&self, index: usize) -> Option<String> {
+        let candidate = &self.candidates[index];
+        let level = Self::level(candidate);
+        let total = self.picked.len() as u32;
+        if total >= self.request.count {
+            return Some("the question count".to_string());
+        }
+        match self.request.difficulty.targets()[level] {
+            Some(target) if self.levels[level] >= target => {
+                return Some(format!("the {} target", LEVELS[level]));
+            }
+            None if total + self.target_need() >= self.request.count => {
+                return Some("the room kept for the difficulty targets".to_string());
+            }
+            _ => {}
+        }
+        match self.request.max_code_items {
+            Some(cap) if candidate.code_heavy && self.code >= cap => {
+                Some("the code-heavy limit".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn open(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.candidates.len()).filter(|&i| !self.taken[i] && self.blocked_by(i).is_none())
+    }
+
+    fn take(&mut self, index: usize) {
+        let candidate = &self.candidates[index];
+        self.taken[index] = true;
+        self.picked.push(index);
+        for &topic in &candidate.topics {
+            self.topics[topic] += 1;
+        }
+        self.levels[Self::level(candidate)] += 1;
+        self.code += u32::from(candidate.code_heavy);
+    }
+
+    fn helps_target(&self, index: usize) -> bool {
+        let level = Self::level(&self.candidates[index]);
+        matches!(self.request.difficulty.targets()[level], Some(t) if self.levels[level] < t)
+    }
+
+    /// Fewest questions so far among the candidate's topics, to spread the test out.
+    fn coverage(&self, index: usize) -> u32 {
+        self.candidates[index]
+            .topics
+            .iter()
+            .map(|&t| self.topics[t])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// "its other 3 entries would exceed the hard target", or that none are left.
+    fn shortfall_reason(&self, matching: impl Fn(&Candidate) -> bool, noun: &str) -> String {
+        let rest: Vec<usize> = (0..self.candidates.len())
+            .filter(|&i| !self.taken[i] && matching(&self.candidates[i]))
+            .collect();
+        if rest.is_empty() {
+            return format!("the bank has no other unused entries for {}", noun);
+        }
+        let mut reasons: Vec<String> = Vec::new();
+        for reason in rest.iter().filter_map(|&i| self.blocked_by(i)) {
+            if !reasons.contains(&reason) {
+                reasons.push(reason);
+            }
+        }
+        format!(
+            "the other {} {} would exceed {}",
+            rest.len(),
+            if rest.len() == 1 { "entry" } else { "entries" },
+            reasons.join(" or ")
+        )
+    }
+}
+
+/// Pick candidates for the request. `labels` names each of `request.topics`.
+pub fn select(request: &AssemblyRequest, labels: &[String], candidates: &[Candidate]) -> Selection {
+    let mut tally = Tally::new(request, candidates);
+    // Only entries on the chosen topics are used
+    for (i, candidate) in candidates.iter().enumerate() {
+        if !request.topics.is_empty() && candidate.topics.is_empty() {
+            tally.taken[i] = true;
+        }
+    }
+
+    // 1. Topic minimums: the entry that covers the most short topics, preferring one
+    //    that also fills a difficulty target
+    loop {
+        let short: Vec<bool> = request
+            .topics
+            .iter()
+            .zip(&tally.topics)
+            .map(|(quota, have)| *have < quota.min)
+            .collect();
+        let best = tally
+            .open()
+            .map(|i| {
+                let covers = candidates[i].topics.iter().filter(|&&t| short[t]).count();
+                (i, covers, tally.helps_target(i))
+            })
+            .filter(|(_, covers, _)| *covers > 0)
+            .max_by_key(|&(i, covers, helps)| (covers, helps, std::cmp::Reverse(i)));
+        match best {
+            Some((i, _, _)) => tally.take(i),
+            None => break,
+        }
+    }
+
+    // 2. Difficulty targets, then 3. the rest of the count, favouring thin topics
+    for targets_only in [true, false] {
+        loop {
+            let best = tally
+                .open()
+                .filter(|&i| !targets_only || tally.helps_target(i))
+                .min_by_key(|&i| (tally.coverage(i), i));
+            match best {
+                Some(i) => tally.take(i),
+                None => break,
+            }
+        }
+    }
+
+    let mut unmet = Vec::new();
+    let total = tally.picked.len() as u32;
+    if total < request.count {
+        unmet.push(format!(
+            "Only {} of {} questions were selected; {}",
+            total,
+            request.count,
+            tally.shortfall_reason(
+                |_| true,
+                if request.topics.is_empty() {
+                    "this subject"
+                } else {
+                    "these topics"
+                }
+            )
+        ));
+    }
+    for (index, quota) in request.topics.iter().enumerate() {
+        if tally.topics[index] < quota.min {
+            let label = labels.get(index).unwrap_or(&quota.topic);
+            unmet.push(format!(
+                "{}: {} of {} questions; {}",
+                label,
+                tally.topics[index],
+                quota.min,
+                tally.shortfall_reason(|c| c.topics.contains(&index), "it")
+            ));
+        }
+    }
+    for (level, target) in request.difficulty.targets().into_iter().enumerate() {
+        match target {
+            Some(target) if tally.levels[level] < target => unmet.push(format!(
+                "{} {} questions asked for, {} selected; {}",
+                target,
+                LEVELS[level],
+                tally.levels[level],
+                tally.shortfall_reason(
+                    |c| c.difficulty == LEVELS[level],
+                    &format!("{} questions on these topics", LEVELS[level])
+                )
+            )),
+            _ => {}
+        }
+    }
+
+    Selection {
+        picked: tally.picked,
+        unmet,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(value: serde_json::Value) -> AssemblyRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn candidate(difficulty: &str, topics: &[usize], code_heavy: bool) -> Candidate {
+        Candidate {
+            difficulty: difficulty.to_string(),
+            topics: topics.to_vec(),
+            code_heavy,
+        }
+    }
+
+    fn picked(selection: &Selection) -> Vec<usize> {
+        let mut picked = selection.picked.clone();
+        picked.sort();
+        picked
+    }
+
+    #[test]
+    fn test_select_meets_topics_difficulty_and_code_cap() {
+        let request = request(serde_json::json!({
+            "subject": "Computer Science",
+            "count": 4,
+            "topics": [{"topic": "T006", "min": 2}, {"topic": "T009", "min": 1}],
+            "difficulty": {"easy": 1, "hard": 1},
+            "max_code_items": 1
+        }));
+        assert!(request.validate().is_ok());
+        let candidates = [
+            candidate("medium", &[0], true),
+            candidate("medium", &[0], true),
+            candidate("easy", &[0, 1], false),
+            candidate("hard", &[1], false),
+            candidate("medium", &[0], false),
+            candidate("easy", &[], false),
+        ];
+
+        let selection = select(
+            &request,
+            &["Arrays".into(), "Recursion".into()],
+            &candidates,
+        );
+        assert!(selection.unmet.is_empty(), "{:?}", selection.unmet);
+        assert_eq!(picked(&selection), [0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_select_explains_unmet_constraints() {
+        let request = request(serde_json::json!({
+            "subject": "Computer Science",
+            "count": 3,
+            "topics": [{"topic": "T006", "min": 3}],
+            "difficulty": {"hard": 2},
+            "max_code_items": 0
+        }));
+        let candidates = [
+            candidate("hard", &[0], false),
+            candidate("medium", &[0], false),
+            candidate("hard", &[0], true),
+        ];
+
+        let selection = select(&request, &["Arrays".into()], &candidates);
+        assert_eq!(picked(&selection), [0, 1]);
+        assert_eq!(
+            selection.unmet,
+            [
+                "Only 2 of 3 questions were selected; the other 1 entry would exceed the code-heavy limit",
+                "Arrays: 2 of 3 questions; the other 1 entry would exceed the code-heavy limit",
+                "2 hard questions asked for, 1 selected; the other 1 entry would exceed the code-heavy limit",
+            ]
+        );
+
+        let mismatched = AssemblyRequest {
+            difficulty: DifficultyMix {
+                easy: Some(1),
+                medium: Some(1),
+                hard: Some(2),
+            },
+            ..request
+        };
+        assert!(mismatched.validate().unwrap_err().contains("adds up to 4"));
+    }
+
+    #[test]
+    fn test_code_heavy_and_exclusion_files() {
+        let trace = "What is printed?\n\n```java\nint a = 1;\nint b = 2;\na += b;\nSystem.out.print(a);\n```";
+        assert!(is_code_heavy(trace));
+        assert!(!is_code_heavy(
+            "Which is valid?\n\n```java\nint[] a = new int[3];\n```"
+        ));
+        assert!(!is_code_heavy("No code here"));
+
+        let path = std::env::temp_dir().join(format!("exclude-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "pt1_q001, pt1_q004\n\"pt1_q011\"\n").unwrap();
+        let request = request(serde_json::json!({
+            "subject": "Computer Science",
+            "count": 5,
+            "exclude": ["pt1_q020 "],
+            "exclude_files": [path.to_string_lossy()]
+        }));
+        let mut excluded: Vec<String> = request.exclusions().unwrap().into_iter().collect();
+        excluded.sort();
+        assert_eq!(excluded, ["pt1_q001", "pt1_q004", "pt1_q011", "pt1_q020"]);
+        let _ = std::fs::remove_file(path);
+    }
+}
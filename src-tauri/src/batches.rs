@@ -0,0 +1,116 @@
+//! Splitting large generation requests
+//!
+//! One prompt asking for 30 questions tends to end in truncated JSON or a timeout, so
+//! `generate_questions` asks for at most `RUBRIX_GENERATION_BATCH_SIZE` questions
+//! (default 8) per call. The first batch runs alone; the rest run concurrently and are
+//! each told the stems produced so far. [`dedupe`] drops questions that came back from
+//! two batches anyway. Batches that fail are listed in [`GenerationRun::failed`] while
+//! the questions of the others are kept.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::Question;
+
+const DEFAULT_BATCH_SIZE: usize = 8;
+
+/// Batches that run at once after the first one.
+pub const BATCH_CONCURRENCY: usize = 4;
+
+/// Emitted as `generation-progress` each time a batch of a split generation finishes.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationProgressEvent {
+    pub job_id: String,
+    /// Batches finished so far, including failed ones.
+    pub completed: usize,
+    pub total: usize,
+    /// Questions produced by the finished batches.
+    pub questions: usize,
+}
+
+/// A batch that produced no questions.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFailure {
+    /// Batch number within the job, from 0.
+    pub batch: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationRun {
+    /// The full question list with the new questions added.
+    pub questions: Vec<Question>,
+    pub failed: Vec<BatchFailure>,
+}
+
+pub fn batch_size() -> usize {
+    std::env::var("RUBRIX_GENERATION_BATCH_SIZE")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+}
+
+/// Question counts for each batch, as even as possible and none above `size`.
+pub fn split(count: usize, size: usize) -> Vec<usize> {
+    if count == 0 {
+        return vec![0];
+    }
+    let batches = count.div_ceil(size.max(1));
+    (0..batches)
+        .map(|i| count / batches + usize::from(i < count % batches))
+        .collect()
+}
+
+/// Drop questions whose stem repeats an earlier one, ignoring case, spacing and
+/// punctuation.
+pub fn dedupe(questions: Vec<Question>) -> Vec<Question> {
+    let mut seen = HashSet::new();
+    questions
+        .into_iter()
+        .filter(|question| {
+            let key: String = question
+                .stem_markdown()
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect();
+            key.is_empty() || seen.insert(key)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_evenly() {
+        assert_eq!(split(5, 8), [5]);
+        assert_eq!(split(8, 8), [8]);
+        assert_eq!(split(30, 8), [8, 8, 7, 7]);
+        assert_eq!(split(9, 8), [5, 4]);
+        assert_eq!(split(3, 0), [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_dedupe_ignores_formatting() {
+        let questions: Vec<Question> = serde_json::from_value(serde_json::json!([
+            {"text": "What does `f(3)` return?", "answers": []},
+            {"text": "what does f(3) return", "answers": []},
+            {"text": "What does f(3) return?", "code": "int f(int n) { return n; }", "answers": []},
+            {"text": "What does f(4) return?", "answers": []}
+        ]))
+        .unwrap();
+
+        let kept: Vec<String> = dedupe(questions).into_iter().map(|q| q.text).collect();
+        assert_eq!(
+            kept,
+            [
+                "What does `f(3)` return?",
+                "What does f(3) return?",
+                "What does f(4) return?"
+            ]
+        );
+    }
+}
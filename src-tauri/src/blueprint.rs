@@ -0,0 +1,271 @@
+//! Test blueprints
+//!
+//! A blueprint lists the mix of a whole test as rows of topics, difficulty, count,
+//! cognitive level and notes ("4 easy arrays, 3 medium ArrayList, 3 hard 2D arrays").
+//! `generate_from_blueprint` runs every row as its own generation request in one job,
+//! with bank examples chosen for that row, and tags each question with its row.
+//!
+//! Blueprints are saved as JSON (`*.blueprint.json`) so the same test can be rebuilt
+//! next year.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::batches::BatchFailure;
+use crate::{GenerationRequest, Question};
+
+/// Rows that generate at once; each row may split further into batches. The job's
+/// gateway calls are capped as a whole, however many rows and batches are running.
+pub const ROW_CONCURRENCY: usize = 4;
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Bloom's taxonomy levels as `(bank code, name)`; the question bank uses the codes.
+pub const COGNITIVE_LEVELS: [(&str, &str); 6] = [
+    ("B1", "remember"),
+    ("B2", "understand"),
+    ("B3", "apply"),
+    ("B4", "analyze"),
+    ("B5", "evaluate"),
+    ("B6", "create"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blueprint {
+    #[serde(default = "format_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub name: String,
+    pub subject: String,
+    pub rows: Vec<BlueprintRow>,
+}
+
+fn format_version() -> u32 {
+    FORMAT_VERSION
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintRow {
+    pub topics: Vec<String>,
+    pub difficulty: String,
+    pub count: u32,
+    /// A Bloom's level name or bank code (`apply`, `B3`); any level when absent.
+    #[serde(default)]
+    pub cognitive_level: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// A blueprint row that produced no questions.
+#[derive(Debug, Clone, Serialize)]
+pub struct RowFailure {
+    /// 1-based row number.
+    pub row: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlueprintRun {
+    /// The full question list with the new questions added.
+    pub questions: Vec<Question>,
+    pub failed: Vec<RowFailure>,
+    /// Batches that produced no questions, in rows that were split.
+    pub failed_batches: Vec<BatchFailure>,
+}
+
+/// The blueprint row a question was generated from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintTag {
+    /// Blueprint name; empty for an unnamed blueprint.
+    #[serde(default)]
+    pub name: String,
+    /// 1-based row number.
+    pub row: usize,
+    /// Short description of the row, e.g. "Arrays · hard · apply".
+    #[serde(default)]
+    pub label: String,
+}
+
+impl Blueprint {
+    /// Reject blueprints that cannot be generated, naming the offending row.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.subject.trim().is_empty() {
+            return Err("The blueprint has no subject".to_string());
+        }
+        if self.rows.is_empty() {
+            return Err("The blueprint has no rows".to_string());
+        }
+        for (i, row) in self.rows.iter().enumerate() {
+            if row.topics.is_empty() {
+                return Err(format!("Blueprint row {} has no topics", i + 1));
+            }
+            if row.count == 0 {
+                return Err(format!("Blueprint row {} asks for no questions", i + 1));
+            }
+            if let Some(level) = row.cognitive_level.as_deref() {
+                if !level.trim().is_empty() && level_code(level).is_none() {
+                    return Err(format!(
+                        "Blueprint row {} has an unknown cognitive level \"{}\"",
+                        i + 1,
+                        level
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The generation request for one row.
+    pub fn request(&self, row: &BlueprintRow) -> GenerationRequest {
+        let mut notes = row.notes.clone().unwrap_or_default().trim().to_string();
+        let level = row.cognitive_level.as_deref().and_then(level_code);
+        if let Some((code, name)) = level {
+            if !notes.is_empty() {
+                notes.push_str("\n\n");
+            }
+            notes.push_str(&format!(
+                "Target cognitive level: {} ({} in Bloom's taxonomy) for every question.",
+                name, code
+            ));
+        }
+        GenerationRequest {
+            subject: self.subject.clone(),
+            topics: row.topics.clone(),
+            difficulty: row.difficulty.clone(),
+            count: row.count,
+            notes: (!notes.is_empty()).then_some(notes),
+            append: true,
+            sources: Vec::new(),
+            cognitive_level: level.map(|(code, _)| code.to_string()),
+        }
+    }
+
+    /// Tag for questions from row `index` (0-based); `topics_label` names its topics.
+    pub fn tag(&self, index: usize, topics_label: &str) -> BlueprintTag {
+        let row = &self.rows[index];
+        let mut parts = vec![topics_label.to_string(), row.difficulty.clone()];
+        if let Some((_, name)) = row.cognitive_level.as_deref().and_then(level_code) {
+            parts.push(name.to_string());
+        }
+        BlueprintTag {
+            name: self.name.trim().to_string(),
+            row: index + 1,
+            label: parts
+                .into_iter()
+                .filter(|p| !p.is_empty())
+                .collect::<Vec<_>>()
+                .join(" · "),
+        }
+    }
+}
+
+/// The `(code, name)` of a cognitive level given as either.
+pub fn level_code(level: &str) -> Option<(&'static str, &'static str)> {
+    let level = level.trim();
+    COGNITIVE_LEVELS
+        .iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(level) || name.eq_ignore_ascii_case(level))
+        .copied()
+}
+
+/// Whether a bank entry's cognitive level is the one a row asks for.
+pub fn same_level(entry_level: &str, wanted: &str) -> bool {
+    matches!((level_code(entry_level), level_code(wanted)), (Some(a), Some(b)) if a == b)
+}
+
+pub fn load(path: &Path) -> Result<Blueprint, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let blueprint: Blueprint = serde_json::from_str(&text)
+        .map_err(|e| format!("{} is not a valid blueprint: {}", path.display(), e))?;
+    if blueprint.version > FORMAT_VERSION {
+        return Err(format!(
+            "{} was saved by a newer version of the app (blueprint format {})",
+            path.display(),
+            blueprint.version
+        ));
+    }
+    blueprint.validate()?;
+    Ok(blueprint)
+}
+
+pub fn save(path: &Path, blueprint: &Blueprint) -> Result<(), String> {
+    blueprint.validate()?;
+    let blueprint = Blueprint {
+        version: FORMAT_VERSION,
+        ..blueprint.clone()
+    };
+    let json = serde_json::to_string_pretty(&blueprint).map_err(|e| e.to_string())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blueprint() -> Blueprint {
+        serde_json::from_value(serde_json::json!({
+            "name": "Unit 6 test",
+            "subject": "Computer Science",
+            "rows": [
+                {"topics": ["arrays"], "difficulty": "easy", "count": 4},
+                {
+                    "topics": ["2d-arrays"],
+                    "difficulty": "hard",
+                    "count": 3,
+                    "cognitive_level": "B3",
+                    "notes": "Use row-major traversal."
+                }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rows_become_requests_and_tags() {
+        let blueprint = blueprint();
+        assert!(blueprint.validate().is_ok());
+
+        let plain = blueprint.request(&blueprint.rows[0]);
+        assert_eq!(plain.count, 4);
+        assert!(plain.notes.is_none());
+        assert!(plain.cognitive_level.is_none());
+
+        let leveled = blueprint.request(&blueprint.rows[1]);
+        assert_eq!(leveled.difficulty, "hard");
+        assert_eq!(leveled.cognitive_level.as_deref(), Some("B3"));
+        let notes = leveled.notes.unwrap();
+        assert!(notes.starts_with("Use row-major traversal."));
+        assert!(notes.contains("apply (B3"));
+
+        let tag = blueprint.tag(1, "2D Arrays");
+        assert_eq!(tag.row, 2);
+        assert_eq!(tag.name, "Unit 6 test");
+        assert_eq!(tag.label, "2D Arrays · hard · apply");
+        assert!(same_level("apply", "B3"));
+        assert!(!same_level("", "B3"));
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = std::env::temp_dir().join(format!("blueprint-test-{}", std::process::id()));
+        let path = dir.join("unit6.blueprint.json");
+        save(&path, &blueprint()).unwrap();
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.version, FORMAT_VERSION);
+        assert_eq!(loaded.rows.len(), 2);
+        assert_eq!(
+            loaded.rows[1].notes.as_deref(),
+            Some("Use row-major traversal.")
+        );
+
+        let mut invalid = blueprint();
+        invalid.rows[0].cognitive_level = Some("memorize".to_string());
+        assert!(save(&path, &invalid).unwrap_err().contains("row 1"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
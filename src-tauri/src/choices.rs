@@ -0,0 +1,73 @@
+//! Lettered answer choices
+//!
+//! Choices are shown to students and to the model as A, B, C and so on. The checkers
+//! and the model-facing prompts name choices through [`letter`], and read a letter back
+//! from a model reply with [`index_of`].
+
+/// Letter of the choice at `index`: 0 is `A`.
+pub fn letter(index: usize) -> String {
+    char::from(b'A' + (index % 26) as u8).to_string()
+}
+
+/// Index of the choice a reply names, e.g. `B`, `b.` or `C)`, when it is one of the
+/// first `count` choices.
+pub fn index_of(reply: &str, count: usize) -> Option<usize> {
+    let reply = reply
+        .trim()
+        .trim_end_matches(['.', ')'])
+        .to_ascii_uppercase();
+    match reply.as_bytes() {
+        [c @ b'A'..=b'Z'] if usize::from(c - b'A') < count => Some(usize::from(c - b'A')),
+        _ => None,
+    }
+}
+
+/// Test setup shared by the modules that check or rewrite questions.
+#[cfg(test)]
+pub mod fixtures {
+    use crate::Question;
+
+    /// `(text, is_correct)` pairs, the shape the local checkers take, with the choice
+    /// at `correct` marked.
+    pub fn choices(texts: &[&str], correct: usize) -> Vec<(String, bool)> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (text.to_string(), i == correct))
+            .collect()
+    }
+
+    /// A code-tracing question with four choices, B correct, and an explanation and
+    /// rationales that must not leak into student-facing prompts.
+    pub fn question() -> Question {
+        serde_json::from_value(serde_json::json!({
+            "text": "Consider the following method. Subsequently, what value is returned by the invocation `mystery(4)`?",
+            "code": "int mystery(int n) {\n    return n * 2;\n}",
+            "answers": [
+                {"text": "4", "is_correct": false, "explanation": "Returned n unchanged"},
+                {"text": "8", "is_correct": true},
+                {"text": "16", "is_correct": false, "explanation": "Squared n"},
+                {"text": "An exception is thrown", "is_correct": false}
+            ],
+            "explanation": "The method doubles its argument."
+        }))
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letters_round_trip() {
+        assert_eq!(letter(0), "A");
+        assert_eq!(letter(3), "D");
+        assert_eq!(index_of(&letter(2), 4), Some(2));
+        assert_eq!(index_of(" b. ", 4), Some(1));
+        assert_eq!(index_of("C)", 4), Some(2));
+        assert_eq!(index_of("E", 4), None);
+        assert_eq!(index_of("AB", 4), None);
+        assert_eq!(index_of("", 4), None);
+    }
+}
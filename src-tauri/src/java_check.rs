@@ -0,0 +1,852 @@
+//! Compile-and-run checks for Java code-tracing questions
+//!
+//! Most AP CS A items show a snippet in a fenced ```java block and ask what it prints.
+//! [`check`] pulls the code out of the question text and wraps it in a `Main` class:
+//! methods become static members, and loose statements, followed by the call named in
+//! the stem (`the call mystery(3)`), become the body of `main`. A call to a non-void
+//! method prints its return value. The program is compiled and run with the local JDK,
+//! and its output (or the name of the exception it throws) is compared with the choices.
+//!
+//! The JDK is found through `RUBRIX_JAVA_HOME`, then `JAVA_HOME`, then `PATH`. Each run
+//! is limited to `RUBRIX_JAVA_TIMEOUT_SECS` (default 5), a 64 MB heap and 64 KB of
+//! output. Outcomes of recent runs are memoised per generated source, so checking again
+//! before an export only runs code that changed.
+//!
+//! The code is written by a model, so it runs sandboxed: in a throwaway directory, under
+//! a security policy that grants nothing (no files, network, processes, environment or
+//! system properties), and in its own process group that is killed as a whole. On Linux
+//! it also gets an empty network namespace where the kernel allows one. JDK 24 removed
+//! the security manager, so with a newer JDK the code is not run at all.
+
+use crate::choices::letter;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+const COMPILE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Run outcomes remembered; the oldest are forgotten first.
+const MAX_REMEMBERED_OUTCOMES: usize = 512;
+
+/// Grants the program nothing beyond what the JDK gives every class: it can compute
+/// and print, but not touch files, sockets, processes, environment or properties.
+const SANDBOX_POLICY: &str = "grant {\n};\n";
+/// Variables that would let the environment add JVM options behind our back.
+const JVM_OPTION_VARS: [&str; 4] = [
+    "JAVA_TOOL_OPTIONS",
+    "_JAVA_OPTIONS",
+    "JDK_JAVA_OPTIONS",
+    "CLASSPATH",
+];
+
+static OUTCOMES: Lazy<Mutex<Outcomes>> = Lazy::new(|| Mutex::new(Outcomes::default()));
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeCheckStatus {
+    /// The output equals a choice marked correct.
+    Match,
+    /// The output equals a choice that is not marked correct.
+    Mismatch,
+    /// The program ran but its output equals none of the choices.
+    NoChoiceMatches,
+    CompileError,
+    RuntimeError,
+    Timeout,
+    /// Nothing could be run: no entry point, or no JDK.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeCheck {
+    pub status: CodeCheckStatus,
+    /// Captured standard output.
+    #[serde(default)]
+    pub output: String,
+    /// Compiler or runtime error, or why the check was skipped.
+    #[serde(default)]
+    pub detail: String,
+    /// Letter of the choice the output matched.
+    #[serde(default)]
+    pub matched_choice: Option<String>,
+    pub checked_at: String,
+}
+
+impl CodeCheck {
+    fn new(status: CodeCheckStatus, output: String, detail: String) -> Self {
+        CodeCheck {
+            status,
+            output,
+            detail,
+            matched_choice: None,
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Whether running the code contradicts the answer key.
+    pub fn is_flagged(&self) -> bool {
+        matches!(
+            self.status,
+            CodeCheckStatus::Mismatch | CodeCheckStatus::NoChoiceMatches
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct JavaConfig {
+    bin_dir: Option<PathBuf>,
+    timeout: Duration,
+}
+
+impl JavaConfig {
+    fn from_env() -> Self {
+        let _ = dotenvy::dotenv();
+        let bin_dir = ["RUBRIX_JAVA_HOME", "JAVA_HOME"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .map(|home| home.trim().to_string())
+            .find(|home| !home.is_empty())
+            .map(|home| PathBuf::from(home).join("bin"));
+        let timeout = std::env::var("RUBRIX_JAVA_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(5);
+        JavaConfig {
+            bin_dir,
+            timeout: Duration::from_secs(timeout),
+        }
+    }
+
+    fn tool(&self, name: &str) -> PathBuf {
+        match &self.bin_dir {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+}
+
+/// Check a question's code against its choices, given as `(text, is_correct)`.
+/// Returns `None` when the text has no Java code block.
+pub fn check(text: &str, choices: &[(String, bool)]) -> Option<CodeCheck> {
+    let code = extract_java(text)?;
+    let program = match build_program(&code, &entry_call(text)) {
+        Ok(program) => program,
+        Err(reason) => {
+            return Some(CodeCheck::new(
+                CodeCheckStatus::Skipped,
+                String::new(),
+                reason,
+            ))
+        }
+    };
+    let outcome = run_cached(&program, &JavaConfig::from_env());
+    Some(compare(outcome, choices))
+}
+
+/// Run [`check`] for many questions, a few JVMs at a time, off the async runtime.
+pub async fn check_all(items: Vec<(String, Vec<(String, bool)>)>) -> Vec<Option<CodeCheck>> {
+    use futures_util::stream::{self, StreamExt};
+
+    let concurrency = thread::available_parallelism()
+        .map(|n| n.get().clamp(1, 4))
+        .unwrap_or(2);
+    stream::iter(items.into_iter().map(|(text, choices)| async move {
+        tokio::task::spawn_blocking(move || check(&text, &choices))
+            .await
+            .unwrap_or_else(|err| {
+                Some(CodeCheck::new(
+                    CodeCheckStatus::Skipped,
+                    String::new(),
+                    format!("Code check crashed: {}", err),
+                ))
+            })
+    }))
+    .buffered(concurrency)
+    .collect()
+    .await
+}
+
+/// Concatenate the fenced Java blocks in `text`. Unlabelled fences count when they
+/// look like Java.
+fn extract_java(text: &str) -> Option<String> {
+    static FENCE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?s)```[ \t]*([A-Za-z]*)[^\n]*\n(.*?)```").unwrap());
+    let blocks: Vec<&str> = FENCE
+        .captures_iter(text)
+        .filter(|caps| {
+            let lang = caps[1].to_ascii_lowercase();
+            lang == "java"
+                || (lang.is_empty() && (caps[2].contains("System.out") || caps[2].contains(';')))
+        })
+        .map(|caps| caps.get(2).map_or("", |m| m.as_str()))
+        .collect();
+    if blocks.is_empty() {
+        None
+    } else {
+        Some(blocks.join("\n"))
+    }
+}
+
+/// The call the stem asks about, e.g. `mystery(3)` in "returned by the call mystery(3)".
+fn entry_call(text: &str) -> Option<String> {
+    static CALL: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"\bcall(?:ing)?\s+(?:to\s+)?`?([A-Za-z_]\w*\s*\([^()`\n]*\))").unwrap()
+    });
+    let stem = text.split("```").step_by(2).collect::<Vec<_>>().join(" ");
+    CALL.captures(&stem).map(|caps| caps[1].to_string())
+}
+
+struct Method {
+    name: String,
+    returns_value: bool,
+    source: String,
+}
+
+/// Wrap a snippet in a runnable `Main` class.
+fn build_program(code: &str, call: &Option<String>) -> Result<String, String> {
+    static CLASS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bclass\s+(\w+)").unwrap());
+    static MAIN: Lazy<Regex> = Lazy::new(|| Regex::new(r"static\s+void\s+main\s*\(").unwrap());
+
+    if CLASS.is_match(code) {
+        // The class declared last before `main` owns it
+        let name = MAIN.find(code).and_then(|main| {
+            CLASS
+                .captures_iter(&code[..main.start()])
+                .last()
+                .map(|caps| caps[1].to_string())
+        });
+        let Some(name) = name else {
+            return Err("The code declares classes but none has a main method".to_string());
+        };
+        // It is renamed so every program compiles as Main.java
+        let renamed = Regex::new(&format!(r"\b{}\b", regex::escape(&name)))
+            .map_err(|e| e.to_string())?
+            .replace_all(code, "Main");
+        let public_main = Regex::new(r"public\s+class\s+Main\b").unwrap();
+        let program = if public_main.is_match(&renamed) {
+            renamed.into_owned()
+        } else {
+            renamed.replacen("class Main", "public class Main", 1)
+        };
+        return Ok(format!("import java.util.*;\n\n{}\n", program));
+    }
+
+    let (methods, statements) = split_members(code);
+    let mut body = statements.trim_end().to_string();
+    if let Some(call) = call {
+        let name = call.split('(').next().unwrap_or_default().trim();
+        if let Some(method) = methods.iter().find(|m| m.name == name) {
+            let invocation = if method.returns_value {
+                format!("System.out.print({});", call)
+            } else {
+                format!("{};", call)
+            };
+            if !body.contains(call.as_str()) {
+                body.push('\n');
+                body.push_str(&invocation);
+            }
+        }
+    }
+    if body.trim().is_empty() {
+        return Err(
+            "No statements to run and no call such as `mystery(3)` named in the question"
+                .to_string(),
+        );
+    }
+
+    let mut program = String::from("import java.util.*;\n\npublic class Main {\n");
+    for method in &methods {
+        program.push_str(&method.source);
+        program.push_str("\n\n");
+    }
+    program.push_str("    public static void main(String[] args) throws Exception {\n");
+    for line in body.lines() {
+        program.push_str("        ");
+        program.push_str(line);
+        program.push('\n');
+    }
+    program.push_str("    }\n}\n");
+    Ok(program)
+}
+
+/// Separate top-level method declarations (made static) from loose statements.
+fn split_members(code: &str) -> (Vec<Method>, String) {
+    static HEADER: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"^\s*((?:(?:public|private|protected|static|final|synchronized)\s+)*)([\w<>\[\],.? ]+?)\s+(\w+)\s*\([^;]*\)\s*(?:throws\s+[\w.,\s]+?)?\s*(?:\{.*)?$",
+        )
+        .unwrap()
+    });
+    const NOT_TYPES: [&str; 8] = [
+        "return", "new", "else", "if", "for", "while", "switch", "do",
+    ];
+
+    let mut methods = Vec::new();
+    let mut statements = String::new();
+    let mut current: Option<(Method, i32, bool)> = None;
+
+    for line in code.lines() {
+        let opens = line.matches('{').count() as i32;
+        let closes = line.matches('}').count() as i32;
+
+        if let Some((method, depth, opened)) = current.as_mut() {
+            method.source.push('\n');
+            method.source.push_str(line);
+            *depth += opens - closes;
+            *opened |= opens > 0;
+            if *opened && *depth <= 0 {
+                methods.push(current.take().unwrap().0);
+            }
+            continue;
+        }
+
+        let header = HEADER.captures(line).filter(|caps| {
+            let return_type = caps[2].trim();
+            !NOT_TYPES.contains(&return_type)
+                && !NOT_TYPES.contains(&&caps[3])
+                && !return_type.contains('.')
+        });
+        match header {
+            Some(caps) => {
+                let modifiers: Vec<&str> = caps[1]
+                    .split_whitespace()
+                    .filter(|m| !matches!(*m, "public" | "private" | "protected" | "static"))
+                    .collect();
+                let signature = line.trim_start()[caps[1].len()..].to_string();
+                let mut source = String::from("    static ");
+                for modifier in modifiers {
+                    source.push_str(modifier);
+                    source.push(' ');
+                }
+                source.push_str(&signature);
+                let method = Method {
+                    name: caps[3].to_string(),
+                    returns_value: caps[2].trim() != "void",
+                    source,
+                };
+                if opens > 0 && opens == closes {
+                    methods.push(method);
+                } else {
+                    current = Some((method, opens - closes, opens > 0));
+                }
+            }
+            None => {
+                statements.push_str(line);
+                statements.push('\n');
+            }
+        }
+    }
+    if let Some((method, _, _)) = current {
+        methods.push(method);
+    }
+    (methods, statements)
+}
+
+#[derive(Debug, Clone)]
+enum RunOutcome {
+    Output(String),
+    CompileError(String),
+    RuntimeError { output: String, error: String },
+    Timeout(String),
+    Unavailable(String),
+}
+
+/// Outcomes by program hash, in insertion order so the oldest can be dropped.
+#[derive(Default)]
+struct Outcomes {
+    by_program: HashMap<String, RunOutcome>,
+    order: VecDeque<String>,
+}
+
+impl Outcomes {
+    fn get(&self, key: &str) -> Option<&RunOutcome> {
+        self.by_program.get(key)
+    }
+
+    fn insert(&mut self, key: String, outcome: RunOutcome) {
+        if self.by_program.insert(key.clone(), outcome).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > MAX_REMEMBERED_OUTCOMES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_program.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn run_cached(program: &str, config: &JavaConfig) -> RunOutcome {
+    let key = format!("{:x}", Sha256::digest(program.as_bytes()));
+    if let Some(outcome) = OUTCOMES.lock().unwrap().get(&key) {
+        return outcome.clone();
+    }
+    let outcome = run_program(program, config);
+    // A missing JDK may be installed later, so only real runs are remembered
+    if !matches!(outcome, RunOutcome::Unavailable(_)) {
+        OUTCOMES.lock().unwrap().insert(key, outcome.clone());
+    }
+    outcome
+}
+
+fn run_program(program: &str, config: &JavaConfig) -> RunOutcome {
+    let dir = std::env::temp_dir().join(format!(
+        "rubrix-java-{}-{}",
+        std::process::id(),
+        RUN_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let outcome = (|| {
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            RunOutcome::Unavailable(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+        std::fs::write(dir.join("Main.java"), program)
+            .map_err(|e| RunOutcome::Unavailable(format!("Failed to write Main.java: {}", e)))?;
+        std::fs::write(dir.join("sandbox.policy"), SANDBOX_POLICY).map_err(|e| {
+            RunOutcome::Unavailable(format!("Failed to write the sandbox policy: {}", e))
+        })?;
+
+        // No annotation processors, so compiling cannot run any of the code
+        let mut javac = Command::new(config.tool("javac"));
+        javac
+            .args([
+                "-J-Xmx256m",
+                "-J-XX:-UsePerfData",
+                "-proc:none",
+                "-nowarn",
+                "-encoding",
+                "UTF-8",
+                "Main.java",
+            ])
+            .current_dir(&dir);
+        let compiled = run_limited(javac, COMPILE_TIMEOUT).map_err(unavailable)?;
+        match compiled.status {
+            Some(status) if status.success() => {}
+            Some(_) => return Err(RunOutcome::CompileError(compiled.stderr)),
+            None => return Err(RunOutcome::Timeout(String::new())),
+        }
+
+        let mut java = Command::new(config.tool("java"));
+        java.args([
+            "-Xmx64m",
+            "-Xss8m",
+            "-XX:+UseSerialGC",
+            "-XX:TieredStopAtLevel=1",
+            "-XX:-UsePerfData",
+            "-Djava.security.manager=default",
+            "-Djava.security.policy==sandbox.policy",
+            "-Djava.io.tmpdir=.",
+            "-cp",
+            ".",
+            "Main",
+        ])
+        .current_dir(&dir);
+        let ran = run_limited(java, config.timeout).map_err(unavailable)?;
+        let error = without_sandbox_warnings(&ran.stderr);
+        Ok(match ran.status {
+            Some(status) if status.success() => RunOutcome::Output(ran.stdout),
+            Some(_) if error.contains("Security Manager is not supported") => {
+                RunOutcome::Unavailable(
+                    "This JDK cannot sandbox the code (JDK 24 removed the security manager); \
+                     point RUBRIX_JAVA_HOME at JDK 23 or older"
+                        .to_string(),
+                )
+            }
+            Some(_) => RunOutcome::RuntimeError {
+                output: ran.stdout,
+                error,
+            },
+            None => RunOutcome::Timeout(ran.stdout),
+        })
+    })()
+    .unwrap_or_else(|outcome| outcome);
+    let _ = std::fs::remove_dir_all(&dir);
+    outcome
+}
+
+fn unavailable(err: io::Error) -> RunOutcome {
+    if err.kind() == io::ErrorKind::NotFound {
+        RunOutcome::Unavailable(
+            "No JDK found; install one or set JAVA_HOME (or RUBRIX_JAVA_HOME)".to_string(),
+        )
+    } else {
+        RunOutcome::Unavailable(format!("Failed to start the JDK: {}", err))
+    }
+}
+
+/// Drop the JVM's deprecation notice for the security manager, which every sandboxed
+/// run prints.
+fn without_sandbox_warnings(stderr: &str) -> String {
+    stderr
+        .lines()
+        .filter(|line| !(line.starts_with("WARNING: ") && line.contains("Security Manager")))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+struct Captured {
+    /// `None` when the process was killed at the timeout.
+    status: Option<ExitStatus>,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run `command` isolated and with capped output, killing it and anything it started
+/// after `timeout`.
+fn run_limited(mut command: Command, timeout: Duration) -> io::Result<Captured> {
+    for name in JVM_OPTION_VARS {
+        command.env_remove(name);
+    }
+    isolate(&mut command);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let read_capped = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe
+                    .by_ref()
+                    .take(MAX_OUTPUT_BYTES)
+                    .read_to_end(&mut buffer);
+                // Keep draining, or a chatty program blocks on a full pipe until the timeout
+                let _ = io::copy(&mut pipe, &mut io::sink());
+            }
+            String::from_utf8_lossy(&buffer).into_owned()
+        })
+    };
+    let stdout = read_capped(
+        child
+            .stdout
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+    let stderr = read_capped(
+        child
+            .stderr
+            .take()
+            .map(|p| Box::new(p) as Box<dyn Read + Send>),
+    );
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            kill_group(&mut child);
+            let _ = child.wait();
+            break None;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    // Anything the process left running would keep the pipes open
+    kill_group(&mut child);
+
+    Ok(Captured {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Start the process as the leader of its own process group, and on Linux in a fresh
+/// network namespace with no interfaces but loopback. The namespace is best effort:
+/// where the kernel refuses one, the security policy still denies sockets.
+#[cfg(unix)]
+fn isolate(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+    #[cfg(target_os = "linux")]
+    // SAFETY: the closure runs between fork and exec and only makes raw syscalls.
+    unsafe {
+        command.pre_exec(|| {
+            if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                libc::unshare(libc::CLONE_NEWNET);
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn isolate(_command: &mut Command) {}
+
+/// Kill the process and, on Unix, every process left in its group.
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: plain kill(2) on the group `isolate` made the child lead.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+/// Strip markdown and quoting from a choice so it can be compared with raw output.
+fn choice_text(text: &str) -> String {
+    let mut text = text.trim();
+    for (open, close) in [("```", "```"), ("`", "`"), ("\"", "\"")] {
+        if text.len() > open.len() + close.len() && text.starts_with(open) && text.ends_with(close)
+        {
+            text = text[open.len()..text.len() - close.len()].trim();
+        }
+    }
+    text.to_string()
+}
+
+fn words(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn squashed(text: &str) -> String {
+    text.split_whitespace().collect()
+}
+
+/// Indices of the choices that read the same as `output`: word for word, or failing
+/// that, once all whitespace is removed.
+fn matching_choices(output: &str, choices: &[(String, bool)]) -> Vec<usize> {
+    let texts: Vec<String> = choices.iter().map(|(text, _)| choice_text(text)).collect();
+    let exact: Vec<usize> = (0..texts.len())
+        .filter(|&i| words(&texts[i]) == words(output))
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    (0..texts.len())
+        .filter(|&i| squashed(&texts[i]) == squashed(output))
+        .collect()
+}
+
+/// Simple name of the exception in a JVM stack trace, e.g. `ArithmeticException`.
+fn exception_name(stderr: &str) -> Option<String> {
+    static EXCEPTION: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"Exception in thread \S+ (?:[\w$]+\.)*([\w$]+)").unwrap());
+    EXCEPTION.captures(stderr).map(|caps| caps[1].to_string())
+}
+
+fn compare(outcome: RunOutcome, choices: &[(String, bool)]) -> CodeCheck {
+    let (output, matched, detail) = match outcome {
+        RunOutcome::Output(output) => {
+            let matched = matching_choices(&output, choices);
+            if matched.is_empty() && output.trim().is_empty() {
+                return CodeCheck::new(
+                    CodeCheckStatus::Skipped,
+                    output,
+                    "The code prints nothing, so the question is probably not about its output"
+                        .to_string(),
+                );
+            }
+            (output, matched, String::new())
+        }
+        RunOutcome::RuntimeError { output, error } => {
+            // "An ArithmeticException is thrown" style choices name the exception
+            let matched: Vec<usize> = exception_name(&error)
+                .map(|name| {
+                    (0..choices.len())
+                        .filter(|&i| choices[i].0.contains(&name))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if matched.is_empty() {
+                return CodeCheck::new(CodeCheckStatus::RuntimeError, output, error);
+            }
+            (output, matched, error)
+        }
+        RunOutcome::CompileError(error) => {
+            return CodeCheck::new(CodeCheckStatus::CompileError, String::new(), error)
+        }
+        RunOutcome::Timeout(output) => {
+            return CodeCheck::new(
+                CodeCheckStatus::Timeout,
+                output,
+                "The program did not finish in time".to_string(),
+            )
+        }
+        RunOutcome::Unavailable(reason) => {
+            return CodeCheck::new(CodeCheckStatus::Skipped, String::new(), reason)
+        }
+    };
+
+    let correct = matched.iter().copied().find(|&i| choices[i].1);
+    let (status, choice) = match (correct, matched.first()) {
+        (Some(i), _) => (CodeCheckStatus::Match, Some(i)),
+        (None, Some(&i)) => (CodeCheckStatus::Mismatch, Some(i)),
+        (None, None) => (CodeCheckStatus::NoChoiceMatches, None),
+    };
+    let mut check = CodeCheck::new(status, output, detail);
+    check.matched_choice = choice.map(letter);
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::fixtures::choices;
+    use std::io::Write;
+
+    const TRIAL: &str = "Consider the following methods. What is printed as the result of the call trial()?\n\n```java\npublic void trial()\n{\n    int a = 10;\n    int b = 5;\n    doublevalues(a,b);\n    System.out.print(b);\n    System.out.print(a);\n}\n\npublic void doublevalues(int c, int d)\n{\n    c = c * 2;\n    d = d * 2;\n    System.out.print(c);\n    System.out.print(d);\n}\n```";
+
+    #[test]
+    fn test_wraps_methods_and_calls_entry_point_from_stem() {
+        let code = extract_java(TRIAL).unwrap();
+        assert_eq!(entry_call(TRIAL).as_deref(), Some("trial()"));
+        let program = build_program(&code, &entry_call(TRIAL)).unwrap();
+        assert!(program.contains("    static void trial()"));
+        assert!(program.contains("    static void doublevalues(int c, int d)"));
+        assert!(program.contains("        trial();\n    }\n}"));
+
+        let valued = "What is returned by the call `sum(3)`?\n```java\npublic int sum(int n) { return n == 0 ? 0 : n + sum(n - 1); }\n```";
+        let program = build_program(&extract_java(valued).unwrap(), &entry_call(valued)).unwrap();
+        assert!(program.contains("    static int sum(int n) {"));
+        assert!(program.contains("System.out.print(sum(3));"));
+
+        let loose = "What is printed?\n```java\nint x = 3;\nif (x > 2) {\n    System.out.println(x);\n}\n```";
+        let program = build_program(&extract_java(loose).unwrap(), &entry_call(loose)).unwrap();
+        assert!(program.contains("        if (x > 2) {\n"));
+
+        let no_entry = "Which replaces the missing code?\n```java\npublic void f(int[] a)\n{\n    // missing\n}\n```";
+        assert!(build_program(&extract_java(no_entry).unwrap(), &entry_call(no_entry)).is_err());
+        assert!(extract_java("No code here").is_none());
+    }
+
+    #[test]
+    fn test_compare_output_with_choices() {
+        let options = choices(&["2010", "`2010510`", "5 10"], 1);
+        let matched = compare(RunOutcome::Output("2010510".to_string()), &options);
+        assert_eq!(matched.status, CodeCheckStatus::Match);
+        assert_eq!(matched.matched_choice.as_deref(), Some("B"));
+
+        let other = compare(RunOutcome::Output("5\n10\n".to_string()), &options);
+        assert_eq!(other.status, CodeCheckStatus::Mismatch);
+        assert_eq!(other.matched_choice.as_deref(), Some("C"));
+        assert!(other.is_flagged());
+
+        let none = compare(RunOutcome::Output("42".to_string()), &options);
+        assert_eq!(none.status, CodeCheckStatus::NoChoiceMatches);
+        let silent = compare(RunOutcome::Output(String::new()), &options);
+        assert_eq!(silent.status, CodeCheckStatus::Skipped);
+
+        let thrown = compare(
+            RunOutcome::RuntimeError {
+                output: String::new(),
+                error: "Exception in thread \"main\" java.lang.ArithmeticException: / by zero"
+                    .to_string(),
+            },
+            &choices(&["0", "An ArithmeticException is thrown"], 1),
+        );
+        assert_eq!(thrown.status, CodeCheckStatus::Match);
+    }
+
+    /// Why the local JDK cannot run sandboxed code, probed once for all tests.
+    static JDK_UNAVAILABLE: Lazy<Option<String>> = Lazy::new(|| {
+        let probe = "public class Main { public static void main(String[] args) {} }";
+        match run_program(probe, &JavaConfig::from_env()) {
+            RunOutcome::Unavailable(reason) => Some(reason),
+            _ => None,
+        }
+    });
+
+    /// libtest cannot mark a test skipped at run time, so the reason goes straight to
+    /// stderr, which the harness does not capture, instead of passing silently.
+    fn skip_without_jdk(test: &str) -> bool {
+        match JDK_UNAVAILABLE.as_ref() {
+            Some(reason) => {
+                let _ = writeln!(io::stderr(), "test {} skipped: {}", test, reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn test_runs_with_local_jdk() {
+        if skip_without_jdk("java_check::tests::test_runs_with_local_jdk") {
+            return;
+        }
+        let check = check(
+            TRIAL,
+            &choices(&["2010", "2010105", "2010510", "20102010"], 2),
+        )
+        .unwrap();
+        assert_eq!(check.status, CodeCheckStatus::Match, "{}", check.detail);
+        assert_eq!(check.output, "2010510");
+    }
+
+    #[test]
+    fn test_sandbox_denies_files_network_and_processes() {
+        if skip_without_jdk("java_check::tests::test_sandbox_denies_files_network_and_processes") {
+            return;
+        }
+        let config = JavaConfig::from_env();
+        for statement in [
+            "new java.io.FileWriter(\"escaped.txt\").close();",
+            "new java.io.FileReader(\"/etc/hosts\").close();",
+            "new java.net.Socket(\"127.0.0.1\", 9).close();",
+            "Runtime.getRuntime().exec(\"sleep 30\");",
+            "System.out.print(System.getenv(\"PATH\"));",
+        ] {
+            let program = format!(
+                "public class Main {{ public static void main(String[] args) throws Exception {{ {} }} }}",
+                statement
+            );
+            match run_program(&program, &config) {
+                RunOutcome::RuntimeError { error, .. } => {
+                    assert!(error.contains("AccessControlException"), "{}", error);
+                    assert!(!error.contains("WARNING"), "{}", error);
+                }
+                other => panic!("{} was not denied: {:?}", statement, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_chatty_program_is_not_a_timeout() {
+        if skip_without_jdk("java_check::tests::test_chatty_program_is_not_a_timeout") {
+            return;
+        }
+        let program = "public class Main { public static void main(String[] args) { \
+                       for (int i = 0; i < 200000; i++) System.out.println(i); } }";
+        match run_program(program, &JavaConfig::from_env()) {
+            RunOutcome::Output(output) => assert_eq!(output.len() as u64, MAX_OUTPUT_BYTES),
+            other => panic!("expected capped output, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_leftover_processes_are_killed_with_the_group() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "sleep 30 & echo started"]);
+        let started = Instant::now();
+        let ran = run_limited(command, Duration::from_secs(20)).unwrap();
+        assert!(ran.status.is_some_and(|status| status.success()));
+        assert_eq!(ran.stdout.trim(), "started");
+        // The background sleep held stdout open; it only closed because it was killed
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_remembered_outcomes_are_bounded() {
+        let mut outcomes = Outcomes::default();
+        for i in 0..=MAX_REMEMBERED_OUTCOMES {
+            outcomes.insert(i.to_string(), RunOutcome::Output(i.to_string()));
+        }
+        assert_eq!(outcomes.by_program.len(), MAX_REMEMBERED_OUTCOMES);
+        assert!(outcomes.get("0").is_none());
+        assert!(outcomes.get(&MAX_REMEMBERED_OUTCOMES.to_string()).is_some());
+    }
+}
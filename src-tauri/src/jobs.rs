@@ -0,0 +1,185 @@
+//! LLM job tracking and cooperative cancellation
+//!
+//! Every generation or regeneration registers a job with an id and a cancellation
+//! token. `cancel_generation` flips the token; the gateway stream and any pending
+//! batch futures observe it and stop, keeping whatever output already arrived.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Cloneable cancellation flag that async code can await.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelInner>,
+}
+
+#[derive(Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called (immediately if it already was).
+    pub async fn cancelled(&self) {
+        loop {
+            // Register interest before checking the flag so a concurrent cancel is not missed.
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A running job. Dropping it unregisters the job from the registry.
+pub struct Job {
+    pub id: String,
+    pub token: CancelToken,
+    jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl Job {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(&self.id);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job. The frontend may supply its own id so it can cancel before the
+    /// command returns; otherwise one is generated. An id that is still in use is
+    /// rejected, since the two jobs would share one cancellation entry.
+    pub fn start(&self, requested_id: Option<String>) -> Result<Job, String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let id = match requested_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+        {
+            Some(id) if jobs.contains_key(&id) => {
+                return Err(format!("Job {} is already running", id));
+            }
+            Some(id) => id,
+            None => loop {
+                let id = new_job_id();
+                if !jobs.contains_key(&id) {
+                    break id;
+                }
+            },
+        };
+        let token = CancelToken::new();
+        jobs.insert(id.clone(), token.clone());
+
+        Ok(Job {
+            id,
+            token,
+            jobs: Arc::clone(&self.jobs),
+        })
+    }
+
+    /// Cancel a running job. Returns `false` when no job with that id is active.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.jobs.lock().unwrap().get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn new_job_id() -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+    let rand_part: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+    format!(
+        "job_{}_{}",
+        chrono::Utc::now().timestamp_millis(),
+        rand_part
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_marks_registered_job() {
+        let registry = JobRegistry::new();
+        let job = registry.start(Some("job-1".to_string())).unwrap();
+        assert!(!job.is_cancelled());
+        assert!(registry.cancel("job-1"));
+        assert!(job.is_cancelled());
+    }
+
+    #[test]
+    fn test_dropped_job_is_unregistered() {
+        let registry = JobRegistry::new();
+        let job = registry.start(None).unwrap();
+        let id = job.id.clone();
+        assert!(id.starts_with("job_"));
+        drop(job);
+        assert!(!registry.cancel(&id));
+    }
+
+    #[test]
+    fn test_id_in_use_is_rejected() {
+        let registry = JobRegistry::new();
+        let first = registry.start(Some("job-1".to_string())).unwrap();
+        assert!(registry.start(Some(" job-1 ".to_string())).is_err());
+        assert!(registry.cancel("job-1"));
+        assert!(first.is_cancelled());
+        drop(first);
+        assert!(registry.start(Some("job-1".to_string())).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_future_resolves_after_cancel() {
+        let token = CancelToken::new();
+        let waiter = {
+            let token = token.clone();
+            tokio::spawn(async move { token.cancelled().await })
+        };
+        token.cancel();
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiter)
+            .await
+            .expect("cancelled() should resolve")
+            .unwrap();
+    }
+}
@@ -1,5 +1,9 @@
 //! Knowledge base management - loads example questions for few-shot prompting
 
+use crate::parametric::ParametricTemplate;
+use crate::prompts::{GENERATION_VARIABLES, REGENERATION_VARIABLES};
+use crate::regenerate::RegenerateMode;
+use crate::template::{Template, Variable};
 use crate::{
     CommonMistake, DistractorInfo, QuestionBankEntry, QuestionBankOption, SubjectInfo,
     SubtopicInfo, TopicInfo,
@@ -51,6 +55,22 @@ fn load_knowledge_file(file: &str) -> Option<String> {
     })
 }
 
+/// Parse and validate a prompt template, logging why it cannot be used.
+fn load_template(file: &str, content: &str, variables: &[Variable]) -> Result<Template, String> {
+    let template = Template::load(content, variables).map_err(|e| {
+        let message = format!("Invalid prompt template {}: {}", file, e);
+        eprintln!("ERROR: {}", message);
+        message
+    })?;
+    println!(
+        "Loaded {} (version {}, hash {})",
+        file,
+        template.version().unwrap_or("unlabelled"),
+        template.hash()
+    );
+    Ok(template)
+}
+
 /// Schema file structure for topics
 #[derive(Debug, Deserialize)]
 struct QuestionSchema {
@@ -150,19 +170,24 @@ pub struct KnowledgeBase {
     pub bank_entries: HashMap<String, Vec<QuestionBankEntry>>,
     /// Mapping of topic_id -> topic_codes for each subject
     pub topic_code_mappings: HashMap<String, HashMap<String, Vec<String>>>,
-    /// Prompt templates for each subject
-    pub prompts: HashMap<String, String>,
-    /// Regeneration prompt templates for each subject
-    pub regeneration_prompts: HashMap<String, String>,
+    /// Prompt templates for each subject, or why the subject's template is invalid
+    pub prompts: HashMap<String, Result<Template, String>>,
+    /// Regeneration prompt templates for each subject and mode, or why they are invalid
+    pub regeneration_prompts: HashMap<(String, RegenerateMode), Result<Template, String>>,
+    /// Parametric question templates from `{subject}/parametric/*.json`; invalid ones are
+    /// logged and left out
+    pub parametric_templates: HashMap<String, Vec<ParametricTemplate>>,
 }
 
 impl KnowledgeBase {
     /// Load knowledge base from embedded files, organized by subject folders
     pub fn load() -> Self {
         let mut subjects: HashMap<String, Vec<TopicInfo>> = HashMap::new();
-        let mut prompts: HashMap<String, String> = HashMap::new();
-        let mut regeneration_prompts: HashMap<String, String> = HashMap::new();
+        let mut prompts: HashMap<String, Result<Template, String>> = HashMap::new();
+        let mut regeneration_prompts: HashMap<(String, RegenerateMode), Result<Template, String>> =
+            HashMap::new();
         let mut bank_entries: HashMap<String, Vec<QuestionBankEntry>> = HashMap::new();
+        let mut parametric_templates: HashMap<String, Vec<ParametricTemplate>> = HashMap::new();
         let mut topic_code_mappings: HashMap<String, HashMap<String, Vec<String>>> = HashMap::new();
 
         // List of subjects to scan (can be expanded)
@@ -355,7 +380,10 @@ impl KnowledgeBase {
             // Load prompt template for this subject
             let prompt_filename = format!("{}/prompt.txt", subject_name);
             if let Some(content) = load_knowledge_file(&prompt_filename) {
-                prompts.insert(subject_name.to_string(), content);
+                prompts.insert(
+                    subject_name.to_string(),
+                    load_template(&prompt_filename, &content, GENERATION_VARIABLES),
+                );
             } else {
                 eprintln!(
                     "Warning: No prompt.txt found for {}, will use default",
@@ -363,15 +391,40 @@ impl KnowledgeBase {
                 );
             }
 
-            // Load regeneration prompt template for this subject
-            let regen_prompt_filename = format!("{}/regeneration-prompt.txt", subject_name);
-            if let Some(content) = load_knowledge_file(&regen_prompt_filename) {
-                regeneration_prompts.insert(subject_name.to_string(), content);
-            } else {
-                eprintln!(
-                    "Warning: No regeneration-prompt.txt found for {}, will use built-in regeneration prompt",
-                    subject_name
-                );
+            // Load regeneration prompt templates for this subject, one slot per mode
+            for mode in RegenerateMode::ALL {
+                let regen_prompt_filename = format!("{}/{}", subject_name, mode.template_file());
+                if let Some(content) = load_knowledge_file(&regen_prompt_filename) {
+                    regeneration_prompts.insert(
+                        (subject_name.to_string(), mode),
+                        load_template(&regen_prompt_filename, &content, REGENERATION_VARIABLES),
+                    );
+                } else if mode == RegenerateMode::Full {
+                    eprintln!(
+                        "Warning: No regeneration-prompt.txt found for {}, will use built-in regeneration prompt",
+                        subject_name
+                    );
+                }
+            }
+
+            // Load parametric question templates for this subject
+            let parametric_prefix = format!("{}/parametric/", subject_name);
+            let mut parametric_files: Vec<String> = KnowledgeAssets::iter()
+                .filter(|file| file.starts_with(&parametric_prefix) && file.ends_with(".json"))
+                .map(|file| file.to_string())
+                .collect();
+            parametric_files.sort();
+            for file in parametric_files {
+                let Some(content) = load_knowledge_file(&file) else {
+                    continue;
+                };
+                match ParametricTemplate::load(&content) {
+                    Ok(template) => parametric_templates
+                        .entry(subject_name.to_string())
+                        .or_default()
+                        .push(template),
+                    Err(e) => eprintln!("Warning: Invalid parametric template {}: {}", file, e),
+                }
             }
         }
 
@@ -386,6 +439,7 @@ impl KnowledgeBase {
             topic_code_mappings,
             prompts,
             regeneration_prompts,
+            parametric_templates,
         }
     }
 
@@ -497,13 +551,61 @@ impl KnowledgeBase {
         results
     }
 
-    /// Get prompt template for a subject, or return default
-    pub fn get_prompt(&self, subject: &str) -> Option<&str> {
-        self.prompts.get(subject).map(|s| s.as_str())
+    /// Whether a bank entry is on a topic or subtopic. Topics match through their codes
+    /// like `get_bank_examples`; a subtopic matches only entries tagged with it.
+    pub fn entry_covers(&self, subject: &str, entry: &QuestionBankEntry, topic_id: &str) -> bool {
+        let tagged = |code: &str| {
+            entry.topics.iter().any(|t| t == code)
+                || entry
+                    .subtopics
+                    .as_ref()
+                    .is_some_and(|subs| subs.iter().any(|s| s == code))
+        };
+        if tagged(topic_id) {
+            return true;
+        }
+        let is_subtopic = self.subjects.get(subject).is_some_and(|topics| {
+            topics
+                .iter()
+                .any(|t| t.children.iter().any(|c| c.id == topic_id))
+        });
+        !is_subtopic
+            && self
+                .topic_code_mappings
+                .get(subject)
+                .and_then(|map| map.get(topic_id))
+                .is_some_and(|codes| codes.iter().any(|code| tagged(code)))
     }
 
-    /// Get regeneration prompt template for a subject
-    pub fn get_regeneration_prompt(&self, subject: &str) -> Option<&str> {
-        self.regeneration_prompts.get(subject).map(|s| s.as_str())
+    /// Get prompt template for a subject (None uses the built-in prompt).
+    /// Fails when the subject's template did not pass validation at load time.
+    pub fn get_prompt(&self, subject: &str) -> Result<Option<&Template>, String> {
+        self.prompts
+            .get(subject)
+            .map(|t| t.as_ref())
+            .transpose()
+            .map_err(String::clone)
+    }
+
+    /// Bundled parametric templates for a subject.
+    pub fn get_parametric_templates(&self, subject: &str) -> &[ParametricTemplate] {
+        self.parametric_templates
+            .get(subject)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Get a subject's regeneration prompt template for `mode`; fails like
+    /// [`Self::get_prompt`].
+    pub fn get_regeneration_prompt(
+        &self,
+        subject: &str,
+        mode: RegenerateMode,
+    ) -> Result<Option<&Template>, String> {
+        self.regeneration_prompts
+            .get(&(subject.to_string(), mode))
+            .map(|t| t.as_ref())
+            .transpose()
+            .map_err(String::clone)
     }
 }
@@ -1,58 +1,227 @@
 //! Bedrock gateway-backed LLM client with streaming SSE
 
+use crate::jobs::Job;
+use crate::llm_log::{self, LlmLogRecord, StreamOutcome};
+use crate::prompts::QuestionStreamParser;
+use crate::response_cache::{self, CachedResponse};
+use crate::sse::{SseDecoder, SseFrame};
+use crate::usage::{self, UsageSample};
+use crate::Question;
 use futures_util::StreamExt;
-use reqwest::header::CONTENT_TYPE;
+use rand::Rng;
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::time::Instant;
 use tauri::Manager;
 use tokio::time::Duration;
 
 const BUILT_GATEWAY_URL: Option<&str> = option_env!("BEDROCK_GATEWAY_URL");
 
-/// Log prompt and response to a file (appends each time)
-fn log_llm_interaction(prompt: &str, response: &str) {
-    // Write to parent directory (rubrix/) to avoid triggering Tauri's file watcher
-    let log_path = std::path::Path::new("../llm_log.txt");
-
-    let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-
-    let formatted = format!(
-        r#"================================================================================
-TIMESTAMP: {}
-================================================================================
-
---- PROMPT ---
-
-{}
-
---- RESPONSE ---
-
-{}
+/// What is written to the interaction log for one `generate` call.
+struct InteractionLog<'a> {
+    prompt: &'a str,
+    stream: &'a StreamTarget,
+    started: Instant,
+    retries: u32,
+    /// Replayed from the response cache: logged, but no gateway tokens were used.
+    from_cache: bool,
+}
 
-"#,
-        timestamp, prompt, response
-    );
+impl InteractionLog<'_> {
+    fn write(
+        &self,
+        response: &str,
+        outcome: StreamOutcome,
+        error: Option<String>,
+        remaining_tokens: Option<u64>,
+        diagnostics: &[String],
+    ) {
+        llm_log::record(LlmLogRecord {
+            id: llm_log::new_record_id(),
+            job_id: self.stream.job_id.clone(),
+            index: self.stream.index,
+            batch: self.stream.batch,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            kind: self.stream.log_kind.to_string(),
+            subject: self.stream.log_subject.clone(),
+            topics: self.stream.log_topics.clone(),
+            prompt: self.prompt.to_string(),
+            response: response.to_string(),
+            outcome,
+            error,
+            parse_outcome: None,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            retries: self.retries,
+            remaining_tokens,
+            diagnostics: diagnostics.to_vec(),
+            redacted: false,
+        });
 
-    match OpenOptions::new().create(true).append(true).open(log_path) {
-        Ok(mut file) => {
-            let _ = write!(file, "{}", formatted);
-            eprintln!("DEBUG: Logged LLM interaction to llm_log.txt");
-        }
-        Err(e) => {
-            eprintln!("DEBUG: Failed to write log: {}", e);
+        if !self.from_cache {
+            usage::record(UsageSample {
+                job_id: &self.stream.job_id,
+                kind: self.stream.log_kind,
+                subject: &self.stream.log_subject,
+                prompt: self.prompt,
+                response,
+                remaining_tokens,
+            });
         }
     }
 }
 
 /// Streaming event payload consumed by the frontend.
-#[derive(Clone, Serialize)]
+#[derive(Clone, Default, Serialize)]
 pub struct StreamEvent {
+    /// Job that produced the event, so concurrent streams can be told apart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Question index within a batch job (regenerate-all); absent for single streams.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Batch number when a large generation is split into batches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<usize>,
     pub text: String,
     pub done: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub remaining_tokens: Option<u64>,
+    /// Set when a stream chunk could not be decoded; `text` is unchanged in that case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostic: Option<String>,
+    /// Present when a failed attempt is about to be retried; the preview restarts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryNotice>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RetryNotice {
+    /// The attempt that is about to start (2 for the first retry).
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+/// Emitted as `question-ready` when a question object finishes streaming, before the
+/// rest of the response arrives.
+#[derive(Clone, Serialize)]
+pub struct QuestionReadyEvent {
+    pub job_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<usize>,
+    /// Zero-based position of the question within the request, across batches.
+    pub position: usize,
+    pub question: Question,
+}
+
+/// Where a generation streams its progress. Every event is tagged with the job id and,
+/// for batch jobs, the question index, so several streams can share `llm-stream`.
+#[derive(Clone)]
+pub struct StreamTarget {
+    app_handle: Option<tauri::AppHandle>,
+    job_id: String,
+    index: Option<usize>,
+    batch: Option<usize>,
+    /// Added to question positions so a batch's questions follow the earlier batches.
+    first_position: usize,
+    question_events: bool,
+    log_kind: &'static str,
+    log_subject: String,
+    log_topics: Vec<String>,
+    bypass_cache: bool,
+    gateway_url: Option<String>,
+}
+
+impl StreamTarget {
+    pub fn new(app_handle: Option<tauri::AppHandle>, job: &Job) -> Self {
+        Self {
+            app_handle,
+            job_id: job.id.clone(),
+            index: None,
+            batch: None,
+            first_position: 0,
+            question_events: false,
+            log_kind: "generate",
+            log_subject: String::new(),
+            log_topics: Vec::new(),
+            bypass_cache: false,
+            gateway_url: None,
+        }
+    }
+
+    /// Send this call to a different gateway than `BEDROCK_GATEWAY_URL`.
+    pub fn with_gateway_url(mut self, url: Option<String>) -> Self {
+        self.gateway_url = url;
+        self
+    }
+
+    /// Skip the response cache for this call; the fresh response replaces the cached one.
+    pub fn with_cache_bypass(mut self, bypass: bool) -> Self {
+        self.bypass_cache = bypass;
+        self
+    }
+
+    /// Describe the request in the interaction log.
+    pub fn with_log_context(
+        mut self,
+        kind: &'static str,
+        subject: &str,
+        topics: &[String],
+    ) -> Self {
+        self.log_kind = kind;
+        self.log_subject = subject.to_string();
+        self.log_topics = topics.to_vec();
+        self
+    }
+
+    /// Also emit `question-ready` for each question object as soon as it is complete.
+    pub fn with_question_events(mut self) -> Self {
+        self.question_events = true;
+        self
+    }
+
+    /// Tag events with the question a batch job is currently working on.
+    pub fn for_question(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Tag events with a batch of a split generation whose questions start at
+    /// `first_position` in the request.
+    pub fn for_batch(mut self, batch: usize, first_position: usize) -> Self {
+        self.batch = Some(batch);
+        self.first_position = first_position;
+        self
+    }
+
+    fn emit(&self, event: StreamEvent) {
+        if let Some(handle) = &self.app_handle {
+            let event = StreamEvent {
+                job_id: Some(self.job_id.clone()),
+                index: self.index,
+                batch: self.batch,
+                ..event
+            };
+            let _ = handle.emit_all("llm-stream", event);
+        }
+    }
+
+    fn emit_question(&self, position: usize, question: Question) {
+        if let Some(handle) = &self.app_handle {
+            let event = QuestionReadyEvent {
+                job_id: self.job_id.clone(),
+                index: self.index,
+                batch: self.batch,
+                position: self.first_position + position,
+                question,
+            };
+            let _ = handle.emit_all("question-ready", event);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -89,6 +258,15 @@ pub fn gateway_url() -> Option<String> {
         .or_else(|| BUILT_GATEWAY_URL.map(|url| url.to_string()))
 }
 
+/// Provider and model recorded with generated questions. The gateway picks the model
+/// server-side, so its URL stands in for the model, as in the response cache.
+pub fn backend_label() -> (String, String) {
+    (
+        response_cache::GATEWAY_PROVIDER.to_string(),
+        gateway_url().unwrap_or_else(|| "(not configured)".to_string()),
+    )
+}
+
 pub async fn validate_gateway_credentials(user: &str, password_hash: &str) -> Result<(), String> {
     let url =
         gateway_url().ok_or_else(|| "Authentication gateway is not configured".to_string())?;
@@ -156,24 +334,158 @@ pub async fn validate_gateway_credentials(user: &str, password_hash: &str) -> Re
     }
 }
 
+/// Retry and timeout settings for gateway generation calls.
+///
+/// Defaults can be overridden through `RUBRIX_LLM_*` environment variables (see
+/// [`GatewayConfig::from_env`]).
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    /// Additional attempts after the first one fails with a transient error.
+    pub max_retries: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+    pub connect_timeout: Duration,
+    /// Time allowed between sending the request and receiving the first stream bytes.
+    pub first_byte_timeout: Duration,
+    /// Time allowed between two consecutive stream chunks.
+    pub idle_timeout: Duration,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(1_000),
+            retry_max_delay: Duration::from_secs(20),
+            connect_timeout: Duration::from_secs(10),
+            first_byte_timeout: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(45),
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// Read overrides from `RUBRIX_LLM_MAX_RETRIES`, `RUBRIX_LLM_RETRY_BASE_MS`,
+    /// `RUBRIX_LLM_RETRY_MAX_MS`, `RUBRIX_LLM_CONNECT_TIMEOUT_SECS`,
+    /// `RUBRIX_LLM_FIRST_BYTE_TIMEOUT_SECS` and `RUBRIX_LLM_IDLE_TIMEOUT_SECS`.
+    pub fn from_env() -> Self {
+        fn env_u64(name: &str) -> Option<u64> {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().trim_matches('"').parse::<u64>().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            max_retries: env_u64("RUBRIX_LLM_MAX_RETRIES")
+                .map(|v| v.min(10) as u32)
+                .unwrap_or(defaults.max_retries),
+            retry_base_delay: env_u64("RUBRIX_LLM_RETRY_BASE_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.retry_base_delay),
+            retry_max_delay: env_u64("RUBRIX_LLM_RETRY_MAX_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.retry_max_delay),
+            connect_timeout: env_u64("RUBRIX_LLM_CONNECT_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.connect_timeout),
+            first_byte_timeout: env_u64("RUBRIX_LLM_FIRST_BYTE_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.first_byte_timeout),
+            idle_timeout: env_u64("RUBRIX_LLM_IDLE_TIMEOUT_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.idle_timeout),
+        }
+    }
+}
+
+/// Why a single gateway attempt failed.
+#[derive(Debug)]
+enum AttemptError {
+    /// Connect errors, dropped streams, timeouts, 429 and 5xx responses.
+    Transient {
+        reason: String,
+        retry_after: Option<Duration>,
+    },
+    /// Auth failures, bad requests and anything else retrying cannot fix.
+    Fatal(String),
+    /// The job was cancelled by the user.
+    Cancelled,
+}
+
+impl AttemptError {
+    fn transient(reason: String) -> Self {
+        AttemptError::Transient {
+            reason,
+            retry_after: None,
+        }
+    }
+
+    fn from_status(status: u16, message: String, retry_after: Option<Duration>) -> Self {
+        if is_retryable_status(status) {
+            AttemptError::Transient {
+                reason: message,
+                retry_after,
+            }
+        } else {
+            AttemptError::Fatal(message)
+        }
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..=599).contains(&status)
+}
+
 /// Generate text using the configured gateway with streaming updates.
+///
+/// Transient failures are retried with exponential backoff. Each attempt restarts the
+/// stream, so the UI receives a `retry` notice and the preview resets. If every attempt
+/// fails, the longest partial output is logged and left in the preview for inspection.
+///
+/// When `job` is cancelled the stream is aborted and the text received so far is
+/// returned as `Ok`; callers check `job.is_cancelled()` to tell it apart from a full
+/// response.
 pub async fn generate(
     prompt: &str,
-    app_handle: Option<tauri::AppHandle>,
+    stream: StreamTarget,
     gateway_auth: Option<GatewayAuth>,
+    job: &Job,
 ) -> Result<String, String> {
-    let gateway_url = gateway_url().ok_or_else(|| {
-        "Gateway mode is required, but BEDROCK_GATEWAY_URL is not configured.".to_string()
-    })?;
+    let gateway_url = stream
+        .gateway_url
+        .clone()
+        .or_else(gateway_url)
+        .ok_or_else(|| {
+            "Gateway mode is required, but BEDROCK_GATEWAY_URL is not configured.".to_string()
+        })?;
     let gateway_auth = gateway_auth
         .ok_or_else(|| "Authentication required. No gateway credentials found.".to_string())?;
+    let config = GatewayConfig::from_env();
 
     let client = Client::builder()
-        .timeout(Duration::from_secs(90))
+        .connect_timeout(config.connect_timeout)
         .build()
         .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
 
-    emit_stream(&app_handle, "", false, None);
+    emit_stream(&stream, "", false, None);
+
+    let mut log = InteractionLog {
+        prompt,
+        stream: &stream,
+        started: Instant::now(),
+        retries: 0,
+        from_cache: false,
+    };
+
+    let cache_key =
+        response_cache::cache_key(response_cache::GATEWAY_PROVIDER, &gateway_url, prompt);
+    if response_cache::is_enabled() && !stream.bypass_cache {
+        if let Some(cached) = response_cache::lookup(&cache_key) {
+            log.from_cache = true;
+            return Ok(replay_cached(&log, job, cached).await);
+        }
+    }
 
     let request = GatewayRequest {
         user: gateway_auth.user,
@@ -181,14 +493,256 @@ pub async fn generate(
         prompt: prompt.to_string(),
     };
 
-    let response = client
-        .post(&gateway_url)
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to connect to gateway: {}", e))?;
+    let max_attempts = config.max_retries + 1;
+    let mut diagnostics: Vec<String> = Vec::new();
+    let mut best_partial = String::new();
+
+    for attempt in 1..=max_attempts {
+        log.retries = attempt - 1;
+        let mut accumulated = String::new();
+        let outcome = stream_attempt(
+            client.post(&gateway_url).json(&request),
+            &config,
+            &stream,
+            job,
+            &mut accumulated,
+            &mut diagnostics,
+        )
+        .await;
+
+        let (reason, retry_after) = match outcome {
+            Ok(remaining_tokens) => {
+                emit_stream(&stream, &accumulated, true, remaining_tokens);
+                log.write(
+                    &accumulated,
+                    StreamOutcome::Completed,
+                    None,
+                    remaining_tokens,
+                    &diagnostics,
+                );
+                if response_cache::is_enabled() {
+                    response_cache::store(&CachedResponse::new(
+                        response_cache::GATEWAY_PROVIDER,
+                        &gateway_url,
+                        prompt,
+                        &accumulated,
+                        remaining_tokens,
+                    ));
+                }
+                return Ok(accumulated);
+            }
+            Err(AttemptError::Cancelled) => {
+                if accumulated.len() > best_partial.len() {
+                    best_partial = accumulated;
+                }
+                return Ok(finish_cancelled(&log, job, best_partial, &mut diagnostics));
+            }
+            Err(AttemptError::Fatal(message)) => {
+                if accumulated.len() > best_partial.len() {
+                    best_partial = accumulated;
+                }
+                return Err(fail_generation(
+                    &log,
+                    &best_partial,
+                    &mut diagnostics,
+                    message,
+                ));
+            }
+            Err(AttemptError::Transient {
+                reason,
+                retry_after,
+            }) => (reason, retry_after),
+        };
+
+        if accumulated.len() > best_partial.len() {
+            best_partial = accumulated;
+        }
+        diagnostics.push(format!(
+            "Attempt {}/{} failed: {}",
+            attempt, max_attempts, reason
+        ));
+
+        if attempt == max_attempts {
+            let message = if max_attempts > 1 {
+                format!("{} (gave up after {} attempts)", reason, max_attempts)
+            } else {
+                reason
+            };
+            return Err(fail_generation(
+                &log,
+                &best_partial,
+                &mut diagnostics,
+                message,
+            ));
+        }
+
+        let delay = backoff_delay(attempt, &config, retry_after);
+        eprintln!(
+            "WARNING: Gateway attempt {}/{} failed ({}); retrying in {} ms",
+            attempt,
+            max_attempts,
+            reason,
+            delay.as_millis()
+        );
+        stream.emit(StreamEvent {
+            retry: Some(RetryNotice {
+                attempt: attempt + 1,
+                max_attempts,
+                delay_ms: delay.as_millis() as u64,
+                reason: reason.clone(),
+            }),
+            ..Default::default()
+        });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = job.token.cancelled() => {
+                return Ok(finish_cancelled(
+                    &log,
+                    job,
+                    best_partial,
+                    &mut diagnostics,
+                ));
+            }
+        }
+    }
+
+    unreachable!("retry loop always returns")
+}
+
+/// Characters emitted per `llm-stream` event when replaying a cached response.
+const REPLAY_CHUNK_CHARS: usize = 400;
+
+/// Stream a cached response through the same events as a live one, so previews and
+/// `question-ready` listeners behave identically.
+async fn replay_cached(log: &InteractionLog<'_>, job: &Job, cached: CachedResponse) -> String {
+    let stream = log.stream;
+    let mut diagnostics = vec![format!("Replayed from response cache ({})", cached.key)];
+    eprintln!("DEBUG: Replaying cached LLM response {}", cached.key);
+
+    let mut question_parser = stream.question_events.then(QuestionStreamParser::new);
+    let mut accumulated = String::new();
+    let chars: Vec<char> = cached.response.chars().collect();
+    for chunk in chars.chunks(REPLAY_CHUNK_CHARS) {
+        if job.is_cancelled() {
+            return finish_cancelled(log, job, accumulated, &mut diagnostics);
+        }
+        let text: String = chunk.iter().collect();
+        push_stream_text(stream, &mut question_parser, &mut accumulated, &text);
+        tokio::task::yield_now().await;
+    }
+
+    // The cached token count is stale, so the live quota display is left untouched.
+    emit_stream(stream, &accumulated, true, None);
+    log.write(
+        &accumulated,
+        StreamOutcome::Completed,
+        None,
+        None,
+        &diagnostics,
+    );
+    accumulated
+}
+
+/// Log and publish the output of a cancelled job, then hand it back to the caller.
+fn finish_cancelled(
+    log: &InteractionLog,
+    job: &Job,
+    partial: String,
+    diagnostics: &mut Vec<String>,
+) -> String {
+    eprintln!(
+        "DEBUG: Job {} cancelled with {} chars of output",
+        job.id,
+        partial.len()
+    );
+    diagnostics.push("Generation cancelled by user".to_string());
+    log.write(&partial, StreamOutcome::Cancelled, None, None, diagnostics);
+
+    log.stream.emit(StreamEvent {
+        text: partial.clone(),
+        done: true,
+        diagnostic: Some("Generation cancelled".to_string()),
+        ..Default::default()
+    });
+
+    partial
+}
+
+/// Log the partial output, leave it visible in the preview and build the final error.
+fn fail_generation(
+    log: &InteractionLog,
+    partial: &str,
+    diagnostics: &mut Vec<String>,
+    message: String,
+) -> String {
+    diagnostics.push(format!("Generation failed: {}", message));
+    log.write(
+        partial,
+        StreamOutcome::Failed,
+        Some(message.clone()),
+        None,
+        diagnostics,
+    );
+
+    log.stream.emit(StreamEvent {
+        text: partial.to_string(),
+        done: true,
+        diagnostic: Some(message.clone()),
+        ..Default::default()
+    });
+
+    if partial.is_empty() {
+        message
+    } else {
+        format!(
+            "{}. Partial output ({} chars) was kept in the preview and LLM log.",
+            message,
+            partial.chars().count()
+        )
+    }
+}
+
+/// One request/stream round-trip. Text received so far is left in `accumulated` even
+/// when the attempt fails.
+async fn stream_attempt(
+    request: reqwest::RequestBuilder,
+    config: &GatewayConfig,
+    stream: &StreamTarget,
+    job: &Job,
+    accumulated: &mut String,
+    diagnostics: &mut Vec<String>,
+) -> Result<Option<u64>, AttemptError> {
+    if job.is_cancelled() {
+        return Err(AttemptError::Cancelled);
+    }
+
+    let send = tokio::time::timeout(config.first_byte_timeout, request.send());
+    let response = match tokio::select! {
+        result = send => result,
+        _ = job.token.cancelled() => return Err(AttemptError::Cancelled),
+    } {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            return Err(AttemptError::transient(format!(
+                "Failed to connect to gateway: {}",
+                e
+            )))
+        }
+        Err(_) => {
+            return Err(AttemptError::transient(format!(
+                "Gateway did not respond within {}s",
+                config.first_byte_timeout.as_secs()
+            )))
+        }
+    };
 
     let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
     let content_type = response
         .headers()
         .get(CONTENT_TYPE)
@@ -200,86 +754,444 @@ pub async fn generate(
         let body = response.text().await.unwrap_or_default();
         let trimmed = body.trim();
         if let Ok(err) = serde_json::from_str::<GatewayErrorResponse>(trimmed) {
+            // Stream-mode Lambda reports errors with HTTP 200 and a `status` field.
+            let effective_status = err.status.unwrap_or(status.as_u16());
             if let Some(message) = err.error.filter(|msg| !msg.trim().is_empty()) {
-                return Err(format!(
-                    "Gateway error ({}): {}",
-                    err.status.unwrap_or(status.as_u16()),
-                    message
+                return Err(AttemptError::from_status(
+                    effective_status,
+                    format!("Gateway error ({}): {}", effective_status, message),
+                    retry_after,
                 ));
             }
             if let Some(code) = err.status {
-                return Err(format!("Gateway error ({})", code));
+                return Err(AttemptError::from_status(
+                    code,
+                    format!("Gateway error ({})", code),
+                    retry_after,
+                ));
             }
         }
         if !status.is_success() {
-            return Err(format!("Gateway error ({}): {}", status.as_u16(), body));
+            return Err(AttemptError::from_status(
+                status.as_u16(),
+                format!("Gateway error ({}): {}", status.as_u16(), body),
+                retry_after,
+            ));
         }
-        return Err("Gateway returned JSON instead of stream.".to_string());
+        return Err(AttemptError::Fatal(
+            "Gateway returned JSON instead of stream.".to_string(),
+        ));
     } else if !status.is_success() {
         let body = response.text().await.unwrap_or_default();
-        return Err(format!("Gateway error ({}): {}", status.as_u16(), body));
+        return Err(AttemptError::from_status(
+            status.as_u16(),
+            format!("Gateway error ({}): {}", status.as_u16(), body),
+            retry_after,
+        ));
     }
 
-    let mut stream = response.bytes_stream();
-    let mut buffer = String::new();
-    let mut accumulated = String::new();
+    let mut body = response.bytes_stream();
+    // Fresh per attempt: a retried stream restarts the question numbering too.
+    let mut question_parser = stream.question_events.then(QuestionStreamParser::new);
+    let mut decoder = SseDecoder::new();
+    let mut received_any = false;
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| format!("Gateway stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
+    loop {
+        let wait = if received_any {
+            config.idle_timeout
+        } else {
+            config.first_byte_timeout
+        };
 
-        while let Some(line_end) = buffer.find('\n') {
-            let line = buffer[..line_end].trim().to_string();
-            buffer = buffer[line_end + 1..].to_string();
+        let next = tokio::time::timeout(wait, body.next());
+        let chunk = match tokio::select! {
+            result = next => result,
+            _ = job.token.cancelled() => return Err(AttemptError::Cancelled),
+        } {
+            Ok(Some(Ok(chunk))) => chunk,
+            Ok(Some(Err(e))) => {
+                return Err(AttemptError::transient(format!(
+                    "Gateway stream error: {}",
+                    e
+                )))
+            }
+            Ok(None) => break,
+            Err(_) => {
+                return Err(AttemptError::transient(if received_any {
+                    format!("Gateway stream stalled for {}s", wait.as_secs())
+                } else {
+                    format!("Gateway sent no data within {}s", wait.as_secs())
+                }))
+            }
+        };
+        received_any = true;
 
-            if line.is_empty() || line.starts_with(':') {
-                continue;
+        for frame in decoder.push(&chunk) {
+            if let StreamProgress::Done(remaining_tokens) = apply_stream_frame(
+                frame,
+                stream,
+                &mut question_parser,
+                accumulated,
+                diagnostics,
+            )? {
+                return Ok(remaining_tokens);
             }
+        }
+    }
 
-            if let Some(data) = line.strip_prefix("data:") {
-                let payload = data.trim();
-                if payload.is_empty() {
-                    continue;
-                }
+    for frame in decoder.finish() {
+        if let StreamProgress::Done(remaining_tokens) = apply_stream_frame(
+            frame,
+            stream,
+            &mut question_parser,
+            accumulated,
+            diagnostics,
+        )? {
+            return Ok(remaining_tokens);
+        }
+    }
 
-                if let Ok(chunk) = serde_json::from_str::<GatewayStreamChunk>(payload) {
-                    if !chunk.text.is_empty() {
-                        let cleaned = chunk
-                            .text
-                            .replace("<reasoning>", "")
-                            .replace("</reasoning>", "");
-                        accumulated.push_str(&cleaned);
-                        emit_stream(&app_handle, &accumulated, false, None);
-                    }
-                    if chunk.done {
-                        emit_stream(&app_handle, &accumulated, true, chunk.remaining_tokens);
-                        log_llm_interaction(prompt, &accumulated);
-                        return Ok(accumulated);
-                    }
-                }
+    // The stream ended without a `done` chunk. Treat an empty stream as a dropped
+    // connection; otherwise keep the historical behaviour of accepting what arrived.
+    if accumulated.is_empty() {
+        return Err(AttemptError::transient(
+            "Gateway stream ended before any output".to_string(),
+        ));
+    }
+    Ok(None)
+}
+
+/// Exponential backoff with equal jitter. `Retry-After` wins when the gateway sends it.
+fn backoff_delay(attempt: u32, config: &GatewayConfig, retry_after: Option<Duration>) -> Duration {
+    if let Some(wait) = retry_after {
+        return wait.min(Duration::from_secs(120));
+    }
+
+    let exponent = attempt.saturating_sub(1).min(16);
+    let ceiling = config
+        .retry_base_delay
+        .saturating_mul(1u32 << exponent)
+        .min(config.retry_max_delay);
+    let half = ceiling / 2;
+    let jitter_ms = rand::thread_rng().gen_range(0..=half.as_millis() as u64);
+    half + Duration::from_millis(jitter_ms)
+}
+
+/// Parse a `Retry-After` header given either as delay seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+enum StreamProgress {
+    Continue,
+    Done(Option<u64>),
+}
+
+/// Apply one decoded SSE frame to the accumulated response.
+///
+/// Chunks that cannot be deserialized are recorded as diagnostics (and forwarded to the
+/// UI) instead of being dropped silently. A gateway `error` event aborts the stream.
+fn apply_stream_frame(
+    frame: SseFrame,
+    stream: &StreamTarget,
+    question_parser: &mut Option<QuestionStreamParser>,
+    accumulated: &mut String,
+    diagnostics: &mut Vec<String>,
+) -> Result<StreamProgress, AttemptError> {
+    let event = match frame {
+        SseFrame::Event(event) => event,
+        SseFrame::Comment(_) => return Ok(StreamProgress::Continue),
+        SseFrame::Invalid(message) => {
+            report_stream_diagnostic(stream, accumulated, diagnostics, message);
+            return Ok(StreamProgress::Continue);
+        }
+    };
+
+    let payload = event.data.trim();
+
+    match event.event.as_str() {
+        "message" => {}
+        "error" => {
+            let (status, message) = match serde_json::from_str::<GatewayErrorResponse>(payload) {
+                Ok(err) => (err.status, err.error.unwrap_or_default()),
+                Err(_) => (None, payload.to_string()),
+            };
+            let message = if message.trim().is_empty() {
+                "stream aborted".to_string()
+            } else {
+                message
+            };
+            return Err(match status {
+                Some(code) => AttemptError::from_status(
+                    code,
+                    format!("Gateway error ({}): {}", code, message),
+                    None,
+                ),
+                None => AttemptError::Fatal(format!("Gateway error: {}", message)),
+            });
+        }
+        other => {
+            eprintln!("DEBUG: Ignoring gateway SSE event '{}'", other);
+            return Ok(StreamProgress::Continue);
+        }
+    }
+
+    if payload.is_empty() {
+        return Ok(StreamProgress::Continue);
+    }
+    if payload == "[DONE]" {
+        return Ok(StreamProgress::Done(None));
+    }
+
+    let chunk = match serde_json::from_str::<GatewayStreamChunk>(payload) {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            // Keep any text we can still recover from a partially conforming payload.
+            let recovered = serde_json::from_str::<serde_json::Value>(payload)
+                .ok()
+                .and_then(|value| {
+                    let text = value.get("text")?.as_str()?.to_string();
+                    let done = value.get("done").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let remaining_tokens = value.get("remaining_tokens").and_then(|v| v.as_u64());
+                    Some(GatewayStreamChunk {
+                        text,
+                        done,
+                        remaining_tokens,
+                    })
+                });
+
+            let message = format!(
+                "Malformed gateway chunk ({}){}: {}",
+                e,
+                if recovered.is_some() {
+                    ", text recovered"
+                } else {
+                    ""
+                },
+                truncate_for_log(payload, 200)
+            );
+            report_stream_diagnostic(stream, accumulated, diagnostics, message);
+
+            match recovered {
+                Some(chunk) => chunk,
+                None => return Ok(StreamProgress::Continue),
             }
         }
+    };
+
+    if !chunk.text.is_empty() {
+        let cleaned = chunk
+            .text
+            .replace("<reasoning>", "")
+            .replace("</reasoning>", "");
+        push_stream_text(stream, question_parser, accumulated, &cleaned);
     }
 
-    emit_stream(&app_handle, &accumulated, true, None);
-    log_llm_interaction(prompt, &accumulated);
-    Ok(accumulated)
+    if chunk.done {
+        return Ok(StreamProgress::Done(chunk.remaining_tokens));
+    }
+
+    Ok(StreamProgress::Continue)
 }
 
-/// Emit a streaming event to the frontend.
-fn emit_stream(
-    app_handle: &Option<tauri::AppHandle>,
+/// Append text to the response, publish the preview and any questions it completed.
+fn push_stream_text(
+    stream: &StreamTarget,
+    question_parser: &mut Option<QuestionStreamParser>,
+    accumulated: &mut String,
     text: &str,
-    done: bool,
-    remaining_tokens: Option<u64>,
 ) {
-    if let Some(handle) = app_handle {
-        let event = StreamEvent {
-            text: text.to_string(),
-            done,
-            remaining_tokens,
+    accumulated.push_str(text);
+    emit_stream(stream, accumulated, false, None);
+    if let Some(parser) = question_parser {
+        let ready = parser.push(text);
+        let first = parser.emitted() - ready.len();
+        for (offset, question) in ready.into_iter().enumerate() {
+            stream.emit_question(first + offset, question);
+        }
+    }
+}
+
+fn report_stream_diagnostic(
+    stream: &StreamTarget,
+    accumulated: &str,
+    diagnostics: &mut Vec<String>,
+    message: String,
+) {
+    eprintln!("WARNING: {}", message);
+    stream.emit(StreamEvent {
+        text: accumulated.to_string(),
+        diagnostic: Some(message.clone()),
+        ..Default::default()
+    });
+    diagnostics.push(message);
+}
+
+fn truncate_for_log(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars).collect();
+    out.push('…');
+    out
+}
+
+/// Emit a streaming event to the frontend.
+fn emit_stream(stream: &StreamTarget, text: &str, done: bool, remaining_tokens: Option<u64>) {
+    stream.emit(StreamEvent {
+        text: text.to_string(),
+        done,
+        remaining_tokens,
+        ..Default::default()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> GatewayConfig {
+        GatewayConfig {
+            retry_base_delay: Duration::from_millis(1_000),
+            retry_max_delay: Duration::from_millis(5_000),
+            ..GatewayConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let config = test_config();
+        for _ in 0..20 {
+            let first = backoff_delay(1, &config, None);
+            assert!(first >= Duration::from_millis(500) && first <= Duration::from_millis(1_000));
+
+            let third = backoff_delay(3, &config, None);
+            assert!(third >= Duration::from_millis(2_000) && third <= Duration::from_millis(4_000));
+
+            let tenth = backoff_delay(10, &config, None);
+            assert!(tenth >= Duration::from_millis(2_500) && tenth <= Duration::from_millis(5_000));
+        }
+    }
+
+    #[test]
+    fn test_backoff_honours_retry_after() {
+        let config = test_config();
+        assert_eq!(
+            backoff_delay(1, &config, Some(Duration::from_secs(7))),
+            Duration::from_secs(7)
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after_seconds_and_dates() {
+        assert_eq!(parse_retry_after(" 12 "), Some(Duration::from_secs(12)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(401));
+        assert!(!is_retryable_status(404));
+    }
+
+    async fn start_mock_gateway(script: &str) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "rubrix-mock-script-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("script.json");
+        std::fs::write(&path, script).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/generate", listener.local_addr().unwrap());
+        let gateway = mock_gateway::MockGateway {
+            users: mock_gateway::UserStore::demo(),
+            script: mock_gateway::Script::load(&path).unwrap(),
         };
-        let _ = handle.emit_all("llm-stream", event);
+        tokio::spawn(mock_gateway::serve(listener, std::sync::Arc::new(gateway)));
+        url
+    }
+
+    async fn attempt(url: &str) -> (Result<Option<u64>, AttemptError>, String) {
+        let job = crate::jobs::JobRegistry::new().start(None).unwrap();
+        let request = GatewayRequest {
+            user: "demo".to_string(),
+            password_hash: crate::auth::hash_password("demo"),
+            prompt: "Generate 2 multiple choice question(s)".to_string(),
+        };
+        let mut accumulated = String::new();
+        let mut diagnostics = Vec::new();
+        let outcome = stream_attempt(
+            Client::new().post(url).json(&request),
+            &GatewayConfig::default(),
+            &StreamTarget::new(None, &job),
+            &job,
+            &mut accumulated,
+            &mut diagnostics,
+        )
+        .await;
+        (outcome, accumulated)
+    }
+
+    #[tokio::test]
+    async fn test_stream_attempt_against_mock_gateway() {
+        let url = start_mock_gateway(
+            r#"{"rules": [
+                {"status": 429, "retry_after_secs": 3, "times": 1},
+                {"stream_error_after_chunks": 1, "times": 1}
+            ]}"#,
+        )
+        .await;
+
+        match attempt(&url).await.0 {
+            Err(AttemptError::Transient { retry_after, .. }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(3)))
+            }
+            _ => panic!("expected a retryable 429"),
+        }
+        match attempt(&url).await.0 {
+            Err(AttemptError::Transient { reason, .. }) => {
+                assert!(reason.contains("Injected stream error"))
+            }
+            _ => panic!("expected the injected stream error"),
+        }
+
+        let (outcome, text) = attempt(&url).await;
+        assert!(outcome.unwrap().is_some());
+        assert_eq!(crate::prompts::parse_llm_response(&text).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_stream_event_routing_fields_are_optional() {
+        let plain = serde_json::to_value(StreamEvent {
+            text: "abc".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(plain.get("job_id").is_none());
+        assert!(plain.get("index").is_none());
+
+        let tagged = serde_json::to_value(StreamEvent {
+            job_id: Some("job_1".to_string()),
+            index: Some(2),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(tagged["job_id"], "job_1");
+        assert_eq!(tagged["index"], 2);
     }
 }
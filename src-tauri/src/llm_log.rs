@@ -0,0 +1,501 @@
+//! Structured, rotating log of LLM interactions
+//!
+//! Records are appended as JSON lines to `<app data>/llm-log/interactions.jsonl`.
+//! The active file is rotated once it passes a size limit, and rotated files older than
+//! the retention window are deleted. Parse outcomes are known only after the response
+//! has been handled, so they are appended as separate lines and merged when reading.
+//!
+//! Configuration (environment):
+//! - `RUBRIX_LLM_LOG_MAX_MB` — rotate the active file above this size (default 10)
+//! - `RUBRIX_LLM_LOG_MAX_AGE_DAYS` — delete rotated files older than this (default 30)
+//! - `RUBRIX_LLM_LOG_REDACT` — when truthy, store only sizes and hashes of prompt/response
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+const ACTIVE_FILE: &str = "interactions.jsonl";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+
+static LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+/// Serializes appends and rotation within the process.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Set the log directory. Called once at startup; until then records are not persisted.
+pub fn init(dir: PathBuf) {
+    let _ = LOG_DIR.set(dir);
+}
+
+pub fn log_dir() -> Option<&'static Path> {
+    LOG_DIR.get().map(PathBuf::as_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamOutcome {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// One prompt/response round-trip with the gateway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmLogRecord {
+    pub id: String,
+    pub job_id: String,
+    /// Question index for batch jobs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Batch number within a split generation job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<usize>,
+    pub timestamp: String,
+    /// `generate`, `regenerate`, `repair`, ...
+    pub kind: String,
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub prompt: String,
+    pub response: String,
+    pub outcome: StreamOutcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Filled in from the matching parse line when reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_outcome: Option<String>,
+    pub duration_ms: u64,
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<String>,
+    #[serde(default)]
+    pub redacted: bool,
+}
+
+/// List/search row: the record without the (possibly large) prompt and response.
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmLogSummary {
+    pub id: String,
+    pub job_id: String,
+    pub index: Option<usize>,
+    pub batch: Option<usize>,
+    pub timestamp: String,
+    pub kind: String,
+    pub subject: String,
+    pub topics: Vec<String>,
+    pub outcome: StreamOutcome,
+    pub error: Option<String>,
+    pub parse_outcome: Option<String>,
+    pub duration_ms: u64,
+    pub retries: u32,
+    pub remaining_tokens: Option<u64>,
+    pub prompt_chars: usize,
+    pub response_chars: usize,
+    pub redacted: bool,
+}
+
+impl From<&LlmLogRecord> for LlmLogSummary {
+    fn from(record: &LlmLogRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            job_id: record.job_id.clone(),
+            index: record.index,
+            batch: record.batch,
+            timestamp: record.timestamp.clone(),
+            kind: record.kind.clone(),
+            subject: record.subject.clone(),
+            topics: record.topics.clone(),
+            outcome: record.outcome,
+            error: record.error.clone(),
+            parse_outcome: record.parse_outcome.clone(),
+            duration_ms: record.duration_ms,
+            retries: record.retries,
+            remaining_tokens: record.remaining_tokens,
+            prompt_chars: record.prompt.chars().count(),
+            response_chars: record.response.chars().count(),
+            redacted: record.redacted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LogLine {
+    Interaction(Box<LlmLogRecord>),
+    Parse {
+        job_id: String,
+        #[serde(default)]
+        index: Option<usize>,
+        #[serde(default)]
+        batch: Option<usize>,
+        timestamp: String,
+        outcome: String,
+    },
+}
+
+/// Append an interaction. Failures are reported on stderr and otherwise ignored.
+pub fn record(mut record: LlmLogRecord) {
+    if redaction_enabled() {
+        record.prompt = redact(&record.prompt);
+        record.response = redact(&record.response);
+        record.redacted = true;
+    }
+    append(&LogLine::Interaction(Box::new(record)));
+}
+
+/// Record how the response of a job (or one of its questions or batches) was parsed,
+/// e.g. "3 question(s)" or the parse error.
+pub fn record_parse_outcome(
+    job_id: &str,
+    index: Option<usize>,
+    batch: Option<usize>,
+    outcome: impl Into<String>,
+) {
+    append(&LogLine::Parse {
+        job_id: job_id.to_string(),
+        index,
+        batch,
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        outcome: outcome.into(),
+    });
+}
+
+/// Most recent interactions first.
+pub fn list(limit: usize, offset: usize) -> Vec<LlmLogSummary> {
+    read_all()
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(LlmLogSummary::from)
+        .collect()
+}
+
+/// Case-insensitive search over job id, subject, topics, prompt, response and errors.
+pub fn search(query: &str, limit: usize) -> Vec<LlmLogSummary> {
+    let needle = query.trim().to_lowercase();
+    read_all()
+        .iter()
+        .filter(|record| needle.is_empty() || matches_query(record, &needle))
+        .take(limit)
+        .map(LlmLogSummary::from)
+        .collect()
+}
+
+pub fn get(id: &str) -> Option<LlmLogRecord> {
+    read_all().into_iter().find(|record| record.id == id)
+}
+
+pub fn latest() -> Option<LlmLogRecord> {
+    read_all().into_iter().next()
+}
+
+pub fn new_record_id() -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+    let rand_part: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect();
+    format!(
+        "llm_{}_{}",
+        chrono::Utc::now().timestamp_millis(),
+        rand_part
+    )
+}
+
+fn matches_query(record: &LlmLogRecord, needle: &str) -> bool {
+    [
+        record.job_id.as_str(),
+        record.subject.as_str(),
+        record.kind.as_str(),
+        record.prompt.as_str(),
+        record.response.as_str(),
+        record.error.as_deref().unwrap_or(""),
+        record.parse_outcome.as_deref().unwrap_or(""),
+    ]
+    .iter()
+    .any(|field| field.to_lowercase().contains(needle))
+        || record
+            .topics
+            .iter()
+            .any(|topic| topic.to_lowercase().contains(needle))
+}
+
+fn append(line: &LogLine) {
+    let Some(dir) = log_dir() else {
+        return;
+    };
+    let json = match serde_json::to_string(line) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("WARNING: Failed to serialize LLM log record: {}", e);
+            return;
+        }
+    };
+
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Err(e) = fs::create_dir_all(dir) {
+        eprintln!("WARNING: Failed to create {}: {}", dir.display(), e);
+        return;
+    }
+    rotate_if_needed(dir, max_bytes(), max_age());
+
+    let path = dir.join(ACTIVE_FILE);
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(mut file) => {
+            let _ = writeln!(file, "{}", json);
+        }
+        Err(e) => eprintln!("WARNING: Failed to write {}: {}", path.display(), e),
+    }
+}
+
+fn rotate_if_needed(dir: &Path, max_bytes: u64, max_age: Duration) {
+    let active = dir.join(ACTIVE_FILE);
+    let size = fs::metadata(&active).map(|m| m.len()).unwrap_or(0);
+    if size > max_bytes {
+        let rotated = dir.join(format!(
+            "interactions-{}.jsonl",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f")
+        ));
+        if let Err(e) = fs::rename(&active, &rotated) {
+            eprintln!("WARNING: Failed to rotate LLM log: {}", e);
+        }
+    }
+
+    let now = SystemTime::now();
+    for path in rotated_files(dir) {
+        let expired = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > max_age);
+        if expired {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// Rotated files, newest first (their names sort by timestamp).
+fn rotated_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("interactions-") && name.ends_with(".jsonl"))
+        })
+        .collect();
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// All interaction records, newest first, with parse outcomes merged in.
+fn read_all() -> Vec<LlmLogRecord> {
+    let Some(dir) = log_dir() else {
+        return Vec::new();
+    };
+    read_dir_records(dir)
+}
+
+fn read_dir_records(dir: &Path) -> Vec<LlmLogRecord> {
+    let mut files = rotated_files(dir);
+    files.reverse();
+    files.push(dir.join(ACTIVE_FILE));
+
+    let mut records = Vec::new();
+    let mut outcomes: HashMap<(String, Option<usize>, Option<usize>), String> = HashMap::new();
+
+    for path in files {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<LogLine>(line) {
+                Ok(LogLine::Interaction(record)) => records.push(*record),
+                Ok(LogLine::Parse {
+                    job_id,
+                    index,
+                    batch,
+                    outcome,
+                    ..
+                }) => {
+                    outcomes.insert((job_id, index, batch), outcome);
+                }
+                Err(e) => eprintln!("WARNING: Skipping unreadable LLM log line: {}", e),
+            }
+        }
+    }
+
+    // A parse outcome belongs to the last interaction of its job (earlier ones are
+    // retries or repair round-trips that were parsed as part of the same outcome).
+    let mut seen = std::collections::HashSet::new();
+    records.reverse();
+    for record in &mut records {
+        let key = (record.job_id.clone(), record.index, record.batch);
+        if record.kind != "repair" && seen.insert(key.clone()) {
+            record.parse_outcome = outcomes.get(&key).cloned();
+        }
+    }
+    records
+}
+
+fn redaction_enabled() -> bool {
+    std::env::var("RUBRIX_LLM_LOG_REDACT")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+fn redact(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    let hex: String = digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("[redacted: {} chars, sha256 {}]", text.chars().count(), hex)
+}
+
+fn max_bytes() -> u64 {
+    std::env::var("RUBRIX_LLM_LOG_MAX_MB")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|mb| mb.max(1) * 1024 * 1024)
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+fn max_age() -> Duration {
+    let days = std::env::var("RUBRIX_LLM_LOG_MAX_AGE_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_AGE_DAYS);
+    Duration::from_secs(days * 24 * 60 * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(job_id: &str, kind: &str) -> LlmLogRecord {
+        LlmLogRecord {
+            id: new_record_id(),
+            job_id: job_id.to_string(),
+            index: None,
+            batch: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            kind: kind.to_string(),
+            subject: "Calculus".to_string(),
+            topics: vec!["limits".to_string()],
+            prompt: "Write questions".to_string(),
+            response: "[]".to_string(),
+            outcome: StreamOutcome::Completed,
+            error: None,
+            parse_outcome: None,
+            duration_ms: 12,
+            retries: 0,
+            remaining_tokens: Some(100),
+            diagnostics: Vec::new(),
+            redacted: false,
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rubrix-{}-{}", name, new_record_id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_lines(dir: &Path, name: &str, lines: &[LogLine]) {
+        let text: Vec<String> = lines
+            .iter()
+            .map(|line| serde_json::to_string(line).unwrap())
+            .collect();
+        fs::write(dir.join(name), text.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn test_reads_newest_first_and_merges_parse_outcome() {
+        let dir = temp_dir("llm-log-read");
+        write_lines(
+            &dir,
+            "interactions-20260101T000000.000.jsonl",
+            &[LogLine::Interaction(Box::new(sample(
+                "job_old", "generate",
+            )))],
+        );
+        write_lines(
+            &dir,
+            ACTIVE_FILE,
+            &[
+                LogLine::Interaction(Box::new(sample("job_new", "generate"))),
+                LogLine::Interaction(Box::new(LlmLogRecord {
+                    batch: Some(1),
+                    ..sample("job_new", "generate")
+                })),
+                LogLine::Interaction(Box::new(sample("job_new", "repair"))),
+                LogLine::Parse {
+                    job_id: "job_new".to_string(),
+                    index: None,
+                    batch: None,
+                    timestamp: String::new(),
+                    outcome: "2 question(s)".to_string(),
+                },
+                LogLine::Parse {
+                    job_id: "job_new".to_string(),
+                    index: None,
+                    batch: Some(1),
+                    timestamp: String::new(),
+                    outcome: "1 question(s)".to_string(),
+                },
+            ],
+        );
+
+        let records = read_dir_records(&dir);
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].kind, "repair");
+        assert_eq!(records[0].parse_outcome, None);
+        assert_eq!(records[1].batch, Some(1));
+        assert_eq!(records[1].parse_outcome.as_deref(), Some("1 question(s)"));
+        assert_eq!(records[2].job_id, "job_new");
+        assert_eq!(records[2].parse_outcome.as_deref(), Some("2 question(s)"));
+        assert_eq!(records[3].job_id, "job_old");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rotation_moves_oversized_file() {
+        let dir = temp_dir("llm-log-rotate");
+        fs::write(dir.join(ACTIVE_FILE), "x".repeat(64)).unwrap();
+        rotate_if_needed(&dir, 16, Duration::from_secs(3600));
+        assert!(!dir.join(ACTIVE_FILE).exists());
+        assert_eq!(rotated_files(&dir).len(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_redact_keeps_size_not_content() {
+        let redacted = redact("secret prompt");
+        assert!(redacted.contains("13 chars"));
+        assert!(!redacted.contains("secret"));
+    }
+}
@@ -1,11 +1,30 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod accommodate;
+mod assembly;
 mod auth;
+mod batches;
+mod blueprint;
+mod choices;
 mod config;
+mod java_check;
+mod jobs;
 mod knowledge;
 mod llm;
+mod llm_log;
+mod math_check;
+mod parametric;
 mod prompts;
 mod qti;
+mod raw_responses;
+mod regenerate;
+mod repair;
+mod response_cache;
+mod sources;
+mod sse;
+mod template;
+mod usage;
+mod verify;
 
 use futures_util::stream::{self, StreamExt};
 use serde::de::Deserializer;
@@ -249,32 +268,312 @@ fn topic_labels_for_prompt(
     labels.join(", ")
 }
 
+/// Prompt for `generate_questions`, with the inputs that top-up requests reuse.
+struct GenerationPrompt<'a> {
+    rendered: template::Rendered,
+    bank_examples: Vec<QuestionBankEntry>,
+    template: Option<&'a template::Template>,
+    topics_label: String,
+    sources: Option<sources::SourceMaterial>,
+}
+
+async fn generation_prompt<'a>(
+    request: &GenerationRequest,
+    knowledge: &'a knowledge::KnowledgeBase,
+) -> Result<GenerationPrompt<'a>, String> {
+    // Get rich examples from question bank (prefer these for better distractors)
+    let mut bank_examples = knowledge.get_bank_examples(
+        &request.subject,
+        &request.topics,
+        Some(&request.difficulty),
+        // Get up to 3 examples; rows with a cognitive level choose from a wider pool
+        if request.cognitive_level.is_some() { 12 } else { 3 },
+    );
+    // Blueprint rows with a cognitive level prefer examples at that level
+    if let Some(level) = &request.cognitive_level {
+        bank_examples.sort_by_key(|e| !blueprint::same_level(&e.cognitive_level, level));
+        bank_examples.truncate(3);
+    }
+
+    // Get prompt template for this subject
+    let template = knowledge.get_prompt(&request.subject)?;
+
+    // Convert selected topic IDs to display names for the prompt while keeping IDs for retrieval
+    let topics_label = topic_labels_for_prompt(&request.subject, &request.topics, knowledge);
+
+    let sources = sources::SourceMaterial::load(&request.sources).await?;
+
+    // Build prompt with JSON examples
+    let rendered = prompts::render_generation_prompt(
+        request,
+        &bank_examples,
+        template,
+        &topics_label,
+        sources.as_ref(),
+    );
+
+    Ok(GenerationPrompt {
+        rendered,
+        bank_examples,
+        template,
+        topics_label,
+        sources,
+    })
+}
+
+/// Prompt for regenerating one question of `all_questions` in some mode.
+struct RegenerationPrompt<'a> {
+    rendered: template::Rendered,
+    bank_examples: Vec<QuestionBankEntry>,
+    template: Option<&'a template::Template>,
+}
+
+fn regeneration_prompt<'a>(
+    current: &Question,
+    all_questions: &[Question],
+    instructions: Option<&str>,
+    mode: regenerate::RegenerateMode,
+    knowledge: &'a knowledge::KnowledgeBase,
+) -> Result<RegenerationPrompt<'a>, String> {
+    // Use the question's subject and topics, or fall back to defaults
+    let subject = if !current.subject.is_empty() {
+        current.subject.clone()
+    } else {
+        "Computer Science".to_string()
+    };
+    let topics: Vec<String> = if !current.topics.is_empty() {
+        current.topics.clone()
+    } else {
+        vec!["recursion".to_string()]
+    };
+
+    // Get one example for reference
+    let bank_examples = knowledge.get_bank_examples(&subject, &topics, None, 1);
+
+    // Get this subject's regeneration prompt template for the mode
+    let template = knowledge.get_regeneration_prompt(&subject, mode)?;
+    let topics_label = topic_labels_for_prompt(&subject, &topics, knowledge);
+
+    // Build prompt for single question regeneration
+    let rendered = prompts::render_regenerate_prompt(
+        current,
+        all_questions,
+        &bank_examples,
+        instructions,
+        mode,
+        template,
+        Some(&topics_label),
+    );
+
+    Ok(RegenerationPrompt {
+        rendered,
+        bank_examples,
+        template,
+    })
+}
+
+/// A prompt built exactly as a generation would build it, without calling the LLM.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptPreview {
+    pub prompt: String,
+    /// Question bank entries included as examples.
+    pub example_ids: Vec<String>,
+    /// Whether the subject's prompt template was used rather than the built-in fallback.
+    pub used_template: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_hash: Option<String>,
+    /// Template variables that were substituted.
+    pub filled_placeholders: Vec<String>,
+    /// Template variables that had no value; they reach the LLM verbatim as `{name}`.
+    pub unresolved_placeholders: Vec<String>,
+    pub estimated_tokens: u64,
+}
+
+impl PromptPreview {
+    fn new(
+        rendered: template::Rendered,
+        examples: &[QuestionBankEntry],
+        template: Option<&template::Template>,
+    ) -> Self {
+        PromptPreview {
+            estimated_tokens: usage::estimate_tokens(&rendered.text),
+            prompt: rendered.text,
+            example_ids: examples.iter().map(|e| e.id.clone()).collect(),
+            used_template: template.is_some(),
+            template_version: template.and_then(|t| t.version()).map(str::to_string),
+            template_hash: template.map(|t| t.hash().to_string()),
+            filled_placeholders: rendered.used,
+            unresolved_placeholders: rendered.missing,
+        }
+    }
+}
+
 // ============================================================================
 // Types
 // ============================================================================
 
 /// Question as displayed/edited in the UI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Question {
     #[serde(default)]
     pub id: String,
     #[serde(default)]
     pub text: String, // Question text in markdown format (may include code blocks)
+    /// The stem under the name some models use. Parsing moves it into an empty `text`,
+    /// so it is never serialized.
+    #[serde(default, skip_serializing)]
+    pub stem: Option<String>,
+    /// Code returned separately from the stem; shown after it as a fenced block.
+    #[serde(
+        default,
+        deserialize_with = "de_opt_string_or_json",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub code: Option<String>,
     #[serde(alias = "options")]
     pub answers: Vec<Answer>,
-    #[serde(default, deserialize_with = "de_opt_string_or_json")]
+    #[serde(default, alias = "correct_explanation", deserialize_with = "de_opt_string_or_json")]
     pub explanation: Option<String>, // Correct answer explanation
     #[serde(default, deserialize_with = "de_opt_string_or_json")]
     pub distractors: Option<String>, // Why wrong answers are tempting
+    /// The model's scratchpad (code trace, planned distractors). Kept as reviewer
+    /// notes; never exported.
+    #[serde(
+        default,
+        deserialize_with = "de_opt_string_or_json",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub work: Option<String>,
+    /// Wrong answer -> misconception map from the model. Parsing moves each entry onto
+    /// the matching `Answer.explanation`, so it is never serialized.
+    #[serde(default, skip_serializing)]
+    pub distractor_explanations: Option<serde_json::Value>,
     #[serde(default)]
     pub subject: String,
     #[serde(default)]
     pub topics: Vec<String>,
     #[serde(default)]
     pub difficulty: String,
+    /// Set when the question only parsed after an automatic repair round-trip.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub repaired: bool,
+    /// How the question was produced; absent for hand-written or imported questions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Outcome of the latest answer-key check, when one has run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<verify::Verification>,
+    /// Result of compiling and running the question's Java code, when it has any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_check: Option<java_check::CodeCheck>,
+    /// Numeric check of a Calculus answer key, when the question could be checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub math_check: Option<math_check::MathCheck>,
+    /// Passage of the attached source material the question was written from.
+    #[serde(
+        default,
+        deserialize_with = "sources::de_source_excerpt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source: Option<sources::SourceExcerpt>,
+    /// Blueprint row the question was generated for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blueprint: Option<blueprint::BlueprintTag>,
+    /// Simplified version for IEP/504 and ELL students, with the same concept and key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accommodation: Option<accommodate::Accommodation>,
+}
+
+impl Question {
+    /// The stem as students see it: `text`, then `code` as a fenced block when the
+    /// model returned the code separately. Only Computer Science code is labelled
+    /// Java; other subjects get a plain fence.
+    pub fn stem_markdown(&self) -> String {
+        let language = if self.subject == "Computer Science" {
+            "java"
+        } else {
+            ""
+        };
+        match self.code.as_deref().map(str::trim) {
+            Some(code) if !code.is_empty() => {
+                format!("{}\n\n```{}\n{}\n```", self.text.trim_end(), language, code)
+            }
+            _ => self.text.clone(),
+        }
+    }
+
+    /// Whether `other` has the same stem, code and choices, so check results for one
+    /// also hold for the other.
+    pub fn same_content(&self, other: &Question) -> bool {
+        self.text == other.text && self.code == other.code && self.answers == other.answers
+    }
+
+    /// Drop the check results and the accommodated version when this edit of
+    /// `previous` changed the stem, code or choices, since they describe the old
+    /// question (an accommodation would otherwise be exported with the old key).
+    pub fn clear_stale_results(&mut self, previous: &Question) {
+        if self.same_content(previous) {
+            return;
+        }
+        self.verification = None;
+        self.code_check = None;
+        self.math_check = None;
+        self.accommodation = None;
+    }
 }
 
+/// Template, examples and model behind a generated question, so prompt revisions
+/// can be compared.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    /// `generate`, `regenerate`, `parametric` or `bank`.
+    pub kind: String,
+    /// Label from the template's `{! version: ... }` comment.
+    #[serde(default)]
+    pub template_version: Option<String>,
+    /// Short hash of the template source; absent when the built-in prompt was used.
+    #[serde(default)]
+    pub template_hash: Option<String>,
+    #[serde(default)]
+    pub example_ids: Vec<String>,
+    pub provider: String,
+    pub model: String,
+    pub job_id: String,
+    pub generated_at: String,
+    /// Which part a targeted regeneration rewrote; absent for full rewrites.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regeneration_mode: Option<regenerate::RegenerateMode>,
+    /// Seed a parametric template was filled in with; the same seed gives the same variants.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Provenance {
+    fn new(
+        kind: &str,
+        template: Option<&template::Template>,
+        examples: &[QuestionBankEntry],
+        job_id: &str,
+    ) -> Self {
+        let (provider, model) = llm::backend_label();
+        Provenance {
+            kind: kind.to_string(),
+            template_version: template.and_then(|t| t.version()).map(str::to_string),
+            template_hash: template.map(|t| t.hash().to_string()),
+            example_ids: examples.iter().map(|e| e.id.clone()).collect(),
+            provider,
+            model,
+            job_id: job_id.to_string(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            regeneration_mode: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Answer {
     pub text: String,
     pub is_correct: bool,
@@ -318,6 +617,30 @@ pub struct GenerationRequest {
     pub notes: Option<String>,
     #[serde(default)]
     pub append: bool, // If true, append to existing questions
+    /// Paths of documents (.md, .txt, .docx, .pdf) to write the questions from.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Bloom's level code (`B3`); bank examples at that level are preferred.
+    #[serde(default)]
+    pub cognitive_level: Option<String>,
+}
+
+/// Variants to fill in from a parametric template, either bundled or from a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParametricRequest {
+    pub subject: String,
+    /// Id of one of the subject's bundled templates.
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Path of a template file; used when `template_id` is not set.
+    #[serde(default)]
+    pub path: Option<String>,
+    pub count: u32,
+    /// Random when absent; recorded in each question's provenance.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub append: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -325,6 +648,64 @@ pub struct RegenerateAllQuestionResult {
     pub index: usize,
     pub question: Option<Question>,
     pub error: Option<String>,
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// Expected token cost of a batch compared with the last known quota.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaEstimate {
+    pub requests: usize,
+    pub estimated_tokens: u64,
+    pub remaining_tokens: Option<u64>,
+    pub likely_to_exceed: bool,
+}
+
+/// Per-question cost assumed before any regeneration has been recorded.
+const DEFAULT_REGENERATE_TOKENS: u64 = 3_000;
+
+fn estimate_regenerate_all(requests: usize) -> QuotaEstimate {
+    let per_request =
+        usage::average_request_tokens("regenerate").unwrap_or(DEFAULT_REGENERATE_TOKENS);
+    let estimated_tokens = per_request * requests as u64;
+    let remaining_tokens = usage::remaining_tokens();
+    QuotaEstimate {
+        requests,
+        estimated_tokens,
+        remaining_tokens,
+        likely_to_exceed: remaining_tokens.is_some_and(|remaining| estimated_tokens > remaining),
+    }
+}
+
+/// A problem found while parsing raw LLM output, located in that text when possible.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseIssue {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl ParseIssue {
+    fn new(message: String, location: Option<prompts::TextLocation>) -> Self {
+        Self {
+            message,
+            line: location.map(|l| l.line),
+            column: location.map(|l| l.column),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReparseResult {
+    pub questions: Vec<Question>,
+    /// Items that were dropped; the parsed questions are still returned.
+    pub issues: Vec<ParseIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReparseError {
+    pub message: String,
+    pub issues: Vec<ParseIssue>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -348,6 +729,9 @@ pub struct WordExportOptions {
     pub shuffle_choices: bool,
     #[serde(default)]
     pub shuffle_questions: bool,
+    /// Export the accommodated versions instead, as one version in the original order.
+    #[serde(default)]
+    pub accommodated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -357,6 +741,11 @@ pub struct MdExportOptions {
     pub include_explanations: bool,
     #[serde(default = "default_true")]
     pub include_answer_key: bool,
+    /// Appendix listing template, examples and model per generated question.
+    #[serde(default)]
+    pub include_provenance: bool,
+    #[serde(default)]
+    pub accommodated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -364,6 +753,8 @@ pub struct MdExportOptions {
 pub struct QtiExportOptions {
     #[serde(default = "default_true")]
     pub shuffle_choices: bool,
+    #[serde(default)]
+    pub accommodated: bool,
 }
 
 fn default_true() -> bool {
@@ -557,6 +948,8 @@ pub struct AppState {
     questions: Mutex<Vec<Question>>,
     knowledge: knowledge::KnowledgeBase,
     credentials: Mutex<Option<SavedCredentials>>,
+    jobs: jobs::JobRegistry,
+    raw_responses: raw_responses::RawResponseStore,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -655,50 +1048,47 @@ fn truncate_tail_with_ellipsis(input: &str, max_chars: usize) -> String {
 }
 
 fn latest_llm_log_snapshot() -> Option<(String, String, String)> {
-    const PROMPT_MARKER: &str = "--- PROMPT ---";
-    const RESPONSE_MARKER: &str = "--- RESPONSE ---";
     const SNAPSHOT_MAX_CHARS: usize = 8_000;
-    const CANDIDATE_PATHS: [&str; 4] = [
-        "../llm_log.txt",
-        "./llm_log.txt",
-        "llm_log.txt",
-        "../llm_log_03012026.txt",
-    ];
-
-    let mut selected_path: Option<String> = None;
-    let mut content: Option<String> = None;
-
-    for candidate in CANDIDATE_PATHS.iter() {
-        if let Ok(text) = fs::read_to_string(candidate) {
-            if !text.trim().is_empty() {
-                selected_path = Some((*candidate).to_string());
-                content = Some(text);
-                break;
-            }
-        }
-    }
 
-    let source = selected_path?;
-    let text = content?;
-
-    let prompt_marker_idx = text.rfind(PROMPT_MARKER)?;
-    let response_marker_idx = text[prompt_marker_idx..]
-        .find(RESPONSE_MARKER)
-        .map(|offset| prompt_marker_idx + offset)?;
-
-    let prompt_raw = text[prompt_marker_idx + PROMPT_MARKER.len()..response_marker_idx].trim();
-    let response_raw = text[response_marker_idx + RESPONSE_MARKER.len()..].trim();
-
-    if prompt_raw.is_empty() || response_raw.is_empty() {
+    let record = llm_log::latest()?;
+    if record.redacted || record.prompt.trim().is_empty() || record.response.trim().is_empty() {
         return None;
     }
 
-    let prompt = truncate_with_ellipsis(prompt_raw, SNAPSHOT_MAX_CHARS);
-    let response = truncate_tail_with_ellipsis(response_raw, SNAPSHOT_MAX_CHARS);
+    let prompt = truncate_with_ellipsis(record.prompt.trim(), SNAPSHOT_MAX_CHARS);
+    let response = truncate_tail_with_ellipsis(record.response.trim(), SNAPSHOT_MAX_CHARS);
+    let source = llm_log::log_dir()
+        .map(|dir| format!("{} ({})", dir.display(), record.id))
+        .unwrap_or(record.id);
 
     Some((prompt, response, source))
 }
 
+/// Interaction-log summary of a parse attempt.
+fn parse_outcome_label(parsed: &Result<Vec<Question>, String>, response: &str) -> String {
+    let label = match parsed {
+        Ok(questions) => {
+            let repaired = questions.iter().filter(|q| q.repaired).count();
+            if repaired > 0 {
+                format!("parsed {} question(s), {} repaired", questions.len(), repaired)
+            } else {
+                format!("parsed {} question(s)", questions.len())
+            }
+        }
+        Err(e) => format!("failed: {}", e),
+    };
+    // Only the first question array is read, so say when the model sent another one
+    if prompts::has_ignored_trailing_array(response) {
+        format!("{}; ignored a later question array", label)
+    } else {
+        label
+    }
+}
+
+fn cancelled_parse_outcome(salvaged: &[Question]) -> String {
+    format!("cancelled; salvaged {} question(s)", salvaged.len())
+}
+
 async fn convert_markdown_to_docx(markdown: String) -> Result<Vec<u8>, String> {
     let payload = serde_json::json!({
         "markdown": markdown,
@@ -863,75 +1253,495 @@ fn get_topics(subject: String, state: State<AppState>) -> Vec<TopicInfo> {
     state.knowledge.get_topics(&subject)
 }
 
+/// Questions checked at once after a generation call.
+const VERIFY_CONCURRENCY: usize = 4;
+
+/// Gateway calls (batches and answer checks) one generation job makes at once, across
+/// all of its requests.
+const JOB_GATEWAY_CALLS: usize = 4;
+
+/// Run the post-parse checks on a freshly parsed question: the local math check
+/// always, and the model's answer-key check when verification is on.
+async fn check_answer(
+    question: Question,
+    all_questions: &[Question],
+    knowledge: &knowledge::KnowledgeBase,
+    gateway_auth: Option<llm::GatewayAuth>,
+    job: &jobs::Job,
+) -> Question {
+    let mut question = verify_answer(question, all_questions, knowledge, gateway_auth, job).await;
+    question.math_check = math_check::check(&question.stem_markdown(), &answer_choices(&question));
+    question
+}
+
+/// Choices as `(text, is_correct)` pairs, the shape the local checkers take.
+fn answer_choices(question: &Question) -> Vec<(String, bool)> {
+    question.answers.iter().map(|a| (a.text.clone(), a.is_correct)).collect()
+}
+
+/// Ask the model to check a question's key when verification is on. In `regenerate`
+/// mode a disputed question is regenerated once, with the verifier's reasoning as
+/// instructions, and the replacement is checked again.
+async fn verify_answer(
+    mut question: Question,
+    all_questions: &[Question],
+    knowledge: &knowledge::KnowledgeBase,
+    gateway_auth: Option<llm::GatewayAuth>,
+    job: &jobs::Job,
+) -> Question {
+    let mode = verify::mode();
+    if mode == verify::VerifyMode::Off || job.is_cancelled() {
+        return question;
+    }
+
+    let verification = verify::verify_question(&question, gateway_auth.clone(), job).await;
+    if verification.verdict == verify::Verdict::Disagree
+        && mode == verify::VerifyMode::Regenerate
+        && !job.is_cancelled()
+    {
+        match regenerate_disputed(
+            &question,
+            &verification,
+            all_questions,
+            knowledge,
+            gateway_auth.clone(),
+            job,
+        )
+        .await
+        {
+            Ok(mut replacement) => {
+                let mut recheck = verify::verify_question(&replacement, gateway_auth, job).await;
+                recheck.regenerated = true;
+                replacement.verification = Some(recheck);
+                return replacement;
+            }
+            Err(err) => eprintln!(
+                "WARNING: Could not regenerate disputed question {}: {}",
+                question.id, err
+            ),
+        }
+    }
+    question.verification = Some(verification);
+    question
+}
+
+async fn regenerate_disputed(
+    question: &Question,
+    verification: &verify::Verification,
+    all_questions: &[Question],
+    knowledge: &knowledge::KnowledgeBase,
+    gateway_auth: Option<llm::GatewayAuth>,
+    job: &jobs::Job,
+) -> Result<Question, String> {
+    let instructions = format!(
+        "An independent solver chose {} but the answer key marks {}. Its working: {}\n\
+         Make sure exactly one answer is correct and that the key matches it.",
+        verification.derived_answer.as_deref().unwrap_or("another choice"),
+        verification.marked_answer.as_deref().unwrap_or("no choice"),
+        verification.reasoning
+    );
+    let built = regeneration_prompt(
+        question,
+        all_questions,
+        Some(&instructions),
+        regenerate::RegenerateMode::Full,
+        knowledge,
+    )?;
+    let prompt = built.rendered.text;
+    let stream = llm::StreamTarget::new(None, job).with_log_context(
+        "regenerate",
+        &question.subject,
+        &question.topics,
+    );
+    let response = llm::generate(&prompt, stream, gateway_auth.clone(), job).await?;
+    if job.is_cancelled() {
+        return Err("Cancelled".to_string());
+    }
+    let mut parsed =
+        repair::parse_with_repair(&response, Some(1), |_, _| prompt.clone(), gateway_auth, job)
+            .await?;
+    if parsed.is_empty() {
+        return Err("Failed to generate replacement question".to_string());
+    }
+
+    regenerate::replace_disputed(
+        question,
+        parsed.remove(0),
+        Provenance::new("regenerate", built.template, &built.bank_examples, &job.id),
+    )
+}
+
 #[tauri::command]
 async fn generate_questions(
     request: GenerationRequest,
+    job_id: Option<String>,
+    bypass_cache: Option<bool>,
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
-) -> Result<Vec<Question>, String> {
-    // Get rich examples from question bank (prefer these for better distractors)
-    let bank_examples = state.knowledge.get_bank_examples(
-        &request.subject,
-        &request.topics,
-        Some(&request.difficulty),
-        3, // Get up to 3 examples
+) -> Result<batches::GenerationRun, String> {
+    let total_batches = batches::split(request.count as usize, batches::batch_size()).len();
+    let job = state.jobs.start(job_id)?;
+    let generation = GenerationJob::new(
+        &state,
+        &job,
+        &app_handle,
+        bypass_cache.unwrap_or(false),
+        total_batches,
     );
+    let new_questions = generate_request(&generation, &request, 0, 0).await?;
 
-    // Get prompt template for this subject
-    let prompt_template = state.knowledge.get_prompt(&request.subject);
+    Ok(batches::GenerationRun {
+        questions: store_generated(&state, new_questions, request.append),
+        failed: generation.failed_batches(),
+    })
+}
 
-    // Convert selected topic IDs to display names for the prompt while keeping IDs for retrieval
-    let topics_label = topic_labels_for_prompt(&request.subject, &request.topics, &state.knowledge);
+/// Add generated questions to the set (or replace it), numbering them after any
+/// existing ones, and return the whole set for the frontend to display.
+fn store_generated(
+    state: &AppState,
+    mut new_questions: Vec<Question>,
+    append: bool,
+) -> Vec<Question> {
+    let mut stored = state.questions.lock().unwrap();
 
-    // Build prompt with JSON examples
-    let prompt =
-        prompts::build_generation_prompt(&request, &bank_examples, prompt_template, &topics_label);
+    // Batches and blueprint rows each start at q1
+    let start_id = if append { stored.len() } else { 0 };
+    for (i, q) in new_questions.iter_mut().enumerate() {
+        q.id = format!("q{}", start_id + i + 1);
+    }
+    if append {
+        stored.extend(new_questions);
+    } else {
+        *stored = new_questions;
+    }
+    stored.clone()
+}
+
+/// One generation job, which may cover several requests (blueprint rows) and batches.
+/// When it has more than one batch, streams are tagged by batch and each finished batch
+/// is reported as `generation-progress`.
+struct GenerationJob<'a> {
+    knowledge: &'a knowledge::KnowledgeBase,
+    gateway_auth: Option<llm::GatewayAuth>,
+    job: &'a jobs::Job,
+    app_handle: &'a AppHandle,
+    raw_responses: &'a raw_responses::RawResponseStore,
+    bypass_cache: bool,
+    total_batches: usize,
+    completed_batches: AtomicUsize,
+    produced_questions: AtomicUsize,
+    failed_batches: Mutex<Vec<batches::BatchFailure>>,
+    gateway_slots: tokio::sync::Semaphore,
+}
+
+impl<'a> GenerationJob<'a> {
+    fn new(
+        state: &'a AppState,
+        job: &'a jobs::Job,
+        app_handle: &'a AppHandle,
+        bypass_cache: bool,
+        total_batches: usize,
+    ) -> Self {
+        let gateway_auth = state
+            .credentials
+            .lock()
+            .unwrap()
+            .clone()
+            .map(|creds| llm::GatewayAuth {
+                user: creds.username,
+                password_hash: auth::hash_password(&creds.password),
+            });
+        GenerationJob {
+            knowledge: &state.knowledge,
+            gateway_auth,
+            job,
+            app_handle,
+            raw_responses: &state.raw_responses,
+            bypass_cache,
+            total_batches,
+            completed_batches: AtomicUsize::new(0),
+            produced_questions: AtomicUsize::new(0),
+            failed_batches: Mutex::new(Vec::new()),
+            gateway_slots: tokio::sync::Semaphore::new(JOB_GATEWAY_CALLS),
+        }
+    }
 
-    let gateway_auth = state
-        .credentials
-        .lock()
-        .unwrap()
-        .clone()
-        .map(|creds| llm::GatewayAuth {
-            user: creds.username,
-            password_hash: auth::hash_password(&creds.password),
-        });
+    fn split(&self) -> bool {
+        self.total_batches > 1
+    }
+
+    /// Wait for one of the job's `JOB_GATEWAY_CALLS` slots.
+    async fn gateway_slot(&self) -> tokio::sync::SemaphorePermit<'_> {
+        self.gateway_slots
+            .acquire()
+            .await
+            .expect("the gateway semaphore is never closed")
+    }
 
-    // Call LLM with streaming
-    let response = llm::generate(&prompt, Some(app_handle), gateway_auth).await?;
+    /// Batches of a split job that failed, in batch order.
+    fn failed_batches(&self) -> Vec<batches::BatchFailure> {
+        let mut failed = self.failed_batches.lock().unwrap().clone();
+        failed.sort_by_key(|failure| failure.batch);
+        failed
+    }
+
+    fn batch_finished(&self, questions: usize) {
+        let produced = self.produced_questions.fetch_add(questions, Ordering::SeqCst) + questions;
+        let completed = self.completed_batches.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.split() {
+            let _ = self.app_handle.emit_all(
+                "generation-progress",
+                batches::GenerationProgressEvent {
+                    job_id: self.job.id.clone(),
+                    completed,
+                    total: self.total_batches,
+                    questions: produced,
+                },
+            );
+        }
+    }
+}
+
+/// Generate, check and label the questions for one request without storing them.
+/// `first_batch` and `first_position` place the request's batches and questions
+/// within the job.
+async fn generate_request(
+    generation: &GenerationJob<'_>,
+    request: &GenerationRequest,
+    first_batch: usize,
+    first_position: usize,
+) -> Result<Vec<Question>, String> {
+    let GenerationPrompt {
+        rendered: template::Rendered { text: prompt, .. },
+        bank_examples,
+        template: prompt_template,
+        topics_label,
+        sources,
+    } = generation_prompt(request, generation.knowledge).await?;
+
+    // Large counts are split into batches; one job covers them all so a cancel stops each
+    let sizes = batches::split(request.count as usize, batches::batch_size());
+    let total = sizes.len();
+    let batch_inputs = GenerationBatches {
+        request,
+        prompt,
+        bank_examples: &bank_examples,
+        template: prompt_template,
+        topics_label: &topics_label,
+        sources: sources.as_ref(),
+        sizes,
+        first_batch,
+        first_position,
+        produced: Mutex::new(Vec::new()),
+    };
+    let run = |batch: usize| {
+        let batch_inputs = &batch_inputs;
+        async move {
+            let result = generate_batch(generation, batch_inputs, batch).await;
+            generation.batch_finished(result.as_ref().map_or(0, Vec::len));
+            (batch, result)
+        }
+    };
 
-    // Parse response into questions
-    let mut new_questions = prompts::parse_llm_response(&response)?;
+    // The first batch runs alone so every later batch can be told what it wrote
+    let mut results = vec![run(0).await];
+    results.extend(
+        stream::iter(1..total)
+            .map(&run)
+            .buffer_unordered(batches::BATCH_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await,
+    );
+    results.sort_by_key(|(batch, _)| *batch);
+
+    // Keep every batch that produced questions and report the others; fail only when
+    // none produced any
+    let mut new_questions = Vec::new();
+    let mut first_error = None;
+    for (batch, result) in results {
+        match result {
+            Ok(questions) => new_questions.extend(questions),
+            Err(err) => {
+                if generation.split() {
+                    generation.failed_batches.lock().unwrap().push(batches::BatchFailure {
+                        batch: first_batch + batch,
+                        error: err.clone(),
+                    });
+                }
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    if let (true, Some(err)) = (new_questions.is_empty(), first_error) {
+        return Err(err);
+    }
+    let mut new_questions = batches::dedupe(new_questions);
 
-    // Set subject and topics on each generated question
+    // Set subject, topics and provenance on each generated question
+    let job = generation.job;
+    let provenance = Provenance::new("generate", prompt_template, &bank_examples, &job.id);
     for question in &mut new_questions {
         question.subject = request.subject.clone();
         question.topics = request.topics.clone();
         question.difficulty = request.difficulty.clone();
+        question.provenance = Some(provenance.clone());
+    }
+    let generated = new_questions.clone();
+    let gateway_auth = &generation.gateway_auth;
+    let generated = &generated;
+    Ok(stream::iter(new_questions.into_iter().map(|question| async move {
+        let _slot = generation.gateway_slot().await;
+        check_answer(question, generated, generation.knowledge, gateway_auth.clone(), job).await
+    }))
+    .buffered(VERIFY_CONCURRENCY)
+    .collect::<Vec<_>>()
+    .await)
+}
+
+/// Inputs shared by the batches of one generation request.
+struct GenerationBatches<'a> {
+    request: &'a GenerationRequest,
+    /// The full-request prompt, used as-is when the first batch covers the whole request.
+    prompt: String,
+    bank_examples: &'a [QuestionBankEntry],
+    template: Option<&'a template::Template>,
+    topics_label: &'a str,
+    sources: Option<&'a sources::SourceMaterial>,
+    /// Question count of each batch.
+    sizes: Vec<usize>,
+    /// Number of this request's first batch within the job.
+    first_batch: usize,
+    /// Position of this request's first question within the job.
+    first_position: usize,
+    /// Questions from finished batches, listed in later prompts to avoid repeats.
+    produced: Mutex<Vec<Question>>,
+}
+
+/// Generate and parse one batch of a generation request.
+async fn generate_batch(
+    generation: &GenerationJob<'_>,
+    inputs: &GenerationBatches<'_>,
+    batch: usize,
+) -> Result<Vec<Question>, String> {
+    let job = generation.job;
+    let request = inputs.request;
+    let _slot = generation.gateway_slot().await;
+    if job.is_cancelled() {
+        return Err("Generation cancelled".to_string());
     }
+    let count = inputs.sizes[batch];
+    let produced = inputs.produced.lock().unwrap().clone();
+    // The first batch gets the regular prompt; later ones are told what it wrote
+    let prompt = if batch == 0 && count == request.count as usize {
+        inputs.prompt.clone()
+    } else if batch == 0 {
+        let first = GenerationRequest {
+            count: count as u32,
+            ..request.clone()
+        };
+        prompts::render_generation_prompt(
+            &first,
+            inputs.bank_examples,
+            inputs.template,
+            inputs.topics_label,
+            inputs.sources,
+        )
+        .text
+    } else {
+        prompts::build_top_up_prompt(
+            request,
+            inputs.bank_examples,
+            inputs.template,
+            inputs.topics_label,
+            inputs.sources,
+            count,
+            &produced,
+        )
+    };
 
-    // Store in state (append or replace)
-    let mut stored = state.questions.lock().unwrap();
+    // Call LLM with streaming; finished questions are pushed to the UI as they close
+    let job_batch = inputs.first_batch + batch;
+    let mut stream = llm::StreamTarget::new(Some(generation.app_handle.clone()), job)
+        .with_question_events()
+        .with_log_context("generate", &request.subject, &request.topics)
+        .with_cache_bypass(generation.bypass_cache);
+    if generation.split() {
+        let first_position = inputs.first_position + inputs.sizes[..batch].iter().sum::<usize>();
+        stream = stream.for_batch(job_batch, first_position);
+    }
+    let response = llm::generate(&prompt, stream, generation.gateway_auth.clone(), job).await?;
+    // Batch responses are stored as `<job>-b<n>`
+    let response_id = if generation.split() {
+        format!("{}-b{}", job.id, job_batch + 1)
+    } else {
+        job.id.clone()
+    };
+    generation.raw_responses.record(
+        Some(generation.app_handle),
+        raw_responses::RawResponse::new(
+            &response_id,
+            &request.subject,
+            &request.topics,
+            &request.difficulty,
+            &response,
+        ),
+    );
 
-    if request.append {
-        // Renumber IDs to continue from existing
-        let start_id = stored.len();
-        for (i, q) in new_questions.iter_mut().enumerate() {
-            q.id = format!("q{}", start_id + i + 1);
+    // Parse response into questions; a cancelled run keeps whatever questions were complete.
+    // Malformed JSON is repaired, and unrecoverable items re-requested, before giving up.
+    let log_batch = generation.split().then_some(job_batch);
+    let mut questions = if job.is_cancelled() {
+        let salvaged = prompts::parse_partial_llm_response(&response);
+        llm_log::record_parse_outcome(&job.id, None, log_batch, cancelled_parse_outcome(&salvaged));
+        if salvaged.is_empty() {
+            return Err("Generation cancelled".to_string());
         }
-        stored.extend(new_questions.clone());
+        salvaged
     } else {
-        *stored = new_questions.clone();
-    }
+        let parsed = repair::parse_with_repair(
+            &response,
+            Some(count),
+            |missing, kept| {
+                let avoid: Vec<Question> = produced.iter().chain(kept).cloned().collect();
+                prompts::build_top_up_prompt(
+                    request,
+                    inputs.bank_examples,
+                    inputs.template,
+                    inputs.topics_label,
+                    inputs.sources,
+                    missing,
+                    &avoid,
+                )
+            },
+            generation.gateway_auth.clone(),
+            job,
+        )
+        .await;
+        llm_log::record_parse_outcome(
+            &job.id,
+            None,
+            log_batch,
+            parse_outcome_label(&parsed, &response),
+        );
+        parsed?
+    };
 
-    // Return all questions (for frontend to display)
-    Ok(stored.clone())
+    // Check each cited passage against the material and keep the exact excerpt
+    if let Some(sources) = inputs.sources {
+        sources::attach_excerpts(&mut questions, sources);
+    }
+    inputs.produced.lock().unwrap().extend(questions.iter().cloned());
+    Ok(questions)
 }
 
 #[tauri::command]
 async fn regenerate_question(
     index: usize,
     instructions: Option<String>,
+    mode: Option<regenerate::RegenerateMode>,
+    job_id: Option<String>,
+    bypass_cache: Option<bool>,
     state: State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<Question, String> {
@@ -942,41 +1752,16 @@ async fn regenerate_question(
     }
 
     let current = &current_questions[index];
+    let mode = mode.unwrap_or_default();
 
-    // Use the question's subject and topics, or fall back to defaults
-    let subject = if !current.subject.is_empty() {
-        &current.subject
-    } else {
-        "Computer Science"
-    };
-    let topics: &Vec<String> = if !current.topics.is_empty() {
-        &current.topics
-    } else {
-        // Use a temporary Vec so the type matches
-        static DEFAULT_TOPICS: [&str; 1] = ["recursion"];
-        // Convert static array to Vec<String> and store in a static once cell
-        use once_cell::sync::Lazy;
-        static DEFAULT_TOPICS_VEC: Lazy<Vec<String>> =
-            Lazy::new(|| DEFAULT_TOPICS.iter().map(|s| s.to_string()).collect());
-        &DEFAULT_TOPICS_VEC
-    };
-
-    // Get one example for reference
-    let bank_examples = state.knowledge.get_bank_examples(subject, topics, None, 1);
-
-    // Get regeneration prompt template for this subject
-    let regeneration_prompt_template = state.knowledge.get_regeneration_prompt(subject);
-    let topics_label = topic_labels_for_prompt(subject, topics, &state.knowledge);
-
-    // Build prompt for single question regeneration
-    let prompt = prompts::build_regenerate_prompt(
+    let built = regeneration_prompt(
         current,
         &current_questions,
-        &bank_examples,
         instructions.as_deref(),
-        regeneration_prompt_template,
-        Some(&topics_label),
-    );
+        mode,
+        &state.knowledge,
+    )?;
+    let prompt = built.rendered.text;
 
     let gateway_auth = state
         .credentials
@@ -988,19 +1773,65 @@ async fn regenerate_question(
             password_hash: auth::hash_password(&creds.password),
         });
 
-    let response = llm::generate(&prompt, Some(app_handle), gateway_auth).await?;
+    let job = state.jobs.start(job_id)?;
+    let stream = llm::StreamTarget::new(Some(app_handle.clone()), &job)
+        .with_log_context("regenerate", &current.subject, &current.topics)
+        .with_cache_bypass(bypass_cache.unwrap_or(false));
+    let response = llm::generate(&prompt, stream, gateway_auth.clone(), &job).await?;
+    state.raw_responses.record(
+        Some(&app_handle),
+        raw_responses::RawResponse::new(
+            &job.id,
+            &current.subject,
+            &current.topics,
+            &current.difficulty,
+            &response,
+        ),
+    );
 
     // Parse the single question
-    let mut new_questions = prompts::parse_llm_response(&response)?;
+    let mut new_questions = if job.is_cancelled() {
+        let salvaged = prompts::parse_partial_llm_response(&response);
+        llm_log::record_parse_outcome(&job.id, None, None, cancelled_parse_outcome(&salvaged));
+        if salvaged.is_empty() {
+            return Err("Regeneration cancelled".to_string());
+        }
+        salvaged
+    } else {
+        let parsed = repair::parse_with_repair(
+            &response,
+            Some(1),
+            |_, _| prompt.clone(),
+            gateway_auth.clone(),
+            &job,
+        )
+        .await;
+        llm_log::record_parse_outcome(
+            &job.id,
+            None,
+            None,
+            parse_outcome_label(&parsed, &response),
+        );
+        parsed?
+    };
     if new_questions.is_empty() {
         return Err("Failed to generate replacement question".to_string());
     }
 
-    let mut new_question = new_questions.remove(0);
-    new_question.id = current.id.clone();
-    new_question.subject = current.subject.clone();
-    new_question.topics = current.topics.clone();
-    new_question.difficulty = current.difficulty.clone();
+    // Keep the id and metadata, and only the parts the mode did not ask to rewrite
+    let mut new_question = regenerate::merge(current, new_questions.remove(0), mode)?;
+    new_question.provenance = Some(Provenance {
+        regeneration_mode: (mode != regenerate::RegenerateMode::Full).then_some(mode),
+        ..Provenance::new("regenerate", built.template, &built.bank_examples, &job.id)
+    });
+    let new_question = check_answer(
+        new_question,
+        &current_questions,
+        &state.knowledge,
+        gateway_auth,
+        &job,
+    )
+    .await;
 
     // Update in state
     let mut stored = state.questions.lock().unwrap();
@@ -1009,9 +1840,604 @@ async fn regenerate_question(
     Ok(new_question)
 }
 
+/// Write accommodated versions of the questions at `indices` (all when absent) and
+/// attach them. A question that fails keeps any accommodation it already had.
+#[tauri::command]
+async fn accommodate_questions(
+    indices: Option<Vec<usize>>,
+    options: Option<accommodate::AccommodationOptions>,
+    job_id: Option<String>,
+    bypass_cache: Option<bool>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<accommodate::AccommodationRun, String> {
+    let snapshot = state.questions.lock().unwrap().clone();
+    let indices = indices.unwrap_or_else(|| (0..snapshot.len()).collect());
+    if indices.is_empty() {
+        return Err("No questions to accommodate".to_string());
+    }
+    if let Some(index) = indices.iter().find(|&&i| i >= snapshot.len()) {
+        return Err(format!("Invalid question index {}", index));
+    }
+    let options = options.unwrap_or_default();
+
+    let gateway_auth = state
+        .credentials
+        .lock()
+        .unwrap()
+        .clone()
+        .map(|creds| llm::GatewayAuth {
+            user: creds.username,
+            password_hash: auth::hash_password(&creds.password),
+        });
+
+    let job = state.jobs.start(job_id)?;
+    let (job, snapshot, gateway_auth, app_handle) = (&job, &snapshot, &gateway_auth, &app_handle);
+    let bypass_cache = bypass_cache.unwrap_or(false);
+    let total = indices.len();
+    let completed = &AtomicUsize::new(0);
+    let results: Vec<(usize, Result<accommodate::Accommodation, String>)> =
+        stream::iter(indices.into_iter().map(|index| async move {
+            let result = accommodate::accommodate_question(
+                &snapshot[index],
+                &options,
+                gateway_auth.clone(),
+                bypass_cache,
+                job,
+            )
+            .await;
+            let _ = app_handle.emit_all(
+                "accommodation-progress",
+                accommodate::AccommodationProgressEvent {
+                    job_id: job.id.clone(),
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                },
+            );
+            (index, result)
+        }))
+        .buffer_unordered(accommodate::ACCOMMODATION_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut stored = state.questions.lock().unwrap();
+    let mut failed = Vec::new();
+    for (index, result) in results {
+        match result {
+            Ok(accommodation) => {
+                if let Some(question) =
+                    stored.get_mut(index).filter(|q| q.id == snapshot[index].id)
+                {
+                    question.accommodation = Some(accommodation);
+                }
+            }
+            Err(error) => failed.push(accommodate::AccommodationFailure { index, error }),
+        }
+    }
+    failed.sort_by_key(|failure| failure.index);
+    if failed.len() == total {
+        let first = &failed[0];
+        return Err(if total == 1 {
+            first.error.clone()
+        } else {
+            format!("Question {}: {}", first.index + 1, first.error)
+        });
+    }
+    Ok(accommodate::AccommodationRun {
+        questions: stored.clone(),
+        failed,
+    })
+}
+
+/// Bundled parametric templates for a subject.
+#[tauri::command]
+fn list_parametric_templates(
+    subject: String,
+    state: State<AppState>,
+) -> Vec<parametric::ParametricTemplateInfo> {
+    state
+        .knowledge
+        .get_parametric_templates(&subject)
+        .iter()
+        .map(parametric::ParametricTemplate::info)
+        .collect()
+}
+
+/// Fill in a parametric template locally; no gateway call is made.
+#[tauri::command]
+fn generate_from_template(
+    request: ParametricRequest,
+    state: State<AppState>,
+) -> Result<Vec<Question>, String> {
+    let from_file;
+    let template = match (&request.template_id, &request.path) {
+        (Some(id), _) => state
+            .knowledge
+            .get_parametric_templates(&request.subject)
+            .iter()
+            .find(|t| t.id() == id)
+            .ok_or_else(|| format!("No parametric template {} for {}", id, request.subject))?,
+        (None, Some(path)) => {
+            let source = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read template {}: {}", path, e))?;
+            from_file = parametric::ParametricTemplate::load(&source)
+                .map_err(|e| format!("Invalid parametric template {}: {}", path, e))?;
+            &from_file
+        }
+        (None, None) => return Err("Choose a template".to_string()),
+    };
+
+    let seed = request.seed.unwrap_or_else(|| u64::from(rand::random::<u32>()));
+    let mut new_questions = template.variants(request.count as usize, seed, &request.subject)?;
+    for question in &mut new_questions {
+        question.math_check =
+            math_check::check(&question.stem_markdown(), &answer_choices(question));
+    }
+
+    let mut stored = state.questions.lock().unwrap();
+    if !request.append {
+        stored.clear();
+    }
+    let start_id = stored.len();
+    for (i, q) in new_questions.iter_mut().enumerate() {
+        q.id = format!("q{}", start_id + i + 1);
+    }
+    stored.extend(new_questions);
+    Ok(stored.clone())
+}
+
+/// Generate every row of a test blueprint in one job. Rows run concurrently, each with
+/// its own bank examples, and every question is tagged with the row it came from.
+#[tauri::command]
+async fn generate_from_blueprint(
+    blueprint: blueprint::Blueprint,
+    append: Option<bool>,
+    job_id: Option<String>,
+    bypass_cache: Option<bool>,
+    state: State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<blueprint::BlueprintRun, String> {
+    blueprint.validate()?;
+    let requests: Vec<GenerationRequest> =
+        blueprint.rows.iter().map(|row| blueprint.request(row)).collect();
+
+    // Place each row's batches and questions after those of the rows before it
+    let batch_size = batches::batch_size();
+    let mut offsets = Vec::new();
+    let (mut total_batches, mut first_position) = (0, 0);
+    for request in &requests {
+        offsets.push((total_batches, first_position));
+        total_batches += batches::split(request.count as usize, batch_size).len();
+        first_position += request.count as usize;
+    }
+
+    let job = state.jobs.start(job_id)?;
+    let generation = GenerationJob::new(
+        &state,
+        &job,
+        &app_handle,
+        bypass_cache.unwrap_or(false),
+        total_batches,
+    );
+    let generation = &generation;
+    let results = stream::iter(requests.iter().zip(offsets).map(
+        |(request, (first_batch, first_position))| async move {
+            generate_request(generation, request, first_batch, first_position).await
+        },
+    ))
+    .buffered(blueprint::ROW_CONCURRENCY)
+    .collect::<Vec<_>>()
+    .await;
+
+    // Keep every row that produced questions and report the others; fail only when none
+    // produced any
+    let mut new_questions = Vec::new();
+    let mut failed = Vec::new();
+    for (index, result) in results.into_iter().enumerate() {
+        match result {
+            Ok(questions) => {
+                let row = &blueprint.rows[index];
+                let topics_label =
+                    topic_labels_for_prompt(&blueprint.subject, &row.topics, &state.knowledge);
+                let tag = blueprint.tag(index, &topics_label);
+                new_questions.extend(questions.into_iter().map(|mut question| {
+                    question.blueprint = Some(tag.clone());
+                    question
+                }));
+            }
+            Err(error) => failed.push(blueprint::RowFailure {
+                row: index + 1,
+                error,
+            }),
+        }
+    }
+    if let (true, Some(first)) = (new_questions.is_empty(), failed.first()) {
+        return Err(format!("Blueprint row {}: {}", first.row, first.error));
+    }
+    let new_questions = batches::dedupe(new_questions);
+
+    Ok(blueprint::BlueprintRun {
+        questions: store_generated(&state, new_questions, append.unwrap_or_default()),
+        failed,
+        failed_batches: generation.failed_batches(),
+    })
+}
+
+/// Build a test from existing bank entries under the request's constraints and load it
+/// for editing. Constraints the bank cannot meet are explained in `unmet`.
+#[tauri::command]
+fn assemble_from_bank(
+    request: assembly::AssemblyRequest,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<assembly::AssemblyResult, String> {
+    request.validate()?;
+    let excluded = request.exclusions()?;
+    let entries: Vec<QuestionBankEntry> =
+        load_question_bank_entries(&request.subject, &state, &app_handle)?
+            .into_iter()
+            .filter(|entry| !excluded.contains(&entry.id))
+            .collect();
+    if entries.is_empty() {
+        return Err(format!("No unused bank entries for {}", request.subject));
+    }
+
+    let knowledge = &state.knowledge;
+    let labels: Vec<String> = request
+        .topics
+        .iter()
+        .map(|quota| {
+            topic_labels_for_prompt(&request.subject, std::slice::from_ref(&quota.topic), knowledge)
+        })
+        .collect();
+    let candidates: Vec<assembly::Candidate> = entries
+        .iter()
+        .map(|entry| assembly::Candidate {
+            difficulty: assembly::difficulty_name(&entry.difficulty).to_string(),
+            topics: (0..request.topics.len())
+                .filter(|&i| {
+                    knowledge.entry_covers(&request.subject, entry, &request.topics[i].topic)
+                })
+                .collect(),
+            code_heavy: assembly::is_code_heavy(&entry.text),
+        })
+        .collect();
+    let selection = assembly::select(&request, &labels, &candidates);
+
+    let new_questions: Vec<Question> = selection
+        .picked
+        .iter()
+        .map(|&i| {
+            let mut question = assembly::to_question(&entries[i], &request.subject);
+            question.math_check =
+                math_check::check(&question.stem_markdown(), &answer_choices(&question));
+            question
+        })
+        .collect();
+    Ok(assembly::AssemblyResult {
+        questions: store_generated(&state, new_questions, request.append),
+        selected: selection.picked.iter().map(|&i| entries[i].id.clone()).collect(),
+        unmet: selection.unmet,
+    })
+}
+
+#[tauri::command]
+fn load_blueprint(path: String) -> Result<blueprint::Blueprint, String> {
+    blueprint::load(&PathBuf::from(path))
+}
+
+#[tauri::command]
+fn save_blueprint(path: String, blueprint: blueprint::Blueprint) -> Result<(), String> {
+    blueprint::save(&PathBuf::from(path), &blueprint)
+}
+
+/// The prompt `generate_questions` would send for this request, without calling the LLM.
+#[tauri::command]
+async fn preview_generation_prompt(
+    request: GenerationRequest,
+    state: State<'_, AppState>,
+) -> Result<PromptPreview, String> {
+    let built = generation_prompt(&request, &state.knowledge).await?;
+    Ok(PromptPreview::new(
+        built.rendered,
+        &built.bank_examples,
+        built.template,
+    ))
+}
+
+/// The prompt `regenerate_question` would send for this question, without calling the LLM.
+#[tauri::command]
+fn preview_regeneration_prompt(
+    index: usize,
+    instructions: Option<String>,
+    mode: Option<regenerate::RegenerateMode>,
+    state: State<AppState>,
+) -> Result<PromptPreview, String> {
+    let current_questions = state.questions.lock().unwrap().clone();
+    let current = current_questions
+        .get(index)
+        .ok_or_else(|| "Invalid question index".to_string())?;
+    let built = regeneration_prompt(
+        current,
+        &current_questions,
+        instructions.as_deref(),
+        mode.unwrap_or_default(),
+        &state.knowledge,
+    )?;
+    Ok(PromptPreview::new(
+        built.rendered,
+        &built.bank_examples,
+        built.template,
+    ))
+}
+
+/// Logged LLM interactions, newest first, without prompt and response bodies.
+#[tauri::command]
+fn list_llm_interactions(
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<llm_log::LlmLogSummary>, String> {
+    Ok(llm_log::list(limit.unwrap_or(50), offset.unwrap_or(0)))
+}
+
+/// Case-insensitive search over job id, subject, topics, prompt, response and errors.
+#[tauri::command]
+fn search_llm_interactions(
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<llm_log::LlmLogSummary>, String> {
+    Ok(llm_log::search(&query, limit.unwrap_or(50)))
+}
+
+/// A single logged interaction including its prompt and response.
+#[tauri::command]
+fn get_llm_interaction(id: String) -> Result<llm_log::LlmLogRecord, String> {
+    llm_log::get(&id).ok_or_else(|| format!("No logged interaction with id {}", id))
+}
+
+/// Whether the response cache is on, and how many entries and bytes it holds.
+#[tauri::command]
+fn get_response_cache_status() -> Result<response_cache::CacheStatus, String> {
+    Ok(response_cache::status())
+}
+
+/// Turn the response cache on or off for this session (overrides `RUBRIX_LLM_CACHE`).
+#[tauri::command]
+fn set_response_cache_enabled(enabled: bool) -> Result<response_cache::CacheStatus, String> {
+    response_cache::set_enabled(enabled);
+    Ok(response_cache::status())
+}
+
+#[tauri::command]
+fn get_answer_verification_mode() -> verify::VerifyMode {
+    verify::mode()
+}
+
+/// Change the answer-verification mode for this session (overrides `RUBRIX_VERIFY_ANSWERS`).
+#[tauri::command]
+fn set_answer_verification_mode(mode: verify::VerifyMode) -> verify::VerifyMode {
+    verify::set_mode(mode);
+    verify::mode()
+}
+
+/// Check one question's answer key now, whatever the verification mode.
+#[tauri::command]
+async fn verify_question_answer(
+    index: usize,
+    job_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Question, String> {
+    let question = state
+        .questions
+        .lock()
+        .unwrap()
+        .get(index)
+        .cloned()
+        .ok_or_else(|| "Invalid question index".to_string())?;
+
+    let gateway_auth = state
+        .credentials
+        .lock()
+        .unwrap()
+        .clone()
+        .map(|creds| llm::GatewayAuth {
+            user: creds.username,
+            password_hash: auth::hash_password(&creds.password),
+        });
+
+    let job = state.jobs.start(job_id)?;
+    let verification = verify::verify_question(&question, gateway_auth, &job).await;
+
+    let mut stored = state.questions.lock().unwrap();
+    let stored_question = stored
+        .get_mut(index)
+        .ok_or_else(|| "Invalid question index".to_string())?;
+    stored_question.verification = Some(verification);
+    Ok(stored_question.clone())
+}
+
+/// Run the local answer checks over every question: compile and run its Java, and
+/// work its Calculus numerically. Results are stored on the questions, which are all
+/// returned.
+#[tauri::command]
+async fn check_question_code(state: State<'_, AppState>) -> Result<Vec<Question>, String> {
+    let snapshot = state.questions.lock().unwrap().clone();
+    let checks = java_check::check_all(
+        snapshot.iter().map(|q| (q.stem_markdown(), answer_choices(q))).collect(),
+    )
+    .await;
+
+    let mut stored = state.questions.lock().unwrap();
+    for (checked, code_check) in snapshot.iter().zip(checks) {
+        // Skip questions whose stem, code or choices were edited, or that were removed,
+        // while the checks ran
+        if let Some(question) = stored
+            .iter_mut()
+            .find(|q| q.id == checked.id && q.same_content(checked))
+        {
+            question.code_check = code_check;
+            question.math_check =
+                math_check::check(&question.stem_markdown(), &answer_choices(question));
+        }
+    }
+    Ok(stored.clone())
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BankCodeCheck {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_check: Option<java_check::CodeCheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    math_check: Option<math_check::MathCheck>,
+}
+
+/// Run the local answer checks over a subject's bank. `entries` checks unsaved edits;
+/// without it the saved bank is loaded. Entries neither checker applies to are left out.
+#[tauri::command]
+async fn check_bank_code(
+    subject: String,
+    entries: Option<Vec<QuestionBankEntry>>,
+    state: State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<Vec<BankCodeCheck>, String> {
+    let entries = match entries {
+        Some(entries) => entries,
+        None => load_question_bank_entries(&subject, &state, &app_handle)?,
+    };
+    let choices: Vec<Vec<(String, bool)>> = entries
+        .iter()
+        .map(|e| e.options.iter().map(|o| (o.text.clone(), o.is_correct)).collect())
+        .collect();
+    let checks = java_check::check_all(
+        entries.iter().zip(&choices).map(|(e, c)| (e.text.clone(), c.clone())).collect(),
+    )
+    .await;
+
+    Ok(entries
+        .into_iter()
+        .zip(choices)
+        .zip(checks)
+        .filter_map(|((entry, choices), code_check)| {
+            let math_check = math_check::check(&entry.text, &choices);
+            (code_check.is_some() || math_check.is_some()).then_some(BankCodeCheck {
+                id: entry.id,
+                code_check,
+                math_check,
+            })
+        })
+        .collect())
+}
+
+/// Delete all cached responses; returns how many were removed.
+#[tauri::command]
+fn clear_response_cache() -> Result<usize, String> {
+    response_cache::clear()
+}
+
+/// Delete a single cached response by key.
+#[tauri::command]
+fn delete_cached_response(key: String) -> Result<bool, String> {
+    response_cache::remove(&key)
+}
+
+/// Token usage per day and per job, and the last quota reported by the gateway.
+#[tauri::command]
+fn get_usage_summary() -> Result<usage::UsageSummary, String> {
+    Ok(usage::summary())
+}
+
+/// Expected cost of regenerating every question, so the UI can warn before starting.
+#[tauri::command]
+fn estimate_regenerate_all_usage(state: State<AppState>) -> Result<QuotaEstimate, String> {
+    Ok(estimate_regenerate_all(state.questions.lock().unwrap().len()))
+}
+
+/// Cancel a running generation, regeneration, or regenerate-all batch by job id.
+/// Returns false when the job already finished.
+#[tauri::command]
+fn cancel_generation(job_id: String, state: State<AppState>) -> Result<bool, String> {
+    Ok(state.jobs.cancel(&job_id))
+}
+
+/// The last raw LLM response of a job (batch items use `<job_id>-q<n>`).
+#[tauri::command]
+fn get_raw_response(
+    job_id: String,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<raw_responses::RawResponse, String> {
+    state
+        .raw_responses
+        .get(Some(&app_handle), &job_id)
+        .ok_or_else(|| format!("No raw response stored for job {}", job_id))
+}
+
+/// Run the response parser again on a job's raw output, optionally edited by the user,
+/// without another LLM call. Does not modify the current questions.
+#[tauri::command]
+fn reparse_raw_response(
+    job_id: String,
+    edited_text: Option<String>,
+    state: State<AppState>,
+    app_handle: AppHandle,
+) -> Result<ReparseResult, ReparseError> {
+    let stored = state.raw_responses.get(Some(&app_handle), &job_id);
+    let text = match (edited_text, &stored) {
+        (Some(text), _) => text,
+        (None, Some(stored)) => stored.text.clone(),
+        (None, None) => {
+            return Err(ReparseError {
+                message: format!("No raw response stored for job {}", job_id),
+                issues: Vec::new(),
+            })
+        }
+    };
+
+    let report = prompts::parse_llm_response_report(&text).map_err(|message| ReparseError {
+        message: message.clone(),
+        issues: vec![ParseIssue::new(message, None)],
+    })?;
+
+    let mut issues: Vec<ParseIssue> = report
+        .dropped
+        .iter()
+        .map(|item| ParseIssue::new(item.error.clone(), item.location))
+        .collect();
+
+    if report.questions.is_empty() {
+        if issues.is_empty() {
+            issues.push(ParseIssue::new(
+                report
+                    .array_error
+                    .clone()
+                    .unwrap_or_else(|| "No questions found in JSON response".to_string()),
+                report.array_error_location,
+            ));
+        }
+        return Err(ReparseError {
+            message: "No questions could be parsed".to_string(),
+            issues,
+        });
+    }
+
+    let mut questions = report.questions;
+    if let Some(stored) = stored {
+        for question in &mut questions {
+            question.subject = stored.subject.clone();
+            question.topics = stored.topics.clone();
+            question.difficulty = stored.difficulty.clone();
+        }
+    }
+
+    Ok(ReparseResult { questions, issues })
+}
+
 #[tauri::command]
 async fn regenerate_all_questions_parallel(
     max_concurrency: Option<usize>,
+    job_id: Option<String>,
+    bypass_cache: Option<bool>,
     state: State<'_, AppState>,
     app_handle: AppHandle,
 ) -> Result<Vec<RegenerateAllQuestionResult>, String> {
@@ -1024,6 +2450,12 @@ async fn regenerate_all_questions_parallel(
     let total = snapshot.len();
     let concurrency = max_concurrency.unwrap_or(35).clamp(1, 40);
 
+    let estimate = estimate_regenerate_all(total);
+    if estimate.likely_to_exceed {
+        // The frontend may have confirmed an older estimate, so report the one this run started with
+        let _ = app_handle.emit_all("regenerate-all-quota", estimate);
+    }
+
     let gateway_auth = state
         .credentials
         .lock()
@@ -1035,6 +2467,11 @@ async fn regenerate_all_questions_parallel(
         });
 
     let knowledge = &state.knowledge;
+    let raw_store = &state.raw_responses;
+    // One job covers the whole batch so a single cancel stops every pending regeneration
+    let bypass_cache = bypass_cache.unwrap_or(false);
+    let job = state.jobs.start(job_id)?;
+    let job = &job;
     let all_questions = snapshot.clone();
     let completed_counter = Arc::new(AtomicUsize::new(0));
 
@@ -1048,61 +2485,152 @@ async fn regenerate_all_questions_parallel(
                 let app_handle = app_handle.clone();
                 let completed_counter = Arc::clone(&completed_counter);
                 async move {
-                    let subject = if !current.subject.is_empty() {
-                        current.subject.clone()
-                    } else {
-                        "Computer Science".to_string()
-                    };
-
-                    let topics: Vec<String> = if !current.topics.is_empty() {
-                        current.topics.clone()
-                    } else {
-                        vec!["recursion".to_string()]
-                    };
-
-                    let bank_examples = knowledge.get_bank_examples(&subject, &topics, None, 1);
-                    let regeneration_prompt_template = knowledge.get_regeneration_prompt(&subject);
-                    let topics_label = topic_labels_for_prompt(&subject, &topics, knowledge);
+                    if job.is_cancelled() {
+                        let completed = completed_counter.fetch_add(1, Ordering::SeqCst) + 1;
+                        let _ = app_handle.emit_all(
+                            "regenerate-all-progress",
+                            RegenerateAllProgressEvent {
+                                completed,
+                                total,
+                                index,
+                                success: false,
+                            },
+                        );
+                        return RegenerateAllQuestionResult {
+                            index,
+                            question: None,
+                            error: Some("Cancelled".to_string()),
+                            cancelled: true,
+                        };
+                    }
 
-                    let prompt = prompts::build_regenerate_prompt(
+                    let response = match regeneration_prompt(
                         &current,
                         &all_questions,
-                        &bank_examples,
                         None,
-                        regeneration_prompt_template,
-                        Some(&topics_label),
-                    );
-
-                    let result = match llm::generate(&prompt, None, gateway_auth).await {
-                        Ok(response) => match prompts::parse_llm_response(&response) {
-                            Ok(mut new_questions) if !new_questions.is_empty() => {
-                                let mut new_question = new_questions.remove(0);
-                                new_question.id = current.id.clone();
-                                new_question.subject = current.subject.clone();
-                                new_question.topics = current.topics.clone();
-                                new_question.difficulty = current.difficulty.clone();
-
-                                RegenerateAllQuestionResult {
+                        regenerate::RegenerateMode::Full,
+                        knowledge,
+                    ) {
+                        Ok(built) => {
+                            let provenance = Provenance::new(
+                                "regenerate",
+                                built.template,
+                                &built.bank_examples,
+                                &job.id,
+                            );
+                            let prompt = built.rendered.text;
+                            // Each question streams on the shared job, tagged with its index
+                            let stream = llm::StreamTarget::new(Some(app_handle.clone()), job)
+                                .for_question(index)
+                                .with_log_context("regenerate", &current.subject, &current.topics)
+                                .with_cache_bypass(bypass_cache);
+                            llm::generate(&prompt, stream, gateway_auth.clone(), job)
+                                .await
+                                .map(|response| (prompt, provenance, response))
+                        }
+                        Err(err) => Err(err),
+                    };
+                    let result = match response {
+                        Ok((_, provenance, response)) if job.is_cancelled() => {
+                            let salvaged = prompts::parse_partial_llm_response(&response);
+                            llm_log::record_parse_outcome(
+                                &job.id,
+                                Some(index),
+                                None,
+                                cancelled_parse_outcome(&salvaged),
+                            );
+                            match salvaged.into_iter().next() {
+                                Some(mut new_question) => {
+                                    new_question.id = current.id.clone();
+                                    new_question.subject = current.subject.clone();
+                                    new_question.topics = current.topics.clone();
+                                    new_question.difficulty = current.difficulty.clone();
+                                    new_question.provenance = Some(provenance);
+
+                                    RegenerateAllQuestionResult {
+                                        index,
+                                        question: Some(new_question),
+                                        error: None,
+                                        cancelled: false,
+                                    }
+                                }
+                                None => RegenerateAllQuestionResult {
                                     index,
-                                    question: Some(new_question),
-                                    error: None,
+                                    question: None,
+                                    error: Some("Cancelled".to_string()),
+                                    cancelled: true,
+                                },
+                            }
+                        }
+                        Ok((prompt, provenance, response)) => {
+                            // Batch responses are stored per question as `<job>-q<n>`
+                            raw_store.record(
+                                Some(&app_handle),
+                                raw_responses::RawResponse::new(
+                                    &format!("{}-q{}", job.id, index + 1),
+                                    &current.subject,
+                                    &current.topics,
+                                    &current.difficulty,
+                                    &response,
+                                ),
+                            );
+                            let parsed = repair::parse_with_repair(
+                                &response,
+                                Some(1),
+                                |_, _| prompt.clone(),
+                                gateway_auth.clone(),
+                                job,
+                            )
+                            .await;
+                            llm_log::record_parse_outcome(
+                                &job.id,
+                                Some(index),
+                                None,
+                                parse_outcome_label(&parsed, &response),
+                            );
+                            match parsed {
+                                Ok(mut new_questions) if !new_questions.is_empty() => {
+                                    let mut new_question = new_questions.remove(0);
+                                    new_question.id = current.id.clone();
+                                    new_question.subject = current.subject.clone();
+                                    new_question.topics = current.topics.clone();
+                                    new_question.difficulty = current.difficulty.clone();
+                                    new_question.provenance = Some(provenance);
+                                    let new_question = check_answer(
+                                        new_question,
+                                        &all_questions,
+                                        knowledge,
+                                        gateway_auth,
+                                        job,
+                                    )
+                                    .await;
+
+                                    RegenerateAllQuestionResult {
+                                        index,
+                                        question: Some(new_question),
+                                        error: None,
+                                        cancelled: false,
+                                    }
                                 }
+                                Ok(_) => RegenerateAllQuestionResult {
+                                    index,
+                                    question: None,
+                                    error: Some("Failed to generate replacement question".to_string()),
+                                    cancelled: false,
+                                },
+                                Err(err) => RegenerateAllQuestionResult {
+                                    index,
+                                    question: None,
+                                    error: Some(err),
+                                    cancelled: false,
+                                },
                             }
-                            Ok(_) => RegenerateAllQuestionResult {
-                                index,
-                                question: None,
-                                error: Some("Failed to generate replacement question".to_string()),
-                            },
-                            Err(err) => RegenerateAllQuestionResult {
-                                index,
-                                question: None,
-                                error: Some(err),
-                            },
-                        },
+                        }
                         Err(err) => RegenerateAllQuestionResult {
                             index,
                             question: None,
                             error: Some(err),
+                            cancelled: false,
                         },
                     };
 
@@ -1140,13 +2668,18 @@ async fn regenerate_all_questions_parallel(
 }
 
 #[tauri::command]
-fn update_question(index: usize, question: Question, state: State<AppState>) -> Result<(), String> {
+fn update_question(
+    index: usize,
+    mut question: Question,
+    state: State<AppState>,
+) -> Result<(), String> {
     let mut stored = state.questions.lock().unwrap();
 
     if index >= stored.len() {
         return Err("Invalid question index".to_string());
     }
 
+    question.clear_stale_results(&stored[index]);
     stored[index] = question;
     Ok(())
 }
@@ -1158,11 +2691,6 @@ fn add_question(state: State<AppState>) -> Question {
     let new_question = Question {
         id: format!("q{}", stored.len() + 1),
         text: "New question".to_string(),
-        explanation: None,
-        distractors: None,
-        subject: String::new(),
-        topics: Vec::new(),
-        difficulty: String::new(),
         answers: vec![
             Answer {
                 text: "Correct answer".to_string(),
@@ -1185,6 +2713,7 @@ fn add_question(state: State<AppState>) -> Question {
                 explanation: None,
             },
         ],
+        ..Default::default()
     };
 
     stored.push(new_question.clone());
@@ -1482,7 +3011,10 @@ fn export_to_md(
     let opts = options.unwrap_or(MdExportOptions {
         include_explanations: false,
         include_answer_key: true,
+        include_provenance: false,
+        accommodated: false,
     });
+    let (questions, larger_spacing) = accommodate::export_set(&questions, opts.accommodated);
 
     qti::export_md_with_options(
         &title,
@@ -1493,6 +3025,8 @@ fn export_to_md(
             include_choices: true,
             shuffle_choices: true,
             shuffle_questions: false,
+            include_provenance: opts.include_provenance,
+            larger_spacing,
         },
     )
 }
@@ -1506,7 +3040,9 @@ fn export_to_qti(
     let questions = state.questions.lock().unwrap();
     let opts = options.unwrap_or(QtiExportOptions {
         shuffle_choices: true,
+        accommodated: false,
     });
+    let (questions, _) = accommodate::export_set(&questions, opts.accommodated);
     qti::export_qti_zip_with_options(
         &title,
         &questions,
@@ -1530,9 +3066,16 @@ async fn export_to_docx(
         version_count: 1,
         shuffle_choices: false,
         shuffle_questions: false,
+        accommodated: false,
     });
 
-    let version_count = opts.version_count.clamp(1, 20);
+    // The accommodated document keeps the original order so it pairs with the standard one
+    let (questions, larger_spacing) = accommodate::export_set(&questions, opts.accommodated);
+    let version_count = if opts.accommodated {
+        1
+    } else {
+        opts.version_count.clamp(1, 20)
+    };
     let include_choices = opts.include_choices;
     let shuffle_choices = include_choices && version_count > 1 && opts.shuffle_choices;
     let shuffle_questions = version_count > 1 && opts.shuffle_questions;
@@ -1547,6 +3090,8 @@ async fn export_to_docx(
                 include_choices,
                 shuffle_choices,
                 shuffle_questions,
+                include_provenance: false,
+                larger_spacing,
             },
         )?
     } else {
@@ -1563,6 +3108,8 @@ async fn export_to_docx(
                     include_choices,
                     shuffle_choices,
                     shuffle_questions,
+                    include_provenance: false,
+                    larger_spacing,
                 },
             )?;
 
@@ -1602,6 +3149,7 @@ async fn export_question_bank_to_docx(
         version_count: 1,
         shuffle_choices: false,
         shuffle_questions: false,
+        accommodated: false,
     });
 
     let version_count = opts.version_count.clamp(1, 20);
@@ -1743,6 +3291,8 @@ fn main() {
         questions: Mutex::new(Vec::new()),
         knowledge,
         credentials: Mutex::new(None),
+        jobs: jobs::JobRegistry::new(),
+        raw_responses: raw_responses::RawResponseStore::new(),
     };
 
     let new_document = CustomMenuItem::new("new_document", "New").accelerator("CmdOrCtrl+N");
@@ -1756,6 +3306,19 @@ fn main() {
     let regenerate_all_questions =
         CustomMenuItem::new("regenerate_all_questions", "Regenerate All Questions")
             .accelerator("CmdOrCtrl+Shift+R");
+    let check_code = CustomMenuItem::new("check_code", "Run Answer Checks")
+        .accelerator("CmdOrCtrl+Shift+J");
+    let from_template =
+        CustomMenuItem::new("generate_from_template", "Generate from Template…")
+            .accelerator("CmdOrCtrl+Shift+T");
+    let from_blueprint =
+        CustomMenuItem::new("generate_from_blueprint", "Generate from Blueprint…")
+            .accelerator("CmdOrCtrl+Shift+B");
+    let from_bank = CustomMenuItem::new("assemble_from_bank", "Assemble Test from Bank…")
+        .accelerator("CmdOrCtrl+Shift+A");
+    let accommodate =
+        CustomMenuItem::new("accommodate_questions", "Write Accommodated Versions…")
+            .accelerator("CmdOrCtrl+Shift+E");
     let export_md =
         CustomMenuItem::new("export_md", "Export Markdown…").accelerator("CmdOrCtrl+Shift+M");
     let export_qti =
@@ -1776,6 +3339,8 @@ fn main() {
     let zoom_reset = CustomMenuItem::new("zoom_reset", "Actual Size").accelerator("CmdOrCtrl+0");
     let about_catie = CustomMenuItem::new("about_catie", "About Catie");
     let submit_bug = CustomMenuItem::new("submit_bug", "Submit Bug");
+    let llm_history = CustomMenuItem::new("llm_history", "LLM History");
+    let usage_summary = CustomMenuItem::new("usage", "Usage");
 
     let export_menu = Submenu::new(
         "Export",
@@ -1795,6 +3360,11 @@ fn main() {
             .add_item(save_session)
             .add_item(close_document.clone())
             .add_item(regenerate_all_questions.clone())
+            .add_item(check_code)
+            .add_item(from_template)
+            .add_item(from_blueprint)
+            .add_item(from_bank)
+            .add_item(accommodate)
             .add_native_item(MenuItem::Separator)
             .add_submenu(export_menu)
             .add_native_item(MenuItem::Separator)
@@ -1811,6 +3381,11 @@ fn main() {
             .add_item(save_session)
             .add_item(close_document)
             .add_item(regenerate_all_questions)
+            .add_item(check_code)
+            .add_item(from_template)
+            .add_item(from_blueprint)
+            .add_item(from_bank)
+            .add_item(accommodate)
             .add_native_item(MenuItem::Separator)
             .add_submenu(export_menu)
             .add_native_item(MenuItem::Separator)
@@ -1853,12 +3428,22 @@ fn main() {
             .add_item(switch_bank),
     );
     #[cfg(target_os = "macos")]
-    let help_menu = Submenu::new("Help", Menu::new().add_item(submit_bug));
+    let help_menu = Submenu::new(
+        "Help",
+        Menu::new()
+            .add_item(llm_history)
+            .add_item(usage_summary)
+            .add_item(submit_bug),
+    );
 
     #[cfg(not(target_os = "macos"))]
     let help_menu = Submenu::new(
         "Help",
-        Menu::new().add_item(about_catie).add_item(submit_bug),
+        Menu::new()
+            .add_item(about_catie)
+            .add_item(llm_history)
+            .add_item(usage_summary)
+            .add_item(submit_bug),
     );
 
     #[cfg(target_os = "macos")]
@@ -1904,6 +3489,12 @@ fn main() {
         .manage(state)
         .menu(menu)
         .setup(|app| {
+            if let Some(dir) = app.path_resolver().app_local_data_dir() {
+                llm_log::init(dir.join("llm-log"));
+                response_cache::init(dir.join("llm-cache"));
+                usage::init(dir.join("usage.json"));
+            }
+            verify::init();
             if let Some(main_window) = app.get_window("main") {
                 restore_window_state(&main_window);
             }
@@ -1931,6 +3522,11 @@ fn main() {
                 "save_session" => Some("save_session"),
                 "close_document" => Some("close_document"),
                 "regenerate_all_questions" => Some("regenerate_all_questions"),
+                "check_code" => Some("check_code"),
+                "generate_from_template" => Some("generate_from_template"),
+                "generate_from_blueprint" => Some("generate_from_blueprint"),
+                "assemble_from_bank" => Some("assemble_from_bank"),
+                "accommodate_questions" => Some("accommodate_questions"),
                 "export_md" => Some("export_md"),
                 "export_qti" => Some("export_qti"),
                 "export_word" => Some("export_word"),
@@ -1939,6 +3535,8 @@ fn main() {
                 "switch_generate" => Some("switch_generate"),
                 "switch_bank" => Some("switch_bank"),
                 "submit_bug" => Some("submit_bug"),
+                "llm_history" => Some("llm_history"),
+                "usage" => Some("usage"),
                 _ => None,
             };
 
@@ -1974,6 +3572,32 @@ fn main() {
             generate_questions,
             regenerate_question,
             regenerate_all_questions_parallel,
+            accommodate_questions,
+            cancel_generation,
+            get_raw_response,
+            reparse_raw_response,
+            list_llm_interactions,
+            search_llm_interactions,
+            get_llm_interaction,
+            get_response_cache_status,
+            set_response_cache_enabled,
+            get_answer_verification_mode,
+            set_answer_verification_mode,
+            verify_question_answer,
+            check_question_code,
+            list_parametric_templates,
+            generate_from_template,
+            generate_from_blueprint,
+            assemble_from_bank,
+            load_blueprint,
+            save_blueprint,
+            check_bank_code,
+            clear_response_cache,
+            delete_cached_response,
+            get_usage_summary,
+            preview_generation_prompt,
+            preview_regeneration_prompt,
+            estimate_regenerate_all_usage,
             update_question,
             add_question,
             delete_question,
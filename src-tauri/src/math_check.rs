@@ -0,0 +1,1354 @@
+//! Numeric checks for Calculus answer keys
+//!
+//! The LaTeX in a question is parsed into expressions that can be evaluated, and the
+//! stem is matched against a few problem shapes: a derivative (`y = ...` followed by a
+//! bare `\frac{dy}{dx} =`, `f'(x)` or `f'(2)`, or `\frac{d}{dx}(...)`), a definite or
+//! improper integral, an indefinite integral, and a limit. The expected answer is
+//! computed numerically (central differences, adaptive Simpson, approaching the limit
+//! point from both sides) and each choice is compared with it at several sample points.
+//! Independently, choices that are mathematically equivalent (equal everywhere, or
+//! differing by a constant for antiderivatives) are reported, since they make the item
+//! ambiguous.
+//!
+//! Anything the parser does not understand is left unchecked rather than flagged.
+
+use crate::choices::letter;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{E, PI};
+
+/// Points where expressions in one variable are compared.
+const SAMPLES: [f64; 10] = [0.31, 0.57, 0.83, 1.27, 1.61, 2.13, 2.71, 3.3, -0.42, -0.77];
+/// Sample points that must agree before two functions count as equal.
+const MIN_SAMPLES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MathCheckStatus {
+    /// The computed answer equals a choice marked correct.
+    Match,
+    /// The computed answer equals a choice that is not marked correct.
+    Mismatch,
+    /// Every choice was understood and none equals the computed answer.
+    NoChoiceMatches,
+    /// The key was not checked; only choice equivalence was.
+    Unchecked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MathCheck {
+    pub status: MathCheckStatus,
+    /// What was computed, e.g. "definite integral of |t - 3/2| dt from 1 to 4".
+    #[serde(default)]
+    pub problem: String,
+    /// The computed value, for problems with a numeric answer.
+    #[serde(default)]
+    pub expected: Option<String>,
+    /// Letter of the choice equal to the computed answer.
+    #[serde(default)]
+    pub matched_choice: Option<String>,
+    /// Groups of choices that are mathematically the same, e.g. "B = D".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equivalent_choices: Vec<String>,
+    pub checked_at: String,
+}
+
+impl MathCheck {
+    /// Whether the check contradicts the key or found an ambiguous item.
+    pub fn is_flagged(&self) -> bool {
+        matches!(
+            self.status,
+            MathCheckStatus::Mismatch | MathCheckStatus::NoChoiceMatches
+        ) || !self.equivalent_choices.is_empty()
+    }
+}
+
+// ============================================================================
+// Expressions
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Sec,
+    Csc,
+    Cot,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Ln,
+    Exp,
+}
+
+impl Func {
+    fn from_command(name: &str) -> Option<Func> {
+        Some(match name {
+            "sin" => Func::Sin,
+            "cos" => Func::Cos,
+            "tan" => Func::Tan,
+            "sec" => Func::Sec,
+            "csc" => Func::Csc,
+            "cot" => Func::Cot,
+            "arcsin" => Func::Asin,
+            "arccos" => Func::Acos,
+            "arctan" => Func::Atan,
+            "sinh" => Func::Sinh,
+            "cosh" => Func::Cosh,
+            "tanh" => Func::Tanh,
+            "ln" | "log" => Func::Ln,
+            "exp" => Func::Exp,
+            _ => return None,
+        })
+    }
+
+    /// `\sin^{-1}` and friends.
+    fn inverse(self) -> Option<Func> {
+        match self {
+            Func::Sin => Some(Func::Asin),
+            Func::Cos => Some(Func::Acos),
+            Func::Tan => Some(Func::Atan),
+            _ => None,
+        }
+    }
+
+    fn apply(self, x: f64) -> f64 {
+        match self {
+            Func::Sin => x.sin(),
+            Func::Cos => x.cos(),
+            Func::Tan => x.tan(),
+            Func::Sec => 1.0 / x.cos(),
+            Func::Csc => 1.0 / x.sin(),
+            Func::Cot => 1.0 / x.tan(),
+            Func::Asin => x.asin(),
+            Func::Acos => x.acos(),
+            Func::Atan => x.atan(),
+            Func::Sinh => x.sinh(),
+            Func::Cosh => x.cosh(),
+            Func::Tanh => x.tanh(),
+            Func::Ln => x.ln(),
+            Func::Exp => x.exp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64),
+    Var(char),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Call(Func, Box<Expr>),
+    Abs(Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate with `var` bound to `x`; other variables evaluate to NaN.
+    fn eval(&self, var: char, x: f64) -> f64 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Var(c) if *c == var => x,
+            Expr::Var(_) => f64::NAN,
+            Expr::Neg(a) => -a.eval(var, x),
+            Expr::Add(a, b) => a.eval(var, x) + b.eval(var, x),
+            Expr::Sub(a, b) => a.eval(var, x) - b.eval(var, x),
+            Expr::Mul(a, b) => a.eval(var, x) * b.eval(var, x),
+            Expr::Div(a, b) => a.eval(var, x) / b.eval(var, x),
+            Expr::Pow(a, b) => pow(a.eval(var, x), b.eval(var, x), b),
+            Expr::Call(f, a) => f.apply(a.eval(var, x)),
+            Expr::Abs(a) => a.eval(var, x).abs(),
+        }
+    }
+
+    fn vars(&self, out: &mut Vec<char>) {
+        match self {
+            Expr::Num(_) => {}
+            Expr::Var(c) => {
+                if !out.contains(c) {
+                    out.push(*c);
+                }
+            }
+            Expr::Neg(a) | Expr::Call(_, a) | Expr::Abs(a) => a.vars(out),
+            Expr::Add(a, b)
+            | Expr::Sub(a, b)
+            | Expr::Mul(a, b)
+            | Expr::Div(a, b)
+            | Expr::Pow(a, b) => {
+                a.vars(out);
+                b.vars(out);
+            }
+        }
+    }
+
+    /// The expression's only variable, `None` for constants, `Err` for several.
+    fn single_var(&self) -> Result<Option<char>, ()> {
+        let mut vars = Vec::new();
+        self.vars(&mut vars);
+        match vars.as_slice() {
+            [] => Ok(None),
+            [v] => Ok(Some(*v)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Powers with a constant odd-denominator exponent keep real roots of negatives.
+fn pow(base: f64, power: f64, exponent: &Expr) -> f64 {
+    let value = base.powf(power);
+    if value.is_nan() && base < 0.0 {
+        if let Expr::Div(n, d) = exponent {
+            if let (Expr::Num(n), Expr::Num(d)) = (n.as_ref(), d.as_ref()) {
+                if d.fract() == 0.0 && (*d as i64) % 2 != 0 {
+                    let magnitude = (-base).powf(n / d);
+                    return if n.fract() == 0.0 && (*n as i64) % 2 != 0 {
+                        -magnitude
+                    } else {
+                        magnitude
+                    };
+                }
+            }
+        }
+    }
+    value
+}
+
+// ============================================================================
+// LaTeX parsing
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(f64),
+    Letter(char),
+    Cmd(String),
+    Sym(char),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Tok>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Tok::Num(
+                text.parse().map_err(|_| format!("bad number {}", text))?,
+            ));
+        } else if c == '\\' {
+            i += 1;
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            if i == start {
+                // `\,` `\;` `\!` `\ ` are spacing; `\{` `\}` are delimiters
+                match chars.get(i) {
+                    Some('{') => tokens.push(Tok::Sym('(')),
+                    Some('}') => tokens.push(Tok::Sym(')')),
+                    Some('|') => tokens.push(Tok::Sym('|')),
+                    _ => {}
+                }
+                i += 1;
+                continue;
+            }
+            let name: String = chars[start..i].iter().collect();
+            match name.as_str() {
+                "displaystyle" | "textstyle" | "left" | "right" | "big" | "Big" | "bigg"
+                | "Bigg" | "quad" | "qquad" | "mathrm" | "limits" => {
+                    // `\left.` and `\right.` are invisible delimiters
+                    if matches!(name.as_str(), "left" | "right") && chars.get(i) == Some(&'.') {
+                        i += 1;
+                    }
+                }
+                "cdot" | "times" => tokens.push(Tok::Sym('*')),
+                "div" => tokens.push(Tok::Sym('/')),
+                "dfrac" | "tfrac" => tokens.push(Tok::Cmd("frac".to_string())),
+                "lvert" | "rvert" => tokens.push(Tok::Sym('|')),
+                _ => tokens.push(Tok::Cmd(name)),
+            }
+        } else if c.is_ascii_alphabetic() {
+            tokens.push(Tok::Letter(c));
+            i += 1;
+        } else {
+            tokens.push(Tok::Sym(c));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Tok>,
+    pos: usize,
+    abs_depth: usize,
+}
+
+/// Parse a LaTeX expression such as `3\sin^2 x \cos x` or `\frac{1}{2e^2}`.
+fn parse(src: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        abs_depth: 0,
+    };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(tok) => Err(format!("unexpected {:?}", tok)),
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, sym: char) -> bool {
+        if self.peek() == Some(&Tok::Sym(sym)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, sym: char) -> Result<(), String> {
+        if self.eat(sym) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", sym))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        loop {
+            if self.eat('+') {
+                lhs = Expr::Add(Box::new(lhs), Box::new(self.term()?));
+            } else if self.eat('-') {
+                lhs = Expr::Sub(Box::new(lhs), Box::new(self.term()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            if self.eat('*') {
+                lhs = Expr::Mul(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.eat('/') {
+                lhs = Expr::Div(Box::new(lhs), Box::new(self.unary()?));
+            } else if self.starts_atom() {
+                if matches!(self.peek(), Some(Tok::Num(_)))
+                    && matches!(self.tokens.get(self.pos - 1), Some(Tok::Num(_)))
+                {
+                    return Err("two numbers in a row".to_string());
+                }
+                lhs = Expr::Mul(Box::new(lhs), Box::new(self.power()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            let exponent = self.exponent()?;
+            return Ok(Expr::Pow(Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    /// A braced group, or a single character: `x^23` is `x^{2}3`.
+    fn exponent(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Tok::Sym('{')) => self.atom(),
+            Some(Tok::Sym('-')) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.exponent()?)))
+            }
+            Some(Tok::Num(n)) => {
+                let text = n.to_string();
+                let mut digits = text.chars();
+                let first = digits.next().unwrap_or('0');
+                let rest: String = digits.collect();
+                if rest.is_empty() || !first.is_ascii_digit() {
+                    self.pos += 1;
+                    return Ok(Expr::Num(n));
+                }
+                self.tokens[self.pos] = Tok::Num(rest.parse().map_err(|_| "bad exponent")?);
+                Ok(Expr::Num(f64::from(first.to_digit(10).unwrap_or(0))))
+            }
+            _ => self.atom(),
+        }
+    }
+
+    fn starts_atom(&self) -> bool {
+        match self.peek() {
+            Some(Tok::Num(_)) | Some(Tok::Letter(_)) => true,
+            Some(Tok::Sym('(' | '[' | '{')) => true,
+            Some(Tok::Sym('|')) => self.abs_depth == 0,
+            Some(Tok::Cmd(name)) => {
+                matches!(name.as_str(), "frac" | "sqrt" | "pi")
+                    || Func::from_command(name).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn group(&mut self, open: char, close: char) -> Result<Expr, String> {
+        self.expect(open)?;
+        let saved = self.abs_depth;
+        self.abs_depth = 0;
+        let inner = self.expr()?;
+        self.abs_depth = saved;
+        self.expect(close)?;
+        Ok(inner)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Tok::Num(n)) => {
+                self.pos += 1;
+                Ok(Expr::Num(n))
+            }
+            Some(Tok::Letter('e')) => {
+                self.pos += 1;
+                Ok(Expr::Num(E))
+            }
+            Some(Tok::Letter(c)) => {
+                self.pos += 1;
+                Ok(Expr::Var(c))
+            }
+            Some(Tok::Sym('(')) => self.group('(', ')'),
+            Some(Tok::Sym('[')) => self.group('[', ']'),
+            Some(Tok::Sym('{')) => self.group('{', '}'),
+            Some(Tok::Sym('|')) => {
+                self.pos += 1;
+                self.abs_depth += 1;
+                let inner = self.expr()?;
+                self.abs_depth -= 1;
+                self.expect('|')?;
+                Ok(Expr::Abs(Box::new(inner)))
+            }
+            Some(Tok::Cmd(name)) => {
+                self.pos += 1;
+                match name.as_str() {
+                    "pi" => Ok(Expr::Num(PI)),
+                    "frac" => {
+                        let numerator = self.group('{', '}')?;
+                        let denominator = self.group('{', '}')?;
+                        Ok(Expr::Div(Box::new(numerator), Box::new(denominator)))
+                    }
+                    "sqrt" => {
+                        let index = if self.peek() == Some(&Tok::Sym('[')) {
+                            self.group('[', ']')?
+                        } else {
+                            Expr::Num(2.0)
+                        };
+                        let radicand = self.group('{', '}')?;
+                        Ok(Expr::Pow(
+                            Box::new(radicand),
+                            Box::new(Expr::Div(Box::new(Expr::Num(1.0)), Box::new(index))),
+                        ))
+                    }
+                    _ => match Func::from_command(&name) {
+                        Some(func) => self.call(&name, func),
+                        None => Err(format!("unsupported command \\{}", name)),
+                    },
+                }
+            }
+            Some(tok) => Err(format!("unexpected {:?}", tok)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    /// `\sin^2 x`, `\ln(3 - x)`, `\log_{2} x`, `\sin^{-1} x`, `\sin 2x`.
+    fn call(&mut self, name: &str, mut func: Func) -> Result<Expr, String> {
+        let mut base = None;
+        if name == "log" && self.eat('_') {
+            base = Some(self.exponent()?);
+        }
+        let mut power = None;
+        if self.eat('^') {
+            let exponent = self.exponent()?;
+            match (
+                exponent == Expr::Neg(Box::new(Expr::Num(1.0))),
+                func.inverse(),
+            ) {
+                (true, Some(inverse)) => func = inverse,
+                _ => power = Some(exponent),
+            }
+        }
+
+        let argument = match self.peek() {
+            Some(Tok::Sym('(' | '[' | '{' | '|')) => self.atom()?,
+            _ => {
+                // An unbracketed argument runs over numbers and letters: `\sin 2x`
+                let mut argument = self.simple_power()?;
+                while matches!(self.peek(), Some(Tok::Num(_)) | Some(Tok::Letter(_)))
+                    || self.peek() == Some(&Tok::Cmd("pi".to_string()))
+                {
+                    argument = Expr::Mul(Box::new(argument), Box::new(self.simple_power()?));
+                }
+                argument
+            }
+        };
+
+        let mut result = Expr::Call(func, Box::new(argument));
+        if let Some(base) = base {
+            result = Expr::Div(
+                Box::new(result),
+                Box::new(Expr::Call(Func::Ln, Box::new(base))),
+            );
+        }
+        if let Some(power) = power {
+            result = Expr::Pow(Box::new(result), Box::new(power));
+        }
+        Ok(result)
+    }
+
+    fn simple_power(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(Tok::Num(_)) | Some(Tok::Letter(_)) | Some(Tok::Cmd(_)) => self.power(),
+            _ => Err("missing function argument".to_string()),
+        }
+    }
+}
+
+// ============================================================================
+// Numerics
+// ============================================================================
+
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance * 1f64.max(a.abs()).max(b.abs())
+}
+
+fn derivative(f: &dyn Fn(f64) -> f64, x: f64, order: u32) -> f64 {
+    if order == 2 {
+        let h = 1e-3 * 1f64.max(x.abs());
+        return (f(x + h) - 2.0 * f(x) + f(x - h)) / (h * h);
+    }
+    let h = 1e-5 * 1f64.max(x.abs());
+    (f(x + h) - f(x - h)) / (2.0 * h)
+}
+
+/// An interval with its endpoint and midpoint values and its Simpson estimate.
+#[derive(Clone, Copy)]
+struct Panel {
+    a: f64,
+    b: f64,
+    fa: f64,
+    fm: f64,
+    fb: f64,
+    estimate: f64,
+}
+
+impl Panel {
+    fn new(a: f64, b: f64, fa: f64, fm: f64, fb: f64) -> Self {
+        let estimate = (b - a) / 6.0 * (fa + 4.0 * fm + fb);
+        Panel {
+            a,
+            b,
+            fa,
+            fm,
+            fb,
+            estimate,
+        }
+    }
+}
+
+/// Adaptive Simpson; `None` when the integrand is not finite somewhere it is sampled.
+fn simpson(f: &dyn Fn(f64) -> f64, a: f64, b: f64) -> Option<f64> {
+    fn step(f: &dyn Fn(f64) -> f64, panel: Panel, tolerance: f64, depth: u32) -> Option<f64> {
+        let m = (panel.a + panel.b) / 2.0;
+        let (flm, frm) = (f((panel.a + m) / 2.0), f((m + panel.b) / 2.0));
+        if !flm.is_finite() || !frm.is_finite() {
+            return None;
+        }
+        let left = Panel::new(panel.a, m, panel.fa, flm, panel.fm);
+        let right = Panel::new(m, panel.b, panel.fm, frm, panel.fb);
+        let error = left.estimate + right.estimate - panel.estimate;
+        if depth == 0 || error.abs() <= 15.0 * tolerance {
+            return Some(left.estimate + right.estimate + error / 15.0);
+        }
+        Some(
+            step(f, left, tolerance / 2.0, depth - 1)?
+                + step(f, right, tolerance / 2.0, depth - 1)?,
+        )
+    }
+
+    let (fa, fm, fb) = (f(a), f((a + b) / 2.0), f(b));
+    if !fa.is_finite() || !fm.is_finite() || !fb.is_finite() {
+        return None;
+    }
+    step(f, Panel::new(a, b, fa, fm, fb), 1e-10, 20)
+}
+
+/// `∫_a^b f`, where either bound may be infinite. Divergence is `Expected::DoesNotExist`.
+fn integrate(f: &dyn Fn(f64) -> f64, a: f64, b: f64) -> Option<Expected> {
+    if a.is_finite() && b.is_finite() {
+        return simpson(f, a, b).map(Expected::Value);
+    }
+    if b.is_infinite() && a.is_infinite() {
+        return None;
+    }
+    // Integrate over growing finite ranges and watch for convergence
+    let (start, sign) = if b.is_infinite() {
+        (a, b.signum())
+    } else {
+        (b, a.signum())
+    };
+    let partial = |length: f64| {
+        let end = start + sign * length;
+        simpson(f, start.min(end), start.max(end))
+    };
+    let mut previous = partial(32.0)?;
+    for length in [64.0, 128.0, 256.0, 512.0] {
+        let current = partial(length)?;
+        if close(current, previous, 1e-7) {
+            return Some(Expected::Value(current));
+        }
+        previous = current;
+    }
+    Some(Expected::DoesNotExist)
+}
+
+/// Richardson-extrapolated value of `g(h)` as `h → 0`, assuming the error is a power
+/// series in `h`. Stepping in from `h` avoids the cancellation of very small steps.
+fn extrapolate(g: &dyn Fn(f64) -> f64, h: f64) -> f64 {
+    let (a, b, c) = (g(h), g(h / 2.0), g(h / 4.0));
+    let (ab, bc) = (2.0 * b - a, 2.0 * c - b);
+    (4.0 * bc - ab) / 3.0
+}
+
+fn limit(f: &dyn Fn(f64) -> f64, target: LimitTarget) -> Option<Expected> {
+    let side = |approach: &dyn Fn(f64) -> f64| -> Option<Expected> {
+        let g = |h: f64| f(approach(h));
+        let (tiny, small) = (g(1e-6), g(1e-5));
+        if tiny.is_nan() || small.is_nan() {
+            return None;
+        }
+        if tiny.abs() > 1e5 && tiny.abs() > 5.0 * small.abs() {
+            return Some(Expected::Infinite(tiny.signum()));
+        }
+        let (coarse, fine) = (extrapolate(&g, 1e-2), extrapolate(&g, 1e-3));
+        if fine.is_finite() && close(coarse, fine, 1e-5) {
+            return Some(Expected::Value(fine));
+        }
+        // Bounded wiggles such as x sin(1/x) defeat extrapolation but settle anyway
+        if close(tiny, small, 1e-4) {
+            return Some(Expected::Value(tiny));
+        }
+        Some(Expected::DoesNotExist)
+    };
+    match target {
+        LimitTarget::Infinity(sign) => side(&|h| sign / h),
+        LimitTarget::Left(a) => side(&|h| a - h),
+        LimitTarget::Right(a) => side(&|h| a + h),
+        LimitTarget::Both(a) => {
+            let (left, right) = (side(&|h| a - h)?, side(&|h| a + h)?);
+            Some(match (left, right) {
+                (Expected::Value(l), Expected::Value(r)) if close(l, r, 1e-4) => {
+                    Expected::Value((l + r) / 2.0)
+                }
+                (Expected::Infinite(l), Expected::Infinite(r)) if l == r => Expected::Infinite(l),
+                _ => Expected::DoesNotExist,
+            })
+        }
+    }
+}
+
+// ============================================================================
+// Problems
+// ============================================================================
+
+#[derive(Debug, Clone, Copy)]
+enum LimitTarget {
+    Both(f64),
+    Left(f64),
+    Right(f64),
+    Infinity(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expected {
+    Value(f64),
+    Infinite(f64),
+    DoesNotExist,
+}
+
+enum Problem {
+    /// The answer is a number (or ∞ / does not exist).
+    Number(Expected),
+    /// The answer is the `order`-th derivative of `expr` as a function of `var`.
+    Derivative { expr: Expr, var: char, order: u32 },
+    /// The answer is any antiderivative of `integrand`.
+    Antiderivative { integrand: Expr, var: char },
+}
+
+/// A choice as something comparable with the computed answer.
+enum ChoiceValue {
+    Expr(Expr, Option<f64>),
+    Special(Expected),
+}
+
+static MATH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)\$\$(.+?)\$\$|\$(.+?)\$|\\\((.+?)\\\)|\\\[(.+?)\\\]").unwrap());
+
+fn math_segments(text: &str) -> Vec<String> {
+    MATH.captures_iter(text)
+        .filter_map(|caps| (1..=4).find_map(|i| caps.get(i)))
+        .map(|m| clean(m.as_str()))
+        .collect()
+}
+
+fn clean(latex: &str) -> String {
+    latex
+        .replace("\\displaystyle", "")
+        .replace("\\textstyle", "")
+        .trim()
+        .to_string()
+}
+
+/// Strip a trailing `=` from a stem segment; `None` when something follows it.
+fn question_part(segment: &str) -> Option<&str> {
+    let trimmed = segment.trim();
+    let body = trimmed.strip_suffix('=').unwrap_or(trimmed).trim();
+    (!body.contains('=')).then_some(body)
+}
+
+fn parse_bound(text: &str) -> Option<f64> {
+    let text = text
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .trim();
+    match text.replace(' ', "").as_str() {
+        "\\infty" | "+\\infty" => return Some(f64::INFINITY),
+        "-\\infty" => return Some(f64::NEG_INFINITY),
+        _ => {}
+    }
+    let expr = parse(text).ok()?;
+    (expr.single_var() == Ok(None)).then(|| expr.eval('\0', 0.0))
+}
+
+fn detect_integral(segments: &[String]) -> Option<(Problem, String)> {
+    static INTEGRAL: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?s)^\\int(?:\s*_\s*(\{[^{}]*\}|\\?[\w.]+))?(?:\s*\^\s*(\{[^{}]*\}|\\?[\w.]+))?(.*)$",
+        )
+        .unwrap()
+    });
+    // The differential must not be glued to a letter, so `\sin x dx` reads as `dx`
+    static DIFFERENTIAL: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?s)^(.*[^A-Za-z])(?:\\mathrm\{d\}|d)\s*([a-z])\s*$").unwrap());
+    for segment in segments {
+        let Some(body) = question_part(segment) else {
+            continue;
+        };
+        let Some(caps) = INTEGRAL.captures(body) else {
+            continue;
+        };
+        let Some(tail) = DIFFERENTIAL.captures(&caps[3]) else {
+            continue;
+        };
+        let var = tail[2].chars().next()?;
+        let mut integrand_src = tail[1].trim();
+        while let Some(rest) = ["\\,", "\\;", "\\!"]
+            .iter()
+            .find_map(|space| integrand_src.strip_suffix(space))
+        {
+            integrand_src = rest.trim_end();
+        }
+        let integrand = parse(integrand_src).ok()?;
+        if !matches!(integrand.single_var(), Ok(Some(v)) if v == var) {
+            return None;
+        }
+        let f = |x: f64| integrand.eval(var, x);
+        return match (caps.get(1), caps.get(2)) {
+            (Some(lower), Some(upper)) => {
+                let (a, b) = (parse_bound(lower.as_str())?, parse_bound(upper.as_str())?);
+                let expected = integrate(&f, a, b)?;
+                Some((
+                    Problem::Number(expected),
+                    format!(
+                        "definite integral of {} d{} from {} to {}",
+                        integrand_src,
+                        var,
+                        lower.as_str(),
+                        upper.as_str()
+                    ),
+                ))
+            }
+            (None, None) => Some((
+                Problem::Antiderivative {
+                    integrand: integrand.clone(),
+                    var,
+                },
+                format!("antiderivative of {} d{}", integrand_src, var),
+            )),
+            _ => None,
+        };
+    }
+    None
+}
+
+fn detect_limit(segments: &[String]) -> Option<(Problem, String)> {
+    static LIMIT: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?s)^\\lim\s*_\s*\{\s*([a-z])\s*\\(?:to|rightarrow)\s*([^{}]+?)\s*\}(.+)$")
+            .unwrap()
+    });
+    for segment in segments {
+        let Some(body) = question_part(segment) else {
+            continue;
+        };
+        let Some(caps) = LIMIT.captures(body) else {
+            continue;
+        };
+        let var = caps[1].chars().next()?;
+        let target_src = caps[2].replace(' ', "");
+        let target = match target_src.as_str() {
+            "\\infty" | "+\\infty" => LimitTarget::Infinity(1.0),
+            "-\\infty" => LimitTarget::Infinity(-1.0),
+            other => {
+                let (point, side) = if let Some(p) = other
+                    .strip_suffix("^+")
+                    .or_else(|| other.strip_suffix("^{+}"))
+                {
+                    (p, 1)
+                } else if let Some(p) = other
+                    .strip_suffix("^-")
+                    .or_else(|| other.strip_suffix("^{-}"))
+                {
+                    (p, -1)
+                } else {
+                    (other, 0)
+                };
+                let a = parse_bound(point)?;
+                match side {
+                    1 => LimitTarget::Right(a),
+                    -1 => LimitTarget::Left(a),
+                    _ => LimitTarget::Both(a),
+                }
+            }
+        };
+        let expr = parse(&caps[3]).ok()?;
+        if !matches!(expr.single_var(), Ok(Some(v)) if v == var) {
+            return None;
+        }
+        let expected = limit(&|x| expr.eval(var, x), target)?;
+        return Some((
+            Problem::Number(expected),
+            format!(
+                "limit of {} as {} → {}",
+                caps[3].trim(),
+                var,
+                caps[2].trim()
+            ),
+        ));
+    }
+    None
+}
+
+fn detect_derivative(segments: &[String]) -> Option<(Problem, String)> {
+    static DEFINITION: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"(?s)^([yfgh])(?:\s*\(\s*([a-z])\s*\))?\s*=(.+)$").unwrap());
+    static OPERATOR: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?s)^\\frac\s*\{\s*d(?:\^\{?2\}?)?\s*\}\s*\{\s*d\s*([a-z])(?:\^\{?2\}?)?\s*\}(.+)$",
+        )
+        .unwrap()
+    });
+
+    let mut definition: Option<(char, Expr, char, String)> = None;
+    for segment in segments {
+        let Some(body) = question_part(segment) else {
+            if let Some(caps) = DEFINITION.captures(segment) {
+                let rhs = caps[3].trim();
+                if let Ok(expr) = parse(rhs) {
+                    let name = caps[1].chars().next()?;
+                    let var = match (caps.get(2), expr.single_var()) {
+                        (Some(v), Ok(Some(e))) if v.as_str().starts_with(e) => e,
+                        (None, Ok(Some(e))) => e,
+                        _ => continue,
+                    };
+                    definition = Some((name, expr, var, rhs.to_string()));
+                }
+            }
+            continue;
+        };
+
+        if let Some(caps) = OPERATOR.captures(body) {
+            let var = caps[1].chars().next()?;
+            let order = if body.contains("d^") { 2 } else { 1 };
+            let expr = parse(&caps[2]).ok()?;
+            if !matches!(expr.single_var(), Ok(Some(v)) if v == var) {
+                return None;
+            }
+            return Some((
+                Problem::Derivative { expr, var, order },
+                format!("derivative of {}", caps[2].trim()),
+            ));
+        }
+
+        let Some((name, expr, var, source)) = definition.clone() else {
+            continue;
+        };
+        let compact = body.replace(' ', "");
+        let order =
+            if compact.contains("''") || compact.contains("d^2") || compact.contains("d^{2}") {
+                2
+            } else {
+                1
+            };
+        let primes = "'".repeat(order as usize);
+        let as_function = [
+            format!("\\frac{{d{}}}{{d{}}}", name, var),
+            format!("\\frac{{d^2{}}}{{d{}^2}}", name, var),
+            format!("{}{}", name, primes),
+            format!("{}{}({})", name, primes, var),
+        ];
+        if as_function.contains(&compact) {
+            return Some((
+                Problem::Derivative { expr, var, order },
+                format!("derivative of {}", source),
+            ));
+        }
+        let at_point = format!("{}{}(", name, primes);
+        if let Some(point) = compact
+            .strip_prefix(&at_point)
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let x = parse_bound(point)?;
+            let value = derivative(&|t| expr.eval(var, t), x, order);
+            if !value.is_finite() {
+                return None;
+            }
+            return Some((
+                Problem::Number(Expected::Value(value)),
+                format!("derivative of {} at {} = {}", source, var, point),
+            ));
+        }
+    }
+    None
+}
+
+fn choice_value(text: &str) -> Option<ChoiceValue> {
+    static CONSTANT: Lazy<Regex> = Lazy::new(|| Regex::new(r"\+\s*C\s*$").unwrap());
+    static UNITS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z ^23²³.]{0,16}$").unwrap());
+    static DECIMALS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-?\d*\.(\d+)$").unwrap());
+    static PLAIN_NUMBER: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^-?(?:0|[1-9]\d*)(?:\.\d+)?$").unwrap());
+
+    let lower = text.to_ascii_lowercase();
+    if lower.contains("diverg") || lower.contains("does not exist") || lower.trim() == "dne" {
+        return Some(ChoiceValue::Special(Expected::DoesNotExist));
+    }
+    let segments = math_segments(text);
+    let latex = match segments.as_slice() {
+        [only] => {
+            let outside = MATH.replace_all(text, "");
+            if !UNITS.is_match(outside.trim()) {
+                return None;
+            }
+            only.clone()
+        }
+        // Outside LaTeX only plain numbers count; `0123` is program output, not 123
+        [] if PLAIN_NUMBER.is_match(text.trim()) => text.trim().to_string(),
+        _ => return None,
+    };
+    if latex.starts_with("\\int") {
+        // A choice that is itself a definite integral, e.g. after a substitution
+        return match detect_integral(std::slice::from_ref(&latex)) {
+            Some((Problem::Number(Expected::Value(value)), _)) => {
+                Some(ChoiceValue::Expr(Expr::Num(value), None))
+            }
+            _ => None,
+        };
+    }
+    let compact = latex.replace(' ', "");
+    match compact.as_str() {
+        "\\infty" | "+\\infty" => return Some(ChoiceValue::Special(Expected::Infinite(1.0))),
+        "-\\infty" => return Some(ChoiceValue::Special(Expected::Infinite(-1.0))),
+        _ => {}
+    }
+    let latex = CONSTANT.replace(&latex, "").to_string();
+    let expr = parse(&latex).ok()?;
+    // A rounded decimal choice such as 0.442 is read to its stated precision
+    let precision = DECIMALS
+        .captures(&compact)
+        .map(|caps| 0.51 * 10f64.powi(-(caps[1].len() as i32)));
+    Some(ChoiceValue::Expr(expr, precision))
+}
+
+/// Values of two functions agree at enough sample points.
+fn same_function(f: &dyn Fn(f64) -> f64, g: &dyn Fn(f64) -> f64, tolerance: f64) -> bool {
+    let mut compared = 0;
+    for x in SAMPLES {
+        let (a, b) = (f(x), g(x));
+        if !a.is_finite() || !b.is_finite() {
+            continue;
+        }
+        if !close(a, b, tolerance) {
+            return false;
+        }
+        compared += 1;
+    }
+    compared >= MIN_SAMPLES
+}
+
+fn choice_var(expr: &Expr, var: Option<char>) -> Option<char> {
+    match (expr.single_var(), var) {
+        (Ok(None), Some(v)) => Some(v),
+        (Ok(None), None) => Some('x'),
+        (Ok(Some(c)), Some(v)) if c == v => Some(v),
+        (Ok(Some(c)), None) => Some(c),
+        _ => None,
+    }
+}
+
+fn matches_problem(problem: &Problem, choice: &ChoiceValue) -> bool {
+    match (problem, choice) {
+        (Problem::Number(expected), ChoiceValue::Special(special)) => expected == special,
+        (Problem::Number(Expected::Value(value)), ChoiceValue::Expr(expr, precision)) => {
+            if expr.single_var() != Ok(None) {
+                return false;
+            }
+            let actual = expr.eval('\0', 0.0);
+            match precision {
+                Some(tolerance) => (actual - value).abs() <= *tolerance,
+                None => close(actual, *value, 1e-6),
+            }
+        }
+        (Problem::Derivative { expr, var, order }, ChoiceValue::Expr(choice, _)) => {
+            let Some(v) = choice_var(choice, Some(*var)) else {
+                return false;
+            };
+            same_function(
+                &|x| derivative(&|t| expr.eval(*var, t), x, *order),
+                &|x| choice.eval(v, x),
+                if *order == 2 { 1e-3 } else { 1e-5 },
+            )
+        }
+        (Problem::Antiderivative { integrand, var }, ChoiceValue::Expr(choice, _)) => {
+            let Some(v) = choice_var(choice, Some(*var)) else {
+                return false;
+            };
+            same_function(
+                &|x| integrand.eval(*var, x),
+                &|x| derivative(&|t| choice.eval(v, t), x, 1),
+                1e-5,
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Choices that are the same function, or differ by a constant for antiderivatives.
+fn equivalent_pairs(choices: &[Option<ChoiceValue>], antiderivative: bool) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for i in 0..choices.len() {
+        for j in i + 1..choices.len() {
+            let (Some(ChoiceValue::Expr(a, _)), Some(ChoiceValue::Expr(b, _))) =
+                (&choices[i], &choices[j])
+            else {
+                continue;
+            };
+            let var = match (a.single_var(), b.single_var()) {
+                (Ok(None), Ok(None)) => {
+                    let (x, y) = (a.eval('\0', 0.0), b.eval('\0', 0.0));
+                    if x.is_finite() && close(x, y, 1e-9) {
+                        pairs.push((i, j));
+                    }
+                    continue;
+                }
+                (Ok(Some(v)), Ok(Some(w))) if v == w => v,
+                (Ok(Some(v)), Ok(None)) | (Ok(None), Ok(Some(v))) => v,
+                _ => continue,
+            };
+            let equal = if antiderivative {
+                same_function(
+                    &|x| derivative(&|t| a.eval(var, t), x, 1),
+                    &|x| derivative(&|t| b.eval(var, t), x, 1),
+                    1e-6,
+                )
+            } else {
+                same_function(&|x| a.eval(var, x), &|x| b.eval(var, x), 1e-9)
+            };
+            if equal {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+fn format_expected(expected: &Expected) -> String {
+    match expected {
+        Expected::Value(v) => {
+            let text = format!("{:.6}", v);
+            let text = text.trim_end_matches('0').trim_end_matches('.');
+            if text == "-0" {
+                "0".to_string()
+            } else {
+                text.to_string()
+            }
+        }
+        Expected::Infinite(sign) if *sign < 0.0 => "-∞".to_string(),
+        Expected::Infinite(_) => "∞".to_string(),
+        Expected::DoesNotExist => "does not exist".to_string(),
+    }
+}
+
+/// Check a question's math against its choices, given as `(text, is_correct)`.
+/// Returns `None` when neither the key nor the choices could be checked.
+pub fn check(text: &str, choices: &[(String, bool)]) -> Option<MathCheck> {
+    let segments = math_segments(text);
+    if segments.is_empty() && choices.iter().all(|(c, _)| math_segments(c).is_empty()) {
+        return None;
+    }
+    let problem = detect_integral(&segments)
+        .or_else(|| detect_limit(&segments))
+        .or_else(|| detect_derivative(&segments));
+    let values: Vec<Option<ChoiceValue>> = choices.iter().map(|(c, _)| choice_value(c)).collect();
+
+    let antiderivative = matches!(problem, Some((Problem::Antiderivative { .. }, _)));
+    let equivalent_choices: Vec<String> = equivalent_pairs(&values, antiderivative)
+        .into_iter()
+        .map(|(i, j)| format!("{} = {}", letter(i), letter(j)))
+        .collect();
+
+    let Some((problem, description)) = problem else {
+        if equivalent_choices.is_empty() {
+            return None;
+        }
+        return Some(MathCheck {
+            status: MathCheckStatus::Unchecked,
+            problem: String::new(),
+            expected: None,
+            matched_choice: None,
+            equivalent_choices,
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        });
+    };
+
+    let matched: Vec<usize> = (0..values.len())
+        .filter(|&i| {
+            values[i]
+                .as_ref()
+                .is_some_and(|value| matches_problem(&problem, value))
+        })
+        .collect();
+    let keyed = matched.iter().copied().find(|&i| choices[i].1);
+    let (status, matched_choice) = match (keyed, matched.first()) {
+        (Some(i), _) => (MathCheckStatus::Match, Some(i)),
+        (None, Some(&i)) => (MathCheckStatus::Mismatch, Some(i)),
+        // A choice the parser could not read may still be the right one
+        (None, None) if values.iter().all(Option::is_some) => {
+            (MathCheckStatus::NoChoiceMatches, None)
+        }
+        (None, None) => (MathCheckStatus::Unchecked, None),
+    };
+    let expected = match &problem {
+        Problem::Number(expected) => Some(format_expected(expected)),
+        _ => None,
+    };
+    Some(MathCheck {
+        status,
+        problem: description,
+        expected,
+        matched_choice: matched_choice.map(letter),
+        equivalent_choices,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::fixtures::choices;
+
+    fn value(latex: &str, x: f64) -> f64 {
+        parse(latex).unwrap().eval('x', x)
+    }
+
+    #[test]
+    fn test_parses_latex_expressions() {
+        assert!(close(
+            value("3\\sin^2 x \\cos x", 0.5),
+            3.0 * 0.5f64.sin().powi(2) * 0.5f64.cos(),
+            1e-12
+        ));
+        assert!(close(value("\\frac{1}{2e^2}", 0.0), 0.5 / E.powi(2), 1e-12));
+        assert!(close(
+            value("\\sqrt{1 + \\left(\\frac{1}{x}\\right)^2}", 2.0),
+            1.25f64.sqrt(),
+            1e-12
+        ));
+        assert!(close(
+            value("\\left|x - \\frac{3}{2}\\right|", 1.0),
+            0.5,
+            1e-12
+        ));
+        assert!(close(
+            value("e^{\\tan x} + 4", 0.3),
+            0.3f64.tan().exp() + 4.0,
+            1e-12
+        ));
+        assert!(close(value("\\sin^{-1} x", 0.5), 0.5f64.asin(), 1e-12));
+        assert!(close(value("x^23", 2.0), 12.0, 1e-12));
+        assert!(close(value("\\sqrt[3]{x}", -8.0), -2.0, 1e-12));
+        assert!(close(value("2\\pi", 0.0), 2.0 * PI, 1e-12));
+        assert!(parse("(0, 0)").is_err());
+    }
+
+    #[test]
+    fn test_checks_derivatives_integrals_and_limits() {
+        let derivative = check(
+            "If $y = \\sin^3 x$, then $\\frac{dy}{dx} =$. ",
+            &choices(
+                &[
+                    "$3\\cos x$",
+                    "$3\\cos^2 x$",
+                    "$3\\sin^2 x$",
+                    "$-3\\sin^2 x \\cos x$",
+                    "$3\\sin^2 x \\cos x$",
+                ],
+                4,
+            ),
+        )
+        .unwrap();
+        assert_eq!(derivative.status, MathCheckStatus::Match);
+        assert_eq!(derivative.matched_choice.as_deref(), Some("E"));
+
+        // |t - 3/2| over [1, 4] is 1/8 + 25/8 = 13/4, which is not offered
+        let wrong_key = check(
+            "$\\displaystyle\\int_1^4 \\left|t - \\frac{3}{2}\\right|\\, dt =$",
+            &choices(
+                &[
+                    "$-1$",
+                    "$-\\frac{7}{8}$",
+                    "$-\\frac{1}{2}$",
+                    "$\\frac{1}{2}$",
+                    "$1$",
+                ],
+                4,
+            ),
+        )
+        .unwrap();
+        assert_eq!(wrong_key.status, MathCheckStatus::NoChoiceMatches);
+        assert_eq!(wrong_key.expected.as_deref(), Some("3.25"));
+        assert!(wrong_key.is_flagged());
+
+        let improper = check(
+            "$\\displaystyle\\int_1^{\\infty} \\frac{1}{e^{2x}}\\, dx$ is",
+            &choices(
+                &[
+                    "$-2e^2$",
+                    "$-\\frac{1}{2e^2}$",
+                    "$\\frac{1}{2e^2}$",
+                    "$\\frac{1}{e^2}$",
+                    "divergent",
+                ],
+                3,
+            ),
+        )
+        .unwrap();
+        assert_eq!(improper.status, MathCheckStatus::Mismatch);
+        assert_eq!(improper.matched_choice.as_deref(), Some("C"));
+
+        let limit = check(
+            "$\\lim_{x \\to 0} \\frac{\\sin 3x}{x} =$",
+            &choices(&["$0$", "$1$", "$3$", "does not exist"], 2),
+        )
+        .unwrap();
+        assert_eq!(limit.status, MathCheckStatus::Match);
+        let cancelling = check(
+            "$\\lim_{x \\to 0} \\frac{e^x - 1 - x}{x^2} =$",
+            &choices(&["$-1$", "$\\frac{1}{2}$", "The limit does not exist"], 1),
+        )
+        .unwrap();
+        assert_eq!(cancelling.status, MathCheckStatus::Match);
+
+        // Choices that are integrals themselves are evaluated too
+        let substitution = check(
+            "Using $u = x^2 - 3$, $\\int_1^4 x(x^2 - 3)^5\\, dx$ is equal to which of the following?",
+            &choices(
+                &["$\\int_{-2}^{13} 2u^5\\, du$", "$\\int_{-2}^{13} \\frac{1}{2}u^5\\, du$"],
+                1,
+            ),
+        )
+        .unwrap();
+        assert_eq!(substitution.status, MathCheckStatus::Match);
+
+        let antiderivative = check(
+            "$\\int 2x \\cos(x^2)\\, dx =$",
+            &choices(
+                &["$\\sin(x^2) + C$", "$-\\sin(x^2) + C$", "$2\\sin(x^2) + C$"],
+                0,
+            ),
+        )
+        .unwrap();
+        assert_eq!(antiderivative.status, MathCheckStatus::Match);
+
+        let at_point = check(
+            "If $f(x) = x^3 - 2x$, what is $f'(2)$?",
+            &choices(&["$10$", "$12$", "$4$"], 0),
+        )
+        .unwrap();
+        assert_eq!(at_point.status, MathCheckStatus::Match);
+    }
+
+    #[test]
+    fn test_flags_equivalent_choices_and_skips_unknown_problems() {
+        let ambiguous = check(
+            "Which expression equals $\\frac{d}{dx}\\left(\\tan x\\right)$?",
+            &choices(&["$\\sec^2 x$", "$1 + \\tan^2 x$", "$\\cos^2 x$"], 0),
+        )
+        .unwrap();
+        assert_eq!(ambiguous.status, MathCheckStatus::Match);
+        assert_eq!(ambiguous.equivalent_choices, vec!["A = B".to_string()]);
+        assert!(ambiguous.is_flagged());
+
+        // A differential equation is not a bare derivative question
+        assert!(check(
+            "If $\\frac{dy}{dx} = y \\sec^2 x$ and $y = 5$ when $x = 0$, then $y =$",
+            &choices(&["$e^{\\tan x} + 4$", "$5e^{\\tan x}$"], 1),
+        )
+        .is_none());
+        assert!(check("Which intervals?", &choices(&["$(-1, 1)$", "$(0, 2)$"], 0)).is_none());
+        // Program output is not math, even when it looks numeric
+        assert!(check("What is printed?", &choices(&["0123", "00123", "4 5"], 0)).is_none());
+    }
+}
@@ -0,0 +1,839 @@
+//! Parametric question templates, filled in locally without the LLM
+//!
+//! A template is a JSON file with variables, constraints and formulas:
+//!
+//! ```json
+//! {
+//!   "id": "array-prefix-sum",
+//!   "title": "Sum of the first n elements",
+//!   "variables": {"a": {"min": 1, "max": 9}, "b": {"min": 1, "max": 9}, "n": {"values": [1, 2]}},
+//!   "derived": {"total": "n >= 2 ? a + b : a"},
+//!   "constraints": ["a != b"],
+//!   "text": "What is printed?",
+//!   "code": "int[] arr = {{a}, {b}};\n...",
+//!   "answer": "total",
+//!   "distractors": [{"formula": "a + b", "explanation": "Ignores n"}],
+//!   "explanation": "The loop adds {n} elements, giving {answer}."
+//! }
+//! ```
+//!
+//! `{name}` in `text`, `code` and the explanations is replaced by a variable or derived
+//! value (`{answer}` by the correct answer); any other brace is kept, so Java array
+//! literals need no escaping. Formulas support `+ - * / % ^`, comparisons, `&& || !`,
+//! `cond ? a : b` and `abs min max sum floor ceil round trunc sqrt div gcd lcm`; `/` is
+//! real division and `div(a, b)` truncates like Java's `int` division.
+//!
+//! [`ParametricTemplate::variants`] draws values from a seeded RNG until it has the
+//! requested number of distinct variants whose constraints hold and whose choices all
+//! differ, so the same seed always gives the same quiz.
+
+use crate::{Answer, Provenance, Question};
+use once_cell::sync::Lazy;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Draws allowed per requested variant before giving up on the constraints.
+const ATTEMPTS_PER_VARIANT: usize = 500;
+
+static PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+#[derive(Debug, Clone, Deserialize)]
+struct Spec {
+    id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    subject: String,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default)]
+    difficulty: String,
+    variables: BTreeMap<String, VariableSpec>,
+    #[serde(default)]
+    derived: BTreeMap<String, String>,
+    #[serde(default)]
+    constraints: Vec<String>,
+    text: String,
+    #[serde(default)]
+    code: Option<String>,
+    answer: String,
+    #[serde(default)]
+    answer_explanation: Option<String>,
+    distractors: Vec<DistractorSpec>,
+    #[serde(default)]
+    explanation: Option<String>,
+    /// Decimal places for the choices; whole numbers print without any by default.
+    #[serde(default)]
+    decimals: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum VariableSpec {
+    Range {
+        min: i64,
+        max: i64,
+        #[serde(default = "default_step")]
+        step: i64,
+    },
+    Values {
+        values: Vec<f64>,
+    },
+}
+
+fn default_step() -> i64 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DistractorSpec {
+    formula: String,
+    #[serde(default)]
+    explanation: Option<String>,
+}
+
+/// What the UI lists for a bundled template.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParametricTemplateInfo {
+    pub id: String,
+    pub title: String,
+    pub subject: String,
+    pub topics: Vec<String>,
+    pub difficulty: String,
+}
+
+/// A checked template, ready to produce variants.
+#[derive(Debug, Clone)]
+pub struct ParametricTemplate {
+    spec: Spec,
+    /// Derived values in an order where each only uses earlier ones.
+    derived: Vec<(String, Expr)>,
+    constraints: Vec<Expr>,
+    answer: Expr,
+    distractors: Vec<(Expr, Option<String>)>,
+    hash: String,
+}
+
+impl ParametricTemplate {
+    /// Parse a template and check every formula and variable range.
+    pub fn load(source: &str) -> Result<Self, String> {
+        let spec: Spec = serde_json::from_str(source).map_err(|e| e.to_string())?;
+        if spec.id.trim().is_empty() {
+            return Err("the template has no id".to_string());
+        }
+        if spec.variables.is_empty() {
+            return Err("the template declares no variables".to_string());
+        }
+        if spec.distractors.is_empty() {
+            return Err("the template declares no distractors".to_string());
+        }
+
+        for (name, variable) in &spec.variables {
+            if name == "answer" || FUNCTIONS.iter().any(|(f, _)| f == name) {
+                return Err(format!("`{}` is reserved and cannot name a variable", name));
+            }
+            match variable {
+                VariableSpec::Range { min, max, step } if min > max || *step <= 0 => {
+                    return Err(format!(
+                        "variable {}: needs min <= max and a positive step",
+                        name
+                    ))
+                }
+                // `(max - min) / step` is what sampling computes; reject bounds so
+                // far apart that it would overflow.
+                VariableSpec::Range { min, max, step }
+                    if max.checked_sub(*min).and_then(|w| w.checked_div(*step)).is_none() =>
+                {
+                    return Err(format!("variable {}: the range is too wide", name))
+                }
+                VariableSpec::Values { values } if values.is_empty() => {
+                    return Err(format!("variable {}: values is empty", name))
+                }
+                _ => {}
+            }
+        }
+
+        let mut known: HashSet<String> = spec.variables.keys().cloned().collect();
+        let mut pending: Vec<(String, Expr)> = Vec::new();
+        for (name, formula) in &spec.derived {
+            if spec.variables.contains_key(name) || name == "answer" {
+                return Err(format!("derived value {} shadows a variable", name));
+            }
+            let expr = parse(formula).map_err(|e| format!("derived {}: {}", name, e))?;
+            pending.push((name.clone(), expr));
+        }
+        let mut derived = Vec::new();
+        while !pending.is_empty() {
+            let before = pending.len();
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, expr)| expr.names().iter().all(|n| known.contains(*n)));
+            for (name, expr) in ready {
+                known.insert(name.clone());
+                derived.push((name, expr));
+            }
+            pending = waiting;
+            if pending.len() == before {
+                let (name, expr) = &pending[0];
+                let missing: Vec<&str> = expr
+                    .names()
+                    .into_iter()
+                    .filter(|n| !known.contains(*n))
+                    .collect();
+                return Err(format!(
+                    "derived {} uses unknown or circular {}",
+                    name,
+                    missing.join(", ")
+                ));
+            }
+        }
+
+        let checked = |label: &str, formula: &str| -> Result<Expr, String> {
+            let expr = parse(formula).map_err(|e| format!("{}: {}", label, e))?;
+            match expr.names().into_iter().find(|n| !known.contains(*n)) {
+                Some(name) => Err(format!("{}: unknown name {}", label, name)),
+                None => Ok(expr),
+            }
+        };
+        let constraints = spec
+            .constraints
+            .iter()
+            .map(|c| checked("constraint", c))
+            .collect::<Result<Vec<_>, _>>()?;
+        let answer = checked("answer", &spec.answer)?;
+        let distractors = spec
+            .distractors
+            .iter()
+            .map(|d| Ok((checked("distractor", &d.formula)?, d.explanation.clone())))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let digest = Sha256::digest(source.as_bytes());
+        Ok(ParametricTemplate {
+            derived,
+            constraints,
+            answer,
+            distractors,
+            hash: format!("{:x}", digest)[..12].to_string(),
+            spec,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.spec.id
+    }
+
+    pub fn info(&self) -> ParametricTemplateInfo {
+        ParametricTemplateInfo {
+            id: self.spec.id.clone(),
+            title: if self.spec.title.trim().is_empty() {
+                self.spec.id.clone()
+            } else {
+                self.spec.title.clone()
+            },
+            subject: self.spec.subject.clone(),
+            topics: self.spec.topics.clone(),
+            difficulty: self.spec.difficulty.clone(),
+        }
+    }
+
+    /// `count` distinct variants drawn from `seed`. Questions without a subject in the
+    /// template get `subject`.
+    pub fn variants(
+        &self,
+        count: usize,
+        seed: u64,
+        subject: &str,
+    ) -> Result<Vec<Question>, String> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut seen = HashSet::new();
+        let mut questions = Vec::new();
+
+        for _ in 0..count.max(1) * ATTEMPTS_PER_VARIANT {
+            if questions.len() == count {
+                break;
+            }
+            let mut values = HashMap::new();
+            for (name, variable) in &self.spec.variables {
+                let value = match variable {
+                    VariableSpec::Range { min, max, step } => {
+                        (min + rng.gen_range(0..=(max - min) / step) * step) as f64
+                    }
+                    VariableSpec::Values { values } => values[rng.gen_range(0..values.len())],
+                };
+                values.insert(name.clone(), value);
+            }
+            let key: Vec<String> = self
+                .spec
+                .variables
+                .keys()
+                .map(|name| format_number(values[name], None))
+                .collect();
+            if seen.contains(&key) {
+                continue;
+            }
+            let Some(question) = self.fill(&mut values, &mut rng, seed, subject) else {
+                continue;
+            };
+            seen.insert(key);
+            questions.push(question);
+        }
+
+        if questions.len() < count {
+            return Err(format!(
+                "Only {} distinct variants of {} satisfy its constraints with distinct choices",
+                questions.len(),
+                self.spec.id
+            ));
+        }
+        for (i, question) in questions.iter_mut().enumerate() {
+            question.id = format!("{}-{}", self.spec.id, i + 1);
+        }
+        Ok(questions)
+    }
+
+    /// One variant from drawn `values`, or `None` when a constraint fails, a formula
+    /// cannot be evaluated or two choices would read the same.
+    fn fill(
+        &self,
+        values: &mut HashMap<String, f64>,
+        rng: &mut StdRng,
+        seed: u64,
+        subject: &str,
+    ) -> Option<Question> {
+        for (name, expr) in &self.derived {
+            let value = expr.eval(values).ok()?;
+            values.insert(name.clone(), value);
+        }
+        for constraint in &self.constraints {
+            if constraint.eval(values).ok()? == 0.0 {
+                return None;
+            }
+        }
+
+        let answer = format_number(self.answer.eval(values).ok()?, self.spec.decimals);
+        let mut labels: HashMap<String, String> = values
+            .iter()
+            .map(|(name, value)| (name.clone(), format_number(*value, None)))
+            .collect();
+        labels.insert("answer".to_string(), answer.clone());
+        let render = |text: &str| substitute(text, &labels);
+
+        let mut answers = vec![Answer {
+            text: answer.clone(),
+            is_correct: true,
+            explanation: self.spec.answer_explanation.as_deref().map(render),
+        }];
+        for (expr, explanation) in &self.distractors {
+            let text = format_number(expr.eval(values).ok()?, self.spec.decimals);
+            if answers.iter().any(|a| a.text == text) {
+                return None;
+            }
+            answers.push(Answer {
+                text,
+                is_correct: false,
+                explanation: explanation.as_deref().map(render),
+            });
+        }
+        answers.shuffle(rng);
+
+        Some(Question {
+            text: render(&self.spec.text),
+            code: self.spec.code.as_deref().map(render),
+            answers,
+            explanation: self.spec.explanation.as_deref().map(render),
+            subject: if self.spec.subject.is_empty() {
+                subject.to_string()
+            } else {
+                self.spec.subject.clone()
+            },
+            topics: self.spec.topics.clone(),
+            difficulty: self.spec.difficulty.clone(),
+            provenance: Some(Provenance {
+                kind: "parametric".to_string(),
+                template_version: Some(
+                    self.spec
+                        .version
+                        .clone()
+                        .unwrap_or_else(|| self.spec.id.clone()),
+                ),
+                template_hash: Some(self.hash.clone()),
+                example_ids: Vec::new(),
+                provider: "local".to_string(),
+                model: "parametric".to_string(),
+                job_id: String::new(),
+                generated_at: chrono::Utc::now().to_rfc3339(),
+                regeneration_mode: None,
+                seed: Some(seed),
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+/// Replace `{name}` for every known name, leaving other braces alone.
+fn substitute(text: &str, labels: &HashMap<String, String>) -> String {
+    PLACEHOLDER
+        .replace_all(text, |caps: &regex::Captures| match labels.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+fn format_number(value: f64, decimals: Option<usize>) -> String {
+    let text = match decimals {
+        Some(places) => format!("{:.*}", places, value),
+        None if (value - value.round()).abs() < 1e-9 => format!("{}", value.round() as i64),
+        None => {
+            let text = format!("{:.4}", value);
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+    // -0 and -0.00 read as zero
+    if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+        text[1..].to_string()
+    } else {
+        text
+    }
+}
+
+/// Functions formulas may call, with their argument count (`None` for one or more).
+const FUNCTIONS: &[(&str, Option<usize>)] = &[
+    ("abs", Some(1)),
+    ("floor", Some(1)),
+    ("ceil", Some(1)),
+    ("round", Some(1)),
+    ("trunc", Some(1)),
+    ("sqrt", Some(1)),
+    ("div", Some(2)),
+    ("gcd", Some(2)),
+    ("lcm", Some(2)),
+    ("min", None),
+    ("max", None),
+    ("sum", None),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64),
+    Name(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(String, Box<Expr>, Box<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    fn names(&self) -> Vec<&str> {
+        match self {
+            Expr::Num(_) => Vec::new(),
+            Expr::Name(name) => vec![name.as_str()],
+            Expr::Neg(inner) | Expr::Not(inner) => inner.names(),
+            Expr::Binary(_, left, right) => [left.names(), right.names()].concat(),
+            Expr::Cond(cond, yes, no) => [cond.names(), yes.names(), no.names()].concat(),
+            Expr::Call(_, args) => args.iter().flat_map(Expr::names).collect(),
+        }
+    }
+
+    fn eval(&self, values: &HashMap<String, f64>) -> Result<f64, String> {
+        let truth = |b: bool| if b { 1.0 } else { 0.0 };
+        let value = match self {
+            Expr::Num(n) => *n,
+            Expr::Name(name) => *values
+                .get(name)
+                .ok_or_else(|| format!("unknown name {}", name))?,
+            Expr::Neg(inner) => -inner.eval(values)?,
+            Expr::Not(inner) => truth(inner.eval(values)? == 0.0),
+            Expr::Cond(cond, yes, no) => {
+                if cond.eval(values)? != 0.0 {
+                    yes.eval(values)?
+                } else {
+                    no.eval(values)?
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let a = left.eval(values)?;
+                // Short-circuit so `n > 0 && 10 / n > 2` is safe
+                match op.as_str() {
+                    "&&" if a == 0.0 => return Ok(0.0),
+                    "||" if a != 0.0 => return Ok(1.0),
+                    _ => {}
+                }
+                let b = right.eval(values)?;
+                match op.as_str() {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" | "%" if b == 0.0 => return Err("division by zero".to_string()),
+                    "/" => a / b,
+                    "%" => a % b,
+                    "^" => a.powf(b),
+                    "<" => truth(a < b),
+                    "<=" => truth(a <= b),
+                    ">" => truth(a > b),
+                    ">=" => truth(a >= b),
+                    "==" => truth((a - b).abs() < 1e-9),
+                    "!=" => truth((a - b).abs() >= 1e-9),
+                    _ => truth(b != 0.0),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(values))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match name.as_str() {
+                    "abs" => args[0].abs(),
+                    "floor" => args[0].floor(),
+                    "ceil" => args[0].ceil(),
+                    "round" => args[0].round(),
+                    "trunc" => args[0].trunc(),
+                    "sqrt" => args[0].sqrt(),
+                    "div" if args[1] == 0.0 => return Err("division by zero".to_string()),
+                    "div" => (args[0] / args[1]).trunc(),
+                    "gcd" => gcd(args[0], args[1]),
+                    "lcm" if args[0] == 0.0 || args[1] == 0.0 => 0.0,
+                    "lcm" => (args[0] * args[1]).abs() / gcd(args[0], args[1]),
+                    "min" => args.iter().copied().fold(f64::INFINITY, f64::min),
+                    "max" => args.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    _ => args.iter().sum(),
+                }
+            }
+        };
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err("the result is not a finite number".to_string())
+        }
+    }
+}
+
+fn gcd(a: f64, b: f64) -> f64 {
+    let (mut a, mut b) = (a.abs().round() as i64, b.abs().round() as i64);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(f64),
+    Name(String),
+    Op(&'static str),
+}
+
+/// Operators, longest first so `<=` wins over `<`.
+const OPERATORS: &[&str] = &[
+    "&&", "||", "<=", ">=", "==", "!=", "+", "-", "*", "/", "%", "^", "<", ">", "!", "?", ":", "(",
+    ")", ",",
+];
+
+fn tokenize(formula: &str) -> Result<Vec<Tok>, String> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse().map_err(|_| format!("bad number {}", text))?;
+            tokens.push(Tok::Num(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Tok::Name(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("unexpected {}", c))?;
+            tokens.push(Tok::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse(formula: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(formula)?,
+        pos: 0,
+    };
+    let expr = parser.conditional()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(expr),
+        Some(tok) => Err(format!("unexpected {:?} in {}", tok, formula)),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Tok>,
+    pos: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Tok::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("expected {}", op))
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[&str],
+        next: fn(&mut Parser) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut left = next(self)?;
+        while let Some(op) = ops.iter().find(|op| self.eat(op)) {
+            left = Expr::Binary(op.to_string(), Box::new(left), Box::new(next(self)?));
+        }
+        Ok(left)
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let cond = self.or()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let yes = self.conditional()?;
+        self.expect(":")?;
+        let no = self.conditional()?;
+        Ok(Expr::Cond(Box::new(cond), Box::new(yes), Box::new(no)))
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&["||"], Parser::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&["&&"], Parser::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(&["<=", ">=", "==", "!=", "<", ">"], Parser::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&["+", "-"], Parser::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        self.binary(&["*", "/", "%"], Parser::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        let base = self.primary()?;
+        if self.eat("^") {
+            return Ok(Expr::Binary(
+                "^".to_string(),
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match tok {
+            Some(Tok::Num(n)) => Ok(Expr::Num(n)),
+            Some(Tok::Name(name)) if self.eat("(") => {
+                let arity = FUNCTIONS
+                    .iter()
+                    .find(|(f, _)| *f == name)
+                    .map(|(_, arity)| *arity)
+                    .ok_or_else(|| format!("unknown function {}", name))?;
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.conditional()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                match arity {
+                    Some(n) if args.len() != n => Err(format!("{} takes {} argument(s)", name, n)),
+                    None if args.is_empty() => Err(format!("{} needs an argument", name)),
+                    _ => Ok(Expr::Call(name, args)),
+                }
+            }
+            Some(Tok::Name(name)) => Ok(Expr::Name(name)),
+            Some(Tok::Op("(")) => {
+                let inner = self.conditional()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Some(tok) => Err(format!("unexpected {:?}", tok)),
+            None => Err("the formula ends early".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = r#"{
+        "id": "prefix-sum",
+        "subject": "Computer Science",
+        "topics": ["arrays"],
+        "difficulty": "easy",
+        "variables": {
+            "a": {"min": 1, "max": 9},
+            "b": {"min": 1, "max": 9},
+            "c": {"min": 1, "max": 9},
+            "n": {"values": [2, 3]}
+        },
+        "derived": {"total": "n == 3 ? ab + c : ab", "ab": "a + b"},
+        "constraints": ["a != b && b != c"],
+        "text": "What is printed?",
+        "code": "int[] arr = {{a}, {b}, {c}};\nint sum = 0;\nfor (int i = 0; i < {n}; i++) { sum += arr[i]; }\nSystem.out.println(sum);",
+        "answer": "total",
+        "distractors": [
+            {"formula": "total - a", "explanation": "Starts at index 1"},
+            {"formula": "n == 3 ? a + b : a + b + c", "explanation": "Wrong loop bound"},
+            {"formula": "a * 10 + b", "explanation": "Concatenates"}
+        ],
+        "explanation": "The loop adds the first {n} elements: {answer}."
+    }"#;
+
+    #[test]
+    fn test_parse_and_eval_formulas() {
+        let values = HashMap::from([("x".to_string(), 7.0), ("y".to_string(), 2.0)]);
+        let eval = |f: &str| parse(f).unwrap().eval(&values).unwrap();
+        assert_eq!(eval("x / y"), 3.5);
+        assert_eq!(eval("div(x, y) + x % y"), 4.0);
+        assert_eq!(eval("-y ^ 2"), -4.0);
+        assert_eq!(eval("2 ^ 3 ^ 2"), 512.0);
+        assert_eq!(eval("x > y && !(x == 3) ? max(x, y, 10) : 0"), 10.0);
+        assert_eq!(eval("gcd(12, 18) + lcm(4, 6)"), 18.0);
+        assert!(parse("x +").is_err());
+        assert!(parse("foo(1)").is_err());
+        assert!(parse("x / (y - 2)").unwrap().eval(&values).is_err());
+        assert_eq!(format_number(-0.0001, Some(2)), "0.00");
+        assert_eq!(format_number(2.5, None), "2.5");
+    }
+
+    #[test]
+    fn test_bundled_templates_fill_in() {
+        let array = include_str!(
+            "../../imports/knowledge/Computer Science/parametric/array-prefix-sum.json"
+        );
+        let power =
+            include_str!("../../imports/knowledge/Calculus/parametric/power-rule-at-point.json");
+        let variants = ParametricTemplate::load(array)
+            .unwrap()
+            .variants(8, 7, "")
+            .unwrap();
+        assert!(variants
+            .iter()
+            .all(|q| q.answers.len() == 4 && q.subject == "Computer Science"));
+
+        // The math checker works each derivative out independently of the formulas
+        for question in ParametricTemplate::load(power)
+            .unwrap()
+            .variants(8, 7, "")
+            .unwrap()
+        {
+            let choices: Vec<(String, bool)> = question
+                .answers
+                .iter()
+                .map(|a| (a.text.clone(), a.is_correct))
+                .collect();
+            let check = crate::math_check::check(&question.stem_markdown(), &choices).unwrap();
+            assert_eq!(
+                check.status,
+                crate::math_check::MathCheckStatus::Match,
+                "{}",
+                question.text
+            );
+        }
+    }
+
+    #[test]
+    fn test_variants_are_deterministic_and_distinct() {
+        let template = ParametricTemplate::load(TEMPLATE).unwrap();
+        let first = template.variants(5, 42, "").unwrap();
+        let again = template.variants(5, 42, "").unwrap();
+        assert_eq!(first.len(), 5);
+        for (a, b) in first.iter().zip(&again) {
+            assert_eq!(a.code, b.code);
+            let texts = |q: &Question| q.answers.iter().map(|a| a.text.clone()).collect::<Vec<_>>();
+            assert_eq!(texts(a), texts(b));
+        }
+
+        let codes: HashSet<_> = first.iter().map(|q| q.code.clone()).collect();
+        assert_eq!(codes.len(), 5);
+        for question in &first {
+            let code = question.code.as_deref().unwrap();
+            assert!(code.starts_with("int[] arr = {"), "{}", code);
+            assert!(!code.contains("{a}"));
+            let correct: Vec<_> = question.answers.iter().filter(|a| a.is_correct).collect();
+            assert_eq!(correct.len(), 1);
+            let choices: HashSet<_> = question.answers.iter().map(|a| &a.text).collect();
+            assert_eq!(choices.len(), question.answers.len());
+            assert!(question
+                .explanation
+                .as_deref()
+                .unwrap()
+                .ends_with(&format!("{}.", correct[0].text)));
+        }
+
+        let wide = TEMPLATE.replacen(
+            "\"min\": 1, \"max\": 9",
+            &format!("\"min\": {}, \"max\": {}", i64::MIN, i64::MAX),
+            1,
+        );
+        assert_ne!(wide, TEMPLATE);
+        assert!(ParametricTemplate::load(&wide)
+            .unwrap_err()
+            .contains("too wide"));
+
+        let impossible = TEMPLATE.replace("a != b && b != c", "a > 100");
+        let template = ParametricTemplate::load(&impossible).unwrap();
+        assert!(template.variants(1, 1, "").is_err());
+        assert!(ParametricTemplate::load(
+            &TEMPLATE.replace("\"answer\": \"total\"", "\"answer\": \"totl\"")
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,9 @@
 //! Prompt templates and response parsing for LLM interactions
 
-use crate::{GenerationRequest, Question, QuestionBankEntry};
+use crate::regenerate::RegenerateMode;
+use crate::sources::SourceMaterial;
+use crate::template::{Rendered, Template, Value, Variable, Vars};
+use crate::{Answer, GenerationRequest, Question, QuestionBankEntry};
 
 /// Configuration for prompt building
 pub struct PromptConfig<'a> {
@@ -10,7 +13,8 @@ pub struct PromptConfig<'a> {
     pub examples: &'a [QuestionBankEntry],
     pub user_instructions: Option<&'a str>,
     pub regenerate_context: Option<RegenerateContext<'a>>,
-    pub prompt_template: Option<&'a str>,
+    pub prompt_template: Option<&'a Template>,
+    pub sources: Option<&'a SourceMaterial>,
 }
 
 /// Context for regeneration requests
@@ -20,10 +24,21 @@ pub struct RegenerateContext<'a> {
 }
 
 /// Build the core prompt (used for both generate and regenerate)
-fn build_core_prompt(config: &PromptConfig) -> String {
+fn build_core_prompt(config: &PromptConfig) -> Rendered {
+    let mut rendered = render_core_prompt(config);
+    // Templates that don't place {source_material} get it at the end
+    if let Some(sources) = config.sources {
+        if !rendered.used.iter().any(|name| name == "source_material") {
+            rendered.text = format!("{}\n\n{}", rendered.text.trim_end(), sources.prompt_block());
+        }
+    }
+    rendered
+}
+
+fn render_core_prompt(config: &PromptConfig) -> Rendered {
     // Use custom prompt template if provided
     if let Some(template) = config.prompt_template {
-        return format_custom_prompt(template, config);
+        return template.render(&generation_vars(config));
     }
 
     // Fallback: No prompt template found
@@ -31,7 +46,7 @@ fn build_core_prompt(config: &PromptConfig) -> String {
     eprintln!("WARNING: No prompt template found. Please add a prompt.txt file for this subject.");
 
     // Return minimal fallback that will at least allow basic generation
-    format!(
+    let text = format!(
         r#"Generate {count} multiple choice question(s) about {topics} at {difficulty} difficulty level.
 
 Return ONLY a JSON array with this structure:
@@ -49,11 +64,56 @@ Return ONLY a JSON array with this structure:
         count = config.count,
         topics = config.topics,
         difficulty = config.difficulty
-    )
+    );
+    Rendered {
+        text,
+        ..Rendered::default()
+    }
 }
 
-/// Format a custom prompt template with config values
-fn format_custom_prompt(template: &str, config: &PromptConfig) -> String {
+/// Fields of each `{#each examples}` item.
+const EXAMPLE_FIELDS: &[&str] = &[
+    "number",
+    "id",
+    "json",
+    "text",
+    "explanation",
+    "difficulty",
+    "cognitive_level",
+];
+
+/// Variables available to a subject's `prompt.txt`.
+pub const GENERATION_VARIABLES: &[Variable] = &[
+    Variable::text("topics"),
+    Variable::text("difficulty"),
+    Variable::text("count"),
+    Variable::list("examples", EXAMPLE_FIELDS),
+    Variable::text("user_instructions"),
+    Variable::text("notes"),
+    Variable::text("regenerate"),
+    Variable::text("source_material"),
+];
+
+/// Variables available to a subject's `regeneration-prompt.txt`.
+pub const REGENERATION_VARIABLES: &[Variable] = &[
+    Variable::text("current_question_json"),
+    Variable::text("difficulty"),
+    Variable::text("topics"),
+    Variable::text("subject"),
+    Variable::text("style"),
+    Variable::list("other_questions", &["text"]),
+    Variable::text("user_instructions"),
+    Variable::text("notes"),
+    Variable::text("mode"),
+    Variable::text("mode_instructions"),
+];
+
+fn text(value: impl Into<String>) -> Value {
+    Value::Text(value.into())
+}
+
+/// Template variables for a generation prompt
+fn generation_vars(config: &PromptConfig) -> Vars {
     let difficulty_desc = match config.difficulty {
         "easy" => "D1 (Easy) - Basic recall or simple application, 1-2 steps",
         "medium" => "D2 (Medium) - Requires analysis or multi-step reasoning, 3-5 steps",
@@ -102,29 +162,73 @@ fn format_custom_prompt(template: &str, config: &PromptConfig) -> String {
             };
             format!(
                 "\n\n**Question to replace:**\n{}\n{}",
-                ctx.current_question.text, other_questions_str
+                ctx.current_question.stem_markdown(),
+                other_questions_str
             )
         }
         None => String::new(),
     };
 
-    // Replace placeholders in template
-    template
-        .replace("{topics}", &config.topics)
-        .replace("{difficulty}", difficulty_desc)
-        .replace("{count}", &config.count.to_string())
-        .replace("{examples}", &examples_str)
-        .replace("{user_instructions}", &user_instructions_str)
-        .replace("{regenerate}", &regenerate_section)
+    let example_items = config
+        .examples
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            Vars::from([
+                ("number".to_string(), text((i + 1).to_string())),
+                ("id".to_string(), text(&e.id)),
+                ("json".to_string(), text(format_example_as_json(e))),
+                ("text".to_string(), text(&e.text)),
+                ("explanation".to_string(), text(&e.explanation)),
+                ("difficulty".to_string(), text(&e.difficulty)),
+                ("cognitive_level".to_string(), text(&e.cognitive_level)),
+            ])
+        })
+        .collect();
+
+    Vars::from([
+        ("topics".to_string(), text(&config.topics)),
+        ("difficulty".to_string(), text(difficulty_desc)),
+        ("count".to_string(), text(config.count.to_string())),
+        (
+            "examples".to_string(),
+            Value::List {
+                text: examples_str,
+                items: example_items,
+            },
+        ),
+        ("user_instructions".to_string(), text(user_instructions_str)),
+        (
+            "notes".to_string(),
+            text(config.user_instructions.unwrap_or_default().trim()),
+        ),
+        ("regenerate".to_string(), text(regenerate_section)),
+        (
+            "source_material".to_string(),
+            text(config.sources.map(SourceMaterial::prompt_block).unwrap_or_default()),
+        ),
+    ])
 }
 
 /// Build the prompt for generating multiple questions using JSON examples
 pub fn build_generation_prompt(
     request: &GenerationRequest,
     examples: &[QuestionBankEntry],
-    prompt_template: Option<&str>,
+    prompt_template: Option<&Template>,
     topics_label: &str,
+    sources: Option<&SourceMaterial>,
 ) -> String {
+    render_generation_prompt(request, examples, prompt_template, topics_label, sources).text
+}
+
+/// [`build_generation_prompt`], also reporting which template variables were used.
+pub fn render_generation_prompt(
+    request: &GenerationRequest,
+    examples: &[QuestionBankEntry],
+    prompt_template: Option<&Template>,
+    topics_label: &str,
+    sources: Option<&SourceMaterial>,
+) -> Rendered {
     let config = PromptConfig {
         topics: topics_label.to_string(),
         difficulty: &request.difficulty,
@@ -133,10 +237,41 @@ pub fn build_generation_prompt(
         user_instructions: request.notes.as_deref(),
         regenerate_context: None,
         prompt_template,
+        sources,
     };
     build_core_prompt(&config)
 }
 
+/// Build a prompt for questions lost to unrecoverable JSON, steering away from the
+/// questions that were kept so the top-up does not duplicate them.
+pub fn build_top_up_prompt(
+    request: &GenerationRequest,
+    examples: &[QuestionBankEntry],
+    prompt_template: Option<&Template>,
+    topics_label: &str,
+    sources: Option<&SourceMaterial>,
+    missing: usize,
+    kept: &[Question],
+) -> String {
+    let mut notes = request.notes.clone().unwrap_or_default();
+    if !kept.is_empty() {
+        if !notes.trim().is_empty() {
+            notes.push_str("\n\n");
+        }
+        notes.push_str("Do not repeat or closely paraphrase these questions, which are already written:\n");
+        for question in kept {
+            notes.push_str(&format!("- {}\n", truncate(&question.text, 150)));
+        }
+    }
+
+    let top_up = GenerationRequest {
+        count: missing as u32,
+        notes: Some(notes),
+        ..request.clone()
+    };
+    build_generation_prompt(&top_up, examples, prompt_template, topics_label, sources)
+}
+
 /// Format a question bank entry as JSON with only pedagogically useful fields
 fn format_example_as_json(q: &QuestionBankEntry) -> String {
     // Build a clean JSON representation with the useful fields
@@ -209,15 +344,17 @@ fn escape_json_string(s: &str) -> String {
         .replace('\t', "\\t")
 }
 
-/// Build prompt for regenerating a single question
-pub fn build_regenerate_prompt(
+/// Build prompt for regenerating a single question in `mode`, also reporting which
+/// template variables were used.
+pub fn render_regenerate_prompt(
     current: &Question,
     context: &[Question],
     examples: &[QuestionBankEntry],
     user_instructions: Option<&str>,
-    prompt_template: Option<&str>,
+    mode: RegenerateMode,
+    prompt_template: Option<&Template>,
     topics_label_override: Option<&str>,
-) -> String {
+) -> Rendered {
     let topics_label = topics_label_override
         .filter(|s| !s.trim().is_empty())
         .map(|s| s.to_string())
@@ -235,10 +372,19 @@ pub fn build_regenerate_prompt(
         current.subject.clone()
     };
 
-    let inferred_difficulty = examples
-        .first()
-        .map(|e| e.difficulty.clone())
-        .unwrap_or_else(|| "same as original".to_string());
+    let inferred_difficulty = match mode {
+        RegenerateMode::Harder | RegenerateMode::Easier => {
+            let shift = format!("one level {} than the current question", mode.as_str());
+            match mode.target_difficulty(&current.difficulty) {
+                target if target.trim().is_empty() => shift,
+                target => format!("{} ({})", target, shift),
+            }
+        }
+        _ => examples
+            .first()
+            .map(|e| e.difficulty.clone())
+            .unwrap_or_else(|| "same as original".to_string()),
+    };
 
     let inferred_style = examples
         .first()
@@ -272,17 +418,34 @@ pub fn build_regenerate_prompt(
     };
 
     if let Some(template) = prompt_template {
-        return template
-            .replace("{current_question_json}", &current_json)
-            .replace("{difficulty}", &inferred_difficulty)
-            .replace("{topics}", &topics_label)
-            .replace("{subject}", &subject_label)
-            .replace("{style}", &inferred_style)
-            .replace("{other_questions}", &other_questions_block)
-            .replace("{user_instructions}", &user_instructions_block);
+        let other_question_items = other_questions
+            .iter()
+            .map(|q| Vars::from([("text".to_string(), text(q))]))
+            .collect();
+        return template.render(&Vars::from([
+            ("current_question_json".to_string(), text(current_json)),
+            ("difficulty".to_string(), text(inferred_difficulty)),
+            ("topics".to_string(), text(topics_label)),
+            ("subject".to_string(), text(subject_label)),
+            ("style".to_string(), text(inferred_style)),
+            (
+                "other_questions".to_string(),
+                Value::List {
+                    text: other_questions_block,
+                    items: other_question_items,
+                },
+            ),
+            ("user_instructions".to_string(), text(user_instructions_block)),
+            (
+                "notes".to_string(),
+                text(user_instructions.unwrap_or_default().trim()),
+            ),
+            ("mode".to_string(), text(mode.as_str())),
+            ("mode_instructions".to_string(), text(mode.instructions())),
+        ]));
     }
 
-    format!(
+    let text = format!(
         r#"You are rewriting ONE multiple-choice question.
 
 Here is the current question JSON (include stem, choices, and metadata):
@@ -301,7 +464,7 @@ Avoid duplicating these other questions in the current set:
 Additional human instructions (must follow):
 {user_instructions}
 
-Please craft a NEW question that is similar in topic, complexity, and style, but not a paraphrase.
+{mode_instructions}
 
 Required guards:
 1. Return ONLY a JSON array with exactly 1 question object.
@@ -333,247 +496,313 @@ Output format (strict):
         style = inferred_style,
         other_questions = other_questions_block,
         user_instructions = user_instructions_block,
-    )
+        mode_instructions = mode.instructions(),
+    );
+    Rendered {
+        text,
+        ..Rendered::default()
+    }
 }
 
-/// Parse LLM response into Question objects
-pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
-    let trimmed = response.trim();
-
-    eprintln!("Parsing LLM response ({} chars)", trimmed.len());
-    eprintln!("First 200 chars: {}", &trimmed[..trimmed.len().min(200)]);
-
-    // Extract the first *complete* JSON array. This is robust against:
-    // - leading/trailing prose
-    // - ```json fences
-    // - models that accidentally output multiple arrays
-    fn extract_question_tag_contents(s: &str) -> Vec<String> {
-        fn find_tag(
-            lowered: &str,
-            mut search_start: usize,
-            prefix: &str,
-        ) -> Option<(usize, usize)> {
-            // Returns (tag_start, tag_end_exclusive)
-            while let Some(rel_idx) = lowered[search_start..].find(prefix) {
-                let tag_start = search_start + rel_idx;
-                let mut i = tag_start + prefix.len();
-                let bytes = lowered.as_bytes();
-
-                // Ensure `<question` does not match `<questionnaire`
-                if i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
-                    search_start = tag_start + 1;
-                    continue;
-                }
-
-                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
-                    i += 1;
-                }
+// Extract the first *complete* JSON array. This is robust against:
+// - leading/trailing prose
+// - ```json fences
+// - models that accidentally output multiple arrays
+fn extract_question_tag_contents(s: &str) -> Vec<String> {
+    fn find_tag(
+        lowered: &str,
+        mut search_start: usize,
+        prefix: &str,
+    ) -> Option<(usize, usize)> {
+        // Returns (tag_start, tag_end_exclusive)
+        while let Some(rel_idx) = lowered[search_start..].find(prefix) {
+            let tag_start = search_start + rel_idx;
+            let mut i = tag_start + prefix.len();
+            let bytes = lowered.as_bytes();
+
+            // Ensure `<question` does not match `<questionnaire`
+            if i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                search_start = tag_start + 1;
+                continue;
+            }
 
-                if i < bytes.len() && bytes[i] == b'>' {
-                    return Some((tag_start, i + 1));
-                }
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
 
-                search_start = tag_start + 1;
+            if i < bytes.len() && bytes[i] == b'>' {
+                return Some((tag_start, i + 1));
             }
 
-            None
+            search_start = tag_start + 1;
         }
 
-        let lowered = s.to_ascii_lowercase();
-        let mut search_start = 0;
-        let mut open_stack: Vec<usize> = Vec::new();
-        let mut contents: Vec<String> = Vec::new();
-
-        loop {
-            let next_open = find_tag(&lowered, search_start, "<question");
-            let next_close = find_tag(&lowered, search_start, "</question");
-
-            let (is_open, tag_start, tag_end) = match (next_open, next_close) {
-                (Some((os, oe)), Some((cs, ce))) => {
-                    if os <= cs {
-                        (true, os, oe)
-                    } else {
-                        (false, cs, ce)
-                    }
-                }
-                (Some((os, oe)), None) => (true, os, oe),
-                (None, Some((cs, ce))) => (false, cs, ce),
-                (None, None) => break,
-            };
+        None
+    }
 
-            if is_open {
-                open_stack.push(tag_end);
-            } else if let Some(open_end) = open_stack.pop() {
-                contents.push(s[open_end..tag_start].trim().to_string());
+    let lowered = s.to_ascii_lowercase();
+    let mut search_start = 0;
+    let mut open_stack: Vec<usize> = Vec::new();
+    let mut contents: Vec<String> = Vec::new();
+
+    loop {
+        let next_open = find_tag(&lowered, search_start, "<question");
+        let next_close = find_tag(&lowered, search_start, "</question");
+
+        let (is_open, tag_start, tag_end) = match (next_open, next_close) {
+            (Some((os, oe)), Some((cs, ce))) => {
+                if os <= cs {
+                    (true, os, oe)
+                } else {
+                    (false, cs, ce)
+                }
             }
-
-            search_start = tag_end;
+            (Some((os, oe)), None) => (true, os, oe),
+            (None, Some((cs, ce))) => (false, cs, ce),
+            (None, None) => break,
+        };
+
+        if is_open {
+            open_stack.push(tag_end);
+        } else if let Some(open_end) = open_stack.pop() {
+            contents.push(s[open_end..tag_start].trim().to_string());
         }
 
-        contents
+        search_start = tag_end;
     }
 
-    fn extract_first_json_array(s: &str) -> Option<String> {
-        let bytes = s.as_bytes();
-        let mut array_depth: i32 = 0;
-        let mut tracked_start: Option<usize> = None;
-        let mut tracked_level: Option<i32> = None;
-        let mut in_string = false;
-        let mut escape = false;
+    contents
+}
 
-        let mut i = 0;
-        while i < bytes.len() {
-            let b = bytes[i];
+fn extract_first_json_array(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut array_depth: i32 = 0;
+    let mut tracked_start: Option<usize> = None;
+    let mut tracked_level: Option<i32> = None;
+    let mut in_string = false;
+    let mut escape = false;
 
-            if in_string {
-                if escape {
-                    escape = false;
-                    i += 1;
-                    continue;
-                }
-                if b == b'\\' {
-                    escape = true;
-                    i += 1;
-                    continue;
-                }
-                if b == b'"' {
-                    in_string = false;
-                }
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+
+        if in_string {
+            if escape {
+                escape = false;
                 i += 1;
                 continue;
             }
-
-            if b == b'"' {
-                in_string = true;
+            if b == b'\\' {
+                escape = true;
                 i += 1;
                 continue;
             }
+            if b == b'"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
 
-            if b == b'[' {
-                array_depth += 1;
+        if b == b'"' {
+            in_string = true;
+            i += 1;
+            continue;
+        }
 
-                if tracked_start.is_none() {
-                    let mut j = i + 1;
-                    while j < bytes.len() && bytes[j].is_ascii_whitespace() {
-                        j += 1;
-                    }
-                    if j < bytes.len() && bytes[j] == b'{' {
-                        tracked_start = Some(i);
-                        tracked_level = Some(array_depth);
-                    }
-                }
+        if b == b'[' {
+            array_depth += 1;
 
-                i += 1;
-                continue;
+            if tracked_start.is_none() {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                    j += 1;
+                }
+                if j < bytes.len() && bytes[j] == b'{' {
+                    tracked_start = Some(i);
+                    tracked_level = Some(array_depth);
+                }
             }
 
-            if b == b']' {
-                if array_depth > 0 {
-                    if let (Some(start), Some(level)) = (tracked_start, tracked_level) {
-                        if array_depth == level {
-                            return Some(s[start..=i].to_string());
-                        }
-                    }
-                    array_depth -= 1;
+            i += 1;
+            continue;
+        }
 
-                    if array_depth == 0 {
-                        tracked_start = None;
-                        tracked_level = None;
+        if b == b']' {
+            if array_depth > 0 {
+                if let (Some(start), Some(level)) = (tracked_start, tracked_level) {
+                    if array_depth == level {
+                        return Some(s[start..=i].to_string());
                     }
                 }
+                array_depth -= 1;
 
-                i += 1;
-                continue;
+                if array_depth == 0 {
+                    tracked_start = None;
+                    tracked_level = None;
+                }
             }
 
             i += 1;
+            continue;
         }
 
-        None
+        i += 1;
     }
 
-    fn extract_top_level_json_objects(array_json: &str) -> Vec<String> {
-        let bytes = array_json.as_bytes();
-        let mut objects = Vec::new();
+    None
+}
 
-        let mut in_string = false;
-        let mut escape = false;
-        let mut array_depth: i32 = 0;
-        let mut object_depth: i32 = 0;
-        let mut object_start: Option<usize> = None;
+fn extract_top_level_json_objects(array_json: &str) -> Vec<String> {
+    extract_top_level_json_object_spans(array_json)
+        .into_iter()
+        .map(|span| array_json[span].to_string())
+        .collect()
+}
 
-        let mut i = 0;
-        while i < bytes.len() {
-            let b = bytes[i];
+/// Byte ranges of the top-level objects in a JSON array (see `extract_top_level_json_objects`).
+fn extract_top_level_json_object_spans(array_json: &str) -> Vec<std::ops::Range<usize>> {
+    let bytes = array_json.as_bytes();
+    let mut objects = Vec::new();
 
-            if in_string {
-                if escape {
-                    escape = false;
-                    i += 1;
-                    continue;
-                }
-                if b == b'\\' {
-                    escape = true;
-                    i += 1;
-                    continue;
-                }
-                if b == b'"' {
-                    in_string = false;
-                }
-                i += 1;
-                continue;
-            }
+    let mut in_string = false;
+    let mut escape = false;
+    let mut array_depth: i32 = 0;
+    let mut object_depth: i32 = 0;
+    let mut object_start: Option<usize> = None;
 
-            if b == b'"' {
-                in_string = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+
+        if in_string {
+            if escape {
+                escape = false;
                 i += 1;
                 continue;
             }
-
-            if b == b'[' {
-                array_depth += 1;
+            if b == b'\\' {
+                escape = true;
                 i += 1;
                 continue;
             }
+            if b == b'"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
 
-            if b == b']' {
-                if array_depth > 0 {
-                    array_depth -= 1;
-                }
-                i += 1;
-                continue;
+        if b == b'"' {
+            in_string = true;
+            i += 1;
+            continue;
+        }
+
+        if b == b'[' {
+            array_depth += 1;
+            i += 1;
+            continue;
+        }
+
+        if b == b']' {
+            if array_depth > 0 {
+                array_depth -= 1;
             }
+            i += 1;
+            continue;
+        }
 
-            if b == b'{' {
-                // Start tracking a top-level array object, then keep counting nested
-                // object braces until that top-level object is complete.
-                if array_depth == 1 && object_depth == 0 {
-                    object_start = Some(i);
-                }
-                if object_start.is_some() {
-                    object_depth += 1;
-                }
-                i += 1;
-                continue;
+        if b == b'{' {
+            // Start tracking a top-level array object, then keep counting nested
+            // object braces until that top-level object is complete.
+            if array_depth == 1 && object_depth == 0 {
+                object_start = Some(i);
+            }
+            if object_start.is_some() {
+                object_depth += 1;
             }
+            i += 1;
+            continue;
+        }
 
-            if b == b'}' && object_depth > 0 {
-                object_depth -= 1;
-                if object_depth == 0 {
-                    if let Some(start) = object_start.take() {
-                        objects.push(array_json[start..=i].to_string());
-                    }
+        if b == b'}' && object_depth > 0 {
+            object_depth -= 1;
+            if object_depth == 0 {
+                if let Some(start) = object_start.take() {
+                    objects.push(start..i + 1);
                 }
-                i += 1;
-                continue;
             }
-
             i += 1;
+            continue;
         }
 
-        objects
+        i += 1;
     }
 
+    objects
+}
+
+/// A question object that could not be deserialized, kept so it can be sent back for repair.
+#[derive(Debug, Clone)]
+pub struct MalformedItem {
+    pub fragment: String,
+    /// serde error including its line/column within `fragment`.
+    pub error: String,
+    /// Where the error is in the original response text, when it can be traced back.
+    pub location: Option<TextLocation>,
+}
+
+/// 1-based line and column (in characters) within a raw LLM response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct TextLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Outcome of parsing a response whose JSON array was located.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedResponse {
+    pub questions: Vec<Question>,
+    /// Items dropped by per-item salvage.
+    pub dropped: Vec<MalformedItem>,
+    /// Error from parsing the whole array at once, when salvage was needed.
+    pub array_error: Option<String>,
+    pub array_error_location: Option<TextLocation>,
+}
+
+/// Parse LLM response into Question objects
+pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
+    let report = parse_llm_response_report(response)?;
+
+    if report.questions.is_empty() {
+        return Err(match report.array_error {
+            Some(e) if !report.dropped.is_empty() => format!(
+                "Failed to parse JSON response: {}. Salvage failed for all {} item(s): {}",
+                e,
+                report.dropped.len(),
+                report
+                    .dropped
+                    .iter()
+                    .map(|item| item.error.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ),
+            _ => "No questions found in JSON response".to_string(),
+        });
+    }
+
+    Ok(report.questions)
+}
+
+/// Like `parse_llm_response`, but keeps the malformed items instead of only logging them.
+/// Returns `Err` only when no JSON array (or no parseable item boundaries) could be found.
+pub fn parse_llm_response_report(response: &str) -> Result<ParsedResponse, String> {
+    let trimmed = response.trim();
+
+    eprintln!("Parsing LLM response ({} chars)", trimmed.len());
+    eprintln!("First 200 chars: {}", &trimmed[..trimmed.len().min(200)]);
+
     let tagged_contents = extract_question_tag_contents(trimmed);
 
     let mut parse_source = "response_fallback";
@@ -629,11 +858,27 @@ pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
 
     eprintln!("Extracted JSON array ({} chars)", json_str.len());
 
-    let sanitized_json = sanitize_json_string(&json_str);
+    let (sanitized_json, source_offsets) = sanitize_json_with_offsets(&json_str);
+    // Map a serde error inside `sanitized_json[base..]` back to the raw response.
+    let array_offset = response.find(json_str.as_str()).unwrap_or(0);
+    let locate = |base: usize, err: &serde_json::Error| {
+        let sanitized_idx = base + byte_index_of(&sanitized_json[base..], err.line(), err.column());
+        let source_idx = source_offsets
+            .get(sanitized_idx)
+            .copied()
+            .unwrap_or(json_str.len());
+        text_location(response, array_offset + source_idx)
+    };
+
+    let mut dropped = Vec::new();
+    let mut array_error = None;
+    let mut array_error_location = None;
 
     let mut questions: Vec<Question> = match serde_json::from_str(&sanitized_json) {
         Ok(parsed) => parsed,
         Err(e) => {
+            array_error = Some(e.to_string());
+            array_error_location = Some(locate(0, &e));
             eprintln!("ERROR: Failed to parse full JSON array: {}", e);
             eprintln!(
                 "JSON string: {}",
@@ -642,8 +887,8 @@ pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
 
             // Salvage path: parse each top-level object independently so one malformed
             // question does not discard all valid questions in the batch.
-            let object_chunks = extract_top_level_json_objects(&sanitized_json);
-            if object_chunks.is_empty() {
+            let object_spans = extract_top_level_json_object_spans(&sanitized_json);
+            if object_spans.is_empty() {
                 return Err(format!(
                     "Failed to parse JSON response: {}. JSON: {}",
                     e,
@@ -652,32 +897,30 @@ pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
             }
 
             let mut recovered = Vec::new();
-            let mut failures = Vec::new();
 
-            for (idx, chunk) in object_chunks.iter().enumerate() {
+            for (idx, span) in object_spans.iter().enumerate() {
+                let chunk = &sanitized_json[span.clone()];
                 match serde_json::from_str::<Question>(chunk) {
                     Ok(q) => recovered.push(q),
-                    Err(err) => failures.push(format!("item {}: {}", idx + 1, err)),
+                    Err(err) => {
+                        dropped.push(MalformedItem {
+                            fragment: chunk.to_string(),
+                            error: format!("item {}: {}", idx + 1, err),
+                            location: Some(locate(span.start, &err)),
+                        });
+                    }
                 }
             }
 
-            if recovered.is_empty() {
-                return Err(format!(
-                    "Failed to parse JSON response: {}. Salvage failed for all {} item(s): {}",
-                    e,
-                    object_chunks.len(),
-                    failures.join(" | ")
-                ));
-            }
-
             eprintln!(
                 "WARNING: Recovered {}/{} question(s) via per-item salvage parsing; dropped {} malformed item(s)",
                 recovered.len(),
-                object_chunks.len(),
-                object_chunks.len() - recovered.len()
+                object_spans.len(),
+                object_spans.len() - recovered.len()
             );
-            if !failures.is_empty() {
-                eprintln!("Dropped item parse errors: {}", failures.join(" | "));
+            if !dropped.is_empty() {
+                let errors: Vec<&str> = dropped.iter().map(|item| item.error.as_str()).collect();
+                eprintln!("Dropped item parse errors: {}", errors.join(" | "));
             }
 
             recovered
@@ -691,11 +934,10 @@ pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
     if questions.is_empty() {
         eprintln!("ERROR: Parsed JSON array is empty");
         eprintln!("JSON was: {}", &json_str[..json_str.len().min(500)]);
-        return Err("No questions found in JSON response".to_string());
+    } else {
+        eprintln!("Successfully parsed {} question(s)", questions.len());
     }
 
-    eprintln!("Successfully parsed {} question(s)", questions.len());
-
     // Assign IDs
     let mut result = Vec::new();
     for (i, mut q) in questions.into_iter().enumerate() {
@@ -703,12 +945,234 @@ pub fn parse_llm_response(response: &str) -> Result<Vec<Question>, String> {
         result.push(q);
     }
 
-    Ok(result)
+    Ok(ParsedResponse {
+        questions: result,
+        dropped,
+        array_error,
+        array_error_location,
+    })
+}
+
+/// Ask the model to fix a fragment that failed to deserialize. The reply is parsed with
+/// `parse_llm_response`, so it must be a JSON array even for a single question.
+pub fn build_repair_prompt(fragment: &str, error: &str) -> String {
+    format!(
+        r#"The following JSON was supposed to describe multiple-choice questions, but it failed to parse.
+
+Parser error: {error}
+
+Broken JSON:
+{fragment}
+
+Fix the JSON so it matches this schema exactly:
+[{{"text": string, "answers": [{{"text": string, "is_correct": boolean, "explanation": string}}], "explanation": string, "distractors": string}}]
+
+Rules:
+- Return ONLY valid JSON: a single array, no markdown fences, no commentary, no <question> tags.
+- Keep the original wording and answers; change only what is needed to make the JSON valid.
+- Keep any other fields (such as work, code, source or distractor_explanations) as they are.
+- Escape backslashes and double quotes inside strings; use \n for line breaks."#,
+        error = error,
+        fragment = fragment,
+    )
+}
+
+/// Recover the questions that were completely written before a response was cut off,
+/// e.g. when the user cancels a generation mid-stream. Incomplete trailing objects are
+/// ignored; returns an empty list when nothing usable arrived.
+pub fn parse_partial_llm_response(response: &str) -> Vec<Question> {
+    let lowered = response.to_ascii_lowercase();
+    // The final answer follows the last opening <question> tag when the model uses tags.
+    let region_start = lowered.rfind("<question>").map(|idx| idx + "<question>".len());
+    let region = &response[region_start.unwrap_or(0)..];
+
+    let bytes = region.as_bytes();
+    let array_start = (0..bytes.len()).find(|&i| {
+        bytes[i] == b'['
+            && bytes[i + 1..]
+                .iter()
+                .find(|b| !b.is_ascii_whitespace())
+                .is_some_and(|b| *b == b'{')
+    });
+    let Some(array_start) = array_start else {
+        return Vec::new();
+    };
+
+    let sanitized = sanitize_json_string(&region[array_start..]);
+    let mut questions: Vec<Question> = extract_top_level_json_objects(&sanitized)
+        .iter()
+        .filter_map(|chunk| serde_json::from_str::<Question>(chunk).ok())
+        .collect();
+
+    for (i, question) in questions.iter_mut().enumerate() {
+        normalize_question_text(question);
+        question.id = format!("q{}", i + 1);
+    }
+
+    questions
+}
+
+/// Incremental extractor for the question array while a response is still streaming.
+///
+/// Feed text deltas as they arrive; each call returns the questions whose top-level
+/// object closed in that delta, normalized and numbered like `parse_llm_response`.
+/// Works with or without `<question>` tags. An opening tag restarts the scan so that
+/// stray brackets in earlier reasoning cannot swallow the real array. The final
+/// `parse_llm_response` result stays authoritative. Like it, only the first array is read;
+/// a later array of questions is noted in `ignored_trailing_array` rather than streamed.
+#[derive(Debug, Default)]
+pub struct QuestionStreamParser {
+    buffer: String,
+    scanned: usize,
+    in_string: bool,
+    escape: bool,
+    array_depth: i32,
+    object_depth: i32,
+    object_start: Option<usize>,
+    emitted: usize,
+    finished: bool,
+    trailing_array: bool,
+}
+
+impl QuestionStreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of questions returned so far.
+    pub fn emitted(&self) -> usize {
+        self.emitted
+    }
+
+    /// Whether another array of questions followed the one that was read.
+    pub fn ignored_trailing_array(&self) -> bool {
+        self.trailing_array
+    }
+
+    pub fn push(&mut self, delta: &str) -> Vec<Question> {
+        const OPEN_TAG: &[u8] = b"<question>";
+
+        self.buffer.push_str(delta);
+        let mut ready = Vec::new();
+        if self.finished {
+            self.scan_trailing();
+            return ready;
+        }
+
+        let bytes = self.buffer.as_bytes();
+        let mut i = self.scanned;
+        while i < bytes.len() {
+            let b = bytes[i];
+
+            if self.in_string {
+                if self.escape {
+                    self.escape = false;
+                } else if b == b'\\' {
+                    self.escape = true;
+                } else if b == b'"' {
+                    self.in_string = false;
+                }
+                i += 1;
+                continue;
+            }
+
+            match b {
+                b'<' => {
+                    let available = (bytes.len() - i).min(OPEN_TAG.len());
+                    if !bytes[i..i + available].eq_ignore_ascii_case(&OPEN_TAG[..available]) {
+                        i += 1;
+                        continue;
+                    }
+                    if available < OPEN_TAG.len() {
+                        // Possibly a tag split across chunks; wait for more text.
+                        break;
+                    }
+                    self.array_depth = 0;
+                    self.object_depth = 0;
+                    self.object_start = None;
+                    i += OPEN_TAG.len();
+                    continue;
+                }
+                // Quotes only matter inside the array; prose apostrophes and quotes
+                // before it must not flip the string state.
+                b'"' if self.array_depth > 0 => self.in_string = true,
+                b'[' => self.array_depth += 1,
+                b']' if self.array_depth > 0 => {
+                    self.array_depth -= 1;
+                    if self.array_depth == 0 && self.emitted > 0 {
+                        self.finished = true;
+                        i += 1;
+                        break;
+                    }
+                }
+                b'{' if self.array_depth > 0 => {
+                    if self.array_depth == 1 && self.object_depth == 0 {
+                        self.object_start = Some(i);
+                    }
+                    if self.object_start.is_some() {
+                        self.object_depth += 1;
+                    }
+                }
+                b'}' if self.object_depth > 0 => {
+                    self.object_depth -= 1;
+                    if self.object_depth == 0 {
+                        if let Some(start) = self.object_start.take() {
+                            let chunk = sanitize_json_string(&self.buffer[start..=i]);
+                            if let Ok(mut question) = serde_json::from_str::<Question>(&chunk) {
+                                normalize_question_text(&mut question);
+                                self.emitted += 1;
+                                question.id = format!("q{}", self.emitted);
+                                ready.push(question);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        self.scanned = i;
+        if self.finished {
+            self.scan_trailing();
+        }
+        ready
+    }
+
+    /// Look past the closed array for the start of another one (`[` followed by `{`).
+    fn scan_trailing(&mut self) {
+        let bytes = self.buffer.as_bytes();
+        let mut i = self.scanned;
+        while i < bytes.len() && !self.trailing_array {
+            if bytes[i] == b'[' {
+                match bytes[i + 1..].iter().find(|b| !b.is_ascii_whitespace()) {
+                    Some(b'{') => self.trailing_array = true,
+                    Some(_) => {}
+                    // The bracket may open an array split across chunks; wait for more text.
+                    None => break,
+                }
+            }
+            i += 1;
+        }
+        self.scanned = if self.trailing_array { bytes.len() } else { i };
+    }
+}
+
+/// Whether `response` holds another array of questions after the one that gets parsed.
+pub fn has_ignored_trailing_array(response: &str) -> bool {
+    let mut parser = QuestionStreamParser::new();
+    parser.push(response);
+    parser.ignored_trailing_array()
 }
 
 const DOUBLE_BACKSLASH_N_EXCEPTIONS: [&str; 5] = ["eq", "abla", "u", "ewline", "ewcommand"];
 
 fn normalize_question_text(question: &mut Question) {
+    if let Some(stem) = question.stem.take() {
+        if question.text.trim().is_empty() {
+            question.text = stem;
+        }
+    }
     question.text = normalize_escaped_math_and_newlines(&question.text);
 
     if let Some(explanation) = question.explanation.as_ref() {
@@ -725,6 +1189,127 @@ fn normalize_question_text(question: &mut Question) {
             answer.explanation = Some(normalize_escaped_math_and_newlines(explanation));
         }
     }
+
+    question.code = question.code.take().and_then(|code| tidy_code(&code, &question.text));
+    question.work = question
+        .work
+        .take()
+        .map(|work| work.trim().to_string())
+        .filter(|work| !work.is_empty());
+    attach_distractor_rationales(question);
+}
+
+/// Strip a fence the model wrapped around `code`; drop it when the stem already
+/// contains the same code.
+fn tidy_code(code: &str, stem: &str) -> Option<String> {
+    let mut code = code.trim();
+    if let Some(rest) = code.strip_prefix("```") {
+        code = rest.split_once('\n').map_or("", |(_, body)| body);
+        code = code.trim_end().strip_suffix("```").unwrap_or(code).trim();
+    }
+    if code.is_empty() || stem.contains(code) {
+        return None;
+    }
+    Some(code.to_string())
+}
+
+/// Move the model's wrong answer -> misconception map onto the matching answers.
+/// The map comes from `distractor_explanations`, or from `distractors` when the model
+/// put it there. Keys may be an answer's text or its letter; entries that match no
+/// answer (or one that already has an explanation) are kept in `distractors` as a list.
+fn attach_distractor_rationales(question: &mut Question) {
+    let map = match question.distractor_explanations.take() {
+        Some(serde_json::Value::Object(map)) => map,
+        Some(serde_json::Value::String(text)) => {
+            if question.distractors.is_none() && !text.trim().is_empty() {
+                question.distractors = Some(normalize_escaped_math_and_newlines(text.trim()));
+            }
+            return;
+        }
+        _ => match question.distractors.as_deref().map(str::trim) {
+            Some(text) if text.starts_with('{') => {
+                match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(text) {
+                    Ok(map) => {
+                        question.distractors = None;
+                        map
+                    }
+                    Err(_) => return,
+                }
+            }
+            _ => return,
+        },
+    };
+
+    let mut unmatched = Vec::new();
+    for (key, value) in map {
+        let rationale = match value {
+            serde_json::Value::String(text) => text,
+            other => other.to_string(),
+        };
+        let rationale = normalize_escaped_math_and_newlines(rationale.trim());
+        if rationale.is_empty() {
+            continue;
+        }
+        match matching_answer(&question.answers, &key) {
+            Some(i) if question.answers[i].explanation.is_none() => {
+                question.answers[i].explanation = Some(rationale);
+            }
+            _ => unmatched.push(format!("- {}: {}", key.trim(), rationale)),
+        }
+    }
+
+    if !unmatched.is_empty() {
+        let unmatched = unmatched.join("\n");
+        question.distractors = Some(match question.distractors.take() {
+            Some(existing) if !existing.trim().is_empty() => {
+                format!("{}\n{}", existing.trim_end(), unmatched)
+            }
+            _ => unmatched,
+        });
+    }
+}
+
+/// Index of the answer a distractor key refers to, by text (ignoring code and math
+/// markup) or by letter. Letters are not tried when an answer's own text is a single
+/// letter, since a key like `B` could then name either.
+pub(crate) fn matching_answer(answers: &[Answer], key: &str) -> Option<usize> {
+    if let Some(i) = matching_text(answers, key) {
+        return Some(i);
+    }
+    if answers.iter().any(|a| is_single_letter(&a.text)) {
+        return None;
+    }
+
+    let letter = key.trim().trim_matches(|c| matches!(c, '(' | ')' | '.' | ':'));
+    match letter.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => {
+            let index = usize::from(c.to_ascii_uppercase() - b'A');
+            (index < answers.len()).then_some(index)
+        }
+        _ => None,
+    }
+}
+
+/// Index of the answer whose text matches `text`, ignoring code and math markup.
+pub(crate) fn matching_text(answers: &[Answer], text: &str) -> Option<usize> {
+    let wanted = plain_answer_text(text);
+    answers
+        .iter()
+        .position(|a| plain_answer_text(&a.text) == wanted)
+}
+
+fn is_single_letter(text: &str) -> bool {
+    let plain = plain_answer_text(text);
+    let plain = plain.trim_matches(|c| matches!(c, '(' | ')' | '.' | ':'));
+    plain.len() == 1 && plain.as_bytes()[0].is_ascii_alphabetic()
+}
+
+/// Answer text compared without whitespace, backticks, `$`, quotes or case.
+pub(crate) fn plain_answer_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '`' | '$' | '"'))
+        .collect::<String>()
+        .to_lowercase()
 }
 
 fn normalize_escaped_math_and_newlines(input: &str) -> String {
@@ -804,15 +1389,25 @@ fn normalize_escaped_math_and_newlines(input: &str) -> String {
 /// Ensure literal control characters inside JSON strings are escaped so serde can parse them.
 /// Also fixes common invalid escape sequences that LLMs generate (e.g., \( \) \[ \] for LaTeX).
 fn sanitize_json_string(input: &str) -> String {
+    sanitize_json_with_offsets(input).0
+}
+
+/// `sanitize_json_string`, also returning for every output byte the byte offset of the
+/// input character it came from, so parse errors can be reported against the raw text.
+fn sanitize_json_with_offsets(input: &str) -> (String, Vec<usize>) {
+    fn push(output: &mut String, offsets: &mut Vec<usize>, text: &str, source: usize) {
+        output.push_str(text);
+        offsets.extend(std::iter::repeat_n(source, text.len()));
+    }
+
     let mut output = String::with_capacity(input.len() + 256);
+    let mut offsets = Vec::with_capacity(input.len() + 256);
     let mut in_string = false;
     let mut escape = false;
 
-    let chars: Vec<char> = input.chars().collect();
-    let mut i = 0;
-
-    while i < chars.len() {
-        let ch = chars[i];
+    for (src, ch) in input.char_indices() {
+        let mut buf = [0u8; 4];
+        let ch_str: &str = ch.encode_utf8(&mut buf);
 
         if in_string {
             if escape {
@@ -820,49 +1415,71 @@ fn sanitize_json_string(input: &str) -> String {
                 match ch {
                     '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u' => {
                         // Valid JSON escape sequence
-                        output.push(ch);
+                        push(&mut output, &mut offsets, ch_str, src);
                     }
-                    '\n' => output.push('n'),
-                    '\r' => output.push('r'),
-                    '\t' => output.push('t'),
+                    '\n' => push(&mut output, &mut offsets, "n", src),
+                    '\r' => push(&mut output, &mut offsets, "r", src),
+                    '\t' => push(&mut output, &mut offsets, "t", src),
                     _ => {
                         // Invalid escape sequence (common for LaTeX like \(, \), \[, \], etc.)
                         // Add another backslash to make it literal
-                        output.push('\\');
-                        output.push(ch);
+                        push(&mut output, &mut offsets, "\\", src);
+                        push(&mut output, &mut offsets, ch_str, src);
                     }
                 }
                 escape = false;
-                i += 1;
                 continue;
             }
 
             match ch {
                 '\\' => {
-                    output.push(ch);
+                    push(&mut output, &mut offsets, ch_str, src);
                     escape = true;
                 }
                 '"' => {
-                    output.push(ch);
+                    push(&mut output, &mut offsets, ch_str, src);
                     in_string = false;
                 }
-                '\n' => output.push_str("\\n"),
-                '\r' => output.push_str("\\r"),
-                '\t' => output.push_str("\\t"),
-                _ => output.push(ch),
+                '\n' => push(&mut output, &mut offsets, "\\n", src),
+                '\r' => push(&mut output, &mut offsets, "\\r", src),
+                '\t' => push(&mut output, &mut offsets, "\\t", src),
+                _ => push(&mut output, &mut offsets, ch_str, src),
             }
         } else {
-            output.push(ch);
+            push(&mut output, &mut offsets, ch_str, src);
             if ch == '"' {
                 in_string = true;
                 escape = false;
             }
         }
-
-        i += 1;
     }
 
-    output
+    (output, offsets)
+}
+
+/// Byte index for a serde_json (line, column) position; columns count bytes from 1.
+fn byte_index_of(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + column.saturating_sub(1)).min(text.len().saturating_sub(1))
+}
+
+/// 1-based line/column (columns in characters) of a byte offset.
+fn text_location(text: &str, byte_offset: usize) -> TextLocation {
+    let mut offset = byte_offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    TextLocation {
+        line,
+        column: before[line_start..].chars().count() + 1,
+    }
 }
 
 /// Truncate string to max length
@@ -929,6 +1546,122 @@ mod tests {
         assert_eq!(questions[1].id, "q2");
     }
 
+    #[test]
+    fn test_parse_structured_schema() {
+        let input = r#"[
+  {
+    "work": "mystery(5) = 5+4+3+2+1+0 = 15. Errors: off-by-one -> 14, factorial -> 120",
+    "stem": "What is returned by `mystery(5)`?",
+    "code": "```java\nint mystery(int n) {\n    return n == 0 ? 0 : n + mystery(n - 1);\n}\n```",
+    "answers": [
+      {"text": "`15`", "is_correct": true},
+      {"text": "`14`", "is_correct": false},
+      {"text": "`120`", "is_correct": false},
+      {"text": "`0`", "is_correct": false}
+    ],
+    "correct_explanation": "5 + 4 + 3 + 2 + 1 + 0 = 15",
+    "distractor_explanations": {
+      "14": "Off-by-one: stops at n=1",
+      "D": "Only considers the base case",
+      "-1": "Not one of the choices"
+    }
+  },
+  {
+    "text": "Legacy map in distractors",
+    "answers": [
+      {"text": "A", "is_correct": true},
+      {"text": "B", "is_correct": false}
+    ],
+    "distractors": {"B": "Confuses the two"}
+  }
+]"#;
+
+        let questions = parse_llm_response(input).unwrap();
+        let q = &questions[0];
+        assert_eq!(q.text, "What is returned by `mystery(5)`?");
+        assert_eq!(
+            q.code.as_deref(),
+            Some("int mystery(int n) {\n    return n == 0 ? 0 : n + mystery(n - 1);\n}")
+        );
+        assert!(q.stem_markdown().ends_with("`mystery(5)`?\n\n```\nint mystery(int n) {\n    return n == 0 ? 0 : n + mystery(n - 1);\n}\n```"));
+        let mut cs = q.clone();
+        cs.subject = "Computer Science".to_string();
+        assert!(cs.stem_markdown().ends_with("`mystery(5)`?\n\n```java\nint mystery(int n) {\n    return n == 0 ? 0 : n + mystery(n - 1);\n}\n```"));
+        assert!(q.work.as_deref().unwrap().starts_with("mystery(5)"));
+        assert_eq!(q.explanation.as_deref(), Some("5 + 4 + 3 + 2 + 1 + 0 = 15"));
+        assert_eq!(q.answers[0].explanation, None);
+        assert_eq!(
+            q.answers[1].explanation.as_deref(),
+            Some("Off-by-one: stops at n=1")
+        );
+        assert_eq!(
+            q.answers[3].explanation.as_deref(),
+            Some("Only considers the base case")
+        );
+        assert_eq!(
+            q.distractors.as_deref(),
+            Some("- -1: Not one of the choices")
+        );
+
+        let serialized = serde_json::to_value(q).unwrap();
+        assert!(serialized.get("distractor_explanations").is_none());
+        assert!(serialized.get("work").is_some());
+
+        assert_eq!(
+            questions[1].answers[1].explanation.as_deref(),
+            Some("Confuses the two")
+        );
+        assert_eq!(questions[1].distractors, None);
+    }
+
+    #[test]
+    fn test_letter_keys_skip_one_letter_answers() {
+        let lettered = [
+            Answer {
+                text: "`x`".to_string(),
+                is_correct: true,
+                explanation: None,
+            },
+            Answer {
+                text: "y".to_string(),
+                is_correct: false,
+                explanation: None,
+            },
+        ];
+        assert_eq!(matching_answer(&lettered, "B"), None);
+        assert_eq!(matching_answer(&lettered, "x"), Some(0));
+
+        let worded = [
+            Answer {
+                text: "ten".to_string(),
+                is_correct: true,
+                explanation: None,
+            },
+            Answer {
+                text: "eleven".to_string(),
+                is_correct: false,
+                explanation: None,
+            },
+        ];
+        assert_eq!(matching_answer(&worded, "B"), Some(1));
+    }
+
+    #[test]
+    fn test_text_and_stem_both_present() {
+        let input = r#"[
+  {"text": "Which is prime?", "stem": "Ignored duplicate", "answers": [{"text": "7", "is_correct": true}]},
+  {"text": "", "stem": "Which is even?", "answers": [{"text": "8", "is_correct": true}]}
+]"#;
+
+        let questions = parse_llm_response(input).unwrap();
+        assert_eq!(questions[0].text, "Which is prime?");
+        assert_eq!(questions[1].text, "Which is even?");
+        assert!(serde_json::to_value(&questions[1])
+            .unwrap()
+            .get("stem")
+            .is_none());
+    }
+
     #[test]
     fn test_backslash_newline_sanitization() {
         let input = "[\n  {\n    \"text\": \"Line 1\\\nLine 2\",\n    \"answers\": [\n      {\"text\": \"A\", \"is_correct\": true}\n    ]\n  }\n]";
@@ -1239,4 +1972,159 @@ Assistant final:
                 assert_eq!(questions[0].text, "Valid question 1");
                 assert_eq!(questions[1].text, "Valid question 2");
         }
+
+    #[test]
+    fn test_parse_partial_keeps_completed_objects_only() {
+        let input = r#"<question>
+[
+    {
+        "text": "Finished question",
+        "answers": [
+            {"text": "A", "is_correct": true},
+            {"text": "B", "is_correct": false}
+        ]
+    },
+    {
+        "text": "Half-written question",
+        "answers": [
+            {"text": "C", "is_corr"#;
+
+        let questions = parse_partial_llm_response(input);
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].id, "q1");
+        assert_eq!(questions[0].text, "Finished question");
+    }
+
+    #[test]
+    fn test_repair_prompt_spells_out_newline_escape() {
+        let prompt = build_repair_prompt("[{\"text\": \"a\nb\"}]", "control character");
+        assert!(prompt.contains("use \\n for line breaks."));
+    }
+
+    #[test]
+    fn test_parse_partial_returns_empty_without_array() {
+        assert!(parse_partial_llm_response("Thinking about loops...").is_empty());
+    }
+
+    #[test]
+    fn test_stream_parser_emits_each_question_as_it_closes() {
+        let response = r#"Thinking about arr[i] and "quotes"...
+<question>
+[
+    {"text": "First {braces} in a string", "answers": [{"text": "A", "is_correct": true}]},
+    {"text": "Second", "answers": [{"text": "B", "is_correct": true}]}
+]
+</question>"#;
+
+        let mut parser = QuestionStreamParser::new();
+        let mut ready_after = Vec::new();
+        for (pos, ch) in response.char_indices() {
+            for question in parser.push(&ch.to_string()) {
+                ready_after.push((pos, question));
+            }
+        }
+
+        assert_eq!(ready_after.len(), 2);
+        assert_eq!(ready_after[0].1.id, "q1");
+        assert_eq!(ready_after[0].1.text, "First {braces} in a string");
+        assert_eq!(ready_after[1].1.id, "q2");
+        // The first question is available before the second one is written.
+        assert!(ready_after[0].0 < response.find("Second").unwrap());
+    }
+
+    #[test]
+    fn test_stream_parser_without_tags_ignores_later_arrays() {
+        let mut parser = QuestionStreamParser::new();
+        let first = parser.push(r#"[{"text": "Only", "answers": []}"#);
+        assert_eq!(first.len(), 1);
+        let rest = parser.push(r#"] and again [{"text": "Dup", "answers": []}]"#);
+        assert!(rest.is_empty());
+        assert!(parser.ignored_trailing_array());
+    }
+
+    #[test]
+    fn test_trailing_array_detection() {
+        // A bracket in the closing prose is not another array of questions.
+        assert!(!has_ignored_trailing_array(
+            r#"[{"text": "Only", "answers": []}] See [1]."#
+        ));
+        assert!(has_ignored_trailing_array(
+            "<question>[{\"text\": \"A\", \"answers\": []}]</question>\n[\n  {\"text\": \"B\", \"answers\": []}]"
+        ));
+
+        // The second array's opening bracket and first object can arrive in separate chunks.
+        let mut parser = QuestionStreamParser::new();
+        parser.push(r#"[{"text": "A", "answers": []}] ["#);
+        assert!(!parser.ignored_trailing_array());
+        parser.push(r#" {"text": "B", "answers": []}]"#);
+        assert!(parser.ignored_trailing_array());
+    }
+
+    #[test]
+    fn test_report_locates_errors_in_raw_text() {
+        // The raw newline inside the first string is escaped by sanitizing, which
+        // shifts offsets; locations must still point into the original text.
+        let response = "Intro line\n<question>\n[\n  {\"text\": \"Line one\nline two\", \"answers\": []},\n  {\"text\": \"Bad\", \"answers\": [{\"text\": \"B\", \"is_correct\": \"yes\"}]}\n]\n</question>";
+
+        let report = parse_llm_response_report(response).unwrap();
+        assert_eq!(report.questions.len(), 1);
+        assert_eq!(report.dropped.len(), 1);
+
+        let location = report.dropped[0].location.expect("location");
+        assert_eq!(location.line, 6);
+        let raw_line = response.lines().nth(location.line - 1).unwrap();
+        let upto: String = raw_line.chars().take(location.column).collect();
+        assert!(upto.ends_with("\"yes\""), "column points at: {}", upto);
+    }
+
+    #[test]
+    fn test_bundled_templates_load_and_do_not_rescan_examples() {
+        for source in [
+            include_str!("../../imports/knowledge/Calculus/prompt.txt"),
+            include_str!("../../imports/knowledge/Computer Science/prompt.txt"),
+        ] {
+            Template::load(source, GENERATION_VARIABLES).unwrap();
+        }
+        for source in [
+            include_str!("../../imports/knowledge/Calculus/regeneration-prompt.txt"),
+            include_str!("../../imports/knowledge/Computer Science/regeneration-prompt.txt"),
+        ] {
+            Template::load(source, REGENERATION_VARIABLES).unwrap();
+        }
+
+        let example: QuestionBankEntry = serde_json::from_value(serde_json::json!({
+            "id": "calc-1",
+            "text": "Evaluate {count} terms",
+            "options": [],
+            "explanation": "",
+            "difficulty": "D1",
+            "cognitive_level": "apply",
+            "topics": [],
+            "skills": [],
+            "distractors": {"common_mistakes": [], "common_errors": []}
+        }))
+        .unwrap();
+        let request = GenerationRequest {
+            subject: "Calculus".to_string(),
+            topics: vec!["limits".to_string()],
+            difficulty: "easy".to_string(),
+            count: 4,
+            notes: None,
+            append: false,
+            sources: Vec::new(),
+            cognitive_level: None,
+        };
+        let template = Template::load(
+            "{count} about {topics}\n{#each examples}\n{id}: {text}\n{/each}\n{#if notes}\nNotes: {notes}\n{/if}\nEnd",
+            GENERATION_VARIABLES,
+        )
+        .unwrap();
+        let rendered =
+            render_generation_prompt(&request, &[example], Some(&template), "Limits", None);
+        assert_eq!(
+            rendered.text,
+            "4 about Limits\ncalc-1: Evaluate {count} terms\nEnd"
+        );
+        assert!(rendered.missing.is_empty());
+    }
 }
@@ -1,6 +1,6 @@
 //! QTI export functionality - generates IMS QTI XML for LMS import
 
-use crate::{Question, QuestionBankEntry};
+use crate::{Provenance, Question, QuestionBankEntry};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use regex::Regex;
@@ -302,6 +302,11 @@ pub struct ExportMdOptions {
     pub include_choices: bool,
     pub shuffle_choices: bool,
     pub shuffle_questions: bool,
+    /// Append a table of how each generated question was produced.
+    pub include_provenance: bool,
+    /// Put each choice in its own paragraph and leave a blank paragraph after each
+    /// question, for accommodated documents.
+    pub larger_spacing: bool,
 }
 
 impl Default for ExportMdOptions {
@@ -312,6 +317,8 @@ impl Default for ExportMdOptions {
             include_choices: true,
             shuffle_choices: true,
             shuffle_questions: false,
+            include_provenance: false,
+            larger_spacing: false,
         }
     }
 }
@@ -362,6 +369,7 @@ pub fn export_md_with_options(
     let mut rng = thread_rng();
     let mut answer_key: Vec<(usize, char)> = Vec::new();
     let mut explanation_key: Vec<(usize, String)> = Vec::new();
+    let mut provenance_key: Vec<(usize, &Provenance)> = Vec::new();
     let mut ordered_questions = questions.to_vec();
 
     if options.shuffle_questions {
@@ -369,8 +377,9 @@ pub fn export_md_with_options(
     }
 
     for (i, q) in ordered_questions.iter().enumerate() {
-        let question_text =
-            convert_codeblock_tables_to_markdown(&normalize_math_delimiters(q.text.trim()));
+        let question_text = convert_codeblock_tables_to_markdown(&normalize_math_delimiters(
+            q.stem_markdown().trim(),
+        ));
         output.push_str(&format!("**Question {}.** {}\n\n", i + 1, question_text));
 
         let mut answers = q.answers.clone();
@@ -387,6 +396,9 @@ pub fn export_md_with_options(
                 let formatted = format!("{}) {}", label, body);
                 output.push_str(&formatted);
                 output.push('\n');
+                if options.larger_spacing {
+                    output.push('\n');
+                }
             }
         }
 
@@ -410,7 +422,16 @@ pub fn export_md_with_options(
             }
         }
 
+        if options.include_provenance {
+            if let Some(provenance) = &q.provenance {
+                provenance_key.push((i + 1, provenance));
+            }
+        }
+
         output.push('\n');
+        if options.larger_spacing {
+            output.push_str("&nbsp;\n\n");
+        }
     }
 
     if options.include_answer_key && options.include_choices && !answer_key.is_empty() {
@@ -427,6 +448,37 @@ pub fn export_md_with_options(
         }
     }
 
+    if options.include_provenance && !provenance_key.is_empty() {
+        output.push_str("\n## Provenance\n\n");
+        output.push_str(
+            "| Question | Kind | Template | Examples | Provider | Model | Job | Generated |\n",
+        );
+        output.push_str("|---|---|---|---|---|---|---|---|\n");
+        for (number, provenance) in provenance_key {
+            let template = match (&provenance.template_version, &provenance.template_hash) {
+                (Some(version), Some(hash)) => format!("{} ({})", version, hash),
+                (None, Some(hash)) => hash.clone(),
+                _ => "built-in".to_string(),
+            };
+            let cells = [
+                number.to_string(),
+                match (provenance.regeneration_mode, provenance.seed) {
+                    (Some(mode), _) => format!("{} ({})", provenance.kind, mode.as_str()),
+                    (None, Some(seed)) => format!("{} (seed {})", provenance.kind, seed),
+                    (None, None) => provenance.kind.clone(),
+                },
+                template,
+                provenance.example_ids.join(", "),
+                provenance.provider.clone(),
+                provenance.model.clone(),
+                provenance.job_id.clone(),
+                provenance.generated_at.clone(),
+            ];
+            let cells: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+            output.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+
     Ok(output)
 }
 
@@ -616,7 +668,7 @@ fn generate_qti_xml(_title: &str, questions: &[Question], options: ExportQtiOpti
 /// Convert a question to HTML format
 fn convert_to_html(q: &Question) -> String {
     // Clean special characters first
-    let text = clean_special_characters(&q.text);
+    let text = clean_special_characters(&q.stem_markdown());
 
     // Parse markdown: extract code blocks, tables, LaTeX, and convert rest
     let code_block_re = Regex::new(r"```(?:java)?\n([^`]+)\n```").unwrap();
@@ -796,11 +848,8 @@ mod tests {
         let questions = vec![Question {
             id: "1".to_string(),
             text: "What is 2+2?".to_string(),
-            explanation: None,
-            distractors: None,
             subject: "Test".to_string(),
             topics: vec!["Math".to_string()],
-            difficulty: String::new(),
             answers: vec![
                 Answer {
                     text: "4".to_string(),
@@ -813,6 +862,7 @@ mod tests {
                     explanation: None,
                 },
             ],
+            ..Default::default()
         }];
 
         let result = export_txt("Test", &questions).unwrap();
@@ -857,12 +907,10 @@ mod tests {
         let q = Question {
             id: "1".to_string(),
             text: "Find $F'(x)$ and $g''(t)$".to_string(),
-            explanation: None,
-            distractors: None,
             subject: "Calculus".to_string(),
             topics: vec!["Derivatives".to_string()],
-            difficulty: String::new(),
             answers: vec![],
+            ..Default::default()
         };
 
         let result = convert_to_html(&q);
@@ -904,12 +952,10 @@ mod tests {
         let q = Question {
             id: "1".to_string(),
             text: "Find $F'(x)$ here".to_string(),
-            explanation: None,
-            distractors: None,
             subject: "Calculus".to_string(),
             topics: vec!["Derivatives".to_string()],
-            difficulty: String::new(),
             answers: vec![],
+            ..Default::default()
         };
 
         let result = convert_to_html(&q);
@@ -949,12 +995,10 @@ mod tests {
         let q = Question {
             id: "1".to_string(),
             text: "Here is an equation: $$F'(x) = 2x$$ and more text".to_string(),
-            explanation: None,
-            distractors: None,
             subject: "Calculus".to_string(),
             topics: vec!["Derivatives".to_string()],
-            difficulty: String::new(),
             answers: vec![],
+            ..Default::default()
         };
 
         let result = convert_to_html(&q);
@@ -1027,10 +1071,8 @@ mod tests {
             id: "q1".to_string(),
             text: "What is 2 + 2?".to_string(),
             explanation: Some("Add the two integers directly.".to_string()),
-            distractors: None,
             subject: "Math".to_string(),
             topics: vec!["arithmetic".to_string()],
-            difficulty: String::new(),
             answers: vec![
                 Answer {
                     text: "4".to_string(),
@@ -1043,6 +1085,7 @@ mod tests {
                     explanation: None,
                 },
             ],
+            ..Default::default()
         }];
 
         let without_explanations =
@@ -1067,4 +1110,57 @@ mod tests {
         assert!(with_explanations.contains("Add the two integers directly."));
         assert!(!with_explanations.contains("1. Add the two integers directly."));
     }
+
+    #[test]
+    fn test_export_md_provenance_appendix_lists_generated_questions() {
+        let answers = vec![Answer {
+            text: "4".to_string(),
+            is_correct: true,
+            explanation: None,
+        }];
+        let generated = Question {
+            id: "q1".to_string(),
+            text: "What is 2 + 2?".to_string(),
+            subject: "Math".to_string(),
+            provenance: Some(Provenance {
+                kind: "generate".to_string(),
+                template_version: Some("v2".to_string()),
+                template_hash: Some("abc123def456".to_string()),
+                example_ids: vec!["ex-1".to_string(), "ex-2".to_string()],
+                provider: "bedrock-gateway".to_string(),
+                model: "http://gateway/generate".to_string(),
+                job_id: "job_1".to_string(),
+                generated_at: "2026-01-01T00:00:00Z".to_string(),
+                regeneration_mode: None,
+                seed: None,
+            }),
+            answers,
+            ..Default::default()
+        };
+        let handwritten = Question {
+            id: "q2".to_string(),
+            provenance: None,
+            ..generated.clone()
+        };
+
+        let output = export_md_with_options(
+            "Quiz",
+            &[handwritten, generated],
+            ExportMdOptions {
+                include_provenance: true,
+                shuffle_choices: false,
+                ..ExportMdOptions::default()
+            },
+        )
+        .unwrap();
+
+        let appendix = &output[output.find("## Provenance").unwrap()..];
+        assert!(appendix.contains(
+            "| 2 | generate | v2 (abc123def456) | ex-1, ex-2 | bedrock-gateway | http://gateway/generate | job_1 | 2026-01-01T00:00:00Z |"
+        ));
+        assert!(!appendix.contains("| 1 |"));
+
+        let plain = export_md_with_options("Quiz", &[], ExportMdOptions::default()).unwrap();
+        assert!(!plain.contains("## Provenance"));
+    }
 }
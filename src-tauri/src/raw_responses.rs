@@ -0,0 +1,182 @@
+//! Last raw LLM response of each job, kept in memory and under the app data dir
+//!
+//! Lets the user fix a response that failed to parse and re-run the parser on it
+//! (`reparse_raw_response`) instead of paying for another generation.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+/// Responses kept in memory; older ones are still available from disk.
+const MAX_IN_MEMORY: usize = 20;
+/// Response files kept on disk; the oldest are pruned first.
+const MAX_ON_DISK: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawResponse {
+    pub job_id: String,
+    pub created_at: String,
+    /// Question metadata to apply to anything parsed from this response.
+    pub subject: String,
+    pub topics: Vec<String>,
+    pub difficulty: String,
+    pub text: String,
+}
+
+impl RawResponse {
+    pub fn new(
+        job_id: &str,
+        subject: &str,
+        topics: &[String],
+        difficulty: &str,
+        text: &str,
+    ) -> Self {
+        Self {
+            job_id: job_id.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            subject: subject.to_string(),
+            topics: topics.to_vec(),
+            difficulty: difficulty.to_string(),
+            text: text.to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RawResponseStore {
+    recent: Mutex<VecDeque<RawResponse>>,
+}
+
+impl RawResponseStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember a response, replacing any earlier one for the same job. Disk errors are
+    /// logged but do not fail the generation.
+    pub fn record(&self, app_handle: Option<&AppHandle>, response: RawResponse) {
+        if let Some(dir) = app_handle.and_then(store_dir) {
+            if let Err(e) = write_response(&dir, &response) {
+                eprintln!("WARNING: Failed to save raw response: {}", e);
+            }
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|entry| entry.job_id != response.job_id);
+        recent.push_back(response);
+        while recent.len() > MAX_IN_MEMORY {
+            recent.pop_front();
+        }
+    }
+
+    pub fn get(&self, app_handle: Option<&AppHandle>, job_id: &str) -> Option<RawResponse> {
+        if let Some(found) = self
+            .recent
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.job_id == job_id)
+        {
+            return Some(found.clone());
+        }
+
+        let path = app_handle.and_then(store_dir)?.join(file_name(job_id));
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+}
+
+fn store_dir(app_handle: &AppHandle) -> Option<PathBuf> {
+    app_handle
+        .path_resolver()
+        .app_local_data_dir()
+        .map(|dir| dir.join("raw-responses"))
+}
+
+/// Job ids come from the frontend. Anything but ASCII letters, digits, `-` and `_` is
+/// percent-encoded, so every id gets its own file name.
+fn file_name(job_id: &str) -> String {
+    let mut safe = String::with_capacity(job_id.len());
+    for byte in job_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            safe.push(char::from(byte));
+        } else {
+            safe.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    format!("{}.json", safe)
+}
+
+fn write_response(dir: &Path, response: &RawResponse) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let json = serde_json::to_string_pretty(response)
+        .map_err(|e| format!("Failed to serialize raw response: {}", e))?;
+    let path = dir.join(file_name(&response.job_id));
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut f = fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create temp file {}: {}", tmp_path.display(), e))?;
+        f.write_all(json.as_bytes())
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    }
+    fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+
+    prune(dir);
+    Ok(())
+}
+
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .collect();
+
+    if files.len() <= MAX_ON_DISK {
+        return;
+    }
+    files.sort();
+    for (_, path) in files.iter().take(files.len() - MAX_ON_DISK) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name_is_sanitized() {
+        assert_eq!(file_name("job_1-ab"), "job_1-ab.json");
+        assert_eq!(file_name("../etc/passwd"), "%2E%2E%2Fetc%2Fpasswd.json");
+        assert_ne!(file_name("a/b"), file_name("a_b"));
+        assert_ne!(file_name("a/b"), file_name("a%2Fb"));
+    }
+
+    #[test]
+    fn test_record_replaces_and_caps_memory() {
+        let store = RawResponseStore::new();
+        for i in 0..(MAX_IN_MEMORY + 5) {
+            let id = format!("job_{}", i);
+            store.record(None, RawResponse::new(&id, "CS", &[], "easy", "old"));
+        }
+        store.record(None, RawResponse::new("job_24", "CS", &[], "easy", "new"));
+
+        assert!(store.get(None, "job_0").is_none());
+        assert_eq!(store.get(None, "job_24").unwrap().text, "new");
+        assert_eq!(store.recent.lock().unwrap().len(), MAX_IN_MEMORY);
+    }
+}
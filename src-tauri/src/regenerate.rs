@@ -0,0 +1,380 @@
+//! Targeted regeneration modes
+//!
+//! A full regeneration rewrites the whole item. The other modes ask the model for the
+//! same question with only one part changed (the wrong answers, the explanation, the
+//! difficulty or the numbers) and [`merge`] copies just that part back onto the
+//! existing question, so ids, metadata and untouched fields survive.
+//!
+//! Each mode has its own per-subject template slot, `{subject}/regeneration-{mode}.txt`
+//! (full mode keeps `regeneration-prompt.txt`). Subjects without one use the built-in
+//! prompt for that mode.
+
+use crate::prompts::{matching_text, plain_answer_text};
+use crate::{Answer, Provenance, Question};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegenerateMode {
+    /// Rewrite the whole question.
+    #[default]
+    Full,
+    /// Keep the stem and correct answer; replace the wrong answers.
+    Distractors,
+    /// Keep the question and answers; rewrite the explanation and answer rationales.
+    Explanation,
+    /// Same concept, one difficulty level harder.
+    Harder,
+    /// Same concept, one difficulty level easier.
+    Easier,
+    /// Same question with different numbers or values.
+    Numbers,
+}
+
+impl RegenerateMode {
+    pub const ALL: [RegenerateMode; 6] = [
+        RegenerateMode::Full,
+        RegenerateMode::Distractors,
+        RegenerateMode::Explanation,
+        RegenerateMode::Harder,
+        RegenerateMode::Easier,
+        RegenerateMode::Numbers,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegenerateMode::Full => "full",
+            RegenerateMode::Distractors => "distractors",
+            RegenerateMode::Explanation => "explanation",
+            RegenerateMode::Harder => "harder",
+            RegenerateMode::Easier => "easier",
+            RegenerateMode::Numbers => "numbers",
+        }
+    }
+
+    /// Knowledge file holding a subject's template for this mode.
+    pub fn template_file(self) -> String {
+        match self {
+            RegenerateMode::Full => "regeneration-prompt.txt".to_string(),
+            mode => format!("regeneration-{}.txt", mode.as_str()),
+        }
+    }
+
+    /// What the model should change, rendered into the prompt as `{mode_instructions}`.
+    pub fn instructions(self) -> &'static str {
+        match self {
+            RegenerateMode::Full => {
+                "Please craft a NEW question that is similar in topic, complexity, and style, \
+                 but not a paraphrase."
+            }
+            RegenerateMode::Distractors => {
+                "Keep the question text, any code and the correct answer exactly as they are. \
+                 Replace every wrong answer with a new, more plausible distractor that reflects \
+                 a specific misconception, and explain that misconception in the answer's \
+                 explanation. Keep the same number of answer choices."
+            }
+            RegenerateMode::Explanation => {
+                "Keep the question text, any code and every answer choice exactly as they are, \
+                 including which answer is correct. Rewrite only the explanation, the \
+                 distractors summary and each answer's explanation so they are accurate and \
+                 consistent with the marked correct answer."
+            }
+            RegenerateMode::Harder => {
+                "Write a question on exactly the same concept that is one difficulty level \
+                 harder: add a reasoning step or a subtler case, not unrelated material."
+            }
+            RegenerateMode::Easier => {
+                "Write a question on exactly the same concept that is one difficulty level \
+                 easier: remove a reasoning step or use a more direct case."
+            }
+            RegenerateMode::Numbers => {
+                "Keep the question's structure, wording and concept. Change only the numbers, \
+                 values, variable names or literals, then recompute every answer choice, the \
+                 correct answer and the explanations for the new values."
+            }
+        }
+    }
+
+    /// Difficulty label for the merged question.
+    pub fn target_difficulty(self, current: &str) -> String {
+        const LEVELS: [&str; 3] = ["easy", "medium", "hard"];
+        if !matches!(self, RegenerateMode::Harder | RegenerateMode::Easier) {
+            return current.to_string();
+        }
+        let Some(level) = LEVELS
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(current.trim()))
+        else {
+            return current.to_string();
+        };
+        let level = match self {
+            RegenerateMode::Harder => (level + 1).min(LEVELS.len() - 1),
+            _ => level.saturating_sub(1),
+        };
+        LEVELS[level].to_string()
+    }
+}
+
+/// Merge a regenerated question into `current` according to `mode`.
+///
+/// The id, subject, topics, source passage and blueprint row always come from
+/// `current`; the difficulty too, shifted one level for harder/easier. Previous check
+/// results and any accommodated version are dropped because they no longer describe
+/// the merged question.
+pub fn merge(
+    current: &Question,
+    regenerated: Question,
+    mode: RegenerateMode,
+) -> Result<Question, String> {
+    let repaired = regenerated.repaired;
+    let mut merged = match mode {
+        RegenerateMode::Full
+        | RegenerateMode::Harder
+        | RegenerateMode::Easier
+        | RegenerateMode::Numbers => regenerated,
+        RegenerateMode::Distractors => Question {
+            answers: merge_distractors(&current.answers, regenerated.answers)?,
+            distractors: regenerated
+                .distractors
+                .or_else(|| current.distractors.clone()),
+            work: regenerated.work.or_else(|| current.work.clone()),
+            ..current.clone()
+        },
+        RegenerateMode::Explanation => {
+            let explanation = regenerated
+                .explanation
+                .filter(|e| !e.trim().is_empty())
+                .ok_or("The regenerated question has no explanation")?;
+            let answers = current
+                .answers
+                .iter()
+                .map(|answer| {
+                    // A rationale written for a choice whose text changed may describe a
+                    // different mistake, so only exact matches are taken.
+                    let rationale = matching_text(&regenerated.answers, &answer.text)
+                        .and_then(|j| regenerated.answers[j].explanation.clone());
+                    Answer {
+                        explanation: rationale.or_else(|| answer.explanation.clone()),
+                        ..answer.clone()
+                    }
+                })
+                .collect();
+            Question {
+                explanation: Some(explanation),
+                distractors: regenerated
+                    .distractors
+                    .or_else(|| current.distractors.clone()),
+                work: regenerated.work.or_else(|| current.work.clone()),
+                answers,
+                ..current.clone()
+            }
+        }
+    };
+
+    merged.id = current.id.clone();
+    merged.subject = current.subject.clone();
+    merged.topics = current.topics.clone();
+    merged.source = current.source.clone();
+    merged.blueprint = current.blueprint.clone();
+    merged.difficulty = mode.target_difficulty(&current.difficulty);
+    merged.repaired = repaired;
+    merged.verification = None;
+    merged.code_check = None;
+    merged.math_check = None;
+    merged.accommodation = None;
+    Ok(merged)
+}
+
+/// Full regeneration of a question whose key an independent solver disputed, merged
+/// like any other so the blueprint row tag and the source citation survive.
+pub fn replace_disputed(
+    current: &Question,
+    regenerated: Question,
+    provenance: Provenance,
+) -> Result<Question, String> {
+    let mut replacement = merge(current, regenerated, RegenerateMode::Full)?;
+    replacement.provenance = Some(provenance);
+    Ok(replacement)
+}
+
+/// Replace the wrong answers of `current` in place with the new wrong answers, keeping
+/// the correct ones where they were.
+fn merge_distractors(current: &[Answer], regenerated: Vec<Answer>) -> Result<Vec<Answer>, String> {
+    let correct: Vec<String> = current
+        .iter()
+        .filter(|a| a.is_correct)
+        .map(|a| plain_answer_text(&a.text))
+        .collect();
+    let mut fresh = regenerated
+        .into_iter()
+        .filter(|a| !a.is_correct && !correct.contains(&plain_answer_text(&a.text)));
+
+    let mut replaced = 0;
+    let answers = current
+        .iter()
+        .map(|answer| {
+            if answer.is_correct {
+                return answer.clone();
+            }
+            match fresh.next() {
+                Some(distractor) => {
+                    replaced += 1;
+                    distractor
+                }
+                None => answer.clone(),
+            }
+        })
+        .collect();
+    if replaced == 0 {
+        return Err("The regenerated question has no new distractors".to_string());
+    }
+    Ok(answers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(value: serde_json::Value) -> Question {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_partial_modes_keep_untouched_fields() {
+        let current = question(serde_json::json!({
+            "id": "q1",
+            "text": "What is 2 + 3?",
+            "explanation": "Add them.",
+            "answers": [
+                {"text": "4", "is_correct": false},
+                {"text": "5", "is_correct": true, "explanation": "2 + 3 = 5"},
+                {"text": "6", "is_correct": false}
+            ],
+            "subject": "Calculus",
+            "topics": ["arithmetic"],
+            "difficulty": "medium",
+            "math_check": {"status": "match", "checked_at": "now"}
+        }));
+        let regenerated = question(serde_json::json!({
+            "text": "What is 7 + 1?",
+            "explanation": "Adding 2 and 3 gives 5.",
+            "answers": [
+                {"text": "5", "is_correct": true, "explanation": "new rationale"},
+                {"text": "23", "is_correct": false, "explanation": "concatenated"},
+                {"text": "-1", "is_correct": false, "explanation": "subtracted"}
+            ],
+            "difficulty": "easy"
+        }));
+
+        let merged = merge(&current, regenerated.clone(), RegenerateMode::Distractors).unwrap();
+        assert_eq!(merged.id, "q1");
+        assert_eq!(merged.text, "What is 2 + 3?");
+        assert_eq!(merged.explanation.as_deref(), Some("Add them."));
+        let texts: Vec<&str> = merged.answers.iter().map(|a| a.text.as_str()).collect();
+        assert_eq!(texts, ["23", "5", "-1"]);
+        assert_eq!(merged.answers[1].explanation.as_deref(), Some("2 + 3 = 5"));
+        assert!(merged.math_check.is_none());
+
+        let merged = merge(&current, regenerated.clone(), RegenerateMode::Explanation).unwrap();
+        assert_eq!(merged.text, "What is 2 + 3?");
+        assert_eq!(
+            merged.explanation.as_deref(),
+            Some("Adding 2 and 3 gives 5.")
+        );
+        let texts: Vec<&str> = merged.answers.iter().map(|a| a.text.as_str()).collect();
+        assert_eq!(texts, ["4", "5", "6"]);
+        assert_eq!(
+            merged.answers[1].explanation.as_deref(),
+            Some("new rationale")
+        );
+
+        let merged = merge(&current, regenerated, RegenerateMode::Harder).unwrap();
+        assert_eq!(merged.text, "What is 7 + 1?");
+        assert_eq!(merged.topics, ["arithmetic"]);
+        assert_eq!(merged.difficulty, "hard");
+    }
+
+    #[test]
+    fn test_rationales_only_follow_matching_text() {
+        let current = question(serde_json::json!({
+            "text": "Which grade passes?",
+            "explanation": "Old explanation.",
+            "answers": [
+                {"text": "A", "is_correct": true, "explanation": "old A"},
+                {"text": "F", "is_correct": false, "explanation": "old F"},
+                {"text": "E", "is_correct": false}
+            ],
+            "source": {"section": "S1", "document": "grades.pdf", "quote": "A passes"},
+            "blueprint": {"name": "Unit 1", "row": 2, "label": "Grades"}
+        }));
+        let regenerated = question(serde_json::json!({
+            "text": "Which grade passes?",
+            "explanation": "New explanation.",
+            "answers": [
+                {"text": "B", "is_correct": true, "explanation": "rationale for B"},
+                {"text": "F", "is_correct": false, "explanation": "new F"},
+                {"text": "D", "is_correct": false, "explanation": "rationale for D"}
+            ]
+        }));
+
+        let merged = merge(&current, regenerated.clone(), RegenerateMode::Explanation).unwrap();
+        let rationales: Vec<Option<&str>> = merged
+            .answers
+            .iter()
+            .map(|a| a.explanation.as_deref())
+            .collect();
+        assert_eq!(rationales, [Some("old A"), Some("new F"), None]);
+
+        for mode in [RegenerateMode::Full, RegenerateMode::Numbers] {
+            let merged = merge(&current, regenerated.clone(), mode).unwrap();
+            assert_eq!(merged.source.as_ref().unwrap().document, "grades.pdf");
+            assert_eq!(merged.blueprint.as_ref().unwrap().row, 2);
+        }
+    }
+
+    #[test]
+    fn test_disputed_replacement_keeps_blueprint_row_and_source() {
+        let current = question(serde_json::json!({
+            "id": "q3",
+            "text": "What is 2 + 3?",
+            "answers": [
+                {"text": "5", "is_correct": false},
+                {"text": "6", "is_correct": true}
+            ],
+            "subject": "Calculus",
+            "difficulty": "medium",
+            "blueprint": {"name": "Unit 1", "row": 2, "label": "Arithmetic"},
+            "source": {"section": "S2", "document": "sums.md", "quote": "2 + 3 = 5", "verified": true},
+            "verification": {"verdict": "disagree", "checked_at": "now"}
+        }));
+        let regenerated = question(serde_json::json!({
+            "text": "What is 4 + 3?",
+            "answers": [
+                {"text": "7", "is_correct": true},
+                {"text": "8", "is_correct": false}
+            ]
+        }));
+        let provenance: Provenance = serde_json::from_value(serde_json::json!({
+            "kind": "regenerate",
+            "provider": "local",
+            "model": "test",
+            "job_id": "job_1",
+            "generated_at": "now"
+        }))
+        .unwrap();
+
+        let replacement = replace_disputed(&current, regenerated, provenance).unwrap();
+        assert_eq!(replacement.id, "q3");
+        assert_eq!(replacement.text, "What is 4 + 3?");
+        assert_eq!(replacement.difficulty, "medium");
+        assert_eq!(replacement.blueprint.as_ref().unwrap().row, 2);
+        let source = replacement.source.as_ref().unwrap();
+        assert_eq!(
+            (source.section.as_str(), source.quote.as_str()),
+            ("S2", "2 + 3 = 5")
+        );
+        assert!(source.verified);
+        assert_eq!(replacement.provenance.unwrap().kind, "regenerate");
+        assert!(replacement.verification.is_none());
+    }
+}
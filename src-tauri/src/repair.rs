@@ -0,0 +1,222 @@
+//! Automatic repair round-trips for LLM responses that do not parse cleanly
+//!
+//! When the question array cannot be found, or per-item salvage drops malformed objects,
+//! the broken fragment is sent back to the model together with the serde error and a
+//! strict "return only valid JSON" instruction. Items that still cannot be recovered are
+//! re-requested so the caller ends up with the number of questions it asked for.
+//! Repair round-trips run silently: they do not touch the streaming preview. They also
+//! skip the response cache, which would otherwise replay the same broken reply.
+
+use crate::jobs::Job;
+use crate::llm::{self, GatewayAuth, StreamTarget};
+use crate::prompts::{self, MalformedItem};
+use crate::Question;
+
+/// Longest fragment sent back for repair; the tail of the response is kept because
+/// that is where the answer array normally is.
+const MAX_REPAIR_FRAGMENT_CHARS: usize = 16_000;
+
+#[derive(Debug, Clone)]
+pub struct RepairConfig {
+    /// Round-trips allowed per malformed item, and for topping up missing questions.
+    pub max_attempts: u32,
+}
+
+impl Default for RepairConfig {
+    fn default() -> Self {
+        Self { max_attempts: 2 }
+    }
+}
+
+impl RepairConfig {
+    /// Read overrides from `RUBRIX_LLM_REPAIR_ATTEMPTS` (0 disables repair).
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(attempts) = std::env::var("RUBRIX_LLM_REPAIR_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u32>().ok())
+        {
+            config.max_attempts = attempts;
+        }
+        config
+    }
+}
+
+/// Parse `response`, repairing malformed JSON through extra LLM round-trips.
+///
+/// `expected` is the number of questions the caller asked for; when fewer survive,
+/// `top_up_prompt(missing, &kept)` builds a prompt for the missing ones. Questions
+/// recovered by a repair round-trip are marked `repaired`. Ids are renumbered q1..qN.
+pub async fn parse_with_repair<F>(
+    response: &str,
+    expected: Option<usize>,
+    top_up_prompt: F,
+    gateway_auth: Option<GatewayAuth>,
+    job: &Job,
+) -> Result<Vec<Question>, String>
+where
+    F: Fn(usize, &[Question]) -> String,
+{
+    let config = RepairConfig::from_env();
+    if config.max_attempts == 0 {
+        return prompts::parse_llm_response(response);
+    }
+
+    let (mut questions, dropped) = match prompts::parse_llm_response_report(response) {
+        Ok(report) if report.questions.is_empty() && report.dropped.is_empty() => {
+            let error = report
+                .array_error
+                .unwrap_or_else(|| "the question array was empty".to_string());
+            (Vec::new(), vec![whole_response_item(response, error)])
+        }
+        Ok(report) => (report.questions, report.dropped),
+        Err(error) => (Vec::new(), vec![whole_response_item(response, error)]),
+    };
+
+    for item in dropped {
+        if let Some(mut repaired) = repair_item(item, &config, &gateway_auth, job).await {
+            for question in &mut repaired {
+                question.repaired = true;
+            }
+            questions.extend(repaired);
+        }
+    }
+
+    if let Some(expected) = expected {
+        let mut attempts = 0;
+        while questions.len() < expected && attempts < config.max_attempts && !job.is_cancelled() {
+            attempts += 1;
+            let missing = expected - questions.len();
+            eprintln!(
+                "DEBUG: Re-requesting {} question(s) dropped during parsing (attempt {}/{})",
+                missing, attempts, config.max_attempts
+            );
+            let prompt = top_up_prompt(missing, &questions);
+            match round_trip(&prompt, &gateway_auth, job).await {
+                Ok(extra) => questions.extend(extra.into_iter().take(missing)),
+                Err(e) => eprintln!("WARNING: Top-up request failed: {}", e),
+            }
+        }
+    }
+
+    if questions.is_empty() {
+        return Err("Could not parse or repair the LLM response".to_string());
+    }
+
+    for (i, question) in questions.iter_mut().enumerate() {
+        question.id = format!("q{}", i + 1);
+    }
+    Ok(questions)
+}
+
+/// Try to fix one malformed item, feeding each failed repair back in as the new fragment.
+async fn repair_item(
+    mut item: MalformedItem,
+    config: &RepairConfig,
+    gateway_auth: &Option<GatewayAuth>,
+    job: &Job,
+) -> Option<Vec<Question>> {
+    for attempt in 1..=config.max_attempts {
+        if job.is_cancelled() {
+            return None;
+        }
+        eprintln!(
+            "DEBUG: Repair attempt {}/{} for malformed JSON ({})",
+            attempt, config.max_attempts, item.error
+        );
+
+        let prompt = prompts::build_repair_prompt(&item.fragment, &item.error);
+        let response = match llm::generate(
+            &prompt,
+            StreamTarget::new(None, job)
+                .with_log_context("repair", "", &[])
+                .with_cache_bypass(true),
+            gateway_auth.clone(),
+            job,
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("WARNING: Repair request failed: {}", e);
+                continue;
+            }
+        };
+
+        match prompts::parse_llm_response_report(&response) {
+            Ok(report) if !report.questions.is_empty() => return Some(report.questions),
+            Ok(report) => {
+                if let Some(next) = report.dropped.into_iter().next() {
+                    item = next;
+                }
+            }
+            Err(error) => item = whole_response_item(&response, error),
+        }
+    }
+
+    eprintln!(
+        "WARNING: Giving up on malformed item after {} repair attempt(s)",
+        config.max_attempts
+    );
+    None
+}
+
+async fn round_trip(
+    prompt: &str,
+    gateway_auth: &Option<GatewayAuth>,
+    job: &Job,
+) -> Result<Vec<Question>, String> {
+    let response = llm::generate(
+        prompt,
+        StreamTarget::new(None, job)
+            .with_log_context("repair", "", &[])
+            .with_cache_bypass(true),
+        gateway_auth.clone(),
+        job,
+    )
+    .await?;
+    prompts::parse_llm_response(&response)
+}
+
+fn whole_response_item(response: &str, error: String) -> MalformedItem {
+    let total = response.chars().count();
+    let fragment = if total > MAX_REPAIR_FRAGMENT_CHARS {
+        response
+            .chars()
+            .skip(total - MAX_REPAIR_FRAGMENT_CHARS)
+            .collect()
+    } else {
+        response.to_string()
+    };
+    MalformedItem {
+        fragment,
+        error,
+        location: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whole_response_item_keeps_tail() {
+        let response = format!("{}[{{\"text\":", "x".repeat(MAX_REPAIR_FRAGMENT_CHARS));
+        let item = whole_response_item(&response, "EOF".to_string());
+        assert_eq!(item.fragment.chars().count(), MAX_REPAIR_FRAGMENT_CHARS);
+        assert!(item.fragment.ends_with("[{\"text\":"));
+    }
+
+    #[test]
+    fn test_report_keeps_malformed_items_for_repair() {
+        let response = r#"[
+            {"text": "Good", "answers": [{"text": "A", "is_correct": true}]},
+            {"text": "Bad", "answers": [{"text": "B", "is_correct": "yes"}]}
+        ]"#;
+        let report = prompts::parse_llm_response_report(response).unwrap();
+        assert_eq!(report.questions.len(), 1);
+        assert_eq!(report.dropped.len(), 1);
+        assert!(report.dropped[0].fragment.contains("\"Bad\""));
+        assert!(report.dropped[0].error.contains("line"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Opt-in on-disk cache of gateway responses
+//!
+//! Entries live in `<app data>/llm-cache/<key>.json`, where the key is a SHA-256 of the
+//! provider, model and prompt. When enabled, `llm::generate` replays a cached response
+//! through the normal `llm-stream` events instead of calling the gateway, which keeps
+//! prompt-template and export iterations cheap and lets a logged interaction be
+//! reproduced exactly. Only completed responses are stored.
+//!
+//! The cache is off unless `RUBRIX_LLM_CACHE` is truthy; it can also be toggled at
+//! runtime with `set_response_cache_enabled`. Generation commands accept `bypass_cache`
+//! to force a fresh response, which then replaces the cached one.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The gateway is the only provider; it picks the model server-side, so the gateway
+/// URL stands in for the model when keying entries.
+pub const GATEWAY_PROVIDER: &str = "bedrock-gateway";
+
+static CACHE_DIR: OnceCell<PathBuf> = OnceCell::new();
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Set the cache directory and read the opt-in from `RUBRIX_LLM_CACHE`.
+pub fn init(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir);
+    ENABLED.store(env_enabled(), Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst) && CACHE_DIR.get().is_some()
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub key: String,
+    pub provider: String,
+    pub model: String,
+    pub created_at: String,
+    pub prompt: String,
+    pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_tokens: Option<u64>,
+}
+
+impl CachedResponse {
+    pub fn new(
+        provider: &str,
+        model: &str,
+        prompt: &str,
+        response: &str,
+        remaining_tokens: Option<u64>,
+    ) -> Self {
+        Self {
+            key: cache_key(provider, model, prompt),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            prompt: prompt.to_string(),
+            response: response.to_string(),
+            remaining_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStatus {
+    pub enabled: bool,
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub dir: Option<String>,
+}
+
+/// Hex SHA-256 over provider, model and prompt, separated so fields cannot run together.
+pub fn cache_key(provider: &str, model: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [provider, model, prompt] {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn lookup(key: &str) -> Option<CachedResponse> {
+    lookup_in(CACHE_DIR.get()?, key)
+}
+
+/// Store a completed response. Failures are reported on stderr and otherwise ignored.
+pub fn store(entry: &CachedResponse) {
+    let Some(dir) = CACHE_DIR.get() else {
+        return;
+    };
+    if let Err(e) = store_in(dir, entry) {
+        eprintln!("WARNING: Failed to cache LLM response: {}", e);
+    }
+}
+
+pub fn status() -> CacheStatus {
+    let (entries, total_bytes) = CACHE_DIR.get().map(|dir| usage(dir)).unwrap_or((0, 0));
+    CacheStatus {
+        enabled: is_enabled(),
+        entries,
+        total_bytes,
+        dir: CACHE_DIR.get().map(|dir| dir.display().to_string()),
+    }
+}
+
+/// Delete every entry; returns how many were removed.
+pub fn clear() -> Result<usize, String> {
+    match CACHE_DIR.get() {
+        Some(dir) => clear_in(dir),
+        None => Ok(0),
+    }
+}
+
+/// Delete one entry; returns false when it did not exist.
+pub fn remove(key: &str) -> Result<bool, String> {
+    let Some(dir) = CACHE_DIR.get() else {
+        return Ok(false);
+    };
+    if !is_valid_key(key) {
+        return Err(format!("Invalid cache key: {}", key));
+    }
+    let path = dir.join(format!("{}.json", key));
+    if !path.exists() {
+        return Ok(false);
+    }
+    fs::remove_file(&path)
+        .map(|_| true)
+        .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+fn env_enabled() -> bool {
+    let _ = dotenvy::dotenv();
+    std::env::var("RUBRIX_LLM_CACHE")
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn lookup_in(dir: &Path, key: &str) -> Option<CachedResponse> {
+    if !is_valid_key(key) {
+        return None;
+    }
+    let data = fs::read_to_string(dir.join(format!("{}.json", key))).ok()?;
+    let entry: CachedResponse = serde_json::from_str(&data).ok()?;
+    (entry.key == key).then_some(entry)
+}
+
+fn store_in(dir: &Path, entry: &CachedResponse) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let json = serde_json::to_string_pretty(entry)
+        .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+    let path = dir.join(format!("{}.json", entry.key));
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut f = fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create temp file {}: {}", tmp_path.display(), e))?;
+        f.write_all(json.as_bytes())
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    }
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+fn entry_paths(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect()
+}
+
+fn usage(dir: &Path) -> (usize, u64) {
+    entry_paths(dir)
+        .iter()
+        .fold((0, 0), |(count, bytes), path| {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            (count + 1, bytes + size)
+        })
+}
+
+fn clear_in(dir: &Path) -> Result<usize, String> {
+    let mut removed = 0;
+    for path in entry_paths(dir) {
+        fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rubrix-{}-{}",
+            name,
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_every_field() {
+        let key = cache_key("gw", "https://a", "prompt");
+        assert!(is_valid_key(&key));
+        assert_eq!(key, cache_key("gw", "https://a", "prompt"));
+        assert_ne!(key, cache_key("gw", "https://b", "prompt"));
+        assert_ne!(key, cache_key("gw", "https://a", "prompt "));
+        // Field boundaries are part of the hash.
+        assert_ne!(cache_key("ab", "c", "p"), cache_key("a", "bc", "p"));
+    }
+
+    #[test]
+    fn test_store_lookup_and_clear() {
+        let dir = temp_dir("cache");
+        let entry = CachedResponse::new("gw", "https://a", "prompt", "[]", Some(42));
+        store_in(&dir, &entry).unwrap();
+
+        let found = lookup_in(&dir, &entry.key).unwrap();
+        assert_eq!(found.response, "[]");
+        assert_eq!(found.remaining_tokens, Some(42));
+        assert!(lookup_in(&dir, &cache_key("gw", "https://a", "other")).is_none());
+        assert!(lookup_in(&dir, "../secrets").is_none());
+
+        assert_eq!(usage(&dir).0, 1);
+        assert_eq!(clear_in(&dir).unwrap(), 1);
+        assert!(lookup_in(&dir, &entry.key).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,579 @@
+//! Teacher-supplied source material for grounded generation
+//!
+//! Markdown and plain-text files are read as they are; `.docx` text comes from the
+//! paragraphs of `word/document.xml`, and `.pdf` text comes from `pdf-extract`. Scanned
+//! PDFs have no text layer and are rejected. Extracted text is cached by path, size and
+//! modification time, so previewing a prompt again does not re-read the files.
+//!
+//! The text is split on paragraphs into numbered sections (`S1`, `S2`, ...) that fit a
+//! prompt budget of `RUBRIX_SOURCE_BUDGET_CHARS` characters (default 24000). When the
+//! material is longer, sections are taken evenly across it. The prompt asks for a
+//! `source` field on every question naming the section and quoting the span it is based
+//! on; [`attach_excerpts`] checks the quote against the material and stores the exact
+//! text as the question's [`SourceExcerpt`].
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::Question;
+
+/// Longest section, in characters.
+const SECTION_CHARS: usize = 1200;
+const DEFAULT_BUDGET_CHARS: usize = 24_000;
+const MAX_CACHED_DOCUMENTS: usize = 32;
+
+static DOCUMENTS: Lazy<Mutex<Documents>> = Lazy::new(|| Mutex::new(Documents::default()));
+
+/// Size and modification time of a file when its text was extracted.
+type Stamp = (u64, Option<SystemTime>);
+
+/// Extracted text of recently loaded files, oldest first in `order`.
+#[derive(Default)]
+struct Documents {
+    by_path: HashMap<PathBuf, (Stamp, Arc<String>)>,
+    order: VecDeque<PathBuf>,
+}
+
+impl Documents {
+    fn get(&self, path: &Path, stamp: Stamp) -> Option<Arc<String>> {
+        self.by_path
+            .get(path)
+            .filter(|(cached, _)| *cached == stamp)
+            .map(|(_, text)| text.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, stamp: Stamp, text: Arc<String>) {
+        if self.by_path.insert(path.clone(), (stamp, text)).is_none() {
+            self.order.push_back(path);
+        }
+        while self.order.len() > MAX_CACHED_DOCUMENTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_path.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// The passage a question was written from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceExcerpt {
+    /// Section label from the prompt, e.g. `S3`.
+    #[serde(default, alias = "chunk")]
+    pub section: String,
+    /// File name of the document the section came from.
+    #[serde(default)]
+    pub document: String,
+    /// The cited span, copied from the material when it could be found there.
+    #[serde(default, alias = "excerpt", alias = "text")]
+    pub quote: String,
+    /// Whether the quote was found in the material.
+    #[serde(default)]
+    pub verified: bool,
+}
+
+/// Accept `"source": {"section": ..., "quote": ...}` or a bare quoted string.
+pub fn de_source_excerpt<'de, D>(deserializer: D) -> Result<Option<SourceExcerpt>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            Some(serde_json::Value::String(quote)) if !quote.trim().is_empty() => {
+                Some(SourceExcerpt {
+                    quote,
+                    ..SourceExcerpt::default()
+                })
+            }
+            Some(value @ serde_json::Value::Object(_)) => serde_json::from_value(value).ok(),
+            _ => None,
+        },
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub label: String,
+    pub document: String,
+    pub text: String,
+}
+
+/// Attached documents, split into the sections that go into the prompt.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMaterial {
+    pub sections: Vec<Section>,
+    /// Sections left out to stay within the budget.
+    pub omitted: usize,
+}
+
+impl SourceMaterial {
+    /// Read and split every file in `paths` off the async runtime; `None` when there
+    /// are none.
+    pub async fn load(paths: &[String]) -> Result<Option<Self>, String> {
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        let paths = paths.to_vec();
+        tokio::task::spawn_blocking(move || Self::read(&paths))
+            .await
+            .map_err(|e| format!("Reading the source material failed: {}", e))?
+            .map(Some)
+    }
+
+    fn read(paths: &[String]) -> Result<Self, String> {
+        let documents = paths
+            .iter()
+            .map(|path| {
+                let path = Path::new(path);
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string());
+                Ok((name, cached_text(path)?.to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::from_documents(&documents, budget_chars()))
+    }
+
+    pub fn from_documents(documents: &[(String, String)], budget: usize) -> Self {
+        let mut all: Vec<(String, String)> = Vec::new();
+        for (name, text) in documents {
+            for section in split_sections(text) {
+                all.push((name.clone(), section));
+            }
+        }
+
+        let total: usize = all.iter().map(|(_, text)| text.chars().count()).sum();
+        let keep = if total <= budget {
+            all.len()
+        } else {
+            // Sections are at most SECTION_CHARS, so this many always fit
+            (budget / SECTION_CHARS).clamp(1, all.len())
+        };
+        let picked: Vec<usize> = (0..keep).map(|i| i * all.len() / keep).collect();
+
+        SourceMaterial {
+            sections: picked
+                .iter()
+                .enumerate()
+                .map(|(n, &i)| Section {
+                    label: format!("S{}", n + 1),
+                    document: all[i].0.clone(),
+                    text: all[i].1.clone(),
+                })
+                .collect(),
+            omitted: all.len() - keep,
+        }
+    }
+
+    /// The material and citation instructions, as inserted into the generation prompt.
+    pub fn prompt_block(&self) -> String {
+        let mut block = String::from(
+            "## Source material\n\
+             Base every question on the source material below, not on outside facts. It is \
+             split into numbered sections. Give each question a \"source\" field naming the \
+             section it comes from and quoting, word for word, the sentence or phrase that \
+             supports the correct answer:\n\
+             \"source\": {\"section\": \"S2\", \"quote\": \"...\"}\n",
+        );
+        if self.omitted > 0 {
+            block.push_str(&format!(
+                "({} further sections of the material were left out to fit the prompt.)\n",
+                self.omitted
+            ));
+        }
+        for section in &self.sections {
+            block.push_str(&format!(
+                "\n[{}] ({})\n{}\n",
+                section.label, section.document, section.text
+            ));
+        }
+        block
+    }
+}
+
+fn budget_chars() -> usize {
+    std::env::var("RUBRIX_SOURCE_BUDGET_CHARS")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|chars| *chars > 0)
+        .unwrap_or(DEFAULT_BUDGET_CHARS)
+}
+
+/// Check each question's cited quote against the material and record the exact span.
+pub fn attach_excerpts(questions: &mut [Question], material: &SourceMaterial) {
+    for question in questions {
+        let Some(source) = question.source.as_mut() else {
+            continue;
+        };
+        let cited = material
+            .sections
+            .iter()
+            .position(|s| s.label.eq_ignore_ascii_case(source.section.trim()));
+        // Look in the cited section first, then anywhere in the material
+        let order = cited
+            .into_iter()
+            .chain((0..material.sections.len()).filter(|i| Some(*i) != cited));
+        let found = order
+            .filter_map(|i| Some((i, find_span(&material.sections[i].text, &source.quote)?)))
+            .next();
+
+        match found {
+            Some((i, span)) => {
+                let section = &material.sections[i];
+                source.section = section.label.clone();
+                source.document = section.document.clone();
+                source.quote = span;
+                source.verified = true;
+            }
+            None => {
+                if let Some(section) = cited.map(|i| &material.sections[i]) {
+                    source.section = section.label.clone();
+                    source.document = section.document.clone();
+                }
+                source.verified = false;
+            }
+        }
+    }
+}
+
+/// The text of `haystack` matching `quote`, ignoring case, whitespace and quote marks.
+fn find_span(haystack: &str, quote: &str) -> Option<String> {
+    let quote = quote
+        .trim()
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '“' | '”' | '…' | '.'))
+        .trim();
+    let key = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let wanted: Vec<char> = quote
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(key)
+        .collect();
+    if wanted.len() < 8 {
+        return None;
+    }
+    // Compare over the non-space characters while remembering where each came from
+    let positions: Vec<(usize, char)> = haystack
+        .char_indices()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(i, c)| (i, key(c)))
+        .collect();
+    let start = positions
+        .windows(wanted.len())
+        .position(|w| w.iter().map(|(_, c)| *c).eq(wanted.iter().copied()))?;
+    let from = positions[start].0;
+    let (last, last_char) = positions[start + wanted.len() - 1];
+    let to = last
+        + haystack[last..]
+            .chars()
+            .next()
+            .map_or(last_char.len_utf8(), char::len_utf8);
+    Some(haystack[from..to].to_string())
+}
+
+/// Split text into paragraph-aligned sections of at most [`SECTION_CHARS`].
+fn split_sections(text: &str) -> Vec<String> {
+    static BLANK_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n\s*\n").unwrap());
+    let mut pieces = Vec::new();
+    for paragraph in BLANK_LINES.split(text) {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+        let mut rest = paragraph;
+        while rest.chars().count() > SECTION_CHARS {
+            // Break a long paragraph after a sentence, else at a space
+            let limit = rest
+                .char_indices()
+                .nth(SECTION_CHARS)
+                .map_or(rest.len(), |(i, _)| i);
+            let head = &rest[..limit];
+            let cut = head
+                .rfind(". ")
+                .map(|i| i + 1)
+                .or_else(|| head.rfind(char::is_whitespace))
+                .filter(|i| *i > 0)
+                .unwrap_or(limit);
+            pieces.push(rest[..cut].trim().to_string());
+            rest = rest[cut..].trim_start();
+        }
+        if !rest.is_empty() {
+            pieces.push(rest.to_string());
+        }
+    }
+
+    let mut sections: Vec<String> = Vec::new();
+    for piece in pieces {
+        match sections.last_mut() {
+            Some(last) if last.chars().count() + piece.chars().count() + 2 <= SECTION_CHARS => {
+                last.push_str("\n\n");
+                last.push_str(&piece);
+            }
+            _ => sections.push(piece),
+        }
+    }
+    sections
+}
+
+/// [`extract_text`], reusing the last result while the file is unchanged.
+fn cached_text(path: &Path) -> Result<Arc<String>, String> {
+    let metadata =
+        std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let stamp = (metadata.len(), metadata.modified().ok());
+    if let Some(text) = DOCUMENTS.lock().unwrap().get(path, stamp) {
+        return Ok(text);
+    }
+    let text = Arc::new(extract_text(path)?);
+    DOCUMENTS
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), stamp, text.clone());
+    Ok(text)
+}
+
+/// Plain text of a Markdown, text, Word or PDF file.
+pub fn extract_text(path: &Path) -> Result<String, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let read =
+        || std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+    let text = match extension.as_str() {
+        "md" | "markdown" | "txt" | "text" => String::from_utf8_lossy(&read()?).into_owned(),
+        "docx" => docx_text(&read()?).map_err(|e| format!("{}: {}", path.display(), e))?,
+        "pdf" => pdf_text(&read()?).map_err(|e| format!("{}: {}", path.display(), e))?,
+        _ => {
+            return Err(format!(
+                "{}: only .md, .txt, .docx and .pdf source files are supported",
+                path.display()
+            ))
+        }
+    };
+    if text.trim().is_empty() {
+        return Err(format!("{}: no text found", path.display()));
+    }
+    Ok(text)
+}
+
+/// Paragraph text of a Word document.
+fn docx_text(bytes: &[u8]) -> Result<String, String> {
+    static TOKEN: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"<w:t(?:\s[^>]*)?>([^<]*)</w:t>|<w:tab/>|<w:br/>|<w:cr/>|</w:p>").unwrap()
+    });
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+        .map_err(|e| format!("not a Word document ({})", e))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|_| "not a Word document (no word/document.xml)".to_string())?
+        .read_to_string(&mut xml)
+        .map_err(|e| e.to_string())?;
+
+    let mut text = String::new();
+    for caps in TOKEN.captures_iter(&xml) {
+        match (caps.get(1), &caps[0]) {
+            (Some(run), _) => text.push_str(
+                &htmlescape::decode_html(run.as_str()).unwrap_or_else(|_| run.as_str().into()),
+            ),
+            (None, "<w:tab/>") => text.push('\t'),
+            (None, "</w:p>") => text.push_str("\n\n"),
+            _ => text.push('\n'),
+        }
+    }
+    Ok(text)
+}
+
+/// Text of a PDF's pages, in page order.
+fn pdf_text(bytes: &[u8]) -> Result<String, String> {
+    if !bytes.starts_with(b"%PDF") {
+        return Err("not a PDF file".to_string());
+    }
+    // pdf-extract panics on some fonts and encodings it does not support
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .map_err(|_| "the PDF uses features that cannot be read".to_string())?
+        .map_err(|e| format!("unreadable PDF ({})", e))?;
+
+    let mut text = String::new();
+    for page in pages {
+        if !page.trim().is_empty() {
+            text.push_str(page.trim());
+            text.push_str("\n\n");
+        }
+    }
+    if text.trim().is_empty() {
+        return Err("the PDF has no text layer (scanned pages are not supported)".to_string());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn pdf(objects: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, body) in objects.iter().enumerate() {
+            offsets.push(bytes.len());
+            bytes.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            bytes.extend_from_slice(body);
+            bytes.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = bytes.len();
+        let size = objects.len() + 1;
+        bytes.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", size).as_bytes());
+        for offset in offsets {
+            bytes.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        bytes.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                size, xref
+            )
+            .as_bytes(),
+        );
+        bytes
+    }
+
+    fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        body
+    }
+
+    #[test]
+    fn test_sections_fit_budget() {
+        let paragraph = "Photosynthesis turns light into chemical energy. ".repeat(10);
+        let text = vec![paragraph.trim(); 40].join("\n\n");
+        let documents = [("notes.md".to_string(), text)];
+
+        let all = SourceMaterial::from_documents(&documents, usize::MAX);
+        assert_eq!(all.omitted, 0);
+        assert!(all
+            .sections
+            .iter()
+            .all(|s| s.text.chars().count() <= SECTION_CHARS));
+        assert_eq!(all.sections[0].label, "S1");
+        assert_eq!(all.sections[0].document, "notes.md");
+
+        let trimmed = SourceMaterial::from_documents(&documents, 3 * SECTION_CHARS);
+        assert_eq!(trimmed.sections.len(), 3);
+        assert_eq!(trimmed.omitted, all.sections.len() - 3);
+        assert_eq!(trimmed.sections[2].label, "S3");
+        assert!(trimmed.prompt_block().contains("further sections"));
+    }
+
+    #[test]
+    fn test_attach_excerpts_uses_exact_span() {
+        let material = SourceMaterial::from_documents(
+            &[
+                ("a.txt".to_string(), "Mitochondria make ATP.".to_string()),
+                (
+                    "b.txt".to_string(),
+                    "The  mitochondrion is\nthe powerhouse of the cell.".to_string(),
+                ),
+            ],
+            usize::MAX,
+        );
+        let mut questions: Vec<Question> = serde_json::from_value(serde_json::json!([
+            {
+                "text": "Q1",
+                "answers": [],
+                "source": {"section": "S1", "quote": "\"the mitochondrion is the Powerhouse\""}
+            },
+            {"text": "Q2", "answers": [], "source": "energy comes from the sun"},
+            {"text": "Q3", "answers": []}
+        ]))
+        .unwrap();
+
+        attach_excerpts(&mut questions, &material);
+        let found = questions[0].source.as_ref().unwrap();
+        assert!(found.verified);
+        assert_eq!(found.section, "S2");
+        assert_eq!(found.document, "b.txt");
+        assert_eq!(found.quote, "The  mitochondrion is\nthe powerhouse");
+        let missing = questions[1].source.as_ref().unwrap();
+        assert!(!missing.verified);
+        assert_eq!(missing.quote, "energy comes from the sun");
+        assert!(questions[2].source.is_none());
+    }
+
+    #[test]
+    fn test_pdf_text_plain_and_compressed_pages() {
+        let first =
+            b"BT /F1 12 Tf 72 720 Td (Water boils at) Tj 0 -14 Td [(100 de)20(grees)] TJ ET";
+        let second = b"BT /F1 12 Tf 72 720 Td (Ice melts at 0 degrees) Tj ET";
+        let mut compressed = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        compressed.write_all(second).unwrap();
+        let compressed = compressed.finish().unwrap();
+
+        let bytes = pdf(&[
+            b"<< /Type /Catalog /Pages 2 0 R >>",
+            b"<< /Type /Pages /Kids [3 0 R 5 0 R] /Count 2 /MediaBox [0 0 612 792] \
+              /Resources << /Font << /F1 7 0 R >> >> >>",
+            b"<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>",
+            &stream("", first),
+            b"<< /Type /Page /Parent 2 0 R /Contents 6 0 R >>",
+            &stream("/Filter /FlateDecode", &compressed),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>",
+        ]);
+
+        let text = pdf_text(&bytes).unwrap();
+        assert_eq!(
+            text.trim(),
+            "Water boils at\n100 degrees\n\nIce melts at 0 degrees"
+        );
+        assert!(pdf_text(b"not a pdf").is_err());
+        assert!(pdf_text(b"%PDF-1.4 truncated").is_err());
+
+        let blank = pdf(&[
+            b"<< /Type /Catalog /Pages 2 0 R >>",
+            b"<< /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox [0 0 612 792] >>",
+            b"<< /Type /Page /Parent 2 0 R >>",
+        ]);
+        assert!(pdf_text(&blank).unwrap_err().contains("no text layer"));
+    }
+
+    #[tokio::test]
+    async fn test_load_rereads_changed_files_only() {
+        let path = std::env::temp_dir().join(format!("sources-test-{}.txt", std::process::id()));
+        let paths = [path.display().to_string()];
+        std::fs::write(&path, "Plants need light.").unwrap();
+
+        let first = SourceMaterial::load(&paths).await.unwrap().unwrap();
+        assert_eq!(first.sections[0].text, "Plants need light.");
+        let cached = cached_text(&path).unwrap();
+        assert!(Arc::ptr_eq(&cached, &cached_text(&path).unwrap()));
+
+        std::fs::write(&path, "Plants need light and water.").unwrap();
+        let second = SourceMaterial::load(&paths).await.unwrap().unwrap();
+        assert_eq!(second.sections[0].text, "Plants need light and water.");
+        assert!(SourceMaterial::load(&[]).await.unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_docx_paragraphs() {
+        let xml = r#"<?xml version="1.0"?><w:document><w:body>
+            <w:p><w:r><w:t>Cells &amp; </w:t></w:r><w:r><w:t xml:space="preserve">tissues</w:t></w:r></w:p>
+            <w:p><w:r><w:t>Organs</w:t><w:tab/><w:t>systems</w:t></w:r></w:p>
+            </w:body></w:document>"#;
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        archive
+            .start_file("word/document.xml", zip::write::FileOptions::default())
+            .unwrap();
+        archive.write_all(xml.as_bytes()).unwrap();
+        let bytes = archive.finish().unwrap().into_inner();
+
+        let text = docx_text(&bytes).unwrap();
+        assert_eq!(text.trim(), "Cells & tissues\n\nOrgans\tsystems");
+    }
+}
@@ -0,0 +1,256 @@
+//! Incremental Server-Sent Events decoder for the gateway stream
+//!
+//! Network chunks are buffered as raw bytes and only decoded once a full line is
+//! available, so multi-byte UTF-8 characters split across chunk boundaries survive.
+//! Field handling follows the WHATWG event-stream rules: `data`, `event`, `id` and
+//! `retry` fields, `:` comments, and `\n`, `\r\n` or `\r` line endings. Unknown
+//! fields are ignored.
+
+/// A dispatched SSE event (terminated by a blank line).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event name; `message` when the stream did not set one.
+    pub event: String,
+    /// Data lines joined with `\n`.
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+}
+
+/// Everything the decoder can surface from the byte stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseFrame {
+    Event(SseEvent),
+    /// Comment line (`: keep-alive`), useful as a liveness signal.
+    Comment(String),
+    /// A line that could not be decoded; reported instead of silently dropped.
+    Invalid(String),
+}
+
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    data_lines: Vec<String>,
+    has_data: bool,
+    event_type: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a network chunk and return every frame it completes.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseFrame> {
+        self.buffer.extend_from_slice(bytes);
+        let mut frames = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            // A trailing `\r` may be the first half of `\r\n`; wait for the next chunk.
+            let terminator_len = if self.buffer[pos] == b'\r' {
+                match self.buffer.get(pos + 1) {
+                    Some(b'\n') => 2,
+                    Some(_) => 1,
+                    None => break,
+                }
+            } else {
+                1
+            };
+
+            let line: Vec<u8> = self
+                .buffer
+                .drain(..pos + terminator_len)
+                .take(pos)
+                .collect();
+            self.process_line(&line, &mut frames);
+        }
+
+        frames
+    }
+
+    /// Flush at end of stream. An unterminated final line and any buffered event are
+    /// dispatched rather than discarded, since some gateways omit the last blank line.
+    pub fn finish(&mut self) -> Vec<SseFrame> {
+        let mut frames = Vec::new();
+
+        let mut rest = std::mem::take(&mut self.buffer);
+        if rest.last() == Some(&b'\r') {
+            rest.pop();
+        }
+        if !rest.is_empty() {
+            self.process_line(&rest, &mut frames);
+        }
+        self.dispatch(&mut frames);
+
+        frames
+    }
+
+    fn process_line(&mut self, raw: &[u8], frames: &mut Vec<SseFrame>) {
+        let line = match std::str::from_utf8(raw) {
+            Ok(line) => line,
+            Err(e) => {
+                frames.push(SseFrame::Invalid(format!(
+                    "invalid UTF-8 in stream line ({}): {}",
+                    e,
+                    String::from_utf8_lossy(raw)
+                )));
+                return;
+            }
+        };
+        let line = line.strip_prefix('\u{feff}').unwrap_or(line);
+
+        if line.is_empty() {
+            self.dispatch(frames);
+            return;
+        }
+
+        if let Some(comment) = line.strip_prefix(':') {
+            frames.push(SseFrame::Comment(comment.trim_start().to_string()));
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "data" => {
+                self.data_lines.push(value.to_string());
+                self.has_data = true;
+            }
+            "event" => self.event_type = Some(value.to_string()),
+            // Per spec, ids containing NUL are ignored.
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.trim().parse::<u64>() {
+                    self.retry = Some(ms);
+                }
+            }
+            // Per spec, unknown fields (and NUL ids) are ignored.
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, frames: &mut Vec<SseFrame>) {
+        let event_type = self.event_type.take();
+        let retry = self.retry.take();
+
+        if !self.has_data {
+            self.data_lines.clear();
+            return;
+        }
+
+        frames.push(SseFrame::Event(SseEvent {
+            event: event_type
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "message".to_string()),
+            data: self.data_lines.join("\n"),
+            id: self.last_event_id.clone(),
+            retry,
+        }));
+        self.data_lines.clear();
+        self.has_data = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(frames: Vec<SseFrame>) -> Vec<SseEvent> {
+        frames
+            .into_iter()
+            .filter_map(|f| match f {
+                SseFrame::Event(e) => Some(e),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_data_event() {
+        let mut decoder = SseDecoder::new();
+        let got = events(decoder.push(b"data: {\"text\":\"hi\",\"done\":false}\n\n"));
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].event, "message");
+        assert_eq!(got[0].data, "{\"text\":\"hi\",\"done\":false}");
+    }
+
+    #[test]
+    fn test_multibyte_char_split_across_chunks() {
+        let payload = "data: {\"text\":\"∫ x dx — “ok”\"}\n\n".as_bytes();
+        // Split inside the three-byte integral sign.
+        let split = payload.iter().position(|b| *b == 0xE2).unwrap() + 1;
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(&payload[..split]).is_empty());
+        let got = events(decoder.push(&payload[split..]));
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].data, "{\"text\":\"∫ x dx — “ok”\"}");
+    }
+
+    #[test]
+    fn test_multiline_data_and_named_event() {
+        let mut decoder = SseDecoder::new();
+        let got = events(decoder.push(b"event: error\ndata: line one\ndata:line two\nid: 7\n\n"));
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].event, "error");
+        assert_eq!(got[0].data, "line one\nline two");
+        assert_eq!(got[0].id.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_crlf_framing_split_between_cr_and_lf() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: a\r").is_empty());
+        assert!(decoder.push(b"\n\r").is_empty());
+        let got = events(decoder.push(b"\ndata: b\r\r"));
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].data, "a");
+        let got = events(decoder.finish());
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].data, "b");
+    }
+
+    #[test]
+    fn test_comments_are_reported_and_do_not_dispatch() {
+        let mut decoder = SseDecoder::new();
+        let frames = decoder.push(b": keep-alive\n\n");
+        assert_eq!(frames, vec![SseFrame::Comment("keep-alive".to_string())]);
+    }
+
+    #[test]
+    fn test_finish_flushes_unterminated_event() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"data: {\"done\":true}").is_empty());
+        let got = events(decoder.finish());
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].data, "{\"done\":true}");
+    }
+
+    #[test]
+    fn test_invalid_utf8_line_is_surfaced() {
+        let mut decoder = SseDecoder::new();
+        let frames = decoder.push(b"data: \xff\xfe\n\n");
+        assert!(matches!(frames[0], SseFrame::Invalid(_)));
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let mut decoder = SseDecoder::new();
+        let frames = decoder.push(b"foo: bar\ndata: 1\nbogus\n\n");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(events(frames)[0].data, "1");
+    }
+
+    #[test]
+    fn test_event_name_resets_between_events() {
+        let mut decoder = SseDecoder::new();
+        let got = events(decoder.push(b"event: ping\ndata: 1\n\ndata: 2\n\n"));
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].event, "ping");
+        assert_eq!(got[1].event, "message");
+    }
+}
@@ -0,0 +1,526 @@
+//! Prompt template language for `prompt.txt` and `regeneration-prompt.txt`
+//!
+//! ```text
+//! {name}                         value of a variable
+//! {#if name} ... {#else} ... {/if}  section kept only when the value is non-empty
+//! {#each name} ... {/each}       repeated for every item of a list; item fields are in scope
+//! {{name}}                       a literal `{name}` (also works for block tags)
+//! {! note }                      a comment; `{! version: 2024-09 }` labels the template
+//! ```
+//!
+//! Any other brace is plain text, so JSON in a template needs no escaping. Values are
+//! substituted in a single pass: text coming from a variable is never re-scanned, so a
+//! `{count}` inside an example cannot be replaced by accident. A block tag alone on its
+//! line removes that whole line from the output.
+//!
+//! Templates are parsed and checked against the known variables when the knowledge base
+//! loads; see [`Template::load`].
+
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Hex digits of the source hash kept in [`Template::hash`].
+const HASH_LEN: usize = 12;
+
+/// Variables available while rendering, by name.
+pub type Vars = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Text(String),
+    /// `text` is what `{name}` renders to; `items` is what `{#each name}` iterates.
+    List {
+        text: String,
+        items: Vec<Vars>,
+    },
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Text(text) => !text.trim().is_empty(),
+            Value::List { items, .. } => !items.is_empty(),
+        }
+    }
+}
+
+/// A variable a template may use, and for lists the fields of each item.
+pub struct Variable {
+    pub name: &'static str,
+    pub fields: Option<&'static [&'static str]>,
+}
+
+impl Variable {
+    pub const fn text(name: &'static str) -> Self {
+        Variable { name, fields: None }
+    }
+
+    pub const fn list(name: &'static str, fields: &'static [&'static str]) -> Self {
+        Variable {
+            name,
+            fields: Some(fields),
+        }
+    }
+}
+
+/// Output of [`Template::render`].
+#[derive(Debug, Clone, Default)]
+pub struct Rendered {
+    pub text: String,
+    /// Variables that were substituted, in order of first use.
+    pub used: Vec<String>,
+    /// Variables with no value; they are left in the text as `{name}`.
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var {
+        name: String,
+        line: usize,
+    },
+    If {
+        name: String,
+        line: usize,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        name: String,
+        line: usize,
+        body: Vec<Node>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+    hash: String,
+    version: Option<String>,
+}
+
+/// A block being parsed: its opening tag and the nodes collected so far.
+struct OpenBlock {
+    kind: &'static str,
+    name: String,
+    line: usize,
+    nodes: Vec<Node>,
+    /// Nodes before `{#else}`, once it has been seen.
+    then: Option<Vec<Node>>,
+}
+
+impl Template {
+    /// Parse a template and check that it only uses `variables`.
+    pub fn load(source: &str, variables: &[Variable]) -> Result<Self, String> {
+        let template = Self::parse(source)?;
+        template.check(variables)?;
+        Ok(template)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tag = Regex::new(
+            r"^\{([#/]?)([A-Za-z_][A-Za-z0-9_]*)(?:[ \t]+([A-Za-z_][A-Za-z0-9_]*))?[ \t]*\}",
+        )
+        .unwrap();
+        let escaped =
+            Regex::new(r"^\{\{([#/]?[A-Za-z_][A-Za-z0-9_]*(?:[ \t]+[A-Za-z_][A-Za-z0-9_]*)?)\}\}")
+                .unwrap();
+        let comment = Regex::new(r"^\{!([^}\n]*)\}").unwrap();
+        let mut version = None;
+
+        let mut stack: Vec<OpenBlock> = vec![OpenBlock {
+            kind: "template",
+            name: String::new(),
+            line: 1,
+            nodes: Vec::new(),
+            then: None,
+        }];
+        let mut text = String::new();
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut pos = 0;
+
+        while let Some(offset) = source[pos..].find(['{', '\n']) {
+            let start = pos + offset;
+            text.push_str(&source[pos..start]);
+            let rest = &source[start..];
+            if rest.starts_with('\n') {
+                text.push('\n');
+                line += 1;
+                line_start = start + 1;
+                pos = start + 1;
+                continue;
+            }
+
+            if let Some(cap) = escaped.captures(rest) {
+                text.push('{');
+                text.push_str(&cap[1]);
+                text.push('}');
+                pos = start + cap[0].len();
+                continue;
+            }
+
+            let (sigil, word, arg, tag_len) = if let Some(cap) = comment.captures(rest) {
+                if let Some(label) = cap[1].trim().strip_prefix("version:") {
+                    version = Some(label.trim().to_string());
+                }
+                ("!".to_string(), String::new(), None, cap[0].len())
+            } else if let Some(cap) = tag.captures(rest) {
+                (
+                    cap[1].to_string(),
+                    cap[2].to_string(),
+                    cap.get(3).map(|m| m.as_str().to_string()),
+                    cap[0].len(),
+                )
+            } else {
+                text.push('{');
+                pos = start + 1;
+                continue;
+            };
+            let mut end = start + tag_len;
+
+            if sigil.is_empty() {
+                if arg.is_some() {
+                    // `{two words}` is not a tag.
+                    text.push('{');
+                    pos = start + 1;
+                    continue;
+                }
+                flush_text(&mut text, &mut stack);
+                current(&mut stack).push(Node::Var { name: word, line });
+                pos = end;
+                continue;
+            }
+
+            // Block tags and comments alone on their line take the whole line with them.
+            let indent = &source[line_start..start];
+            let trailing = source[end..]
+                .find(|c: char| c != ' ' && c != '\t')
+                .map(|i| end + i)
+                .unwrap_or(source.len());
+            let line_end = match source[trailing..]
+                .strip_prefix('\r')
+                .unwrap_or(&source[trailing..])
+            {
+                "" => Some(source.len()),
+                after if after.starts_with('\n') => Some(source.len() - after.len() + 1),
+                _ => None,
+            };
+            if let Some(line_end) =
+                line_end.filter(|_| indent.chars().all(|c| c == ' ' || c == '\t'))
+            {
+                text.truncate(text.len() - indent.len());
+                end = line_end;
+            }
+            flush_text(&mut text, &mut stack);
+
+            match (sigil.as_str(), word.as_str(), arg) {
+                ("!", _, _) => {}
+                ("#", "if", Some(name)) | ("#", "each", Some(name)) => {
+                    stack.push(OpenBlock {
+                        kind: if word == "if" { "if" } else { "each" },
+                        name,
+                        line,
+                        nodes: Vec::new(),
+                        then: None,
+                    });
+                }
+                ("#", "else", None) => {
+                    let block = stack.last_mut().unwrap();
+                    if block.kind != "if" || block.then.is_some() {
+                        return Err(format!("line {}: {{#else}} outside of {{#if ...}}", line));
+                    }
+                    block.then = Some(std::mem::take(&mut block.nodes));
+                }
+                ("/", "if", None) | ("/", "each", None) => {
+                    let block = stack.pop().unwrap();
+                    if block.kind != word {
+                        let message = if block.kind == "template" {
+                            format!("line {}: {{/{}}} without a matching opening tag", line, word)
+                        } else {
+                            format!(
+                                "line {}: {{/{}}} closes {{#{} {}}} from line {}",
+                                line, word, block.kind, block.name, block.line
+                            )
+                        };
+                        return Err(message);
+                    }
+                    let node = if block.kind == "if" {
+                        match block.then {
+                            Some(then) => Node::If {
+                                name: block.name,
+                                line: block.line,
+                                then,
+                                otherwise: block.nodes,
+                            },
+                            None => Node::If {
+                                name: block.name,
+                                line: block.line,
+                                then: block.nodes,
+                                otherwise: Vec::new(),
+                            },
+                        }
+                    } else {
+                        Node::Each {
+                            name: block.name,
+                            line: block.line,
+                            body: block.nodes,
+                        }
+                    };
+                    current(&mut stack).push(node);
+                }
+                _ => {
+                    return Err(format!(
+                        "line {}: unknown tag {}; use {{#if name}}, {{#else}}, {{/if}}, {{#each name}} or {{/each}}",
+                        line,
+                        &source[start..start + tag_len]
+                    ))
+                }
+            }
+
+            if source[start..end].contains('\n') {
+                line += 1;
+                line_start = end;
+            }
+            pos = end;
+        }
+        text.push_str(&source[pos..]);
+        flush_text(&mut text, &mut stack);
+
+        if stack.len() > 1 {
+            let block = stack.pop().unwrap();
+            return Err(format!(
+                "line {}: {{#{} {}}} is never closed with {{/{}}}",
+                block.line, block.kind, block.name, block.kind
+            ));
+        }
+        let digest = Sha256::digest(source.as_bytes());
+        Ok(Template {
+            nodes: stack.pop().unwrap().nodes,
+            hash: format!("{:x}", digest)[..HASH_LEN].to_string(),
+            version: version.filter(|label| !label.is_empty()),
+        })
+    }
+
+    /// Short SHA-256 of the template source; changes with any edit.
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Label from a `{! version: ... }` comment, if the template declares one.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Report every variable the template uses that is not in `variables`.
+    pub fn check(&self, variables: &[Variable]) -> Result<(), String> {
+        let mut problems = Vec::new();
+        check_nodes(&self.nodes, variables, &[], &mut problems);
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let known: Vec<&str> = variables.iter().map(|v| v.name).collect();
+        Err(format!(
+            "{} (known variables: {}; write {{{{name}}}} for a literal brace)",
+            problems.join("; "),
+            known.join(", ")
+        ))
+    }
+
+    pub fn render(&self, vars: &Vars) -> Rendered {
+        let mut out = Rendered::default();
+        render_nodes(&self.nodes, &[vars], &mut out);
+        out
+    }
+}
+
+fn current(stack: &mut [OpenBlock]) -> &mut Vec<Node> {
+    &mut stack.last_mut().unwrap().nodes
+}
+
+fn flush_text(text: &mut String, stack: &mut [OpenBlock]) {
+    if !text.is_empty() {
+        current(stack).push(Node::Text(std::mem::take(text)));
+    }
+}
+
+fn check_nodes(
+    nodes: &[Node],
+    variables: &[Variable],
+    item_fields: &[&[&str]],
+    problems: &mut Vec<String>,
+) {
+    let in_item = |name: &str| item_fields.iter().any(|fields| fields.contains(&name));
+    let find = |name: &str| variables.iter().find(|v| v.name == name);
+
+    for node in nodes {
+        match node {
+            Node::Text(_) => {}
+            Node::Var { name, line } | Node::If { name, line, .. } => {
+                if !in_item(name) && find(name).is_none() {
+                    problems.push(format!("line {}: unknown variable {{{}}}", line, name));
+                }
+                if let Node::If {
+                    then, otherwise, ..
+                } = node
+                {
+                    check_nodes(then, variables, item_fields, problems);
+                    check_nodes(otherwise, variables, item_fields, problems);
+                }
+            }
+            Node::Each { name, line, body } => match find(name).map(|v| v.fields) {
+                Some(Some(fields)) => {
+                    let mut scopes = item_fields.to_vec();
+                    scopes.push(fields);
+                    check_nodes(body, variables, &scopes, problems);
+                }
+                Some(None) => problems.push(format!(
+                    "line {}: {{#each {}}} needs a list, but {} is text",
+                    line, name, name
+                )),
+                None => problems.push(format!("line {}: unknown list {{#each {}}}", line, name)),
+            },
+        }
+    }
+}
+
+fn lookup<'a>(scopes: &[&'a Vars], name: &str) -> Option<&'a Value> {
+    scopes.iter().rev().find_map(|vars| vars.get(name))
+}
+
+fn note(list: &mut Vec<String>, name: &str) {
+    if !list.iter().any(|n| n == name) {
+        list.push(name.to_string());
+    }
+}
+
+fn render_nodes(nodes: &[Node], scopes: &[&Vars], out: &mut Rendered) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.text.push_str(text),
+            Node::Var { name, .. } => match lookup(scopes, name) {
+                Some(Value::Text(text)) | Some(Value::List { text, .. }) => {
+                    note(&mut out.used, name);
+                    out.text.push_str(text);
+                }
+                None => {
+                    note(&mut out.missing, name);
+                    out.text.push_str(&format!("{{{}}}", name));
+                }
+            },
+            Node::If {
+                name,
+                then,
+                otherwise,
+                ..
+            } => {
+                let value = lookup(scopes, name);
+                match value {
+                    Some(_) => note(&mut out.used, name),
+                    None => note(&mut out.missing, name),
+                }
+                if value.is_some_and(Value::is_truthy) {
+                    render_nodes(then, scopes, out);
+                } else {
+                    render_nodes(otherwise, scopes, out);
+                }
+            }
+            Node::Each { name, body, .. } => match lookup(scopes, name) {
+                Some(Value::List { items, .. }) => {
+                    note(&mut out.used, name);
+                    for item in items {
+                        let mut inner = scopes.to_vec();
+                        inner.push(item);
+                        render_nodes(body, &inner, out);
+                    }
+                }
+                _ => note(&mut out.missing, name),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES: &[Variable] = &[
+        Variable::text("count"),
+        Variable::text("notes"),
+        Variable::list("examples", &["number", "json"]),
+    ];
+
+    fn vars() -> Vars {
+        let mut item = Vars::new();
+        item.insert("number".into(), Value::Text("1".into()));
+        item.insert(
+            "json".into(),
+            Value::Text(r#"{"text": "uses {count}"}"#.into()),
+        );
+        let mut vars = Vars::new();
+        vars.insert("count".into(), Value::Text("3".into()));
+        vars.insert("notes".into(), Value::Text(String::new()));
+        vars.insert(
+            "examples".into(),
+            Value::List {
+                text: "(block)".into(),
+                items: vec![item],
+            },
+        );
+        vars
+    }
+
+    #[test]
+    fn test_render_blocks_escapes_and_single_pass_substitution() {
+        let source = "Write {count}. Literal {{count}} and {\"json\": 1}.\n\
+                      {#if notes}\nNotes: {notes}\n{#else}\nNo notes.\n{/if}\n\
+                      {#each examples}\n{number}: {json}\n{/each}\n\
+                      All: {examples}";
+        let template = Template::load(source, VARIABLES).unwrap();
+        let rendered = template.render(&vars());
+        assert_eq!(
+            rendered.text,
+            "Write 3. Literal {count} and {\"json\": 1}.\nNo notes.\n1: {\"text\": \"uses {count}\"}\nAll: (block)"
+        );
+        assert_eq!(
+            rendered.used,
+            vec!["count", "notes", "examples", "number", "json"]
+        );
+        assert!(rendered.missing.is_empty());
+    }
+
+    #[test]
+    fn test_load_reports_unknown_variables_and_unbalanced_blocks() {
+        let err = Template::load("Hi {cuont}\n{#each count}{x}{/each}", VARIABLES).unwrap_err();
+        assert!(err.contains("line 1: unknown variable {cuont}"), "{}", err);
+        assert!(
+            err.contains("line 2: {#each count} needs a list"),
+            "{}",
+            err
+        );
+
+        let err = Template::parse("{#if notes}\nopen").unwrap_err();
+        assert!(
+            err.contains("line 1: {#if notes} is never closed"),
+            "{}",
+            err
+        );
+        let err = Template::parse("{#if notes}{/each}").unwrap_err();
+        assert!(err.contains("closes {#if notes}"), "{}", err);
+        assert!(Template::parse("{#unless notes}").is_err());
+    }
+
+    #[test]
+    fn test_version_comment_and_hash() {
+        let template = Template::parse("{! version: 2024-09 calc }\nHello {count}\n").unwrap();
+        assert_eq!(template.version(), Some("2024-09 calc"));
+        assert_eq!(template.hash().len(), HASH_LEN);
+        assert_eq!(template.render(&vars()).text, "Hello 3\n");
+
+        let edited = Template::parse("{! version: 2024-09 calc }\nHello {count}!\n").unwrap();
+        assert_ne!(edited.hash(), template.hash());
+        assert_eq!(Template::parse("{! just a note }").unwrap().version(), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! Token usage and quota accounting
+//!
+//! Every live gateway call adds its prompt and response size, plus an estimated token
+//! count, to per-day and per-job totals. The latest `remaining_tokens` reported by the
+//! gateway is kept so the UI can show it at startup and warn before large batches.
+//! Totals are saved to `<app data>/usage.json`. Replays from the response cache are
+//! not counted because they never reach the gateway.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Rough characters-per-token ratio; good enough for warnings and previews.
+const CHARS_PER_TOKEN: usize = 4;
+const MAX_DAYS: usize = 90;
+const MAX_JOBS: usize = 200;
+const SUMMARY_DAYS: usize = 30;
+const SUMMARY_JOBS: usize = 20;
+
+static USAGE_PATH: OnceCell<PathBuf> = OnceCell::new();
+static DATA: Mutex<Option<UsageData>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_chars: u64,
+    pub response_chars: u64,
+    pub estimated_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.prompt_chars += other.prompt_chars;
+        self.response_chars += other.response_chars;
+        self.estimated_tokens += other.estimated_tokens;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobUsage {
+    pub job_id: String,
+    /// Kind of the job's first request (`generate`, `regenerate`, ...); repair
+    /// round-trips are counted in the job that triggered them.
+    pub kind: String,
+    pub subject: String,
+    pub started_at: String,
+    pub updated_at: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayUsage {
+    pub date: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageSummary {
+    pub remaining_tokens: Option<u64>,
+    pub remaining_updated_at: Option<String>,
+    pub today: UsageTotals,
+    /// Newest first.
+    pub days: Vec<DayUsage>,
+    /// Newest first.
+    pub recent_jobs: Vec<JobUsage>,
+    /// Across all retained days.
+    pub total: UsageTotals,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UsageData {
+    #[serde(default)]
+    remaining_tokens: Option<u64>,
+    #[serde(default)]
+    remaining_updated_at: Option<String>,
+    #[serde(default)]
+    days: BTreeMap<String, UsageTotals>,
+    #[serde(default)]
+    jobs: VecDeque<JobUsage>,
+}
+
+/// One gateway round-trip to account for.
+pub struct UsageSample<'a> {
+    pub job_id: &'a str,
+    pub kind: &'a str,
+    pub subject: &'a str,
+    pub prompt: &'a str,
+    pub response: &'a str,
+    pub remaining_tokens: Option<u64>,
+}
+
+/// Approximate token count of a prompt or response.
+pub fn estimate_tokens(text: &str) -> u64 {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+/// Load saved totals. Called once at startup; until then usage is kept in memory only.
+pub fn init(path: PathBuf) {
+    let loaded = fs::read_to_string(&path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<UsageData>(&data).ok());
+    let _ = USAGE_PATH.set(path);
+    let mut data = DATA.lock().unwrap();
+    if let Some(loaded) = loaded {
+        *data = Some(loaded);
+    }
+}
+
+pub fn record(sample: UsageSample) {
+    let now = chrono::Local::now();
+    let mut guard = DATA.lock().unwrap();
+    let data = guard.get_or_insert_with(UsageData::default);
+    data.apply(
+        &sample,
+        &now.format("%Y-%m-%d").to_string(),
+        &now.to_rfc3339(),
+    );
+
+    if let Some(path) = USAGE_PATH.get() {
+        if let Err(e) = save(path, data) {
+            eprintln!("WARNING: Failed to save usage totals: {}", e);
+        }
+    }
+}
+
+pub fn summary() -> UsageSummary {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let guard = DATA.lock().unwrap();
+    match guard.as_ref() {
+        Some(data) => data.summary(&today),
+        None => UsageData::default().summary(&today),
+    }
+}
+
+pub fn remaining_tokens() -> Option<u64> {
+    DATA.lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|data| data.remaining_tokens)
+}
+
+/// Average estimated tokens per request for jobs of `kind`, from recent history.
+pub fn average_request_tokens(kind: &str) -> Option<u64> {
+    let guard = DATA.lock().unwrap();
+    let data = guard.as_ref()?;
+    let mut totals = UsageTotals::default();
+    for job in data.jobs.iter().filter(|job| job.kind == kind) {
+        totals.add(&job.totals);
+    }
+    (totals.requests > 0).then(|| totals.estimated_tokens / totals.requests)
+}
+
+impl UsageData {
+    fn apply(&mut self, sample: &UsageSample, date: &str, timestamp: &str) {
+        let totals = UsageTotals {
+            requests: 1,
+            prompt_chars: sample.prompt.chars().count() as u64,
+            response_chars: sample.response.chars().count() as u64,
+            estimated_tokens: estimate_tokens(sample.prompt) + estimate_tokens(sample.response),
+        };
+
+        if let Some(remaining) = sample.remaining_tokens {
+            self.remaining_tokens = Some(remaining);
+            self.remaining_updated_at = Some(timestamp.to_string());
+        }
+
+        self.days.entry(date.to_string()).or_default().add(&totals);
+        while self.days.len() > MAX_DAYS {
+            let oldest = self.days.keys().next().cloned();
+            if let Some(oldest) = oldest {
+                self.days.remove(&oldest);
+            }
+        }
+
+        match self.jobs.iter_mut().find(|job| job.job_id == sample.job_id) {
+            Some(job) => {
+                job.totals.add(&totals);
+                job.updated_at = timestamp.to_string();
+            }
+            None => {
+                self.jobs.push_back(JobUsage {
+                    job_id: sample.job_id.to_string(),
+                    kind: sample.kind.to_string(),
+                    subject: sample.subject.to_string(),
+                    started_at: timestamp.to_string(),
+                    updated_at: timestamp.to_string(),
+                    totals,
+                });
+                while self.jobs.len() > MAX_JOBS {
+                    self.jobs.pop_front();
+                }
+            }
+        }
+    }
+
+    fn summary(&self, today: &str) -> UsageSummary {
+        let mut total = UsageTotals::default();
+        for totals in self.days.values() {
+            total.add(totals);
+        }
+        UsageSummary {
+            remaining_tokens: self.remaining_tokens,
+            remaining_updated_at: self.remaining_updated_at.clone(),
+            today: self.days.get(today).cloned().unwrap_or_default(),
+            days: self
+                .days
+                .iter()
+                .rev()
+                .take(SUMMARY_DAYS)
+                .map(|(date, totals)| DayUsage {
+                    date: date.clone(),
+                    totals: totals.clone(),
+                })
+                .collect(),
+            recent_jobs: self.jobs.iter().rev().take(SUMMARY_JOBS).cloned().collect(),
+            total,
+        }
+    }
+}
+
+fn save(path: &Path, data: &UsageData) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("Failed to serialize usage: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut f = fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create temp file {}: {}", tmp_path.display(), e))?;
+        f.write_all(json.as_bytes())
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    }
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample<'a>(job_id: &'a str, kind: &'a str, remaining: Option<u64>) -> UsageSample<'a> {
+        UsageSample {
+            job_id,
+            kind,
+            subject: "Calculus",
+            prompt: "12345678",
+            response: "1234",
+            remaining_tokens: remaining,
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_apply_groups_by_day_and_job() {
+        let mut data = UsageData::default();
+        data.apply(&sample("job_1", "generate", Some(500)), "2026-01-01", "t1");
+        data.apply(&sample("job_1", "repair", None), "2026-01-01", "t2");
+        data.apply(
+            &sample("job_2", "regenerate", Some(420)),
+            "2026-01-02",
+            "t3",
+        );
+
+        let summary = data.summary("2026-01-02");
+        assert_eq!(summary.remaining_tokens, Some(420));
+        assert_eq!(summary.remaining_updated_at.as_deref(), Some("t3"));
+        assert_eq!(summary.today.requests, 1);
+        assert_eq!(summary.days[0].date, "2026-01-02");
+        assert_eq!(summary.days[1].totals.requests, 2);
+        assert_eq!(summary.total.estimated_tokens, 3 * 3);
+
+        let job_1 = summary
+            .recent_jobs
+            .iter()
+            .find(|job| job.job_id == "job_1")
+            .unwrap();
+        assert_eq!(job_1.kind, "generate");
+        assert_eq!(job_1.totals.requests, 2);
+        assert_eq!(job_1.updated_at, "t2");
+    }
+}
@@ -0,0 +1,282 @@
+//! Optional self-verification of answer keys
+//!
+//! After a question is parsed it can be sent back to the model, or to a second gateway,
+//! as a plain multiple-choice item with the answer key and explanations removed. The
+//! model works the problem and names one choice; that choice is compared with the
+//! answers marked `is_correct`. The outcome is stored on the question as a
+//! [`Verification`] so the UI can flag disputed keys.
+//!
+//! The pass is off unless `RUBRIX_VERIFY_ANSWERS` is set: `flag` (or any truthy value)
+//! only records the verdict, `regenerate` also regenerates disputed questions once.
+//! `RUBRIX_VERIFY_GATEWAY_URL` sends verification requests to a different gateway than
+//! generation. The mode can be changed at runtime with `set_answer_verification_mode`.
+
+use crate::choices::{self, letter};
+use crate::jobs::Job;
+use crate::llm::{self, GatewayAuth, StreamTarget};
+use crate::Question;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMode {
+    Off,
+    /// Record the verdict on the question.
+    Flag,
+    /// Record the verdict and regenerate disputed questions once.
+    Regenerate,
+}
+
+impl VerifyMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "0" | "false" | "no" | "off" => Some(VerifyMode::Off),
+            "1" | "true" | "yes" | "on" | "flag" => Some(VerifyMode::Flag),
+            "regenerate" => Some(VerifyMode::Regenerate),
+            _ => None,
+        }
+    }
+}
+
+/// Read the mode from `RUBRIX_VERIFY_ANSWERS`.
+pub fn init() {
+    let _ = dotenvy::dotenv();
+    let mode = match std::env::var("RUBRIX_VERIFY_ANSWERS") {
+        Ok(value) => VerifyMode::parse(&value).unwrap_or_else(|| {
+            eprintln!(
+                "WARNING: Ignoring RUBRIX_VERIFY_ANSWERS={:?}; expected off, flag or regenerate",
+                value
+            );
+            VerifyMode::Off
+        }),
+        Err(_) => VerifyMode::Off,
+    };
+    set_mode(mode);
+}
+
+pub fn mode() -> VerifyMode {
+    match MODE.load(Ordering::SeqCst) {
+        1 => VerifyMode::Flag,
+        2 => VerifyMode::Regenerate,
+        _ => VerifyMode::Off,
+    }
+}
+
+pub fn set_mode(mode: VerifyMode) {
+    let value = match mode {
+        VerifyMode::Off => 0,
+        VerifyMode::Flag => 1,
+        VerifyMode::Regenerate => 2,
+    };
+    MODE.store(value, Ordering::SeqCst);
+}
+
+/// Gateway used for verification when it should not be the generating model.
+fn verify_gateway_url() -> Option<String> {
+    std::env::var("RUBRIX_VERIFY_GATEWAY_URL")
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// The model derived one of the answers marked correct.
+    Agree,
+    /// The model derived a different answer.
+    Disagree,
+    /// The check failed or the model did not commit to a choice.
+    Inconclusive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verification {
+    pub verdict: Verdict,
+    /// Letter of the choice the verifier picked.
+    #[serde(default)]
+    pub derived_answer: Option<String>,
+    /// Letters of the choices marked correct when the check ran.
+    #[serde(default)]
+    pub marked_answer: Option<String>,
+    #[serde(default)]
+    pub reasoning: String,
+    /// Set when the question was regenerated after a disputed first check.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub regenerated: bool,
+    pub checked_at: String,
+}
+
+impl Verification {
+    fn inconclusive(question: &Question, reasoning: String) -> Self {
+        Verification {
+            verdict: Verdict::Inconclusive,
+            derived_answer: None,
+            marked_answer: marked_letters(question),
+            reasoning,
+            regenerated: false,
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+fn marked_letters(question: &Question) -> Option<String> {
+    let letters: Vec<String> = question
+        .answers
+        .iter()
+        .enumerate()
+        .filter(|(_, answer)| answer.is_correct)
+        .map(|(i, _)| letter(i))
+        .collect();
+    (!letters.is_empty()).then(|| letters.join(", "))
+}
+
+/// The question as a student would see it: stem and lettered choices, no key.
+pub fn build_verification_prompt(question: &Question) -> String {
+    let mut prompt = String::from(
+        "Solve the following multiple-choice question. Work it out independently; \
+         if it contains code, trace the code step by step.\n\n",
+    );
+    prompt.push_str(question.stem_markdown().trim());
+    prompt.push_str("\n\nChoices:\n");
+    for (i, answer) in question.answers.iter().enumerate() {
+        prompt.push_str(&format!("{}. {}\n", letter(i), answer.text.trim()));
+    }
+    prompt.push_str(
+        "\nReturn ONLY a JSON object of the form \
+         {\"reasoning\": \"<your working>\", \"answer\": \"<letter>\"} \
+         naming exactly one choice.",
+    );
+    prompt
+}
+
+#[derive(Deserialize)]
+struct RawVerdict {
+    #[serde(default)]
+    reasoning: String,
+    #[serde(default)]
+    answer: String,
+}
+
+/// Compare the verifier's response with the answer key.
+pub fn parse_verdict(question: &Question, response: &str) -> Verification {
+    let parsed = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<RawVerdict>(&response[start..=end]).ok()
+        }
+        _ => None,
+    };
+    let Some(raw) = parsed else {
+        return Verification::inconclusive(
+            question,
+            "The verifier did not return a JSON verdict".to_string(),
+        );
+    };
+
+    let derived = match choices::index_of(&raw.answer, question.answers.len()) {
+        Some(index) => index,
+        None => {
+            let reasoning = format!(
+                "The verifier answered {:?}, which is not one of the choices. {}",
+                raw.answer,
+                raw.reasoning.trim()
+            );
+            return Verification::inconclusive(question, reasoning.trim().to_string());
+        }
+    };
+
+    let verdict = if question.answers[derived].is_correct {
+        Verdict::Agree
+    } else {
+        Verdict::Disagree
+    };
+    Verification {
+        verdict,
+        derived_answer: Some(letter(derived)),
+        marked_answer: marked_letters(question),
+        reasoning: raw.reasoning.trim().to_string(),
+        regenerated: false,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Ask the model to answer `question` without its key. Errors become an inconclusive
+/// verdict rather than failing the generation that triggered the check.
+pub async fn verify_question(
+    question: &Question,
+    gateway_auth: Option<GatewayAuth>,
+    job: &Job,
+) -> Verification {
+    let prompt = build_verification_prompt(question);
+    let stream = StreamTarget::new(None, job)
+        .with_log_context("verify", &question.subject, &question.topics)
+        .with_gateway_url(verify_gateway_url());
+    match llm::generate(&prompt, stream, gateway_auth, job).await {
+        Ok(_) if job.is_cancelled() => {
+            Verification::inconclusive(question, "Verification was cancelled".to_string())
+        }
+        Ok(response) => parse_verdict(question, &response),
+        Err(err) => Verification::inconclusive(question, format!("Verification failed: {}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choices::fixtures::question;
+    use crate::Answer;
+
+    #[test]
+    fn test_prompt_omits_answer_key() {
+        let prompt = build_verification_prompt(&question());
+        assert!(prompt.contains("A. 4\nB. 8\nC. 16\nD. An exception is thrown\n"));
+        assert!(!prompt.contains("is_correct"));
+        assert!(!prompt.contains("Returned n unchanged"));
+        assert!(!prompt.contains("doubles its argument"));
+    }
+
+    #[test]
+    fn test_parse_verdict() {
+        let q = question();
+        let agree = parse_verdict(
+            &q,
+            "```json\n{\"reasoning\": \"4*2\", \"answer\": \"B\"}\n```",
+        );
+        assert_eq!(agree.verdict, Verdict::Agree);
+        assert_eq!(agree.marked_answer.as_deref(), Some("B"));
+
+        let disagree = parse_verdict(&q, "{\"reasoning\": \"4*4\", \"answer\": \"c.\"}");
+        assert_eq!(disagree.verdict, Verdict::Disagree);
+        assert_eq!(disagree.derived_answer.as_deref(), Some("C"));
+
+        assert_eq!(
+            parse_verdict(&q, "{\"answer\": \"E\"}").verdict,
+            Verdict::Inconclusive
+        );
+        assert_eq!(parse_verdict(&q, "B").verdict, Verdict::Inconclusive);
+
+        let mut unkeyed = q.clone();
+        unkeyed.answers = vec![Answer {
+            text: "8".to_string(),
+            is_correct: false,
+            explanation: None,
+        }];
+        let verdict = parse_verdict(&unkeyed, "{\"answer\": \"A\"}");
+        assert_eq!(verdict.verdict, Verdict::Disagree);
+        assert_eq!(verdict.marked_answer, None);
+    }
+
+    #[test]
+    fn test_mode_values() {
+        assert_eq!(
+            VerifyMode::parse("Regenerate"),
+            Some(VerifyMode::Regenerate)
+        );
+        assert_eq!(VerifyMode::parse("on"), Some(VerifyMode::Flag));
+        assert_eq!(VerifyMode::parse("off"), Some(VerifyMode::Off));
+        assert_eq!(VerifyMode::parse("sometimes"), None);
+    }
+}
@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef, useMemo } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
-import { open, save } from "@tauri-apps/api/dialog";
+import { confirm, open, save } from "@tauri-apps/api/dialog";
 import { writeBinaryFile, writeTextFile } from "@tauri-apps/api/fs";
 import Sidebar from "./components/Sidebar";
 import QuestionList from "./components/QuestionList";
@@ -12,16 +12,44 @@ import SubmitBugModal from "./components/SubmitBugModal";
 import ExportOptionsModal from "./components/ExportOptionsModal";
 import PreferencesModal from "./components/PreferencesModal";
 import OpenRecentModal from "./components/OpenRecentModal";
+import TemplateVariantsModal, {
+  TemplateVariantsSelection,
+} from "./components/TemplateVariantsModal";
+import BlueprintModal from "./components/BlueprintModal";
+import AssembleModal from "./components/AssembleModal";
+import AccommodateModal from "./components/AccommodateModal";
 import SaveChangesModal from "./components/SaveChangesModal";
+import RawResponseModal from "./components/RawResponseModal";
+import LlmHistoryModal from "./components/LlmHistoryModal";
+import UsageModal from "./components/UsageModal";
+import PromptPreviewModal from "./components/PromptPreviewModal";
 import {
   Question,
   TopicInfo,
   SubjectInfo,
   GenerationRequest,
+  Blueprint,
   WordExportOptions,
   Answer,
   BugSubmissionInput,
   SubmitBugResult,
+  QuotaEstimate,
+  UsageSummary,
+  PromptPreview,
+  Provenance,
+  Verification,
+  CodeCheck,
+  MathCheck,
+  BankCodeCheck,
+  RegenerateMode,
+  ParametricTemplateInfo,
+  ParametricRequest,
+  AssemblyRequest,
+  AssemblyResult,
+  AccommodationOptions,
+  AccommodationRun,
+  GenerationRun,
+  BlueprintRun,
 } from "./types";
 import {
   Loader2,
@@ -31,15 +59,47 @@ import AlertModal from "./components/AlertModal";
 
 // Event payload from Rust backend
 interface StreamEvent {
+  job_id?: string;
+  index?: number;
+  batch?: number; // set when a large generation is split into batches
   text: string;
   done: boolean;
   remaining_tokens?: number;
+  diagnostic?: string;
+  retry?: {
+    attempt: number;
+    max_attempts: number;
+    delay_ms: number;
+    reason: string;
+  };
+}
+
+interface QuestionReadyEvent {
+  job_id: string;
+  index?: number;
+  batch?: number;
+  position: number; // position within the whole request, across batches
+  question: Question;
+}
+
+interface GenerationProgressEvent {
+  job_id: string;
+  completed: number; // batches finished, including failed ones
+  total: number;
+  questions: number; // questions produced so far
+}
+
+interface AccommodationProgressEvent {
+  job_id: string;
+  completed: number;
+  total: number;
 }
 
 interface RegenerateAllQuestionResult {
   index: number;
   question?: Question | null;
   error?: string | null;
+  cancelled?: boolean;
 }
 
 interface RegenerateAllProgressEvent {
@@ -57,6 +117,12 @@ const preferredSubjectStorageKey = "preferredSubject";
 const preferredDifficultyStorageKey = "preferredDifficulty";
 const recentDocumentsStorageKey = "recentDocuments";
 
+// "quiz.md" -> "quiz (accommodated).md", for the document exported alongside the standard one
+function accommodatedPath(filePath: string): string {
+  const match = filePath.match(/^(.*?)(\.[^.\\/]+)?$/);
+  return `${match?.[1] ?? filePath} (accommodated)${match?.[2] ?? ""}`;
+}
+
 function parseSessionQuestions(raw: unknown): Question[] {
   const payload: unknown[] | null = Array.isArray(raw)
     ? raw
@@ -168,15 +234,33 @@ function parseSessionQuestions(raw: unknown): Question[] {
             ? q.id
             : `q${questionIndex + 1}`,
         text,
+        code: typeof q.code === "string" && q.code.trim().length > 0 ? q.code : undefined,
         answers,
         explanation: coerceRichText(q.explanation),
         distractors: coerceRichText(q.distractors),
+        work: typeof q.work === "string" && q.work.trim().length > 0 ? q.work : undefined,
         subject: typeof q.subject === "string" ? q.subject : undefined,
         topics,
         difficulty:
           typeof q.difficulty === "string" && q.difficulty.trim().length > 0
             ? q.difficulty
             : undefined,
+        provenance:
+          q.provenance && typeof q.provenance === "object"
+            ? (q.provenance as Provenance)
+            : undefined,
+        verification:
+          q.verification && typeof q.verification === "object"
+            ? (q.verification as Verification)
+            : undefined,
+        code_check:
+          q.code_check && typeof q.code_check === "object"
+            ? (q.code_check as CodeCheck)
+            : undefined,
+        math_check:
+          q.math_check && typeof q.math_check === "object"
+            ? (q.math_check as MathCheck)
+            : undefined,
       };
 
       return normalizedQuestion;
@@ -190,6 +274,24 @@ function parseSessionQuestions(raw: unknown): Question[] {
   return normalized;
 }
 
+/** Running the code contradicts the answer key. */
+function isCodeCheckFlagged(check?: CodeCheck | null): boolean {
+  return check?.status === "mismatch" || check?.status === "no_choice_matches";
+}
+
+/** Working the math contradicts the answer key, or two choices are the same. */
+function isMathCheckFlagged(check?: MathCheck | null): boolean {
+  return (
+    check?.status === "mismatch" ||
+    check?.status === "no_choice_matches" ||
+    (check?.equivalent_choices?.length ?? 0) > 0
+  );
+}
+
+function isAnswerCheckFlagged(checks: { code_check?: CodeCheck; math_check?: MathCheck }): boolean {
+  return isCodeCheckFlagged(checks.code_check) || isMathCheckFlagged(checks.math_check);
+}
+
 function parseMarkdownQuestions(content: string): Question[] {
   const warn = (message: string) => {
     console.warn(`[Catie Markdown] ${message}`);
@@ -299,6 +401,7 @@ function App() {
   });
   const [questionCount, setQuestionCount] = useState(1);
   const [notes, setNotes] = useState("");
+  const [sourcePaths, setSourcePaths] = useState<string[]>([]);
   const [questions, setQuestions] = useState<Question[]>([]);
   const [rawTextByQuestionId, setRawTextByQuestionId] = useState<Record<string, string>>({});
   const [isGenerating, setIsGenerating] = useState(false);
@@ -307,6 +410,12 @@ function App() {
   const [currentDocumentPath, setCurrentDocumentPath] = useState<string | null>(null);
   const [documentMode, setDocumentMode] = useState<DocumentMode>("blank");
   const [openRecentOpen, setOpenRecentOpen] = useState(false);
+  const [templateVariantsOpen, setTemplateVariantsOpen] = useState(false);
+  const [blueprintOpen, setBlueprintOpen] = useState(false);
+  const [assembleOpen, setAssembleOpen] = useState(false);
+  const [assemblyUnmet, setAssemblyUnmet] = useState<string[]>([]);
+  const [accommodateOpen, setAccommodateOpen] = useState(false);
+  const [parametricTemplates, setParametricTemplates] = useState<ParametricTemplateInfo[]>([]);
   const [saveChangesOpen, setSaveChangesOpen] = useState(false);
   const [recentDocuments, setRecentDocuments] = useState<string[]>(() => {
     if (typeof localStorage === "undefined") return [];
@@ -345,7 +454,23 @@ function App() {
   const [showPreview, setShowPreview] = useState(true);
   const [regeneratingQuestionId, setRegeneratingQuestionId] = useState<string | null>(null);
   const [isRegeneratingAll, setIsRegeneratingAll] = useState(false);
+  const [verifyingQuestionId, setVerifyingQuestionId] = useState<string | null>(null);
+  const [activeJobId, setActiveJobId] = useState<string | null>(null);
+  const activeJobIdRef = useRef<string | null>(null);
+  // Questions that finished streaming before the whole response arrived, plus any
+  // edits made to them; the edits are applied once the final result is stored.
+  const [provisionalQuestions, setProvisionalQuestions] = useState<Question[]>([]);
+  const provisionalBaseRef = useRef(0);
+  const provisionalEditsRef = useRef<Record<number, Question>>({});
+  const batchTextsRef = useRef<Record<number, string>>({});
+  // Generation whose output failed to parse; its raw text can be fixed and re-parsed.
+  const [failedParseJobId, setFailedParseJobId] = useState<string | null>(null);
+  const [rawFixJobId, setRawFixJobId] = useState<string | null>(null);
+  const [batchStreams, setBatchStreams] = useState<Record<number, { text: string; done: boolean }>>(
+    {}
+  );
   const latestStreamingTextRef = useRef("");
+  const regenerateAllQuotaRef = useRef<QuotaEstimate | null>(null);
   const [activeTab, setActiveTab] = useState<"generate" | "bank">("generate");
   const [sidebarCollapsed, setSidebarCollapsed] = useState(false);
   const [wordGeneratePreset, setWordGeneratePreset] = useState<WordPreset>(() => {
@@ -454,6 +579,11 @@ function App() {
   const [isAuthenticated, setIsAuthenticated] = useState(false);
   const [isDevMode, setIsDevMode] = useState(false);
   const [submitBugOpen, setSubmitBugOpen] = useState(false);
+  const [llmHistoryOpen, setLlmHistoryOpen] = useState(false);
+  const [usageOpen, setUsageOpen] = useState(false);
+  const [promptPreview, setPromptPreview] = useState<{ title: string; preview: PromptPreview } | null>(
+    null
+  );
   const [isSubmittingBug, setIsSubmittingBug] = useState(false);
   const [preferencesOpen, setPreferencesOpen] = useState(false);
   const [savedQuestionsSnapshot, setSavedQuestionsSnapshot] = useState<string | null>(null);
@@ -581,6 +711,17 @@ function App() {
     }
   }, [zoom]);
 
+  // The backend keeps the last quota the gateway reported; prefer it over the cached value
+  useEffect(() => {
+    invoke<UsageSummary>("get_usage_summary")
+      .then((summary) => {
+        if (summary.remaining_tokens != null) {
+          setRemainingTokens(summary.remaining_tokens);
+        }
+      })
+      .catch((err) => console.error("Failed to load usage summary:", err));
+  }, []);
+
   useEffect(() => {
     if (typeof localStorage === "undefined") return;
     if (remainingTokens === null) return;
@@ -633,6 +774,49 @@ function App() {
   // Listen for streaming events from backend
   useEffect(() => {
     const unlisten = listen<StreamEvent>("llm-stream", (event) => {
+      // Batch jobs tag each stream with its question index; route those to per-question previews.
+      if (typeof event.payload.index === "number") {
+        const { index, text, done } = event.payload;
+        setBatchStreams((prev) => ({ ...prev, [index]: { text, done } }));
+        if (event.payload.diagnostic) {
+          console.warn(`LLM stream (Q${index + 1}):`, event.payload.diagnostic);
+        }
+        return;
+      }
+      // Split generations stream their batches side by side; show them one after another.
+      if (typeof event.payload.batch === "number") {
+        const { batch, text } = event.payload;
+        batchTextsRef.current = { ...batchTextsRef.current, [batch]: text };
+        const combined = Object.keys(batchTextsRef.current)
+          .map(Number)
+          .sort((a, b) => a - b)
+          .map((key) => batchTextsRef.current[key])
+          .join("\n\n");
+        setStreamingText(combined);
+        latestStreamingTextRef.current = combined;
+        if (event.payload.diagnostic) {
+          console.warn(`LLM stream (batch ${batch + 1}):`, event.payload.diagnostic);
+        }
+        if (event.payload.retry) {
+          console.warn(`LLM retry (batch ${batch + 1}):`, event.payload.retry.reason);
+        }
+        if (typeof event.payload.remaining_tokens === "number") {
+          setRemainingTokens(event.payload.remaining_tokens);
+        }
+        return;
+      }
+      if (event.payload.diagnostic) {
+        console.warn("LLM stream:", event.payload.diagnostic);
+      }
+      if (event.payload.retry) {
+        const { attempt, max_attempts, delay_ms, reason } = event.payload.retry;
+        console.warn("LLM retry:", reason);
+        setStatus(
+          `Connection problem, retrying in ${Math.ceil(delay_ms / 1000)}s (attempt ${attempt}/${max_attempts})...`
+        );
+        // The stream restarts from scratch, so questions seen so far will be re-sent.
+        setProvisionalQuestions([]);
+      }
       setStreamingText(event.payload.text);
       latestStreamingTextRef.current = event.payload.text;
       setStreamingComplete(event.payload.done);
@@ -646,6 +830,72 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    activeJobIdRef.current = activeJobId;
+  }, [activeJobId]);
+
+  // Show each generated question as soon as its JSON object is complete
+  useEffect(() => {
+    const unlisten = listen<QuestionReadyEvent>("question-ready", (event) => {
+      const { job_id, index, position, question } = event.payload;
+      if (typeof index === "number" || job_id !== activeJobIdRef.current) {
+        return;
+      }
+      const pendingEdit = provisionalEditsRef.current[position];
+      setProvisionalQuestions((prev) => {
+        const next = [...prev];
+        next[position] = pendingEdit ?? {
+          ...question,
+          id: `q${provisionalBaseRef.current + position + 1}`,
+        };
+        return next.filter(Boolean);
+      });
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<GenerationProgressEvent>("generation-progress", (event) => {
+      const { job_id, completed, total, questions } = event.payload;
+      if (job_id !== activeJobIdRef.current) {
+        return;
+      }
+      const qLabel = questions === 1 ? "question" : "questions";
+      setStatus(`Generating in ${total} batches... ${completed}/${total} done, ${questions} ${qLabel} so far`);
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<AccommodationProgressEvent>("accommodation-progress", (event) => {
+      const { job_id, completed, total } = event.payload;
+      if (job_id !== activeJobIdRef.current) {
+        return;
+      }
+      setStatus(`Writing accommodated versions... ${completed}/${total} done`);
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<QuotaEstimate>("regenerate-all-quota", (event) => {
+      regenerateAllQuotaRef.current = event.payload;
+    });
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
   useEffect(() => {
     const unlisten = listen<RegenerateAllProgressEvent>("regenerate-all-progress", (event) => {
       if (!isRegeneratingAll) {
@@ -695,6 +945,10 @@ function App() {
 
       if (action === "submit_bug") {
         setSubmitBugOpen(true);
+      } else if (action === "llm_history") {
+        setLlmHistoryOpen(true);
+      } else if (action === "usage") {
+        setUsageOpen(true);
       } else if (action === "new_document") {
         void handleNewDocument();
       } else if (action === "add_custom_question") {
@@ -726,6 +980,35 @@ function App() {
         } else {
           setStatus("No raw stream available yet");
         }
+      } else if (action === "check_code") {
+        void handleRunAnswerChecks();
+      } else if (action === "generate_from_template") {
+        if (activeTab !== "generate") {
+          setStatus("Switch to Generator tab to generate from a template");
+        } else {
+          void handleOpenTemplateVariants();
+        }
+      } else if (action === "generate_from_blueprint") {
+        if (activeTab !== "generate") {
+          setStatus("Switch to Generator tab to generate from a blueprint");
+        } else {
+          setBlueprintOpen(true);
+        }
+      } else if (action === "assemble_from_bank") {
+        if (activeTab !== "generate") {
+          setStatus("Switch to Generator tab to assemble a test from the bank");
+        } else {
+          setAssemblyUnmet([]);
+          setAssembleOpen(true);
+        }
+      } else if (action === "accommodate_questions") {
+        if (activeTab !== "generate") {
+          setStatus("Switch to Generator tab to write accommodated versions");
+        } else if (questions.length === 0) {
+          setStatus("Generate questions before writing accommodated versions");
+        } else {
+          setAccommodateOpen(true);
+        }
       } else if (action === "regenerate_all_questions") {
         if (activeTab !== "generate") {
           setStatus("Switch to Generator tab to regenerate questions");
@@ -818,10 +1101,18 @@ function App() {
     const previousQuestionCount = questions.length;
     setStreamingText("");
     latestStreamingTextRef.current = "";
+    batchTextsRef.current = {};
     setStreamingComplete(false);
     setShowPreview(true);
     setRegeneratingQuestionId(null);
     setStatus("Adding more questions...");
+    const jobId = crypto.randomUUID();
+    activeJobIdRef.current = jobId;
+    setActiveJobId(jobId);
+    provisionalBaseRef.current = previousQuestionCount;
+    provisionalEditsRef.current = {};
+    setProvisionalQuestions([]);
+    setFailedParseJobId(null);
 
     try {
       const request: GenerationRequest = {
@@ -831,11 +1122,27 @@ function App() {
         count: questionCount,
         notes: notes || null,
         append: true,
+        sources: sourcePaths,
       };
 
-      const allQuestions = await invoke<Question[]>("generate_questions", {
+      const run = await invoke<GenerationRun>("generate_questions", {
         request,
+        jobId,
       });
+      let allQuestions = run.questions;
+
+      // Carry over edits made while the question was still streaming
+      for (const [position, edited] of Object.entries(provisionalEditsRef.current)) {
+        const targetIndex = previousQuestionCount + Number(position);
+        if (targetIndex >= allQuestions.length) continue;
+        const question = { ...edited, id: allQuestions[targetIndex].id };
+        try {
+          await invoke("update_question", { index: targetIndex, question });
+          allQuestions = allQuestions.map((q, i) => (i === targetIndex ? question : q));
+        } catch (err) {
+          console.error("Failed to apply edit made during generation:", err);
+        }
+      }
       setQuestions(allQuestions);
       if (documentMode === "blank") {
         setDocumentMode("new");
@@ -874,12 +1181,19 @@ function App() {
       } else {
         setStatus(`Added ${addedCount} questions (${allQuestions.length} total)`);
       }
+      if (run.failed.length > 0) {
+        const detailLines = run.failed.map((f) => `Batch ${f.batch + 1}: ${f.error}`);
+        setAlertMessage(`Some batches produced no questions.\n\n${detailLines.join("\n")}`);
+        setAlertOpen(true);
+      }
     } catch (err) {
       console.error("Generation failed:", err);
       const errorMsg = String(err);
 
       // If error suggests missing auth, show login modal
-      if (
+      if (errorMsg.includes("cancelled")) {
+        setStatus("Generation cancelled");
+      } else if (
         errorMsg.includes("BEDROCK_GATEWAY_URL") ||
         errorMsg.toLowerCase().includes("gateway") ||
         errorMsg.toLowerCase().includes("auth")
@@ -888,13 +1202,24 @@ function App() {
         setStatus("Authentication required");
       } else {
         setStatus(`Error: ${err}`);
+        // Offer a fix-and-reparse if the model's output was received
+        invoke("get_raw_response", { jobId })
+          .then(() => setFailedParseJobId(jobId))
+          .catch(() => setFailedParseJobId(null));
       }
     } finally {
       setIsGenerating(false);
+      setActiveJobId(null);
+      provisionalEditsRef.current = {};
+      setProvisionalQuestions([]);
     }
   };
 
-  const handleRegenerate = async (index: number, instructions?: string) => {
+  const handleRegenerate = async (
+    index: number,
+    instructions?: string,
+    mode: RegenerateMode = "full"
+  ) => {
     if (isGenerating || isRegeneratingAll) {
       return;
     }
@@ -906,11 +1231,15 @@ function App() {
     latestStreamingTextRef.current = "";
     setStreamingComplete(false);
     setShowPreview(true);
+    const jobId = crypto.randomUUID();
+    setActiveJobId(jobId);
 
     try {
       const newQuestion = await invoke<Question>("regenerate_question", {
         index,
         instructions: instructions || null,
+        mode,
+        jobId,
       });
       setQuestions((prev) => {
         const updated = [...prev];
@@ -931,9 +1260,236 @@ function App() {
       setStatus("Question regenerated");
     } catch (err) {
       console.error("Regeneration failed:", err);
-      setStatus(`Error: ${err}`);
+      setStatus(String(err).includes("cancelled") ? "Regeneration cancelled" : `Error: ${err}`);
     } finally {
       setRegeneratingQuestionId(null);
+      setActiveJobId(null);
+    }
+  };
+
+  const handleOpenTemplateVariants = async () => {
+    try {
+      const templates = await invoke<ParametricTemplateInfo[]>("list_parametric_templates", {
+        subject: selectedSubject,
+      });
+      setParametricTemplates(templates);
+    } catch (err) {
+      console.error("Failed to list parametric templates:", err);
+      setParametricTemplates([]);
+    }
+    setTemplateVariantsOpen(true);
+  };
+
+  const handleGenerateFromTemplate = async (selection: TemplateVariantsSelection) => {
+    if (isGenerating || isRegeneratingAll) return;
+
+    setIsGenerating(true);
+    setStatus("Filling in template...");
+    const previousQuestionCount = questions.length;
+    try {
+      const request: ParametricRequest = {
+        subject: selectedSubject,
+        template_id: selection.templateId ?? null,
+        path: selection.path ?? null,
+        count: selection.count,
+        seed: selection.seed ?? null,
+        append: true,
+      };
+      const allQuestions = await invoke<Question[]>("generate_from_template", { request });
+      setQuestions(allQuestions);
+      if (documentMode === "blank") {
+        setDocumentMode("new");
+      }
+      setTemplateVariantsOpen(false);
+      const added = allQuestions.slice(previousQuestionCount);
+      const seed = added[0]?.provenance?.seed;
+      setStatus(
+        `Added ${added.length} template variants${seed !== undefined && seed !== null ? ` (seed ${seed})` : ""} (${allQuestions.length} total)`
+      );
+    } catch (err) {
+      console.error("Template generation failed:", err);
+      setStatus(`Template error: ${err}`);
+    } finally {
+      setIsGenerating(false);
+    }
+  };
+
+  const handleGenerateFromBlueprint = async (blueprint: Blueprint) => {
+    if (isGenerating || isRegeneratingAll) return;
+
+    if (!isAuthenticated) {
+      setLoginModalOpen(true);
+      setStatus("Authentication required");
+      return;
+    }
+
+    const requested = blueprint.rows.reduce((sum, row) => sum + row.count, 0);
+    setBlueprintOpen(false);
+    setIsGenerating(true);
+    const previousQuestionCount = questions.length;
+    setStreamingText("");
+    latestStreamingTextRef.current = "";
+    batchTextsRef.current = {};
+    setStreamingComplete(false);
+    setShowPreview(true);
+    setRegeneratingQuestionId(null);
+    setStatus(`Generating ${blueprint.rows.length} blueprint rows...`);
+    const jobId = crypto.randomUUID();
+    activeJobIdRef.current = jobId;
+    setActiveJobId(jobId);
+    provisionalBaseRef.current = previousQuestionCount;
+    provisionalEditsRef.current = {};
+    setProvisionalQuestions([]);
+    setFailedParseJobId(null);
+
+    try {
+      const run = await invoke<BlueprintRun>("generate_from_blueprint", {
+        blueprint,
+        append: true,
+        jobId,
+      });
+      let allQuestions = run.questions;
+
+      // Carry over edits made while the question was still streaming
+      for (const [position, edited] of Object.entries(provisionalEditsRef.current)) {
+        const targetIndex = previousQuestionCount + Number(position);
+        if (targetIndex >= allQuestions.length) continue;
+        const question = { ...edited, id: allQuestions[targetIndex].id };
+        try {
+          await invoke("update_question", { index: targetIndex, question });
+          allQuestions = allQuestions.map((q, i) => (i === targetIndex ? question : q));
+        } catch (err) {
+          console.error("Failed to apply edit made during generation:", err);
+        }
+      }
+      setQuestions(allQuestions);
+      if (documentMode === "blank") {
+        setDocumentMode("new");
+      }
+
+      const addedCount = Math.max(allQuestions.length - previousQuestionCount, 0);
+      const failedCount = Math.max(requested - addedCount, 0);
+      setStatus(
+        failedCount > 0
+          ? `Generated ${addedCount} of ${requested} blueprint questions, ${failedCount} failed (${allQuestions.length} total)`
+          : `Added ${addedCount} blueprint questions (${allQuestions.length} total)`
+      );
+      if (run.failed.length > 0 || run.failed_batches.length > 0) {
+        const detailLines = [
+          ...run.failed.map((f) => `Row ${f.row}: ${f.error}`),
+          ...run.failed_batches.map((f) => `Batch ${f.batch + 1}: ${f.error}`),
+        ];
+        setAlertMessage(`Some blueprint rows or batches produced no questions.\n\n${detailLines.join("\n")}`);
+        setAlertOpen(true);
+      }
+    } catch (err) {
+      console.error("Blueprint generation failed:", err);
+      const errorMsg = String(err);
+      if (errorMsg.includes("cancelled")) {
+        setStatus("Generation cancelled");
+      } else if (
+        errorMsg.includes("BEDROCK_GATEWAY_URL") ||
+        errorMsg.toLowerCase().includes("gateway") ||
+        errorMsg.toLowerCase().includes("auth")
+      ) {
+        setLoginModalOpen(true);
+        setStatus("Authentication required");
+      } else {
+        setStatus(`Error: ${err}`);
+      }
+    } finally {
+      setIsGenerating(false);
+      setActiveJobId(null);
+      provisionalEditsRef.current = {};
+      setProvisionalQuestions([]);
+    }
+  };
+
+  const handleAssembleFromBank = async (request: AssemblyRequest) => {
+    if (isGenerating || isRegeneratingAll) return;
+
+    setIsGenerating(true);
+    setStatus("Assembling test from the question bank...");
+    const previousQuestionCount = questions.length;
+    try {
+      const result = await invoke<AssemblyResult>("assemble_from_bank", { request });
+      setQuestions(result.questions);
+      if (documentMode === "blank") {
+        setDocumentMode("new");
+      }
+      setAssemblyUnmet(result.unmet);
+      if (result.unmet.length === 0) {
+        setAssembleOpen(false);
+      }
+      const added = result.questions.length - previousQuestionCount;
+      setStatus(
+        `Added ${added} of ${request.count} bank questions (${result.questions.length} total)`
+      );
+    } catch (err) {
+      console.error("Test assembly failed:", err);
+      setStatus(`Assembly error: ${err}`);
+    } finally {
+      setIsGenerating(false);
+    }
+  };
+
+  const handleAccommodate = async (options: AccommodationOptions, onlyMissing: boolean) => {
+    if (isGenerating || isRegeneratingAll) return;
+
+    if (!isAuthenticated) {
+      setLoginModalOpen(true);
+      setStatus("Authentication required");
+      return;
+    }
+
+    const indices = questions.flatMap((q, i) => (onlyMissing && q.accommodation ? [] : [i]));
+    if (indices.length === 0) return;
+    setAccommodateOpen(false);
+    setIsGenerating(true);
+    setStatus(`Writing ${indices.length} accommodated versions...`);
+    const jobId = crypto.randomUUID();
+    activeJobIdRef.current = jobId;
+    setActiveJobId(jobId);
+
+    try {
+      const run = await invoke<AccommodationRun>("accommodate_questions", {
+        indices,
+        options,
+        jobId,
+      });
+      setQuestions(run.questions);
+      const written = indices.length - run.failed.length;
+      if (run.failed.length > 0) {
+        const detailLines = run.failed.map((f) => `Question ${f.index + 1}: ${f.error}`);
+        setAlertMessage(
+          `Some accommodated versions could not be written.\n\n${detailLines.join("\n")}`
+        );
+        setAlertOpen(true);
+      }
+      setStatus(
+        run.failed.length > 0
+          ? `Wrote ${written} of ${indices.length} accommodated versions, ${run.failed.length} failed`
+          : `Wrote ${written} accommodated versions`
+      );
+    } catch (err) {
+      console.error("Accommodation failed:", err);
+      const errorMsg = String(err);
+      setStatus(errorMsg.includes("cancelled") ? "Accommodation cancelled" : `Error: ${err}`);
+    } finally {
+      setIsGenerating(false);
+      setActiveJobId(null);
+    }
+  };
+
+  const handleRemoveAccommodation = async (index: number) => {
+    const question = { ...questions[index], accommodation: undefined };
+    try {
+      await invoke("update_question", { index, question });
+      setQuestions((prev) => prev.map((q, i) => (i === index ? question : q)));
+      setStatus(`Removed the accommodated version of question ${index + 1}`);
+    } catch (err) {
+      console.error("Failed to remove accommodated version:", err);
+      setStatus(`Error: ${err}`);
     }
   };
 
@@ -953,6 +1509,23 @@ function App() {
       return;
     }
 
+    try {
+      const estimate = await invoke<QuotaEstimate>("estimate_regenerate_all_usage");
+      if (estimate.likely_to_exceed) {
+        const proceed = await confirm(
+          `Regenerating ${estimate.requests} questions needs about ${estimate.estimated_tokens.toLocaleString()} tokens, ` +
+            `but only ${(estimate.remaining_tokens ?? 0).toLocaleString()} remain. Some questions may fail. Continue?`,
+          { title: "Token quota", type: "warning" }
+        );
+        if (!proceed) {
+          setStatus("Regenerate all cancelled");
+          return;
+        }
+      }
+    } catch (err) {
+      console.error("Failed to estimate usage:", err);
+    }
+
     setIsRegeneratingAll(true);
     const total = questions.length;
     const previousQuestionIds = questions.map((question) => question.id);
@@ -961,20 +1534,26 @@ function App() {
     latestStreamingTextRef.current = "";
     setStreamingComplete(true);
     setShowPreview(false);
+    setBatchStreams({});
     setStatus(`Regenerating ${total} questions...`);
+    const jobId = crypto.randomUUID();
+    setActiveJobId(jobId);
+    regenerateAllQuotaRef.current = null;
 
     try {
       const results = await invoke<RegenerateAllQuestionResult[]>(
         "regenerate_all_questions_parallel",
         {
           maxConcurrency: 3,
+          jobId,
         }
       );
 
       const succeededIndexes = results
         .filter((item): item is RegenerateAllQuestionResult & { question: Question } => Boolean(item.question))
         .map((item) => item.index);
-      const failedResults = results.filter((item) => !item.question);
+      const failedResults = results.filter((item) => !item.question && !item.cancelled);
+      const cancelledCount = results.filter((item) => item.cancelled).length;
 
       setQuestions((prev) => {
         const updated = [...prev];
@@ -1000,7 +1579,9 @@ function App() {
       const successCount = succeededIndexes.length;
       const failureCount = failedResults.length;
 
-      if (failureCount === 0) {
+      if (failureCount === 0 && cancelledCount > 0) {
+        setStatus(`Cancelled: regenerated ${successCount}/${total} questions`);
+      } else if (failureCount === 0) {
         setStatus(`Regenerated all ${successCount} questions`);
       } else {
         console.error("Regenerate all partial failures:", failedResults);
@@ -1019,7 +1600,12 @@ function App() {
         const detailLines = failedResults.map(
           (item) => `Q${item.index + 1}: ${(item.error || "Unknown error").trim()}`
         );
-        setAlertMessage(`Some questions failed to regenerate:\n\n${detailLines.join("\n")}`);
+        const quota = regenerateAllQuotaRef.current;
+        const quotaNote = quota
+          ? `This run needed about ${quota.estimated_tokens.toLocaleString()} tokens but only ` +
+            `${(quota.remaining_tokens ?? 0).toLocaleString()} remained, so the token quota may have run out.\n\n`
+          : "";
+        setAlertMessage(`${quotaNote}Some questions failed to regenerate:\n\n${detailLines.join("\n")}`);
         setAlertOpen(true);
       }
     } catch (err) {
@@ -1028,6 +1614,136 @@ function App() {
     } finally {
       setRegeneratingQuestionId(null);
       setIsRegeneratingAll(false);
+      setActiveJobId(null);
+      setBatchStreams({});
+    }
+  };
+
+  const handleCancelGeneration = async () => {
+    if (!activeJobId) return;
+    setStatus("Cancelling...");
+    try {
+      await invoke<boolean>("cancel_generation", { jobId: activeJobId });
+    } catch (err) {
+      console.error("Cancel failed:", err);
+    }
+  };
+
+  const handleRawResponseParsed = async (parsed: Question[]) => {
+    const merged = [
+      ...questions,
+      ...parsed.map((question, i) => ({ ...question, id: `q${questions.length + i + 1}` })),
+    ];
+    try {
+      await invoke("set_questions", { newQuestions: merged });
+      setQuestions(merged);
+      if (documentMode === "blank") {
+        setDocumentMode("new");
+      }
+      setRawFixJobId(null);
+      setFailedParseJobId(null);
+      setStatus(`Added ${parsed.length} questions from edited output (${merged.length} total)`);
+    } catch (err) {
+      console.error("Failed to add re-parsed questions:", err);
+      setStatus(`Error: ${err}`);
+    }
+  };
+
+  const handlePreviewGenerationPrompt = async () => {
+    const request: GenerationRequest = {
+      subject: selectedSubject,
+      topics: selectedTopics,
+      difficulty,
+      count: questionCount,
+      notes: notes || null,
+      append: true,
+      sources: sourcePaths,
+    };
+    try {
+      const preview = await invoke<PromptPreview>("preview_generation_prompt", { request });
+      setPromptPreview({ title: "Generation prompt", preview });
+    } catch (err) {
+      setStatus(`Prompt preview failed: ${err}`);
+    }
+  };
+
+  const handlePreviewRegenerationPrompt = async (
+    index: number,
+    instructions?: string,
+    mode: RegenerateMode = "full"
+  ) => {
+    try {
+      const preview = await invoke<PromptPreview>("preview_regeneration_prompt", {
+        index,
+        instructions: instructions || null,
+        mode,
+      });
+      setPromptPreview({ title: `Regeneration prompt for question ${index + 1}`, preview });
+    } catch (err) {
+      setStatus(`Prompt preview failed: ${err}`);
+    }
+  };
+
+  const handleVerify = async (index: number) => {
+    if (!isAuthenticated) {
+      setLoginModalOpen(true);
+      setStatus("Authentication required");
+      return;
+    }
+
+    const questionId = questions[index]?.id ?? null;
+    setVerifyingQuestionId(questionId);
+    setStatus(`Checking the answer key of question ${index + 1}...`);
+
+    try {
+      const checked = await invoke<Question>("verify_question_answer", { index, jobId: crypto.randomUUID() });
+      setQuestions((prev) => {
+        const updated = [...prev];
+        updated[index] = checked;
+        return updated;
+      });
+      const verdict = checked.verification?.verdict;
+      setStatus(
+        verdict === "agree"
+          ? `Question ${index + 1}: the verifier agrees with the answer key`
+          : verdict === "disagree"
+          ? `Question ${index + 1}: the verifier disputes the answer key`
+          : `Question ${index + 1}: the answer check was inconclusive`
+      );
+    } catch (err) {
+      console.error("Answer check failed:", err);
+      setStatus(`Answer check failed: ${err}`);
+    } finally {
+      setVerifyingQuestionId(null);
+    }
+  };
+
+  const handleRunAnswerChecks = async () => {
+    if (activeTab !== "generate") {
+      setStatus("Use Run checks in the Bank Editor to check bank entries");
+      return;
+    }
+    if (questions.length === 0) {
+      setStatus("No questions to check");
+      return;
+    }
+
+    setStatus("Running answer checks...");
+    try {
+      const checked = await invoke<Question[]>("check_question_code");
+      setQuestions(checked);
+      const ran = checked.filter(
+        (q) => (q.code_check && q.code_check.status !== "skipped") || q.math_check
+      ).length;
+      const flagged = checked.filter(isAnswerCheckFlagged).length;
+      setStatus(
+        ran === 0
+          ? "No runnable Java code or checkable math found"
+          : `Checked ${ran} question(s); ${flagged} flagged`
+      );
+    } catch (err) {
+      console.error("Answer checks failed:", err);
+      setStatus(`Answer checks failed: ${err}`);
     }
   };
 
@@ -1037,13 +1753,43 @@ function App() {
 
   const handleSaveEdit = async (question: Question) => {
     if (editingIndex === null) return;
-    const previousId = questions[editingIndex]?.id;
+
+    // Question still streaming: keep the edit locally until the generation finishes
+    if (editingIndex >= questions.length) {
+      const position = editingIndex - questions.length;
+      provisionalEditsRef.current[position] = question;
+      setProvisionalQuestions((prev) => prev.map((q, i) => (i === position ? question : q)));
+      setEditingIndex(null);
+      setStatus("Edit saved; it will be applied when generation finishes");
+      return;
+    }
+
+    const previous = questions[editingIndex];
+    const previousId = previous?.id;
+    // A changed stem, code or key invalidates the last answer, code and math checks and
+    // the accommodated version (update_question drops them too)
+    const saved =
+      (previous?.verification ||
+        previous?.code_check ||
+        previous?.math_check ||
+        previous?.accommodation) &&
+      (previous.text !== question.text ||
+        (previous.code ?? "") !== (question.code ?? "") ||
+        JSON.stringify(previous.answers) !== JSON.stringify(question.answers))
+        ? {
+            ...question,
+            verification: undefined,
+            code_check: undefined,
+            math_check: undefined,
+            accommodation: undefined,
+          }
+        : question;
 
     try {
-      await invoke("update_question", { index: editingIndex, question });
+      await invoke("update_question", { index: editingIndex, question: saved });
       setQuestions((prev) => {
         const updated = [...prev];
-        updated[editingIndex] = question;
+        updated[editingIndex] = saved;
         return updated;
       });
       if (previousId && previousId !== question.id) {
@@ -1096,6 +1842,9 @@ function App() {
     }
   };
 
+  // Accommodated versions are exported as a second document next to the standard one
+  const hasAccommodations = questions.some((q) => q.accommodation);
+
   const handleExportMd = async () => {
     const filePath = await save({
       defaultPath: "questions.md",
@@ -1109,13 +1858,24 @@ function App() {
       const options = {
         include_explanations: markdownPreset === "teacher_markdown",
         include_answer_key: markdownPreset === "teacher_markdown",
+        include_provenance: markdownPreset === "teacher_markdown",
       };
       const content = await invoke<string>("export_to_md", {
         title: "Quiz",
         options,
       });
       await writeTextFile(filePath, content);
-      setStatus(`Exported to ${filePath} • Preset: ${markdownPresetLabel}`);
+      if (hasAccommodations) {
+        const pairedPath = accommodatedPath(filePath);
+        const accommodated = await invoke<string>("export_to_md", {
+          title: "Quiz (Accommodated)",
+          options: { ...options, accommodated: true },
+        });
+        await writeTextFile(pairedPath, accommodated);
+        setStatus(`Exported to ${filePath} and ${pairedPath} • Preset: ${markdownPresetLabel}`);
+      } else {
+        setStatus(`Exported to ${filePath} • Preset: ${markdownPresetLabel}`);
+      }
     } catch (err) {
       console.error("Export failed:", err);
       setStatus(`Export error: ${err}`);
@@ -1142,9 +1902,18 @@ function App() {
         options,
       });
       await writeBinaryFile(filePath, new Uint8Array(data));
-      setStatus(`Exported to ${filePath} • Preset: ${qtiPresetLabel}`);
+      const pairedPath = hasAccommodations ? accommodatedPath(filePath) : null;
+      if (pairedPath) {
+        const accommodated = await invoke<number[]>("export_to_qti", {
+          title: "Quiz (Accommodated)",
+          options: { ...options, accommodated: true },
+        });
+        await writeBinaryFile(pairedPath, new Uint8Array(accommodated));
+      }
+      const savedFiles = pairedPath ? `${filePath} and ${pairedPath}` : filePath;
+      setStatus(`Exported to ${savedFiles} • Preset: ${qtiPresetLabel}`);
       setAlertMessage(
-        `File saved to ${filePath}.\nYou can import this file into Schoology as an .imscc file.`
+        `File saved to ${savedFiles}.\nYou can import this file into Schoology as an .imscc file.`
       );
       setAlertOpen(true);
     } catch (err) {
@@ -1212,9 +1981,21 @@ function App() {
           options,
         });
         await writeBinaryFile(filePath, new Uint8Array(data));
-        setStatus(
-          `Exported to ${filePath} • Preset: ${wordGeneratePresetLabel} • Versions: ${normalizedVersionCount}`
-        );
+        if (hasAccommodations) {
+          const pairedPath = accommodatedPath(filePath);
+          const accommodated = await invoke<number[]>("export_to_docx", {
+            title: "Quiz (Accommodated)",
+            options: { ...options, accommodated: true },
+          });
+          await writeBinaryFile(pairedPath, new Uint8Array(accommodated));
+          setStatus(
+            `Exported to ${filePath} and ${pairedPath} • Preset: ${wordGeneratePresetLabel} • Versions: ${normalizedVersionCount}`
+          );
+        } else {
+          setStatus(
+            `Exported to ${filePath} • Preset: ${wordGeneratePresetLabel} • Versions: ${normalizedVersionCount}`
+          );
+        }
       }
     } catch (err) {
       console.error("Export failed:", err);
@@ -1249,6 +2030,35 @@ function App() {
     setExportOptionsOpen(true);
   };
 
+  // Code runs are cached by source and math checks are local, so re-checking before
+  // every export is cheap
+  const confirmAnswerChecksBeforeExport = async (kind: ExportKind): Promise<boolean> => {
+    const exportingBank = kind === "word" && activeTab === "bank";
+    if (exportingBank && !selectedSubject) return true;
+
+    setStatus("Checking answer keys before export...");
+    let flagged: string[];
+    try {
+      if (exportingBank) {
+        const checks = await invoke<BankCodeCheck[]>("check_bank_code", { subject: selectedSubject });
+        flagged = checks.filter(isAnswerCheckFlagged).map((item) => item.id);
+      } else {
+        const checked = await invoke<Question[]>("check_question_code");
+        setQuestions(checked);
+        flagged = checked.flatMap((q, i) => (isAnswerCheckFlagged(q) ? [`question ${i + 1}`] : []));
+      }
+    } catch (err) {
+      console.error("Answer checks failed:", err);
+      return true;
+    }
+
+    if (flagged.length === 0) return true;
+    return confirm(
+      `Running the code or working the math contradicts the marked answer, or two choices are equivalent, for ${flagged.join(", ")}. Export anyway?`,
+      { title: "Answer check", type: "warning" }
+    );
+  };
+
   const confirmExportFromOptions = async () => {
     if (!pendingExportKind) return;
 
@@ -1256,6 +2066,11 @@ function App() {
     setExportOptionsOpen(false);
     setPendingExportKind(null);
 
+    if (!(await confirmAnswerChecksBeforeExport(kind))) {
+      setStatus("Export cancelled");
+      return;
+    }
+
     if (kind === "md") {
       await handleExportMd();
       return;
@@ -1496,11 +2311,57 @@ function App() {
 
   return (
     <div className="flex flex-col h-screen bg-background">
+      <RawResponseModal
+        jobId={rawFixJobId}
+        onClose={() => setRawFixJobId(null)}
+        onParsed={handleRawResponseParsed}
+      />
+      <LlmHistoryModal open={llmHistoryOpen} onClose={() => setLlmHistoryOpen(false)} />
+      <UsageModal open={usageOpen} onClose={() => setUsageOpen(false)} />
+      <PromptPreviewModal
+        title={promptPreview?.title ?? ""}
+        preview={promptPreview?.preview ?? null}
+        onClose={() => setPromptPreview(null)}
+      />
       <AlertModal
         open={alertOpen}
         message={alertMessage}
         onClose={() => setAlertOpen(false)}
       />
+      <TemplateVariantsModal
+        open={templateVariantsOpen}
+        subjectName={subjects.find((s) => s.id === selectedSubject)?.name ?? selectedSubject}
+        templates={parametricTemplates}
+        defaultCount={questionCount}
+        isGenerating={isGenerating}
+        onGenerate={(selection) => void handleGenerateFromTemplate(selection)}
+        onClose={() => setTemplateVariantsOpen(false)}
+      />
+      <BlueprintModal
+        open={blueprintOpen}
+        subject={selectedSubject}
+        topics={topics}
+        isGenerating={isGenerating}
+        onGenerate={(blueprint) => void handleGenerateFromBlueprint(blueprint)}
+        onClose={() => setBlueprintOpen(false)}
+      />
+      <AssembleModal
+        open={assembleOpen}
+        subject={selectedSubject}
+        topics={topics}
+        isAssembling={isGenerating}
+        unmet={assemblyUnmet}
+        onAssemble={(request) => void handleAssembleFromBank(request)}
+        onClose={() => setAssembleOpen(false)}
+      />
+      <AccommodateModal
+        open={accommodateOpen}
+        questionCount={questions.length}
+        accommodatedCount={questions.filter((q) => q.accommodation).length}
+        isAccommodating={isGenerating}
+        onAccommodate={(options, onlyMissing) => void handleAccommodate(options, onlyMissing)}
+        onClose={() => setAccommodateOpen(false)}
+      />
       <OpenRecentModal
         open={openRecentOpen}
         recentPaths={recentDocuments}
@@ -1550,8 +2411,11 @@ function App() {
             onQuestionCountChange={setQuestionCount}
             notes={notes}
             onNotesChange={setNotes}
+            sourcePaths={sourcePaths}
+            onSourcePathsChange={setSourcePaths}
             existingCount={questions.length}
             onGenerate={handleGenerate}
+            onPreviewPrompt={handlePreviewGenerationPrompt}
             isGenerating={isGenerating}
             collapsed={sidebarCollapsed}
             onToggleCollapsed={() => setSidebarCollapsed((prev) => !prev)}
@@ -1603,14 +2467,20 @@ function App() {
                   rawTextByQuestionId={rawTextByQuestionId}
                   regeneratingQuestionId={regeneratingQuestionId}
                   isRegeneratingAll={isRegeneratingAll}
+                  batchStreamsByIndex={batchStreams}
                   regenerationStreamingText={streamingText}
                   regenerationStreamingComplete={streamingComplete}
                   showStreamingCard={showStreamingCard}
+                  provisionalQuestions={provisionalQuestions}
                   streamingText={streamingText}
                   streamingComplete={streamingComplete}
                   showRawStream={showPreview}
                   onToggleRawStream={() => setShowPreview((prev) => !prev)}
                   onRegenerate={handleRegenerate}
+                  onPreviewRegenerationPrompt={handlePreviewRegenerationPrompt}
+                  verifyingQuestionId={verifyingQuestionId}
+                  onVerify={handleVerify}
+                  onRemoveAccommodation={handleRemoveAccommodation}
                   onEdit={handleEdit}
                   onDelete={handleDelete}
                 />
@@ -1627,6 +2497,25 @@ function App() {
             <span className="flex items-center gap-2 min-w-0">
               {(isGenerating || isRegeneratingAll) && <Loader2 className="w-4 h-4 animate-spin" />}
               <span className="truncate">{status}</span>
+              {failedParseJobId && !activeJobId && (
+                <button
+                  onClick={() => setRawFixJobId(failedParseJobId)}
+                  className="inline-flex items-center gap-1 px-2 py-0.5 rounded border text-xs font-medium hover:bg-secondary"
+                  title="Edit the raw model output and parse it again without a new request"
+                >
+                  Fix raw output
+                </button>
+              )}
+              {activeJobId && (
+                <button
+                  onClick={handleCancelGeneration}
+                  className="inline-flex items-center gap-1 px-2 py-0.5 rounded border text-xs font-medium hover:bg-secondary"
+                  title="Stop the running generation and keep completed questions"
+                >
+                  <X className="w-3 h-3" />
+                  Cancel
+                </button>
+              )}
             </span>
             <div className="flex flex-wrap items-center justify-end gap-2">
               {isDevMode && (
@@ -1656,9 +2545,15 @@ function App() {
       </div>
 
       {/* Edit Modal */}
-      {editingIndex !== null && (
+      {editingIndex !== null &&
+        (editingIndex < questions.length ||
+          provisionalQuestions[editingIndex - questions.length]) && (
         <EditModal
-          question={questions[editingIndex]}
+          question={
+            editingIndex < questions.length
+              ? questions[editingIndex]
+              : provisionalQuestions[editingIndex - questions.length]
+          }
           onSave={handleSaveEdit}
           onClose={() => setEditingIndex(null)}
         />
@@ -0,0 +1,125 @@
+import { useState } from "react";
+import { AccommodationOptions } from "../types";
+
+interface AccommodateModalProps {
+  open: boolean;
+  questionCount: number;
+  accommodatedCount: number; // questions that already have an accommodated version
+  isAccommodating: boolean;
+  onAccommodate: (options: AccommodationOptions, onlyMissing: boolean) => void;
+  onClose: () => void;
+}
+
+const OPTION_LABELS: { key: keyof AccommodationOptions; label: string; hint: string }[] = [
+  {
+    key: "simplify_language",
+    label: "Simplify language",
+    hint: "Short sentences and common words, aiming for grade 6 or lower",
+  },
+  {
+    key: "three_choices",
+    label: "Three choices",
+    hint: "Keep the correct answer and the two most telling distractors",
+  },
+  {
+    key: "gloss_vocabulary",
+    label: "Gloss vocabulary",
+    hint: "List hard words with short definitions under the question",
+  },
+  {
+    key: "larger_spacing",
+    label: "Larger spacing",
+    hint: "More space between choices and questions in the exported document",
+  },
+];
+
+export default function AccommodateModal({
+  open,
+  questionCount,
+  accommodatedCount,
+  isAccommodating,
+  onAccommodate,
+  onClose,
+}: AccommodateModalProps) {
+  const [options, setOptions] = useState<AccommodationOptions>({
+    simplify_language: true,
+    three_choices: true,
+    gloss_vocabulary: true,
+    larger_spacing: true,
+  });
+  const [onlyMissing, setOnlyMissing] = useState(true);
+
+  if (!open) return null;
+
+  const missingCount = questionCount - accommodatedCount;
+  const targetCount = onlyMissing ? missingCount : questionCount;
+  const canRun = targetCount > 0 && !isAccommodating;
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 p-4">
+      <div className="w-full max-w-lg rounded-lg bg-white shadow-xl border">
+        <div className="px-5 py-4 border-b flex items-center justify-between">
+          <h2 className="text-lg font-semibold text-foreground">Accommodated Versions</h2>
+          <button
+            onClick={onClose}
+            className="text-sm text-muted-foreground hover:text-foreground"
+          >
+            Close
+          </button>
+        </div>
+
+        <div className="p-5 space-y-4">
+          <p className="text-sm text-muted-foreground">
+            Write a linked accommodated version of each question for IEP/504 and ELL students.
+            Each keeps the same concept and answer key, and shows its reading level before and
+            after.
+          </p>
+
+          <div className="space-y-2">
+            {OPTION_LABELS.map(({ key, label, hint }) => (
+              <label key={key} className="flex items-start gap-2 text-sm text-foreground">
+                <input
+                  type="checkbox"
+                  checked={options[key]}
+                  onChange={(e) => setOptions((prev) => ({ ...prev, [key]: e.target.checked }))}
+                  className="mt-0.5"
+                />
+                <span>
+                  {label}
+                  <span className="block text-xs text-muted-foreground">{hint}</span>
+                </span>
+              </label>
+            ))}
+          </div>
+
+          <div className="space-y-1 text-sm text-foreground">
+            <label className="flex items-center gap-2">
+              <input type="radio" checked={onlyMissing} onChange={() => setOnlyMissing(true)} />
+              Questions without one ({missingCount})
+            </label>
+            <label className="flex items-center gap-2">
+              <input type="radio" checked={!onlyMissing} onChange={() => setOnlyMissing(false)} />
+              All questions ({questionCount}), replacing existing versions
+            </label>
+          </div>
+        </div>
+
+        <div className="px-5 py-3 border-t flex items-center justify-end gap-2">
+          <button
+            onClick={onClose}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary"
+          >
+            Cancel
+          </button>
+          <button
+            onClick={() => canRun && onAccommodate(options, onlyMissing)}
+            disabled={!canRun}
+            className="px-3 py-1.5 text-sm rounded-md bg-primary text-primary-foreground hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {isAccommodating ? "Writing..." : `Write ${targetCount}`}
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,249 @@
+import { useEffect, useState } from "react";
+import { open as openDialog } from "@tauri-apps/api/dialog";
+import { X } from "lucide-react";
+import { AssemblyRequest, DifficultyMix, TopicInfo } from "../types";
+
+interface AssembleModalProps {
+  open: boolean;
+  subject: string;
+  topics: TopicInfo[];
+  isAssembling: boolean;
+  unmet: string[]; // constraints the last assembly could not meet
+  onAssemble: (request: AssemblyRequest) => void;
+  onClose: () => void;
+}
+
+const DIFFICULTY_LEVELS: (keyof DifficultyMix)[] = ["easy", "medium", "hard"];
+
+// Empty means "no constraint"
+function optionalCount(value: string): number | null {
+  if (value.trim() === "") return null;
+  return Math.max(0, Math.floor(Number(value) || 0));
+}
+
+function fileName(path: string): string {
+  return path.split(/[\\/]/).pop() || path;
+}
+
+export default function AssembleModal({
+  open,
+  subject,
+  topics,
+  isAssembling,
+  unmet,
+  onAssemble,
+  onClose,
+}: AssembleModalProps) {
+  const [count, setCount] = useState(20);
+  const [quotas, setQuotas] = useState<Record<string, number>>({});
+  const [difficulty, setDifficulty] = useState<DifficultyMix>({});
+  const [maxCodeItems, setMaxCodeItems] = useState<number | null>(null);
+  const [excludeText, setExcludeText] = useState("");
+  const [excludeFiles, setExcludeFiles] = useState<string[]>([]);
+
+  useEffect(() => {
+    setQuotas({});
+  }, [subject]);
+
+  if (!open) return null;
+
+  const targeted = DIFFICULTY_LEVELS.reduce((sum, level) => sum + (difficulty[level] ?? 0), 0);
+  const fullMix = DIFFICULTY_LEVELS.every((level) => difficulty[level] != null);
+  const mixError =
+    targeted > count || (fullMix && targeted !== count)
+      ? `The difficulty mix adds up to ${targeted} questions but the test has ${count}`
+      : null;
+  const canAssemble = count >= 1 && !mixError && !isAssembling;
+
+  const request: AssemblyRequest = {
+    subject,
+    count,
+    topics: Object.entries(quotas).map(([topic, min]) => ({ topic, min })),
+    difficulty,
+    max_code_items: maxCodeItems,
+    exclude: excludeText.split(/[\s,]+/).filter(Boolean),
+    exclude_files: excludeFiles,
+    append: true,
+  };
+
+  const toggleTopic = (id: string) => {
+    setQuotas((prev) => {
+      const next = { ...prev };
+      if (id in next) {
+        delete next[id];
+      } else {
+        next[id] = 0;
+      }
+      return next;
+    });
+  };
+
+  const handleAddExcludeFiles = async () => {
+    const selection = await openDialog({
+      multiple: true,
+      filters: [{ name: "Id list", extensions: ["txt", "csv"] }],
+    });
+    const paths = Array.isArray(selection) ? selection : selection ? [selection] : [];
+    setExcludeFiles((prev) => [...prev, ...paths.filter((path) => !prev.includes(path))]);
+  };
+
+  const topicRow = (id: string, label: string, indent: boolean) => (
+    <div key={id} className={`flex items-center gap-2 text-sm ${indent ? "pl-5" : ""}`}>
+      <input type="checkbox" checked={id in quotas} onChange={() => toggleTopic(id)} />
+      <span className="flex-1 text-foreground">{label}</span>
+      <label className="text-xs text-muted-foreground">
+        at least
+        <input
+          type="number"
+          min={0}
+          value={quotas[id] ?? 0}
+          disabled={!(id in quotas)}
+          onChange={(e) =>
+            setQuotas((prev) => ({ ...prev, [id]: optionalCount(e.target.value) ?? 0 }))
+          }
+          className="ml-2 w-16 px-2 py-1 border rounded-md text-sm text-foreground disabled:opacity-40"
+        />
+      </label>
+    </div>
+  );
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 p-4">
+      <div className="w-full max-w-3xl rounded-lg bg-white shadow-xl border">
+        <div className="px-5 py-4 border-b flex items-center justify-between">
+          <h2 className="text-lg font-semibold text-foreground">Assemble Test from Bank</h2>
+          <button
+            onClick={onClose}
+            className="text-sm text-muted-foreground hover:text-foreground"
+          >
+            Close
+          </button>
+        </div>
+
+        <div className="p-5 space-y-4 max-h-[65vh] overflow-auto">
+          <p className="text-sm text-muted-foreground">
+            Pick existing {subject} bank entries instead of generating new questions. Leave a
+            field empty for no constraint; anything the bank cannot meet is explained below.
+          </p>
+
+          <div className="grid grid-cols-5 gap-3">
+            <label className="text-xs font-medium text-muted-foreground">
+              Questions
+              <input
+                type="number"
+                min={1}
+                value={count}
+                onChange={(e) => setCount(Math.max(1, Math.floor(Number(e.target.value) || 1)))}
+                className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground"
+              />
+            </label>
+            {DIFFICULTY_LEVELS.map((level) => (
+              <label key={level} className="text-xs font-medium text-muted-foreground capitalize">
+                {level}
+                <input
+                  type="number"
+                  min={0}
+                  value={difficulty[level] ?? ""}
+                  placeholder="any"
+                  onChange={(e) =>
+                    setDifficulty((prev) => ({ ...prev, [level]: optionalCount(e.target.value) }))
+                  }
+                  className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground"
+                />
+              </label>
+            ))}
+            <label className="text-xs font-medium text-muted-foreground">
+              Code-heavy at most
+              <input
+                type="number"
+                min={0}
+                value={maxCodeItems ?? ""}
+                placeholder="no limit"
+                onChange={(e) => setMaxCodeItems(optionalCount(e.target.value))}
+                className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground"
+              />
+            </label>
+          </div>
+          {mixError && <p className="text-sm text-destructive">{mixError}</p>}
+
+          <div>
+            <div className="text-xs font-medium text-muted-foreground mb-1">
+              Topics (none checked uses the whole bank)
+            </div>
+            <div className="rounded-md border p-3 space-y-1.5 max-h-56 overflow-auto">
+              {topics.map((topic) => [
+                topicRow(topic.id, topic.name, false),
+                ...(topic.children ?? []).map((child) => topicRow(child.id, child.name, true)),
+              ])}
+            </div>
+          </div>
+
+          <div>
+            <label className="block text-xs font-medium text-muted-foreground">
+              Leave out (bank ids, e.g. last year's test)
+              <textarea
+                value={excludeText}
+                onChange={(e) => setExcludeText(e.target.value)}
+                placeholder="pt1_q001, pt1_q004"
+                rows={2}
+                className="mt-1 w-full px-3 py-2 border rounded-md text-sm text-foreground font-mono"
+              />
+            </label>
+            <div className="mt-2 flex flex-wrap items-center gap-2">
+              {excludeFiles.map((path) => (
+                <span
+                  key={path}
+                  title={path}
+                  className="inline-flex items-center gap-1 rounded bg-secondary px-2 py-0.5 text-xs"
+                >
+                  {fileName(path)}
+                  <button
+                    onClick={() => setExcludeFiles((prev) => prev.filter((p) => p !== path))}
+                    aria-label={`Remove ${fileName(path)}`}
+                  >
+                    <X className="w-3 h-3" />
+                  </button>
+                </span>
+              ))}
+              <button
+                onClick={() => void handleAddExcludeFiles()}
+                className="text-sm text-primary hover:underline"
+              >
+                Add id list file…
+              </button>
+            </div>
+          </div>
+
+          {unmet.length > 0 && (
+            <div className="rounded-md border border-amber-200 bg-amber-50 p-3 text-sm text-amber-800">
+              <div className="font-medium mb-1">The selection was loaded, but:</div>
+              <ul className="list-disc pl-5 space-y-0.5">
+                {unmet.map((line) => (
+                  <li key={line}>{line}</li>
+                ))}
+              </ul>
+            </div>
+          )}
+        </div>
+
+        <div className="px-5 py-3 border-t flex items-center justify-end gap-2">
+          <button
+            onClick={onClose}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary"
+          >
+            {unmet.length > 0 ? "Done" : "Cancel"}
+          </button>
+          {unmet.length === 0 && (
+            <button
+              onClick={() => canAssemble && onAssemble(request)}
+              disabled={!canAssemble}
+              className="px-3 py-1.5 text-sm rounded-md bg-primary text-primary-foreground hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {isAssembling ? "Assembling..." : "Assemble"}
+            </button>
+          )}
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -1,7 +1,7 @@
 import { useEffect, useMemo, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
-import { QuestionBankEntry, TopicInfo } from "../types";
-import { Loader2, Save, RotateCcw, X, Plus } from "lucide-react";
+import { BankCodeCheck, CodeCheck, MathCheck, QuestionBankEntry, TopicInfo } from "../types";
+import { Loader2, Save, RotateCcw, X, Plus, Play } from "lucide-react";
 import ReactMarkdown, { Components } from "react-markdown";
 import remarkGfm from "remark-gfm";
 import remarkMath from "remark-math";
@@ -75,6 +75,28 @@ function RichMarkdown({
   );
 }
 
+const codeCheckLabels: Record<CodeCheck["status"], string> = {
+  match: "Output matches the marked answer",
+  mismatch: "Output matches a choice not marked correct",
+  no_choice_matches: "Output matches none of the choices",
+  compile_error: "Did not compile",
+  runtime_error: "Threw an exception",
+  timeout: "Timed out",
+  skipped: "Not run",
+};
+
+const mathCheckLabels: Record<MathCheck["status"], string> = {
+  match: "Result matches the marked answer",
+  mismatch: "Result matches a choice not marked correct",
+  no_choice_matches: "Result matches none of the choices",
+  unchecked: "Key not checked",
+};
+
+const isMathFlagged = (check: MathCheck) =>
+  check.status === "mismatch" ||
+  check.status === "no_choice_matches" ||
+  (check.equivalent_choices?.length ?? 0) > 0;
+
 interface BankEditorProps {
   subject: string;
 }
@@ -87,6 +109,9 @@ export default function BankEditor({ subject }: BankEditorProps) {
   const [error, setError] = useState<string | null>(null);
   const [dirty, setDirty] = useState(false);
   const [topicOptions, setTopicOptions] = useState<TopicInfo[]>([]);
+  const [codeChecks, setCodeChecks] = useState<Record<string, CodeCheck>>({});
+  const [mathChecks, setMathChecks] = useState<Record<string, MathCheck>>({});
+  const [checkingCode, setCheckingCode] = useState(false);
 
   const selected = useMemo(
     () => entries.find((e) => e.id === selectedId) || null,
@@ -107,6 +132,8 @@ export default function BankEditor({ subject }: BankEditorProps) {
       setEntries(data);
       setSelectedId(data[0]?.id ?? null);
       setDirty(false);
+      setCodeChecks({});
+      setMathChecks({});
     } catch (e: any) {
       setError(String(e));
     } finally {
@@ -123,11 +150,23 @@ export default function BankEditor({ subject }: BankEditorProps) {
     }
   };
 
+  const clearChecks = (id: string) => {
+    const without = <T,>(prev: Record<string, T>) => {
+      if (!prev[id]) return prev;
+      const next = { ...prev };
+      delete next[id];
+      return next;
+    };
+    setCodeChecks(without);
+    setMathChecks(without);
+  };
+
   const updateEntry = (id: string, patch: Partial<QuestionBankEntry>) => {
     setEntries((prev) =>
       prev.map((e) => (e.id === id ? { ...e, ...patch } : e))
     );
     setDirty(true);
+    if (patch.text !== undefined) clearChecks(id);
   };
 
   const updateOption = (
@@ -147,6 +186,26 @@ export default function BankEditor({ subject }: BankEditorProps) {
       })
     );
     setDirty(true);
+    clearChecks(id);
+  };
+
+  // Checks the entries as edited, including unsaved changes
+  const handleRunChecks = async () => {
+    setCheckingCode(true);
+    setError(null);
+    try {
+      const checks = await invoke<BankCodeCheck[]>("check_bank_code", { subject, entries });
+      setCodeChecks(
+        Object.fromEntries(checks.flatMap((item) => (item.code_check ? [[item.id, item.code_check] as const] : [])))
+      );
+      setMathChecks(
+        Object.fromEntries(checks.flatMap((item) => (item.math_check ? [[item.id, item.math_check] as const] : [])))
+      );
+    } catch (e: any) {
+      setError(String(e));
+    } finally {
+      setCheckingCode(false);
+    }
   };
 
   const handleSave = async () => {
@@ -170,7 +229,19 @@ export default function BankEditor({ subject }: BankEditorProps) {
     <div className="w-64 border-r bg-slate-50 h-full overflow-auto">
       <div className="px-3 py-2 flex items-center justify-between border-b bg-white">
         <div className="text-sm font-semibold">Questions</div>
-        {loading && <Loader2 className="w-4 h-4 animate-spin text-primary" />}
+        {loading ? (
+          <Loader2 className="w-4 h-4 animate-spin text-primary" />
+        ) : (
+          <button
+            onClick={handleRunChecks}
+            disabled={checkingCode || entries.length === 0}
+            className="flex items-center gap-1 px-2 py-0.5 text-xs border rounded hover:bg-secondary disabled:opacity-50"
+            title="Run each entry's Java code and work its Calculus numerically, and compare the results with its key"
+          >
+            {checkingCode ? <Loader2 className="w-3 h-3 animate-spin" /> : <Play className="w-3 h-3" />}
+            Run checks
+          </button>
+        )}
       </div>
       <div className="divide-y">
         {entries.map((q) => (
@@ -181,7 +252,33 @@ export default function BankEditor({ subject }: BankEditorProps) {
               q.id === selectedId ? "bg-primary/10" : ""
             }`}
           >
-            <div className="font-semibold">{q.id}</div>
+            <div className="flex items-center gap-2 font-semibold">
+              {q.id}
+              {codeChecks[q.id] && codeChecks[q.id].status !== "skipped" && (
+                <span
+                  className={`px-1.5 rounded-full text-[10px] font-medium ${
+                    codeChecks[q.id].status === "match"
+                      ? "bg-green-50 text-green-700"
+                      : codeChecks[q.id].status === "mismatch" || codeChecks[q.id].status === "no_choice_matches"
+                      ? "bg-red-50 text-red-700"
+                      : "bg-gray-100 text-gray-600"
+                  }`}
+                  title={codeCheckLabels[codeChecks[q.id].status]}
+                >
+                  {codeChecks[q.id].status === "match" ? "code ok" : codeChecks[q.id].status.replace(/_/g, " ")}
+                </span>
+              )}
+              {mathChecks[q.id] && (isMathFlagged(mathChecks[q.id]) || mathChecks[q.id].status === "match") && (
+                <span
+                  className={`px-1.5 rounded-full text-[10px] font-medium ${
+                    isMathFlagged(mathChecks[q.id]) ? "bg-red-50 text-red-700" : "bg-green-50 text-green-700"
+                  }`}
+                  title={mathCheckLabels[mathChecks[q.id].status]}
+                >
+                  {isMathFlagged(mathChecks[q.id]) ? "math flagged" : "math ok"}
+                </span>
+              )}
+            </div>
             <div className="text-xs text-slate-600 line-clamp-2">{q.text}</div>
           </button>
         ))}
@@ -460,6 +557,45 @@ export default function BankEditor({ subject }: BankEditorProps) {
               </div>
             ))}
           </div>
+
+          {codeChecks[selected.id] && (
+            <div className="space-y-2 border rounded px-3 py-2 bg-slate-50 text-xs">
+              <div className="font-semibold text-slate-700">
+                Code check: {codeCheckLabels[codeChecks[selected.id].status]}
+                {codeChecks[selected.id].matched_choice && ` (${codeChecks[selected.id].matched_choice})`}
+              </div>
+              {codeChecks[selected.id].output && (
+                <pre className="whitespace-pre-wrap bg-white border rounded p-2 max-h-40 overflow-auto">
+                  {codeChecks[selected.id].output}
+                </pre>
+              )}
+              {codeChecks[selected.id].detail && (
+                <pre className="whitespace-pre-wrap text-slate-600 max-h-40 overflow-auto">
+                  {codeChecks[selected.id].detail}
+                </pre>
+              )}
+            </div>
+          )}
+
+          {mathChecks[selected.id] && (
+            <div className="space-y-1 border rounded px-3 py-2 bg-slate-50 text-xs">
+              <div className="font-semibold text-slate-700">
+                Math check: {mathCheckLabels[mathChecks[selected.id].status]}
+                {mathChecks[selected.id].matched_choice && ` (${mathChecks[selected.id].matched_choice})`}
+              </div>
+              {mathChecks[selected.id].problem && (
+                <div className="text-slate-600">
+                  Computed the {mathChecks[selected.id].problem}
+                  {mathChecks[selected.id].expected && `: ${mathChecks[selected.id].expected}`}
+                </div>
+              )}
+              {(mathChecks[selected.id].equivalent_choices?.length ?? 0) > 0 && (
+                <div className="text-red-700">
+                  Equivalent choices: {mathChecks[selected.id].equivalent_choices?.join(", ")}
+                </div>
+              )}
+            </div>
+          )}
         </div>
       </div>
     );
@@ -0,0 +1,255 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { open as openDialog, save as saveDialog } from "@tauri-apps/api/dialog";
+import { Plus, Trash2 } from "lucide-react";
+import { Blueprint, BlueprintRow, TopicInfo } from "../types";
+
+interface BlueprintModalProps {
+  open: boolean;
+  subject: string;
+  topics: TopicInfo[];
+  isGenerating: boolean;
+  onGenerate: (blueprint: Blueprint) => void;
+  onClose: () => void;
+}
+
+const blueprintFileFilter = { name: "Test Blueprint", extensions: ["json"] };
+const COGNITIVE_LEVELS = ["remember", "understand", "apply", "analyze", "evaluate", "create"];
+
+function emptyRow(): BlueprintRow {
+  return { topics: [], difficulty: "medium", count: 3, cognitive_level: null, notes: null };
+}
+
+export default function BlueprintModal({
+  open,
+  subject,
+  topics,
+  isGenerating,
+  onGenerate,
+  onClose,
+}: BlueprintModalProps) {
+  const [name, setName] = useState("");
+  const [rows, setRows] = useState<BlueprintRow[]>([emptyRow()]);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!open) return;
+    setError(null);
+  }, [open]);
+
+  if (!open) return null;
+
+  const blueprint: Blueprint = { name, subject, rows };
+  const total = rows.reduce((sum, row) => sum + row.count, 0);
+  const rowsValid = rows.length > 0 && rows.every((row) => row.topics.length > 0 && row.count >= 1);
+  const canGenerate = rowsValid && !isGenerating;
+
+  const updateRow = (index: number, patch: Partial<BlueprintRow>) => {
+    setRows((prev) => prev.map((row, i) => (i === index ? { ...row, ...patch } : row)));
+  };
+
+  const handleOpenFile = async () => {
+    const selection = await openDialog({ multiple: false, filters: [blueprintFileFilter] });
+    const filePath = Array.isArray(selection) ? selection[0] : selection;
+    if (!filePath) return;
+    try {
+      const loaded = await invoke<Blueprint>("load_blueprint", { path: filePath });
+      if (loaded.subject !== subject) {
+        setError(`This blueprint is for ${loaded.subject}; switch subjects to use it.`);
+        return;
+      }
+      setName(loaded.name);
+      setRows(loaded.rows);
+      setError(null);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const handleSaveFile = async () => {
+    const base = (name.trim() || "test").replace(/[^\w-]+/g, "-");
+    const filePath = await saveDialog({
+      defaultPath: `${base}.blueprint.json`,
+      filters: [blueprintFileFilter],
+    });
+    if (!filePath) return;
+    try {
+      await invoke("save_blueprint", { path: filePath, blueprint });
+      setError(null);
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 p-4">
+      <div className="w-full max-w-4xl rounded-lg bg-white shadow-xl border">
+        <div className="px-5 py-4 border-b flex items-center justify-between">
+          <h2 className="text-lg font-semibold text-foreground">Generate from Blueprint</h2>
+          <button
+            onClick={onClose}
+            className="text-sm text-muted-foreground hover:text-foreground"
+          >
+            Close
+          </button>
+        </div>
+
+        <div className="p-5 space-y-4 max-h-[65vh] overflow-auto">
+          <p className="text-sm text-muted-foreground">
+            List the mix of the whole test, one row per topic and difficulty. All rows are
+            generated in one job and each question is tagged with its row. Save the blueprint to
+            rebuild the test next year.
+          </p>
+
+          <label className="block text-sm font-medium text-foreground">
+            Name
+            <input
+              type="text"
+              value={name}
+              onChange={(e) => setName(e.target.value)}
+              placeholder={`${subject || "Unit"} test`}
+              className="mt-1 w-full px-3 py-2 border rounded-md text-sm"
+            />
+          </label>
+
+          <div className="space-y-3">
+            {rows.map((row, index) => (
+              <div key={index} className="rounded-md border p-3 space-y-2">
+                <div className="flex items-center justify-between">
+                  <span className="text-sm font-medium text-foreground">Row {index + 1}</span>
+                  <button
+                    onClick={() => setRows((prev) => prev.filter((_, i) => i !== index))}
+                    disabled={rows.length === 1}
+                    className="text-muted-foreground hover:text-destructive disabled:opacity-40"
+                    aria-label={`Remove row ${index + 1}`}
+                  >
+                    <Trash2 className="w-4 h-4" />
+                  </button>
+                </div>
+                <div className="grid grid-cols-[2fr_1fr_1fr_1fr] gap-3">
+                  <label className="text-xs font-medium text-muted-foreground">
+                    Topics
+                    <select
+                      multiple
+                      value={row.topics}
+                      onChange={(e) =>
+                        updateRow(index, {
+                          topics: Array.from(e.target.selectedOptions, (option) => option.value),
+                        })
+                      }
+                      className="mt-1 w-full h-24 px-2 py-1 border rounded-md text-sm text-foreground"
+                    >
+                      {topics.map((topic) => [
+                        <option key={topic.id} value={topic.id}>
+                          {topic.name}
+                        </option>,
+                        ...(topic.children ?? []).map((child) => (
+                          <option key={child.id} value={child.id}>
+                            {"\u00a0\u00a0\u00a0"}
+                            {child.name}
+                          </option>
+                        )),
+                      ])}
+                    </select>
+                  </label>
+                  <label className="text-xs font-medium text-muted-foreground">
+                    Difficulty
+                    <select
+                      value={row.difficulty}
+                      onChange={(e) => updateRow(index, { difficulty: e.target.value })}
+                      className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground capitalize"
+                    >
+                      {["easy", "medium", "hard"].map((level) => (
+                        <option key={level} value={level}>
+                          {level}
+                        </option>
+                      ))}
+                    </select>
+                  </label>
+                  <label className="text-xs font-medium text-muted-foreground">
+                    Questions
+                    <input
+                      type="number"
+                      min={1}
+                      max={30}
+                      value={row.count}
+                      onChange={(e) =>
+                        updateRow(index, {
+                          count: Math.max(1, Math.min(30, Number(e.target.value) || 1)),
+                        })
+                      }
+                      className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground"
+                    />
+                  </label>
+                  <label className="text-xs font-medium text-muted-foreground">
+                    Cognitive level
+                    <select
+                      value={row.cognitive_level ?? ""}
+                      onChange={(e) => updateRow(index, { cognitive_level: e.target.value || null })}
+                      className="mt-1 w-full px-2 py-2 border rounded-md text-sm text-foreground capitalize"
+                    >
+                      <option value="">Any</option>
+                      {COGNITIVE_LEVELS.map((level) => (
+                        <option key={level} value={level}>
+                          {level}
+                        </option>
+                      ))}
+                    </select>
+                  </label>
+                </div>
+                <input
+                  type="text"
+                  value={row.notes ?? ""}
+                  onChange={(e) => updateRow(index, { notes: e.target.value || null })}
+                  placeholder="Notes for this row (optional)"
+                  className="w-full px-3 py-1.5 border rounded-md text-sm"
+                />
+              </div>
+            ))}
+            <button
+              onClick={() => setRows((prev) => [...prev, emptyRow()])}
+              className="flex items-center gap-1 text-sm text-primary hover:underline"
+            >
+              <Plus className="w-3.5 h-3.5" />
+              Add row
+            </button>
+          </div>
+
+          {error && <p className="text-sm text-destructive">{error}</p>}
+        </div>
+
+        <div className="px-5 py-3 border-t flex items-center gap-2">
+          <button
+            onClick={() => void handleOpenFile()}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary"
+          >
+            Open…
+          </button>
+          <button
+            onClick={() => void handleSaveFile()}
+            disabled={!rowsValid}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            Save…
+          </button>
+          <span className="ml-auto text-sm text-muted-foreground">
+            {total} question{total === 1 ? "" : "s"}
+          </span>
+          <button
+            onClick={onClose}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary"
+          >
+            Cancel
+          </button>
+          <button
+            onClick={() => canGenerate && onGenerate(blueprint)}
+            disabled={!canGenerate}
+            className="px-3 py-1.5 text-sm rounded-md bg-primary text-primary-foreground hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {isGenerating ? "Generating..." : "Generate"}
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
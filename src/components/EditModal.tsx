@@ -10,6 +10,7 @@ interface EditModalProps {
 
 export default function EditModal({ question, onSave, onClose }: EditModalProps) {
   const [content, setContent] = useState(question.text);
+  const [code, setCode] = useState(question.code ?? "");
   const [answers, setAnswers] = useState<Answer[]>(question.answers);
 
   const handleAnswerChange = (index: number, newText: string) => {
@@ -50,6 +51,7 @@ export default function EditModal({ question, onSave, onClose }: EditModalProps)
     onSave({
       ...question,
       text: content,
+      code: code.trim() ? code : undefined,
       answers: finalAnswers,
     });
   };
@@ -92,6 +94,21 @@ You can also use tables, \`inline code\`, **bold**, etc.`}
             </p>
           </div>
 
+          {/* Code kept apart from the stem */}
+          {question.code !== undefined && (
+            <div>
+              <label className="text-sm font-medium text-foreground mb-1.5 block">
+                Code (shown after the question as a Java block)
+              </label>
+              <textarea
+                value={code}
+                onChange={(e) => setCode(e.target.value)}
+                className="w-full h-40 px-3 py-2 border rounded-md resize-none font-mono text-sm focus:outline-none focus:ring-2 focus:ring-primary"
+                spellCheck={false}
+              />
+            </div>
+          )}
+
           {/* Answers */}
           <div>
             <label className="text-sm font-medium text-foreground mb-1.5 block">
@@ -203,7 +203,7 @@ export default function ExportOptionsModal({
               </select>
               <div className="rounded-md border border-gray-200 bg-gray-50 px-3 py-2 text-sm text-gray-700">
                 {markdownPreset === "teacher_markdown"
-                  ? "Teacher Markdown includes an answer key and explanations section."
+                  ? "Teacher Markdown includes an answer key, explanations, and a provenance appendix for generated questions."
                   : "Student Markdown omits answer key and explanations for handouts."}
               </div>
             </div>
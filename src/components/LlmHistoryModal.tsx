@@ -0,0 +1,156 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { Loader2 } from "lucide-react";
+import { LlmInteraction, LlmInteractionSummary } from "../types";
+
+interface LlmHistoryModalProps {
+  open: boolean;
+  onClose: () => void;
+}
+
+const PAGE_SIZE = 50;
+
+export default function LlmHistoryModal({ open, onClose }: LlmHistoryModalProps) {
+  const [query, setQuery] = useState("");
+  const [rows, setRows] = useState<LlmInteractionSummary[]>([]);
+  const [selected, setSelected] = useState<LlmInteraction | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState("");
+
+  const load = async (search: string) => {
+    setLoading(true);
+    setError("");
+    try {
+      const result = search.trim()
+        ? await invoke<LlmInteractionSummary[]>("search_llm_interactions", {
+            query: search,
+            limit: PAGE_SIZE,
+          })
+        : await invoke<LlmInteractionSummary[]>("list_llm_interactions", {
+            limit: PAGE_SIZE,
+            offset: 0,
+          });
+      setRows(result);
+    } catch (err) {
+      setError(String(err));
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  useEffect(() => {
+    if (!open) return;
+    setSelected(null);
+    void load(query);
+  }, [open]);
+
+  if (!open) return null;
+
+  const openInteraction = async (id: string) => {
+    try {
+      setSelected(await invoke<LlmInteraction>("get_llm_interaction", { id }));
+    } catch (err) {
+      setError(String(err));
+    }
+  };
+
+  const describe = (row: LlmInteractionSummary) => {
+    const parts = [row.kind, row.outcome, `${(row.duration_ms / 1000).toFixed(1)}s`];
+    if (row.retries > 0) parts.push(`${row.retries} retr${row.retries === 1 ? "y" : "ies"}`);
+    if (row.parse_outcome) parts.push(row.parse_outcome);
+    return parts.join(" · ");
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-30">
+      <div className="bg-white rounded-lg shadow-lg p-6 w-full max-w-5xl max-h-[90vh] flex flex-col gap-3">
+        <div>
+          <h2 className="text-lg font-semibold text-foreground">LLM History</h2>
+          <p className="text-sm text-muted-foreground">
+            Past prompts and responses from the interaction log.
+          </p>
+        </div>
+
+        <form
+          className="flex gap-2"
+          onSubmit={(e) => {
+            e.preventDefault();
+            setSelected(null);
+            void load(query);
+          }}
+        >
+          <input
+            value={query}
+            onChange={(e) => setQuery(e.target.value)}
+            placeholder="Search job id, subject, topic, prompt or response"
+            className="flex-1 px-3 py-2 text-sm border rounded-md focus:outline-none focus:ring-2 focus:ring-primary"
+          />
+          <button type="submit" className="px-4 py-2 rounded border hover:bg-secondary">
+            Search
+          </button>
+        </form>
+
+        {error && <div className="text-sm text-destructive">{error}</div>}
+
+        <div className="flex flex-1 min-h-[360px] gap-3 overflow-hidden">
+          <ul className="w-2/5 overflow-auto border rounded-md divide-y text-sm">
+            {loading && (
+              <li className="flex items-center gap-2 p-3 text-muted-foreground">
+                <Loader2 className="w-4 h-4 animate-spin" />
+                Loading...
+              </li>
+            )}
+            {!loading && rows.length === 0 && (
+              <li className="p-3 text-muted-foreground">No logged interactions.</li>
+            )}
+            {!loading &&
+              rows.map((row) => (
+                <li
+                  key={row.id}
+                  className={`p-2 cursor-pointer hover:bg-secondary ${
+                    selected?.id === row.id ? "bg-secondary" : ""
+                  }`}
+                  onClick={() => void openInteraction(row.id)}
+                >
+                  <div className="font-medium text-foreground">
+                    {new Date(row.timestamp).toLocaleString()}
+                    {row.subject ? ` — ${row.subject}` : ""}
+                  </div>
+                  <div className="text-xs text-muted-foreground">{describe(row)}</div>
+                </li>
+              ))}
+          </ul>
+
+          <div className="flex-1 overflow-auto border rounded-md p-3 text-xs font-mono whitespace-pre-wrap">
+            {selected ? (
+              <>
+                <div className="mb-2 text-muted-foreground">
+                  Job {selected.job_id}
+                  {selected.index != null ? ` (question ${selected.index + 1})` : ""}
+                  {selected.batch != null ? ` (batch ${selected.batch + 1})` : ""}
+                  {selected.remaining_tokens != null
+                    ? ` · ${selected.remaining_tokens} tokens remaining`
+                    : ""}
+                  {selected.redacted ? " · redacted" : ""}
+                </div>
+                {selected.error && <div className="mb-2 text-destructive">{selected.error}</div>}
+                <div className="font-semibold">Prompt</div>
+                <div className="mb-3">{selected.prompt}</div>
+                <div className="font-semibold">Response</div>
+                <div>{selected.response}</div>
+              </>
+            ) : (
+              <span className="text-muted-foreground">Select an interaction to view it.</span>
+            )}
+          </div>
+        </div>
+
+        <div className="flex justify-end">
+          <button className="px-4 py-2 rounded border hover:bg-secondary" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
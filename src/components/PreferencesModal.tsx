@@ -1,5 +1,6 @@
 import { useEffect, useState } from "react";
-import { SubjectInfo } from "../types";
+import { invoke } from "@tauri-apps/api/tauri";
+import { ResponseCacheStatus, SubjectInfo, VerificationMode } from "../types";
 
 interface PreferencesModalProps {
   isOpen: boolean;
@@ -20,6 +21,8 @@ export default function PreferencesModal({
 }: PreferencesModalProps) {
   const [draftSubject, setDraftSubject] = useState("");
   const [draftDifficulty, setDraftDifficulty] = useState("medium");
+  const [cacheStatus, setCacheStatus] = useState<ResponseCacheStatus | null>(null);
+  const [verificationMode, setVerificationMode] = useState<VerificationMode | null>(null);
 
   useEffect(() => {
     if (!isOpen) return;
@@ -27,10 +30,47 @@ export default function PreferencesModal({
     setDraftDifficulty(selectedDifficulty || "medium");
   }, [isOpen, selectedSubject, selectedDifficulty]);
 
+  useEffect(() => {
+    if (!isOpen) return;
+    invoke<ResponseCacheStatus>("get_response_cache_status")
+      .then(setCacheStatus)
+      .catch(() => setCacheStatus(null));
+    invoke<VerificationMode>("get_answer_verification_mode")
+      .then(setVerificationMode)
+      .catch(() => setVerificationMode(null));
+  }, [isOpen]);
+
   if (!isOpen) return null;
 
   const canSave = draftSubject.trim().length > 0;
 
+  // Cache changes apply immediately and only for this session.
+  const toggleCache = async (enabled: boolean) => {
+    try {
+      setCacheStatus(await invoke<ResponseCacheStatus>("set_response_cache_enabled", { enabled }));
+    } catch (err) {
+      console.error("Failed to toggle response cache:", err);
+    }
+  };
+
+  // Like the cache toggle, the verification mode applies immediately for this session.
+  const changeVerificationMode = async (mode: VerificationMode) => {
+    try {
+      setVerificationMode(await invoke<VerificationMode>("set_answer_verification_mode", { mode }));
+    } catch (err) {
+      console.error("Failed to change answer verification mode:", err);
+    }
+  };
+
+  const clearCache = async () => {
+    try {
+      await invoke<number>("clear_response_cache");
+      setCacheStatus(await invoke<ResponseCacheStatus>("get_response_cache_status"));
+    } catch (err) {
+      console.error("Failed to clear response cache:", err);
+    }
+  };
+
   return (
     <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50 p-4">
       <div className="bg-white rounded-lg shadow-xl p-6 w-full max-w-md">
@@ -71,6 +111,53 @@ export default function PreferencesModal({
           ))}
         </div>
 
+        {cacheStatus && (
+          <div className="mb-6">
+            <label className="flex items-center gap-2 text-sm font-medium text-gray-700">
+              <input
+                type="checkbox"
+                checked={cacheStatus.enabled}
+                onChange={(e) => void toggleCache(e.target.checked)}
+              />
+              Replay identical prompts from the response cache
+            </label>
+            <div className="flex items-center justify-between mt-1 text-xs text-gray-500">
+              <span>
+                {cacheStatus.entries} cached response(s),{" "}
+                {(cacheStatus.total_bytes / 1024).toFixed(1)} KB
+              </span>
+              <button
+                type="button"
+                onClick={() => void clearCache()}
+                disabled={cacheStatus.entries === 0}
+                className="text-blue-600 hover:underline disabled:text-gray-400 disabled:no-underline"
+              >
+                Clear cache
+              </button>
+            </div>
+          </div>
+        )}
+
+        {verificationMode && (
+          <div className="mb-6">
+            <label className="block text-sm font-medium text-gray-700 mb-2">
+              Check answer keys after generation
+            </label>
+            <select
+              value={verificationMode}
+              onChange={(e) => void changeVerificationMode(e.target.value as VerificationMode)}
+              className="w-full px-3 py-2 border border-gray-300 rounded-md"
+            >
+              <option value="off">Off</option>
+              <option value="flag">Flag disputed answers</option>
+              <option value="regenerate">Regenerate disputed questions once</option>
+            </select>
+            <p className="mt-1 text-xs text-gray-500">
+              The model answers each new question without its key; disagreements are marked on the card.
+            </p>
+          </div>
+        )}
+
         <div className="flex gap-3">
           <button
             type="button"
@@ -0,0 +1,70 @@
+import { PromptPreview } from "../types";
+
+interface PromptPreviewModalProps {
+  title: string;
+  preview: PromptPreview | null;
+  onClose: () => void;
+}
+
+export default function PromptPreviewModal({ title, preview, onClose }: PromptPreviewModalProps) {
+  if (!preview) return null;
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-30">
+      <div className="bg-white rounded-lg shadow-lg p-6 w-full max-w-3xl max-h-[90vh] flex flex-col gap-4">
+        <div>
+          <h2 className="text-lg font-semibold text-foreground">{title}</h2>
+          <p className="text-sm text-muted-foreground">
+            This is the prompt that would be sent; nothing has been sent to the model.
+          </p>
+        </div>
+
+        <div className="grid grid-cols-2 gap-3 text-sm">
+          <div className="border rounded-md p-3">
+            <div className="text-xs text-muted-foreground">Estimated prompt tokens</div>
+            <div className="text-lg font-semibold">~{preview.estimated_tokens.toLocaleString()}</div>
+          </div>
+          <div className="border rounded-md p-3">
+            <div className="text-xs text-muted-foreground">Bank examples</div>
+            <div>{preview.example_ids.length > 0 ? preview.example_ids.join(", ") : "None"}</div>
+          </div>
+          <div className="border rounded-md p-3">
+            <div className="text-xs text-muted-foreground">Filled placeholders</div>
+            <div>
+              {preview.used_template
+                ? preview.filled_placeholders.map((name) => `{${name}}`).join(", ") || "None"
+                : "No template; built-in prompt used"}
+            </div>
+            {preview.template_hash && (
+              <div className="text-xs text-muted-foreground">
+                Template {preview.template_version ?? "unlabelled"} · {preview.template_hash}
+              </div>
+            )}
+          </div>
+          <div className="border rounded-md p-3">
+            <div className="text-xs text-muted-foreground">Unresolved placeholders</div>
+            <div className={preview.unresolved_placeholders.length > 0 ? "text-destructive" : ""}>
+              {preview.unresolved_placeholders.map((name) => `{${name}}`).join(", ") || "None"}
+            </div>
+          </div>
+        </div>
+
+        <pre className="flex-1 min-h-0 overflow-auto text-xs leading-relaxed bg-slate-950 text-slate-100 rounded-lg p-3 whitespace-pre-wrap">
+          {preview.prompt}
+        </pre>
+
+        <div className="flex justify-end gap-2">
+          <button
+            className="px-4 py-2 rounded border hover:bg-secondary"
+            onClick={() => void navigator.clipboard.writeText(preview.prompt)}
+          >
+            Copy
+          </button>
+          <button className="px-4 py-2 rounded border hover:bg-secondary" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -1,5 +1,5 @@
-import { Question } from "../types";
-import { RefreshCw, Pencil, Trash2, Check, ChevronDown, ChevronUp, Eye, EyeOff } from "lucide-react";
+import { Question, RegenerateMode } from "../types";
+import { RefreshCw, Pencil, Trash2, Check, ChevronDown, ChevronUp, Eye, EyeOff, ShieldCheck } from "lucide-react";
 import { useState } from "react";
 import ReactMarkdown from "react-markdown";
 import remarkGfm from "remark-gfm";
@@ -18,7 +18,14 @@ interface QuestionCardProps {
   liveRawText?: string;
   isRegenerating?: boolean;
   isRegenerationDisabled?: boolean;
-  onRegenerate: (instructions?: string) => void;
+  /** Still part of a running generation: editable, but not yet regenerable or deletable. */
+  isProvisional?: boolean;
+  isVerifying?: boolean;
+  onRegenerate: (instructions?: string, mode?: RegenerateMode) => void;
+  onPreviewPrompt?: (instructions?: string, mode?: RegenerateMode) => void;
+  /** Ask the model to answer the question without its key and compare. */
+  onVerify?: () => void;
+  onRemoveAccommodation?: () => void;
   onEdit: () => void;
   onDelete: () => void;
 }
@@ -120,6 +127,19 @@ function inferTopicKind(id: string): "topic" | "subtopic" | null {
   return null;
 }
 
+const REGENERATE_MODES: { value: RegenerateMode; label: string }[] = [
+  { value: "full", label: "Whole question" },
+  { value: "distractors", label: "Distractors only (keep stem and correct answer)" },
+  { value: "explanation", label: "Explanation only" },
+  { value: "harder", label: "Harder, same concept" },
+  { value: "easier", label: "Easier, same concept" },
+  { value: "numbers", label: "Change the numbers/values only" },
+];
+
+function regenerateModeLabel(mode: RegenerateMode): string {
+  return REGENERATE_MODES.find((m) => m.value === mode)?.label ?? mode;
+}
+
 function normalizeDifficultyLabel(value?: string): string | null {
   if (!value) return null;
   const normalized = value.trim().toLowerCase();
@@ -138,14 +158,22 @@ export default function QuestionCard({
   liveRawText,
   isRegenerating = false,
   isRegenerationDisabled = false,
+  isProvisional = false,
+  isVerifying = false,
   onRegenerate,
+  onPreviewPrompt,
+  onVerify,
+  onRemoveAccommodation,
   onEdit,
   onDelete,
 }: QuestionCardProps) {
   const [showInstructions, setShowInstructions] = useState(false);
   const [instructions, setInstructions] = useState("");
+  const [regenerateMode, setRegenerateMode] = useState<RegenerateMode>("full");
   const [showExplanation, setShowExplanation] = useState(false);
   const [showRaw, setShowRaw] = useState(false);
+  const [showWork, setShowWork] = useState(false);
+  const [showAccommodation, setShowAccommodation] = useState(false);
 
   const iconButtonBase = "p-1.5 rounded transition-colors";
   const iconButtonNeutral =
@@ -153,7 +181,14 @@ export default function QuestionCard({
 
   const explanationContent = question.explanation?.trim() ?? "";
   const formattedExplanation = formatExplanation(explanationContent);
-  const hasExplanation = Boolean(formattedExplanation);
+  const hasAnswerRationales = question.answers.some((answer) => answer.explanation?.trim());
+  const hasExplanation = Boolean(formattedExplanation) || hasAnswerRationales;
+  const work = question.work?.trim() ?? "";
+  const source = question.source;
+  const accommodation = question.accommodation;
+  const gradeBefore = accommodation?.readability.standard;
+  const gradeAfter = accommodation?.readability.accommodated;
+  const gradeDropped = gradeBefore != null && gradeAfter != null && gradeAfter < gradeBefore;
   const liveRaw = liveRawText?.trim() ?? "";
   const storedRaw = rawText?.trim() ?? "";
   const activeRaw = liveRaw || storedRaw;
@@ -174,16 +209,110 @@ export default function QuestionCard({
     (item) => item.kind === "subtopic" && item.id !== topicChip?.id
   ) ?? null;
   const difficultyChip = normalizeDifficultyLabel(question.difficulty);
+  const provenance = question.provenance;
+  const provenanceTitle = provenance?.kind === "bank"
+    ? `From question bank entry ${provenance.example_ids.join(", ")}`
+    : provenance
+    ? [
+        `${
+          provenance.kind === "regenerate"
+            ? "Regenerated"
+            : provenance.kind === "parametric"
+            ? "Filled in from template"
+            : "Generated"
+        }${
+          provenance.regeneration_mode ? ` (${regenerateModeLabel(provenance.regeneration_mode)})` : ""
+        } ${new Date(provenance.generated_at).toLocaleString()}`,
+        `Template: ${
+          provenance.template_hash
+            ? `${provenance.template_version ?? "unlabelled"} (${provenance.template_hash})`
+            : "built-in"
+        }`,
+        `Examples: ${provenance.example_ids.join(", ") || "none"}`,
+        provenance.seed !== undefined && provenance.seed !== null
+          ? `Seed: ${provenance.seed}`
+          : `Model: ${provenance.provider} ${provenance.model}`,
+        provenance.job_id ? `Job: ${provenance.job_id}` : null,
+      ]
+        .filter(Boolean)
+        .join("\n")
+    : undefined;
+  const verification = question.verification;
+  const verificationChip = verification
+    ? {
+        agree: { label: "Answer verified", className: "bg-green-50 text-green-700" },
+        disagree: { label: "Answer disputed", className: "bg-red-50 text-red-700" },
+        inconclusive: { label: "Check inconclusive", className: "bg-gray-100 text-gray-600" },
+      }[verification.verdict]
+    : null;
+  const verificationTitle = verification
+    ? [
+        verification.verdict === "inconclusive"
+          ? null
+          : `Verifier chose ${verification.derived_answer ?? "?"}; key marks ${verification.marked_answer ?? "nothing"}`,
+        verification.regenerated ? "Regenerated after a disputed check" : null,
+        verification.reasoning || null,
+        `Checked ${new Date(verification.checked_at).toLocaleString()}`,
+      ]
+        .filter(Boolean)
+        .join("\n")
+    : undefined;
+
+  const codeCheck = question.code_check;
+  const codeCheckChip =
+    codeCheck && codeCheck.status !== "skipped"
+      ? codeCheck.status === "match"
+        ? { label: "Code output matches", className: "bg-green-50 text-green-700" }
+        : codeCheck.status === "mismatch" || codeCheck.status === "no_choice_matches"
+        ? { label: "Code contradicts key", className: "bg-red-50 text-red-700" }
+        : { label: "Code did not run", className: "bg-gray-100 text-gray-600" }
+      : null;
+  const codeCheckTitle = codeCheck
+    ? [
+        codeCheck.status === "no_choice_matches"
+          ? "The output matches none of the choices"
+          : codeCheck.matched_choice
+          ? `Output matches choice ${codeCheck.matched_choice}`
+          : null,
+        codeCheck.output ? `Output:\n${codeCheck.output.slice(0, 500)}` : null,
+        codeCheck.detail ? codeCheck.detail.slice(0, 500) : null,
+      ]
+        .filter(Boolean)
+        .join("\n")
+    : undefined;
+
+  const mathCheck = question.math_check;
+  const mathEquivalents = mathCheck?.equivalent_choices ?? [];
+  const mathCheckChip = mathCheck
+    ? mathCheck.status === "mismatch" || mathCheck.status === "no_choice_matches"
+      ? { label: "Math contradicts key", className: "bg-red-50 text-red-700" }
+      : mathEquivalents.length > 0
+      ? { label: "Equivalent choices", className: "bg-red-50 text-red-700" }
+      : mathCheck.status === "match"
+      ? { label: "Math checks out", className: "bg-green-50 text-green-700" }
+      : null
+    : null;
+  const mathCheckTitle = mathCheck
+    ? [
+        mathCheck.problem ? `Computed the ${mathCheck.problem}` : null,
+        mathCheck.expected ? `Value: ${mathCheck.expected}` : null,
+        mathCheck.status === "no_choice_matches"
+          ? "The result matches none of the choices"
+          : mathCheck.matched_choice
+          ? `Result matches choice ${mathCheck.matched_choice}`
+          : null,
+        mathEquivalents.length > 0 ? `Same value: ${mathEquivalents.join(", ")}` : null,
+      ]
+        .filter(Boolean)
+        .join("\n")
+    : undefined;
 
   const handleRegenerate = () => {
     const trimmed = instructions.trim();
 
-    if (trimmed) {
-      onRegenerate(trimmed);
-      setInstructions("");
-    } else {
-      onRegenerate();
-    }
+    onRegenerate(trimmed || undefined, regenerateMode);
+    setInstructions("");
+    setRegenerateMode("full");
   };
 
   return (
@@ -191,7 +320,7 @@ export default function QuestionCard({
       {/* Header */}
       <div className="px-4 py-3 bg-sky-50/70 border-b border-sky-100">
         <div className="w-full max-w-4xl mx-auto flex items-center justify-between">
-          <span className="font-medium text-foreground">
+          <span className="font-medium text-foreground" title={provenanceTitle}>
             Question {index + 1}
           </span>
           <div className="flex items-center gap-1.5 mr-auto ml-3">
@@ -210,6 +339,46 @@ export default function QuestionCard({
                 Difficulty: {difficultyChip}
               </span>
             )}
+            {question.blueprint && (
+              <span
+                className="px-2 py-0.5 rounded-full text-[11px] font-medium bg-violet-50 text-violet-700"
+                title={[question.blueprint.name, question.blueprint.label].filter(Boolean).join(": ")}
+              >
+                Row {question.blueprint.row}
+              </span>
+            )}
+            {question.repaired && (
+              <span
+                className="px-2 py-0.5 rounded-full text-[11px] font-medium bg-orange-50 text-orange-700"
+                title="The model's JSON for this question was malformed and was fixed automatically. Double-check it."
+              >
+                Repaired
+              </span>
+            )}
+            {verificationChip && (
+              <span
+                className={`px-2 py-0.5 rounded-full text-[11px] font-medium ${verificationChip.className}`}
+                title={verificationTitle}
+              >
+                {verificationChip.label}
+              </span>
+            )}
+            {codeCheckChip && (
+              <span
+                className={`px-2 py-0.5 rounded-full text-[11px] font-medium ${codeCheckChip.className}`}
+                title={codeCheckTitle}
+              >
+                {codeCheckChip.label}
+              </span>
+            )}
+            {mathCheckChip && (
+              <span
+                className={`px-2 py-0.5 rounded-full text-[11px] font-medium ${mathCheckChip.className}`}
+                title={mathCheckTitle}
+              >
+                {mathCheckChip.label}
+              </span>
+            )}
           </div>
           <div className="flex items-center gap-1">
             <button
@@ -229,12 +398,22 @@ export default function QuestionCard({
             </button>
             <button
               onClick={handleRegenerate}
-              disabled={isRegenerationDisabled}
+              disabled={isRegenerationDisabled || isProvisional}
               className={iconButtonNeutral}
               title="Regenerate"
             >
               <RefreshCw className={`w-4 h-4 ${isRegenerating ? "animate-spin" : ""}`} />
             </button>
+            {onVerify && (
+              <button
+                onClick={onVerify}
+                disabled={isVerifying || isProvisional}
+                className={iconButtonNeutral}
+                title="Check answer key"
+              >
+                <ShieldCheck className={`w-4 h-4 ${isVerifying ? "animate-pulse" : ""}`} />
+              </button>
+            )}
             {hasRawText && (
               <button
                 onClick={() => setShowRaw((prev) => !prev)}
@@ -257,7 +436,8 @@ export default function QuestionCard({
             </button>
             <button
               onClick={onDelete}
-              className="p-1.5 rounded hover:bg-destructive/10 text-muted-foreground hover:text-destructive transition-colors"
+              disabled={isProvisional}
+              className="p-1.5 rounded hover:bg-destructive/10 disabled:opacity-50 disabled:cursor-not-allowed text-muted-foreground hover:text-destructive transition-colors"
               title="Delete"
             >
               <Trash2 className="w-4 h-4" />
@@ -270,6 +450,20 @@ export default function QuestionCard({
       {showInstructions && (
         <div className="px-4 py-3 bg-blue-50 border-b border-blue-200">
           <div className="w-full max-w-4xl mx-auto">
+            <label className="block text-sm font-medium text-slate-700 mb-2">
+              Regenerate:
+            </label>
+            <select
+              value={regenerateMode}
+              onChange={(e) => setRegenerateMode(e.target.value as RegenerateMode)}
+              className="w-full mb-3 px-3 py-2 text-sm border border-slate-300 rounded-md bg-white focus:outline-none focus:ring-2 focus:ring-primary"
+            >
+              {REGENERATE_MODES.map((mode) => (
+                <option key={mode.value} value={mode.value}>
+                  {mode.label}
+                </option>
+              ))}
+            </select>
             <label className="block text-sm font-medium text-slate-700 mb-2">
               Custom instructions for regeneration:
             </label>
@@ -280,9 +474,19 @@ export default function QuestionCard({
               className="w-full px-3 py-2 text-sm border border-slate-300 rounded-md focus:outline-none focus:ring-2 focus:ring-primary focus:border-transparent resize-none"
               rows={3}
             />
-            <p className="text-xs text-slate-600 mt-1">
-              These instructions will be included when regenerating this question.
-            </p>
+            <div className="flex items-center justify-between mt-1">
+              <p className="text-xs text-slate-600">
+                These instructions will be included when regenerating this question.
+              </p>
+              {onPreviewPrompt && (
+                <button
+                  onClick={() => onPreviewPrompt(instructions.trim() || undefined, regenerateMode)}
+                  className="text-xs text-slate-600 hover:text-foreground"
+                >
+                  Preview prompt
+                </button>
+              )}
+            </div>
           </div>
         </div>
       )}
@@ -299,6 +503,17 @@ export default function QuestionCard({
           {/* Question Text (with HTML, Markdown, LaTeX, and code blocks) */}
           <div className="prose max-w-none mb-4">
             <RichMarkdown content={question.text} />
+            {question.code?.trim() && (
+              <RichMarkdown
+                content={
+                  "```" +
+                  (question.subject === "Computer Science" ? "java" : "") +
+                  "\n" +
+                  question.code.trim() +
+                  "\n```"
+                }
+              />
+            )}
           </div>
 
         {/* Answers */}
@@ -324,6 +539,11 @@ export default function QuestionCard({
                     },
                   }}
                 />
+                {showExplanation && answer.explanation?.trim() && (
+                  <div className="mt-1 text-xs text-muted-foreground">
+                    <RichMarkdown content={answer.explanation} />
+                  </div>
+                )}
               </div>
               {answer.is_correct && (
                 <Check className="w-4 h-4 text-green-600 flex-shrink-0" />
@@ -346,13 +566,148 @@ export default function QuestionCard({
                   <ChevronDown className="w-4 h-4" />
                 )}
               </button>
-              {showExplanation && (
+              {showExplanation && formattedExplanation && (
                 <div className="prose px-4 py-3 border-t border-slate-200 bg-white">
                   <RichMarkdown content={formattedExplanation} />
                 </div>
               )}
             </div>
           )}
+
+          {work && (
+            <div className="mt-2 border border-dashed border-slate-300 rounded-lg">
+              <button
+                type="button"
+                onClick={() => setShowWork((prev) => !prev)}
+                className="w-full flex items-center justify-between px-4 py-2 text-xs font-medium text-muted-foreground hover:bg-secondary/50 transition-colors"
+                title="The model's scratchpad for this question. It is never exported."
+              >
+                <span>Reviewer notes</span>
+                {showWork ? <ChevronUp className="w-4 h-4" /> : <ChevronDown className="w-4 h-4" />}
+              </button>
+              {showWork && (
+                <pre className="px-4 py-3 border-t border-dashed border-slate-300 text-xs whitespace-pre-wrap text-slate-700">
+                  {work}
+                </pre>
+              )}
+            </div>
+          )}
+
+          {source?.quote.trim() && (
+            <div className="mt-2 rounded-lg border border-slate-200 bg-slate-50/60 px-4 py-2 text-xs">
+              <div className="flex items-center gap-2 font-medium text-muted-foreground">
+                <span>
+                  Source{source.section ? ` ${source.section}` : ""}
+                  {source.document ? ` · ${source.document}` : ""}
+                </span>
+                {!source.verified && (
+                  <span
+                    className="px-2 py-0.5 rounded-full text-[11px] font-medium bg-amber-50 text-amber-700"
+                    title="The cited words could not be found in the attached material."
+                  >
+                    Not found in source
+                  </span>
+                )}
+              </div>
+              <blockquote className="mt-1 border-l-2 border-slate-300 pl-2 italic text-slate-700 whitespace-pre-wrap">
+                {source.quote}
+              </blockquote>
+            </div>
+          )}
+
+          {accommodation && (
+            <div className="mt-2 border border-slate-200 rounded-lg">
+              <button
+                type="button"
+                onClick={() => setShowAccommodation((prev) => !prev)}
+                className="w-full flex items-center justify-between gap-2 px-4 py-2 text-sm font-medium text-foreground bg-secondary/50 hover:bg-secondary transition-colors"
+              >
+                <span className="flex items-center gap-2">
+                  Accommodated version
+                  {gradeBefore != null && gradeAfter != null && (
+                    <span
+                      className={`px-2 py-0.5 rounded-full text-[11px] font-medium ${
+                        gradeDropped ? "bg-green-50 text-green-700" : "bg-amber-50 text-amber-700"
+                      }`}
+                      title="Flesch-Kincaid grade level of the prose, code left out"
+                    >
+                      Grade {gradeBefore.toFixed(1)} → {gradeAfter.toFixed(1)}
+                    </span>
+                  )}
+                </span>
+                {showAccommodation ? (
+                  <ChevronUp className="w-4 h-4" />
+                ) : (
+                  <ChevronDown className="w-4 h-4" />
+                )}
+              </button>
+              {showAccommodation && (
+                <div className="px-4 py-3 border-t border-slate-200 bg-white space-y-3">
+                  <div className="prose max-w-none">
+                    <RichMarkdown content={accommodation.text} />
+                  </div>
+                  <div className="space-y-2">
+                    {accommodation.answers.map((answer, i) => (
+                      <div
+                        key={i}
+                        className={`flex items-start gap-2 px-3 py-2 rounded-md ${
+                          answer.is_correct
+                            ? "bg-green-50 border border-green-200"
+                            : "bg-secondary/50"
+                        }`}
+                      >
+                        <span className="flex-shrink-0 w-6 h-6 flex items-center justify-center rounded-full text-xs font-medium bg-white border">
+                          {String.fromCharCode(65 + i)}
+                        </span>
+                        <div className="flex-1 text-sm">
+                          <RichMarkdown
+                            content={answer.text}
+                            components={{
+                              p({ children }: any) {
+                                return <>{children}</>;
+                              },
+                            }}
+                          />
+                        </div>
+                        {answer.is_correct && (
+                          <Check className="w-4 h-4 text-green-600 flex-shrink-0" />
+                        )}
+                      </div>
+                    ))}
+                  </div>
+                  {accommodation.glossary.length > 0 && (
+                    <div className="text-sm">
+                      <div className="font-medium text-foreground">Key words</div>
+                      <ul className="list-disc pl-5 text-slate-700">
+                        {accommodation.glossary.map((gloss) => (
+                          <li key={gloss.term}>
+                            <span className="font-medium">{gloss.term}</span>: {gloss.definition}
+                          </li>
+                        ))}
+                      </ul>
+                    </div>
+                  )}
+                  {accommodation.warnings.length > 0 && (
+                    <ul className="rounded-md border border-amber-200 bg-amber-50 px-3 py-2 text-xs text-amber-800 list-disc pl-6">
+                      {accommodation.warnings.map((warning) => (
+                        <li key={warning}>{warning}</li>
+                      ))}
+                    </ul>
+                  )}
+                  {onRemoveAccommodation && (
+                    <div className="flex justify-end">
+                      <button
+                        onClick={onRemoveAccommodation}
+                        className="text-xs text-muted-foreground hover:text-destructive"
+                      >
+                        Remove accommodated version
+                      </button>
+                    </div>
+                  )}
+                </div>
+              )}
+            </div>
+          )}
             </>
           )}
         </div>
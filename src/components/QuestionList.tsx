@@ -1,4 +1,4 @@
-import { Question } from "../types";
+import { Question, RegenerateMode } from "../types";
 import QuestionCard from "./QuestionCard";
 import StreamingQuestionCard from "./StreamingQuestionCard";
 
@@ -7,15 +7,25 @@ interface QuestionListProps {
   topicMetaById?: Record<string, { label: string; kind: "topic" | "subtopic" }>;
   rawTextByQuestionId?: Record<string, string>;
   regeneratingQuestionId?: string | null;
+  verifyingQuestionId?: string | null;
   isRegeneratingAll?: boolean;
+  batchStreamsByIndex?: Record<number, { text: string; done: boolean }>;
   regenerationStreamingText?: string;
   regenerationStreamingComplete?: boolean;
   showStreamingCard?: boolean;
+  provisionalQuestions?: Question[];
   streamingText?: string;
   streamingComplete?: boolean;
   showRawStream?: boolean;
   onToggleRawStream?: () => void;
-  onRegenerate: (index: number, instructions?: string) => void;
+  onRegenerate: (index: number, instructions?: string, mode?: RegenerateMode) => void;
+  onPreviewRegenerationPrompt?: (
+    index: number,
+    instructions?: string,
+    mode?: RegenerateMode
+  ) => void;
+  onVerify?: (index: number) => void;
+  onRemoveAccommodation?: (index: number) => void;
   onEdit: (index: number) => void;
   onDelete: (index: number) => void;
 }
@@ -25,15 +35,21 @@ export default function QuestionList({
   topicMetaById = {},
   rawTextByQuestionId = {},
   regeneratingQuestionId = null,
+  verifyingQuestionId = null,
   isRegeneratingAll = false,
+  batchStreamsByIndex = {},
   regenerationStreamingText = "",
   regenerationStreamingComplete = true,
   showStreamingCard = false,
+  provisionalQuestions = [],
   streamingText = "",
   streamingComplete = false,
   showRawStream = true,
   onToggleRawStream,
   onRegenerate,
+  onPreviewRegenerationPrompt,
+  onVerify,
+  onRemoveAccommodation,
   onEdit,
   onDelete,
 }: QuestionListProps) {
@@ -41,21 +57,54 @@ export default function QuestionList({
 
   return (
     <div className="space-y-4">
-      {questions.map((question, index) => (
-        <div key={question.id} className={centeredContainerClass}>
+      {questions.map((question, index) => {
+        const batchStream = batchStreamsByIndex[index];
+        const isSingleRegeneration = question.id === regeneratingQuestionId;
+
+        return (
+          <div key={question.id} className={centeredContainerClass}>
+            <QuestionCard
+              question={question}
+              index={index}
+              topicMetaById={topicMetaById}
+              rawText={rawTextByQuestionId[question.id]}
+              liveRawText={
+                batchStream ? batchStream.text : isSingleRegeneration ? regenerationStreamingText : undefined
+              }
+              isRegenerating={
+                batchStream
+                  ? !batchStream.done
+                  : isSingleRegeneration && !regenerationStreamingComplete
+              }
+              isRegenerationDisabled={isRegeneratingAll}
+              isVerifying={question.id === verifyingQuestionId}
+              onRegenerate={(instructions, mode) => onRegenerate(index, instructions, mode)}
+              onPreviewPrompt={
+                onPreviewRegenerationPrompt
+                  ? (instructions, mode) => onPreviewRegenerationPrompt(index, instructions, mode)
+                  : undefined
+              }
+              onVerify={onVerify ? () => onVerify(index) : undefined}
+              onRemoveAccommodation={
+                onRemoveAccommodation ? () => onRemoveAccommodation(index) : undefined
+              }
+              onEdit={() => onEdit(index)}
+              onDelete={() => onDelete(index)}
+            />
+          </div>
+        );
+      })}
+
+      {provisionalQuestions.map((question, position) => (
+        <div key={`provisional-${question.id}`} className={centeredContainerClass}>
           <QuestionCard
             question={question}
-            index={index}
+            index={questions.length + position}
             topicMetaById={topicMetaById}
-            rawText={rawTextByQuestionId[question.id]}
-            liveRawText={question.id === regeneratingQuestionId ? regenerationStreamingText : undefined}
-            isRegenerating={
-              question.id === regeneratingQuestionId && !regenerationStreamingComplete
-            }
-            isRegenerationDisabled={isRegeneratingAll}
-            onRegenerate={(instructions) => onRegenerate(index, instructions)}
-            onEdit={() => onEdit(index)}
-            onDelete={() => onDelete(index)}
+            isProvisional
+            onRegenerate={() => {}}
+            onEdit={() => onEdit(questions.length + position)}
+            onDelete={() => {}}
           />
         </div>
       ))}
@@ -0,0 +1,120 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { Loader2 } from "lucide-react";
+import { ParseIssue, Question, RawResponse, ReparseError, ReparseResult } from "../types";
+
+interface RawResponseModalProps {
+  jobId: string | null;
+  onClose: () => void;
+  onParsed: (questions: Question[]) => void;
+}
+
+export default function RawResponseModal({ jobId, onClose, onParsed }: RawResponseModalProps) {
+  const [text, setText] = useState("");
+  const [loading, setLoading] = useState(false);
+  const [parsing, setParsing] = useState(false);
+  const [message, setMessage] = useState("");
+  const [issues, setIssues] = useState<ParseIssue[]>([]);
+  const [parsed, setParsed] = useState<Question[] | null>(null);
+
+  useEffect(() => {
+    if (!jobId) return;
+    setLoading(true);
+    setMessage("");
+    setIssues([]);
+    setParsed(null);
+    invoke<RawResponse>("get_raw_response", { jobId })
+      .then((raw) => setText(raw.text))
+      .catch((err) => setMessage(String(err)))
+      .finally(() => setLoading(false));
+  }, [jobId]);
+
+  if (!jobId) return null;
+
+  const handleReparse = async () => {
+    setParsing(true);
+    setMessage("");
+    setParsed(null);
+    try {
+      const result = await invoke<ReparseResult>("reparse_raw_response", {
+        jobId,
+        editedText: text,
+      });
+      setIssues(result.issues);
+      setParsed(result.questions);
+      setMessage(
+        result.issues.length > 0
+          ? `Parsed ${result.questions.length} question(s); ${result.issues.length} item(s) still invalid.`
+          : `Parsed ${result.questions.length} question(s).`
+      );
+    } catch (err) {
+      const error = err as ReparseError;
+      setMessage(error?.message ?? String(err));
+      setIssues(error?.issues ?? []);
+    } finally {
+      setParsing(false);
+    }
+  };
+
+  const formatLocation = (issue: ParseIssue) =>
+    issue.line != null ? `Line ${issue.line}, column ${issue.column ?? 1}: ` : "";
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-30">
+      <div className="bg-white rounded-lg shadow-lg p-6 w-full max-w-4xl max-h-[90vh] flex flex-col gap-3">
+        <div>
+          <h2 className="text-lg font-semibold text-foreground">Fix raw output</h2>
+          <p className="text-sm text-muted-foreground">
+            Edit the model's response and parse it again. No new generation is requested.
+          </p>
+        </div>
+
+        {loading ? (
+          <div className="flex items-center gap-2 text-sm text-muted-foreground">
+            <Loader2 className="w-4 h-4 animate-spin" />
+            Loading raw response...
+          </div>
+        ) : (
+          <textarea
+            value={text}
+            onChange={(e) => setText(e.target.value)}
+            spellCheck={false}
+            className="flex-1 min-h-[320px] w-full px-3 py-2 font-mono text-xs border rounded-md resize-none focus:outline-none focus:ring-2 focus:ring-primary"
+          />
+        )}
+
+        {message && <div className="text-sm text-foreground">{message}</div>}
+        {issues.length > 0 && (
+          <ul className="max-h-32 overflow-auto text-xs font-mono text-destructive space-y-1">
+            {issues.map((issue, i) => (
+              <li key={i}>
+                {formatLocation(issue)}
+                {issue.message}
+              </li>
+            ))}
+          </ul>
+        )}
+
+        <div className="flex justify-end gap-2">
+          <button className="px-4 py-2 rounded border hover:bg-secondary" onClick={onClose}>
+            Close
+          </button>
+          <button
+            className="px-4 py-2 rounded border hover:bg-secondary disabled:opacity-50"
+            onClick={handleReparse}
+            disabled={loading || parsing || !text.trim()}
+          >
+            {parsing ? "Parsing..." : "Parse again"}
+          </button>
+          <button
+            className="px-4 py-2 bg-primary text-primary-foreground rounded hover:bg-primary/90 disabled:opacity-50"
+            onClick={() => parsed && onParsed(parsed)}
+            disabled={!parsed || parsed.length === 0}
+          >
+            Add {parsed?.length ?? 0} question(s)
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -1,4 +1,5 @@
 import { useEffect, useState } from "react";
+import { open as openDialog } from "@tauri-apps/api/dialog";
 import {
   ChevronDown,
   ChevronRight,
@@ -7,6 +8,8 @@ import {
   Plus,
   PanelLeftClose,
   PanelLeftOpen,
+  Paperclip,
+  X,
 } from "lucide-react";
 import { TopicInfo } from "../types";
 
@@ -20,8 +23,11 @@ interface SidebarProps {
   onQuestionCountChange: (count: number) => void;
   notes: string;
   onNotesChange: (notes: string) => void;
+  sourcePaths: string[];
+  onSourcePathsChange: (paths: string[]) => void;
   existingCount: number;
   onGenerate: () => void;
+  onPreviewPrompt?: () => void;
   isGenerating: boolean;
   collapsed?: boolean;
   onToggleCollapsed?: () => void;
@@ -37,8 +43,11 @@ export default function Sidebar({
   onQuestionCountChange,
   notes,
   onNotesChange,
+  sourcePaths,
+  onSourcePathsChange,
   existingCount,
   onGenerate,
+  onPreviewPrompt,
   isGenerating,
   collapsed = false,
   onToggleCollapsed,
@@ -46,6 +55,16 @@ export default function Sidebar({
   const [expandedTopics, setExpandedTopics] = useState<Record<string, boolean>>({});
   const generateDisabled = isGenerating || selectedTopics.length === 0;
 
+  const handleAttachSources = async () => {
+    const selection = await openDialog({
+      multiple: true,
+      filters: [{ name: "Source material", extensions: ["md", "markdown", "txt", "docx", "pdf"] }],
+    });
+    if (!selection) return;
+    const picked = Array.isArray(selection) ? selection : [selection];
+    onSourcePathsChange([...sourcePaths, ...picked.filter((path) => !sourcePaths.includes(path))]);
+  };
+
   useEffect(() => {
     setExpandedTopics((prev) => {
       const next: Record<string, boolean> = {};
@@ -223,14 +242,14 @@ export default function Sidebar({
           <input
             type="range"
             min="1"
-            max="8"
+            max="30"
             value={questionCount}
             onChange={(e) => onQuestionCountChange(parseInt(e.target.value, 10))}
             className="w-full accent-primary"
           />
           <div className="flex justify-between text-xs text-muted-foreground">
             <span>1</span>
-            <span>8</span>
+            <span>30</span>
           </div>
         </div>
 
@@ -247,6 +266,43 @@ export default function Sidebar({
           />
         </div>
 
+        {/* Source material */}
+        <div className="shrink-0">
+          <label className="text-sm font-medium text-foreground mb-2 block">
+            Source Material
+          </label>
+          {sourcePaths.length > 0 && (
+            <ul className="space-y-1 mb-2">
+              {sourcePaths.map((path) => (
+                <li
+                  key={path}
+                  className="flex items-center gap-2 rounded-md border px-2 py-1 text-xs"
+                  title={path}
+                >
+                  <span className="flex-1 truncate">{path.split(/[\\/]/).pop() || path}</span>
+                  <button
+                    onClick={() => onSourcePathsChange(sourcePaths.filter((p) => p !== path))}
+                    className="text-muted-foreground hover:text-foreground"
+                    aria-label="Remove source"
+                  >
+                    <X className="w-3 h-3" />
+                  </button>
+                </li>
+              ))}
+            </ul>
+          )}
+          <button
+            onClick={() => void handleAttachSources()}
+            className="flex items-center gap-1 text-sm text-primary hover:underline"
+          >
+            <Paperclip className="w-3.5 h-3.5" />
+            Attach reading, notes or handout…
+          </button>
+          <p className="mt-1 text-xs text-muted-foreground">
+            Markdown, text, Word or PDF. Questions are written from it and cite their passage.
+          </p>
+        </div>
+
       </div>
 
       {/* Generate Button */}
@@ -273,6 +329,15 @@ export default function Sidebar({
             </>
           )}
         </button>
+        {onPreviewPrompt && (
+          <button
+            onClick={onPreviewPrompt}
+            disabled={generateDisabled}
+            className="w-full mt-2 text-xs text-muted-foreground hover:text-foreground disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            Preview prompt
+          </button>
+        )}
       </div>
     </aside>
   );
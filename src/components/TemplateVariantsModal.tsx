@@ -0,0 +1,189 @@
+import { useEffect, useState } from "react";
+import { open as openDialog } from "@tauri-apps/api/dialog";
+import { ParametricTemplateInfo } from "../types";
+
+export interface TemplateVariantsSelection {
+  templateId?: string;
+  path?: string;
+  count: number;
+  seed?: number;
+}
+
+interface TemplateVariantsModalProps {
+  open: boolean;
+  subjectName: string;
+  templates: ParametricTemplateInfo[];
+  defaultCount: number;
+  isGenerating: boolean;
+  onGenerate: (selection: TemplateVariantsSelection) => void;
+  onClose: () => void;
+}
+
+function fileNameFromPath(path: string): string {
+  const normalized = path.replace(/\\/g, "/");
+  const parts = normalized.split("/");
+  return parts[parts.length - 1] || path;
+}
+
+export default function TemplateVariantsModal({
+  open,
+  subjectName,
+  templates,
+  defaultCount,
+  isGenerating,
+  onGenerate,
+  onClose,
+}: TemplateVariantsModalProps) {
+  const [templateId, setTemplateId] = useState<string | null>(null);
+  const [path, setPath] = useState<string | null>(null);
+  const [count, setCount] = useState(defaultCount);
+  const [seed, setSeed] = useState("");
+
+  useEffect(() => {
+    if (!open) return;
+    setTemplateId(templates[0]?.id ?? null);
+    setPath(null);
+    setCount(defaultCount);
+    setSeed("");
+  }, [open, templates, defaultCount]);
+
+  if (!open) return null;
+
+  const handleOpenFile = async () => {
+    const selection = await openDialog({
+      multiple: false,
+      filters: [{ name: "Parametric template", extensions: ["json"] }],
+    });
+    const filePath = Array.isArray(selection) ? selection[0] : selection;
+    if (!filePath) return;
+    setPath(filePath);
+    setTemplateId(null);
+  };
+
+  const parsedSeed = seed.trim() ? Number(seed.trim()) : undefined;
+  const seedIsValid =
+    parsedSeed === undefined || (Number.isSafeInteger(parsedSeed) && parsedSeed >= 0);
+  const canGenerate = (templateId || path) && count >= 1 && seedIsValid && !isGenerating;
+
+  const handleGenerate = () => {
+    if (!canGenerate) return;
+    onGenerate({
+      templateId: templateId ?? undefined,
+      path: templateId ? undefined : path ?? undefined,
+      count,
+      seed: parsedSeed,
+    });
+  };
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50 p-4">
+      <div className="w-full max-w-lg rounded-lg bg-white shadow-xl border">
+        <div className="px-5 py-4 border-b flex items-center justify-between">
+          <h2 className="text-lg font-semibold text-foreground">Generate from Template</h2>
+          <button
+            onClick={onClose}
+            className="text-sm text-muted-foreground hover:text-foreground"
+          >
+            Close
+          </button>
+        </div>
+
+        <div className="p-5 space-y-4 max-h-[60vh] overflow-auto">
+          <p className="text-sm text-muted-foreground">
+            Fill in a parametric template locally. Answers and distractors are computed from its
+            formulas, so no gateway call is made. The same seed always gives the same variants.
+          </p>
+
+          <div className="space-y-2">
+            {templates.length === 0 && !path && (
+              <p className="text-sm text-muted-foreground">
+                No bundled templates for {subjectName || "this subject"}.
+              </p>
+            )}
+            {templates.map((template) => (
+              <label
+                key={template.id}
+                className={`flex items-start gap-2 rounded-md border px-3 py-2 cursor-pointer ${
+                  templateId === template.id ? "border-primary bg-secondary/50" : "hover:bg-secondary/30"
+                }`}
+              >
+                <input
+                  type="radio"
+                  name="parametric-template"
+                  checked={templateId === template.id}
+                  onChange={() => {
+                    setTemplateId(template.id);
+                    setPath(null);
+                  }}
+                  className="mt-1"
+                />
+                <span>
+                  <span className="block text-sm font-medium text-foreground">{template.title}</span>
+                  <span className="block text-xs text-muted-foreground">
+                    {template.id}
+                    {template.difficulty ? ` · ${template.difficulty}` : ""}
+                  </span>
+                </span>
+              </label>
+            ))}
+            {path && (
+              <div className="rounded-md border border-primary bg-secondary/50 px-3 py-2" title={path}>
+                <div className="text-sm font-medium text-foreground">{fileNameFromPath(path)}</div>
+                <div className="text-xs text-muted-foreground truncate">{path}</div>
+              </div>
+            )}
+            <button
+              onClick={() => void handleOpenFile()}
+              className="text-sm text-primary hover:underline"
+            >
+              Open template file…
+            </button>
+          </div>
+
+          <div className="grid grid-cols-2 gap-3">
+            <label className="text-sm font-medium text-foreground">
+              Variants
+              <input
+                type="number"
+                min={1}
+                max={100}
+                value={count}
+                onChange={(e) => setCount(Math.max(1, Math.min(100, Number(e.target.value) || 1)))}
+                className="mt-1 w-full px-3 py-2 border rounded-md text-sm"
+              />
+            </label>
+            <label className="text-sm font-medium text-foreground">
+              Seed
+              <input
+                type="text"
+                inputMode="numeric"
+                value={seed}
+                onChange={(e) => setSeed(e.target.value)}
+                placeholder="Random"
+                className={`mt-1 w-full px-3 py-2 border rounded-md text-sm ${
+                  seedIsValid ? "" : "border-destructive"
+                }`}
+              />
+            </label>
+          </div>
+        </div>
+
+        <div className="px-5 py-3 border-t flex items-center justify-end gap-2">
+          <button
+            onClick={onClose}
+            className="px-3 py-1.5 text-sm border rounded-md hover:bg-secondary"
+          >
+            Cancel
+          </button>
+          <button
+            onClick={handleGenerate}
+            disabled={!canGenerate}
+            className="px-3 py-1.5 text-sm rounded-md bg-primary text-primary-foreground hover:bg-primary/90 disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {isGenerating ? "Generating..." : "Generate"}
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
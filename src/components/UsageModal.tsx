@@ -0,0 +1,126 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/tauri";
+import { Loader2 } from "lucide-react";
+import { UsageSummary, UsageTotals } from "../types";
+
+interface UsageModalProps {
+  open: boolean;
+  onClose: () => void;
+}
+
+const formatTotals = (totals: UsageTotals) =>
+  `${totals.requests} request(s) · ~${totals.estimated_tokens.toLocaleString()} tokens`;
+
+export default function UsageModal({ open, onClose }: UsageModalProps) {
+  const [summary, setSummary] = useState<UsageSummary | null>(null);
+  const [error, setError] = useState("");
+
+  useEffect(() => {
+    if (!open) return;
+    setError("");
+    invoke<UsageSummary>("get_usage_summary")
+      .then(setSummary)
+      .catch((err) => setError(String(err)));
+  }, [open]);
+
+  if (!open) return null;
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black bg-opacity-30">
+      <div className="bg-white rounded-lg shadow-lg p-6 w-full max-w-2xl max-h-[90vh] flex flex-col gap-4">
+        <div>
+          <h2 className="text-lg font-semibold text-foreground">Usage</h2>
+          <p className="text-sm text-muted-foreground">
+            Token counts are estimates from prompt and response size.
+          </p>
+        </div>
+
+        {error && <div className="text-sm text-destructive">{error}</div>}
+        {!summary && !error && (
+          <div className="flex items-center gap-2 text-sm text-muted-foreground">
+            <Loader2 className="w-4 h-4 animate-spin" />
+            Loading usage...
+          </div>
+        )}
+
+        {summary && (
+          <div className="flex flex-col gap-4 overflow-auto text-sm">
+            <div className="grid grid-cols-3 gap-3">
+              <div className="border rounded-md p-3">
+                <div className="text-xs text-muted-foreground">Remaining quota</div>
+                <div className="text-lg font-semibold">
+                  {summary.remaining_tokens != null
+                    ? summary.remaining_tokens.toLocaleString()
+                    : "Unknown"}
+                </div>
+                {summary.remaining_updated_at && (
+                  <div className="text-xs text-muted-foreground">
+                    as of {new Date(summary.remaining_updated_at).toLocaleString()}
+                  </div>
+                )}
+              </div>
+              <div className="border rounded-md p-3">
+                <div className="text-xs text-muted-foreground">Today</div>
+                <div>{formatTotals(summary.today)}</div>
+              </div>
+              <div className="border rounded-md p-3">
+                <div className="text-xs text-muted-foreground">Last {summary.days.length} day(s)</div>
+                <div>{formatTotals(summary.total)}</div>
+              </div>
+            </div>
+
+            <div>
+              <h3 className="font-medium mb-1">Recent jobs</h3>
+              {summary.recent_jobs.length === 0 ? (
+                <div className="text-muted-foreground">No generations recorded yet.</div>
+              ) : (
+                <table className="w-full text-xs">
+                  <thead className="text-left text-muted-foreground">
+                    <tr>
+                      <th className="py-1">When</th>
+                      <th>Kind</th>
+                      <th>Subject</th>
+                      <th className="text-right">Prompt / response chars</th>
+                      <th className="text-right">~Tokens</th>
+                    </tr>
+                  </thead>
+                  <tbody>
+                    {summary.recent_jobs.map((job) => (
+                      <tr key={job.job_id} className="border-t">
+                        <td className="py-1">{new Date(job.started_at).toLocaleString()}</td>
+                        <td>{job.kind}</td>
+                        <td>{job.subject}</td>
+                        <td className="text-right">
+                          {job.prompt_chars.toLocaleString()} / {job.response_chars.toLocaleString()}
+                        </td>
+                        <td className="text-right">{job.estimated_tokens.toLocaleString()}</td>
+                      </tr>
+                    ))}
+                  </tbody>
+                </table>
+              )}
+            </div>
+
+            <div>
+              <h3 className="font-medium mb-1">By day</h3>
+              <ul className="text-xs space-y-1">
+                {summary.days.map((day) => (
+                  <li key={day.date} className="flex justify-between">
+                    <span>{day.date}</span>
+                    <span>{formatTotals(day)}</span>
+                  </li>
+                ))}
+              </ul>
+            </div>
+          </div>
+        )}
+
+        <div className="flex justify-end">
+          <button className="px-4 py-2 rounded border hover:bg-secondary" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
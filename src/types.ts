@@ -1,12 +1,163 @@
 export interface Question {
   id: string;
   text: string; // Question text in markdown format (may include code blocks)
+  code?: string; // Code returned separately from the stem, shown after it as a Java block
   answers: Answer[];
   explanation?: string; // Correct answer explanation
   distractors?: string; // Why wrong answers are tempting
+  work?: string; // The model's scratchpad, kept as reviewer notes and never exported
   subject?: string;
   topics?: string[];
   difficulty?: string;
+  repaired?: boolean; // Set when the JSON only parsed after an automatic repair round-trip
+  provenance?: Provenance; // How a generated question was produced
+  verification?: Verification; // Latest independent check of the answer key
+  code_check?: CodeCheck; // Result of running the question's Java code
+  math_check?: MathCheck; // Numeric check of a Calculus answer key
+  source?: SourceExcerpt; // Passage of the attached source material the question is based on
+  blueprint?: BlueprintTag; // Blueprint row the question was generated for
+  accommodation?: Accommodation; // Simplified version with the same concept and key
+}
+
+// Accommodated versions for IEP/504 and ELL students
+export interface AccommodationOptions {
+  simplify_language: boolean;
+  three_choices: boolean; // drop wrong answers down to three choices
+  gloss_vocabulary: boolean;
+  larger_spacing: boolean; // applied when the accommodated document is exported
+}
+
+export interface Gloss {
+  term: string;
+  definition: string;
+}
+
+export interface Accommodation {
+  text: string; // rewritten stem, code included
+  answers: Answer[]; // kept choices in original order, key copied from the original
+  glossary: Gloss[];
+  options: AccommodationOptions;
+  readability: {
+    standard: number | null; // Flesch-Kincaid grade of the standard wording
+    accommodated: number | null;
+  };
+  warnings: string[]; // e.g. a reading level that did not drop
+  job_id: string;
+  generated_at: string;
+}
+
+export interface AccommodationRun {
+  questions: Question[];
+  failed: { index: number; error: string }[];
+}
+
+export interface GenerationRun {
+  questions: Question[];
+  failed: { batch: number; error: string }[]; // batches that produced no questions
+}
+
+export interface BlueprintRun {
+  questions: Question[];
+  failed: { row: number; error: string }[]; // 1-based rows that produced no questions
+  failed_batches: { batch: number; error: string }[];
+}
+
+export interface BlueprintTag {
+  name: string; // blueprint name, empty when unnamed
+  row: number; // 1-based row number
+  label: string; // e.g. "Arrays · hard · apply"
+}
+
+export interface SourceExcerpt {
+  section: string; // section label from the prompt, e.g. "S3"
+  document: string; // file name of the source document
+  quote: string; // cited span, copied from the document when found
+  verified: boolean; // false when the quote could not be found in the material
+}
+
+export type CodeCheckStatus =
+  | "match"
+  | "mismatch"
+  | "no_choice_matches"
+  | "compile_error"
+  | "runtime_error"
+  | "timeout"
+  | "skipped";
+
+export interface CodeCheck {
+  status: CodeCheckStatus;
+  output: string;
+  detail: string; // compiler/runtime error or why the check was skipped
+  matched_choice?: string | null; // letter of the choice the output matched
+  checked_at: string;
+}
+
+export type MathCheckStatus = "match" | "mismatch" | "no_choice_matches" | "unchecked";
+
+export interface MathCheck {
+  status: MathCheckStatus;
+  problem: string; // what was computed, e.g. "limit of ... as x → 0"
+  expected?: string | null; // computed value for numeric answers
+  matched_choice?: string | null; // letter of the choice equal to the computed answer
+  equivalent_choices?: string[]; // choices that are mathematically the same, e.g. "A = B"
+  checked_at: string;
+}
+
+export interface BankCodeCheck {
+  id: string;
+  code_check?: CodeCheck;
+  math_check?: MathCheck;
+}
+
+export type VerificationMode = "off" | "flag" | "regenerate";
+
+export interface Verification {
+  verdict: "agree" | "disagree" | "inconclusive";
+  derived_answer?: string | null; // letter the verifier chose
+  marked_answer?: string | null; // letters marked correct when checked
+  reasoning: string;
+  regenerated?: boolean; // replaced once after a disputed check
+  checked_at: string;
+}
+
+// Which part of a question a regeneration rewrites
+export type RegenerateMode =
+  | "full"
+  | "distractors"
+  | "explanation"
+  | "harder"
+  | "easier"
+  | "numbers";
+
+export interface Provenance {
+  kind: string; // "generate", "regenerate", "parametric" or "bank"
+  template_version?: string | null;
+  template_hash?: string | null; // absent when the built-in prompt was used
+  example_ids: string[];
+  provider: string;
+  model: string;
+  job_id: string;
+  generated_at: string;
+  regeneration_mode?: RegenerateMode | null; // absent for full rewrites
+  seed?: number | null; // parametric templates only
+}
+
+// Bundled parametric template (filled in locally, no LLM)
+export interface ParametricTemplateInfo {
+  id: string;
+  title: string;
+  subject: string;
+  topics: string[];
+  difficulty: string;
+}
+
+export interface ParametricRequest {
+  subject: string;
+  template_id?: string | null;
+  path?: string | null;
+  count: number;
+  seed?: number | null;
+  append: boolean;
 }
 
 // Question bank (rich) entries
@@ -74,6 +225,53 @@ export interface GenerationRequest {
   count: number;
   notes: string | null;
   append: boolean;
+  sources: string[]; // paths of .md, .txt, .docx or .pdf files to write the questions from
+  cognitive_level?: string | null; // Bloom's level code such as "B3"; set for blueprint rows
+}
+
+// Test blueprint: the topic × difficulty mix of a whole test
+export interface BlueprintRow {
+  topics: string[];
+  difficulty: string;
+  count: number;
+  cognitive_level?: string | null; // "remember" … "create" (or a bank code B1–B6)
+  notes?: string | null;
+}
+
+export interface Blueprint {
+  version?: number;
+  name: string;
+  subject: string;
+  rows: BlueprintRow[];
+}
+
+// Test assembled from existing bank entries
+export interface TopicQuota {
+  topic: string;
+  min: number; // 0 keeps the topic in the pool without a minimum
+}
+
+export interface DifficultyMix {
+  easy?: number | null; // absent levels take whatever fits
+  medium?: number | null;
+  hard?: number | null;
+}
+
+export interface AssemblyRequest {
+  subject: string;
+  count: number;
+  topics: TopicQuota[]; // empty for the whole bank
+  difficulty: DifficultyMix;
+  max_code_items?: number | null;
+  exclude: string[]; // bank entry ids
+  exclude_files: string[]; // files listing more ids
+  append: boolean;
+}
+
+export interface AssemblyResult {
+  questions: Question[];
+  selected: string[]; // bank ids in test order
+  unmet: string[]; // one sentence per constraint the bank could not meet
 }
 
 export interface WordExportOptions {
@@ -82,6 +280,7 @@ export interface WordExportOptions {
   version_count: number;
   shuffle_choices: boolean;
   shuffle_questions: boolean;
+  accommodated?: boolean; // export the accommodated versions, in the original order
 }
 
 export type BugSeverity = "low" | "medium" | "high" | "critical";
@@ -120,3 +319,125 @@ export interface SubmitBugResult {
   upstream_url?: string;
   message: string;
 }
+
+// Raw LLM output kept per job for re-parsing (reparse_raw_response)
+export interface RawResponse {
+  job_id: string;
+  created_at: string;
+  subject: string;
+  topics: string[];
+  difficulty: string;
+  text: string;
+}
+
+export interface ParseIssue {
+  message: string;
+  line?: number | null;
+  column?: number | null;
+}
+
+export interface ReparseResult {
+  questions: Question[];
+  issues: ParseIssue[];
+}
+
+export interface ReparseError {
+  message: string;
+  issues: ParseIssue[];
+}
+
+export type LlmStreamOutcome = "completed" | "cancelled" | "failed";
+
+export interface LlmInteractionSummary {
+  id: string;
+  job_id: string;
+  index?: number | null;
+  batch?: number | null;
+  timestamp: string;
+  kind: string;
+  subject: string;
+  topics: string[];
+  outcome: LlmStreamOutcome;
+  error?: string | null;
+  parse_outcome?: string | null;
+  duration_ms: number;
+  retries: number;
+  remaining_tokens?: number | null;
+  prompt_chars: number;
+  response_chars: number;
+  redacted: boolean;
+}
+
+export interface LlmInteraction {
+  id: string;
+  job_id: string;
+  index?: number | null;
+  batch?: number | null;
+  timestamp: string;
+  kind: string;
+  subject: string;
+  topics: string[];
+  prompt: string;
+  response: string;
+  outcome: LlmStreamOutcome;
+  error?: string | null;
+  parse_outcome?: string | null;
+  duration_ms: number;
+  retries: number;
+  remaining_tokens?: number | null;
+  diagnostics?: string[];
+  redacted: boolean;
+}
+
+export interface ResponseCacheStatus {
+  enabled: boolean;
+  entries: number;
+  total_bytes: number;
+  dir?: string | null;
+}
+
+export interface UsageTotals {
+  requests: number;
+  prompt_chars: number;
+  response_chars: number;
+  estimated_tokens: number;
+}
+
+export interface DayUsage extends UsageTotals {
+  date: string;
+}
+
+export interface JobUsage extends UsageTotals {
+  job_id: string;
+  kind: string;
+  subject: string;
+  started_at: string;
+  updated_at: string;
+}
+
+export interface UsageSummary {
+  remaining_tokens?: number | null;
+  remaining_updated_at?: string | null;
+  today: UsageTotals;
+  days: DayUsage[];
+  recent_jobs: JobUsage[];
+  total: UsageTotals;
+}
+
+export interface QuotaEstimate {
+  requests: number;
+  estimated_tokens: number;
+  remaining_tokens?: number | null;
+  likely_to_exceed: boolean;
+}
+
+export interface PromptPreview {
+  prompt: string;
+  example_ids: string[];
+  used_template: boolean;
+  template_version?: string | null;
+  template_hash?: string | null;
+  filled_placeholders: string[];
+  unresolved_placeholders: string[];
+  estimated_tokens: number;
+}